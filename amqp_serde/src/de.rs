@@ -13,6 +13,9 @@ use serde::de::{
     VariantAccess, Visitor,
 };
 
+/// Deserializer of AMQP 0-9-1 wire encoding, which reads from a byte slice.
+///
+/// Use [`from_bytes`](fn.from_bytes.html) instead of using it directly.
 pub struct Deserializer<'de> {
     input: &'de [u8],
     last_parsed_len: Option<usize>,
@@ -25,6 +28,7 @@ impl<'de> Deserializer<'de> {
     // `serde_json::from_str(...)` while advanced use cases that require a
     // deserializer can make one with `serde_json::Deserializer::from_str(...)`.
 
+    /// Returns a deserializer reading from `input`.
     pub fn from_bytes(input: &'de [u8]) -> Self {
         Deserializer {
             input,
@@ -39,6 +43,15 @@ impl<'de> Deserializer<'de> {
 // depending on what Rust types the deserializer is able to consume as input.
 //
 // This basic deserializer supports only `from_str`.
+/// Decode a value from `s`, which must contain exactly one encoded value.
+///
+/// # Errors
+///
+/// Returns [`Error::Eof`] if `s` is too short, [`Error::Incomplete`] if bytes remain
+/// after the value, or other error if `s` is not a valid encoding of the value.
+///
+/// [`Error::Eof`]: enum.Error.html#variant.Eof
+/// [`Error::Incomplete`]: enum.Error.html#variant.Incomplete
 pub fn from_bytes<'a, T>(s: &'a [u8]) -> Result<T>
 where
     T: Deserialize<'a>,
@@ -134,7 +147,6 @@ impl<'de> Deserializer<'de> {
     fn get_parsed_length(&self) -> Result<usize> {
         match self.last_parsed_len {
             Some(len) if len <= u32::MAX as usize => {
-                let len = len;
                 if self.input.len() < len {
                     Err(Error::Syntax)
                 } else {
@@ -165,7 +177,7 @@ impl<'de> Deserializer<'de> {
     }
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     type Error = Error;
 
     // Look at the input data to decide what Serde data model type to
@@ -461,6 +473,19 @@ impl<'a, 'de> DataSequence<'a, 'de> {
     fn new_struct(de: &'a mut Deserializer<'de>, len: usize) -> Self {
         DataSequence { de, len, is_struct: true }
     }
+
+    // deduct bytes consumed since `start` from the remaining length,
+    // an element must not exceed the length of sequence
+    fn consume_since(&mut self, start: usize) -> Result<()> {
+        let consumed = self.de.cursor - start;
+        self.len = self.len.checked_sub(consumed).ok_or_else(|| {
+            Error::Message(format!(
+                "element exceeds sequence length, cursor: {}",
+                self.de.cursor
+            ))
+        })?;
+        Ok(())
+    }
 }
 
 // `SeqAccess` is provided to the `Visitor` to give it the ability to iterate
@@ -478,10 +503,9 @@ impl<'de, 'a> SeqAccess<'de> for DataSequence<'a, 'de> {
                 seed.deserialize(&mut *self.de).map(Some)
             } else {
                 let start = self.de.cursor;
-                let res = seed.deserialize(&mut *self.de).map(Some);
-                let end = self.de.cursor;
-                self.len -= end - start;
-                res
+                let res = seed.deserialize(&mut *self.de).map(Some)?;
+                self.consume_since(start)?;
+                Ok(res)
             }
         } else {
             Ok(None)
@@ -500,10 +524,9 @@ impl<'de, 'a> MapAccess<'de> for DataSequence<'a, 'de> {
     {
         if self.len > 0 {
             let start = self.de.cursor;
-            let res = seed.deserialize(&mut *self.de).map(Some);
-            let end = self.de.cursor;
-            self.len -= end - start;
-            res
+            let res = seed.deserialize(&mut *self.de).map(Some)?;
+            self.consume_since(start)?;
+            Ok(res)
         } else {
             Ok(None)
         }
@@ -514,10 +537,9 @@ impl<'de, 'a> MapAccess<'de> for DataSequence<'a, 'de> {
         V: DeserializeSeed<'de>,
     {
         let start = self.de.cursor;
-        let res = seed.deserialize(&mut *self.de);
-        let end = self.de.cursor;
-        self.len -= end - start;
-        res
+        let res = seed.deserialize(&mut *self.de)?;
+        self.consume_since(start)?;
+        Ok(res)
     }
 }
 
@@ -552,10 +574,10 @@ impl<'de, 'a> EnumAccess<'de> for Enum<'a, 'de> {
                 let val = seed.deserialize(variant.into_deserializer())?;
                 Ok((val, self))
             }
-            v => panic!(
+            v => Err(Error::Message(format!(
                 "unsupported enum variant for AMQP field value: {}, cursor: {}",
                 v, self.de.cursor
-            ),
+            ))),
         }
     }
 }
@@ -686,6 +708,16 @@ mod tests {
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn test_malformed_field_table() {
+        // unsupported field value type
+        let input = vec![0x00, 0x00, 0x00, 0x04, 0x01, b'a', b'#', 0x01];
+        assert!(from_bytes::<FieldTable>(&input).is_err());
+        // field exceeds the table length
+        let input = vec![0x00, 0x00, 0x00, 0x03, 0x01, b'a', b't', 0x01];
+        assert!(from_bytes::<FieldTable>(&input).is_err());
+    }
+
     #[test]
     fn test_map() {
         #[derive(Deserialize, PartialEq, Debug)]
@@ -9,12 +9,14 @@
 use serde::{de, ser};
 use std::fmt;
 
+/// Result type of encoding and decoding.
 pub type Result<T> = std::result::Result<T, Error>;
 
 // This is a bare-bones implementation. A real library would provide additional
 // information in its error type, for example the line and column at which the
 // error occurred, the byte offset into the input, or the current key being
 // processed.
+/// Error of encoding and decoding.
 #[derive(Debug)]
 pub enum Error {
     // One or more variants that can be created by data structures through the
@@ -22,14 +24,19 @@ pub enum Error {
     // Mutex<T> might return an error because the mutex is poisoned, or the
     // Deserialize impl for a struct may return an error because a required
     // field is missing.
+    /// Custom error raised by `Serialize` or `Deserialize` implementation.
     Message(String),
 
     // Zero or more variants that can be created directly by the Serializer and
     // Deserializer without going through `ser::Error` and `de::Error`. These
     // are specific to the format, in this case JSON.
+    /// Input ends before a value is completely decoded.
     Eof,
+    /// Input is not a valid encoding.
     Syntax,
+    /// Bytes remain in input after a value is decoded.
     Incomplete,
+    /// Raw bytes are not preceded by a length value.
     ExpectedLength,
 }
 
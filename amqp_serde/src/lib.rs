@@ -1,3 +1,46 @@
+//! Serde implementation of the AMQP 0-9-1 wire encoding.
+//!
+//! The crate can be used standalone, e.g. by proxies, fuzzers or brokers, to encode and decode
+//! AMQP field tables, frames and any other type built from the [domain types](types/index.html)
+//! without depending on the whole client library.
+//!
+//! - [`to_bytes`] and [`to_buffer`] encode a value implementing [`serde::Serialize`].
+//! - [`from_bytes`] decodes a value implementing [`serde::Deserialize`].
+//!
+//! Structs are encoded as the sequence of their fields without any delimiter,
+//! so a frame or method can be described by a plain struct of domain types.
+//!
+//! # Example
+//!
+//! ```
+//! use amqp_serde::{
+//!     from_bytes, to_bytes,
+//!     types::{FieldTable, FieldValue, ShortUint},
+//! };
+//! use serde::{Deserialize, Serialize};
+//!
+//! // encode and decode a field table, e.g. arguments of `queue.declare`
+//! let mut table = FieldTable::new();
+//! table.insert("x-max-length".try_into().unwrap(), FieldValue::I(1000));
+//! let bytes = to_bytes(&table).unwrap();
+//! let decoded: FieldTable = from_bytes(&bytes).unwrap();
+//! assert_eq!(table, decoded);
+//!
+//! // encode and decode a user defined structure
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct ClassMethod {
+//!     class_id: ShortUint,
+//!     method_id: ShortUint,
+//! }
+//! let bytes = to_bytes(&ClassMethod { class_id: 10, method_id: 11 }).unwrap();
+//! assert_eq!(vec![0, 10, 0, 11], bytes);
+//! let decoded: ClassMethod = from_bytes(&bytes).unwrap();
+//! assert_eq!(ClassMethod { class_id: 10, method_id: 11 }, decoded);
+//! ```
+//!
+//! [`to_bytes`]: fn.to_bytes.html
+//! [`to_buffer`]: fn.to_buffer.html
+//! [`from_bytes`]: fn.from_bytes.html
 mod de;
 mod error;
 mod ser;
@@ -3,10 +3,22 @@ use bytes::BufMut;
 use serde::{ser, Serialize};
 use std::ops::DerefMut;
 
+/// Serializer of AMQP 0-9-1 wire encoding, which writes into a buffer.
+///
+/// Use [`to_bytes`] or [`to_buffer`] instead of using it directly.
+///
+/// [`to_bytes`]: fn.to_bytes.html
+/// [`to_buffer`]: fn.to_buffer.html
 pub struct Serializer<'a, W: BufMut> {
     output: &'a mut W, // TODO: buffer generic interfaces?
 }
 
+/// Encode `value` into a new byte vector.
+///
+/// # Errors
+///
+/// Returns error if `value` can not be represented in AMQP 0-9-1 encoding,
+/// e.g. a type not supported by the encoding.
 pub fn to_bytes<T>(value: &T) -> Result<Vec<u8>>
 where
     T: Serialize,
@@ -17,6 +29,11 @@ where
     Ok(buf)
 }
 
+/// Encode `value` by appending to `buf`, and returns the number of bytes written.
+///
+/// # Errors
+///
+/// See [`to_bytes`](fn.to_bytes.html).
 pub fn to_buffer<T, U>(value: &T, buf: &mut U) -> Result<usize>
 where
     T: Serialize,
@@ -135,9 +152,9 @@ where
         self.serialize_unit()
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         value.serialize(self)
     }
@@ -161,15 +178,15 @@ where
     }
 
     // serialize only contained value
-    fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         value.serialize(self)
     }
 
     // serialize variant name and contained value
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
@@ -177,7 +194,7 @@ where
         value: &T,
     ) -> Result<Self::Ok>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         variant.serialize(&mut *self)?;
         value.serialize(&mut *self)
@@ -248,9 +265,9 @@ where
 
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(&mut **self)
     }
@@ -347,12 +364,7 @@ where
         if !self.is_len_known {
             let len: u32 = (self.ser.output.len() - self.start - 4) as u32;
 
-            let mut start = self.start;
-            for b in len.to_be_bytes() {
-                let p = self.ser.output.get_mut(start).unwrap();
-                *p = b;
-                start += 1;
-            }
+            self.ser.output[self.start..self.start + 4].copy_from_slice(&len.to_be_bytes());
         }
         Ok(())
     }
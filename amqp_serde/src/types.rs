@@ -441,13 +441,32 @@ pub type FieldName = ShortStr;
 pub struct FieldTable(LongUint, HashMap<FieldName, FieldValue>);
 
 impl FieldTable {
+    /// Returns an empty table.
     pub fn new() -> Self {
         Self(0, HashMap::new())
     }
 
+    /// Insert a field, and returns the replaced value if the field exists.
     pub fn insert(&mut self, k: FieldName, v: FieldValue) -> Option<FieldValue> {
+        let old = self.remove(&k);
         self.0 += LongUint::try_from(size_of_val(&k.0) + k.0 as usize + FieldValue::TAG_SIZE + v.len()).unwrap();
-        self.1.insert(k, v)
+        self.1.insert(k, v);
+        old
+    }
+
+    /// Returns the number of fields.
+    pub fn len(&self) -> usize {
+        self.1.len()
+    }
+
+    /// Returns `true` if the table has no field.
+    pub fn is_empty(&self) -> bool {
+        self.1.is_empty()
+    }
+
+    /// Returns an iterator over the fields in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&FieldName, &FieldValue)> {
+        self.1.iter()
     }
 
     pub fn remove(&mut self, k: &FieldName) -> Option<FieldValue> {
@@ -604,6 +623,21 @@ mod tests {
         assert_eq!("{ Cash: Decimal(3, 123456) }", format!("{}", table));
     }
 
+    #[test]
+    fn test_field_table_replace() {
+        let mut table = FieldTable::new();
+        table.insert("A".try_into().unwrap(), FieldValue::u(1));
+        // Size (1 byte) + "A" (1 byte) + Type (1 byte) + ShortUint (2 bytes) = 5 bytes
+        assert_eq!(5, table.0);
+        let old = table.insert("A".try_into().unwrap(), FieldValue::I(2));
+        assert_eq!(Some(FieldValue::u(1)), old);
+        // ShortUint is replaced by LongInt (4 bytes)
+        assert_eq!(7, table.0);
+        assert_eq!(1, table.len());
+        let key: ShortStr = "A".try_into().unwrap();
+        assert_eq!(Some((&key, &FieldValue::I(2))), table.iter().next());
+    }
+
     #[test]
     fn test_field_array() {
         let exp = vec![FieldValue::t(true), FieldValue::D(DecimalValue(3, 123456))];
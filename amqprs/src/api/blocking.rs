@@ -0,0 +1,688 @@
+//! Blocking (synchronous) API.
+//!
+//! [`Connection`] and [`Channel`] of this module mirror the async API with blocking methods,
+//! for applications which do not use an async runtime.
+//!
+//! A [`Connection`] owns a small multi-thread tokio runtime internally, which drives the
+//! network I/O, heartbeat and dispatching of the connection and all its channels.
+//! The runtime is shutdown when the connection and all its channels are dropped.
+//!
+//! Methods of this module must not be called within an async context, otherwise they panic.
+//! Dropping a [`Connection`] or [`Channel`] does not panic there, but only starts to close it
+//! in background.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use amqprs::{
+//!     blocking::Connection,
+//!     callbacks::{DefaultChannelCallback, DefaultConnectionCallback},
+//!     channel::{BasicPublishArguments, QueueDeclareArguments},
+//!     connection::OpenConnectionArguments,
+//!     BasicProperties,
+//! };
+//!
+//! let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami");
+//! let connection = Connection::open(&args).unwrap();
+//! connection.register_callback(DefaultConnectionCallback).unwrap();
+//!
+//! let channel = connection.open_channel(None).unwrap();
+//! channel.register_callback(DefaultChannelCallback).unwrap();
+//!
+//! let (queue_name, _, _) = channel
+//!     .queue_declare(QueueDeclareArguments::default())
+//!     .unwrap()
+//!     .unwrap();
+//! channel
+//!     .basic_publish(
+//!         BasicProperties::default(),
+//!         b"hello".to_vec(),
+//!         BasicPublishArguments::new("", &queue_name),
+//!     )
+//!     .unwrap();
+//!
+//! channel.close().unwrap();
+//! connection.close().unwrap();
+//! ```
+use std::{fmt, future::Future, ops::Deref, sync::Arc, time::Duration};
+
+use amqp_serde::types::{AmqpChannelId, AmqpMessageCount};
+use bytes::Bytes;
+use tokio::{
+    runtime::{Builder, Handle, Runtime},
+    time,
+};
+
+use super::{
+    callbacks::{ChannelCallback, ConnectionCallback},
+    channel::{
+        self, BasicAckArguments, BasicCancelArguments, BasicConsumeArguments, BasicGetArguments,
+        BasicNackArguments, BasicPublishArguments, BasicQosArguments, BasicRejectArguments,
+        ConfirmSelectArguments, ConsumerMessage, ConsumerStream, ExchangeBindArguments,
+        ExchangeDeclareArguments, ExchangeDeleteArguments, ExchangeUnbindArguments, GetMessage,
+        QueueBindArguments, QueueDeclareArguments, QueueDeleteArguments, QueuePurgeArguments,
+        QueueUnbindArguments,
+    },
+    connection::{self, OpenConnectionArguments, ServerProperties},
+    consumer::BlockingConsumer,
+    error::Error,
+    state::State,
+};
+use crate::frame::BasicProperties;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Time limit of closing a connection or channel when it is dropped.
+const DROP_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Returns `true` if called within an async context, where blocking on a runtime panics.
+fn in_async_context() -> bool {
+    Handle::try_current().is_ok()
+}
+
+/// Internal runtime shared by a connection, its channels and consumers.
+struct SharedRuntime(Option<Runtime>);
+
+impl Deref for SharedRuntime {
+    type Target = Runtime;
+
+    fn deref(&self) -> &Runtime {
+        self.0.as_ref().unwrap()
+    }
+}
+
+impl SharedRuntime {
+    /// Run `close` of a connection or channel being dropped, bounded by [`DROP_CLOSE_TIMEOUT`].
+    /// Within an async context, it is spawned instead of blocking the current thread.
+    fn close_at_drop<F>(&self, close: F)
+    where
+        F: Future<Output = Result<()>> + Send + 'static,
+    {
+        // error ignored, user should call `close` to handle error
+        let close = async move {
+            let _ = time::timeout(DROP_CLOSE_TIMEOUT, close).await;
+        };
+        if in_async_context() {
+            self.spawn(close);
+        } else {
+            self.block_on(close);
+        }
+    }
+}
+
+impl Drop for SharedRuntime {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.0.take() {
+            // dropping a runtime waits for its threads, which panics within an async context
+            if in_async_context() {
+                runtime.shutdown_background();
+            }
+        }
+    }
+}
+
+/// Blocking version of [`connection::Connection`].
+///
+/// See documentation of [module][`self`].
+pub struct Connection {
+    /// always `Some` until dropped.
+    inner: Option<connection::Connection>,
+    runtime: Arc<SharedRuntime>,
+}
+
+impl Connection {
+    /// Open and returns a new connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if fail to start the runtime or any step goes wrong during openning an connection.
+    pub fn open(args: &OpenConnectionArguments) -> Result<Self> {
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("amqprs-blocking")
+            .enable_all()
+            .build()
+            .map_err(|err| Error::ConnectionOpenError(err.to_string()))?;
+        let inner = runtime.block_on(connection::Connection::open(args))?;
+        Ok(Self {
+            inner: Some(inner),
+            runtime: Arc::new(SharedRuntime(Some(runtime))),
+        })
+    }
+
+    fn inner(&self) -> &connection::Connection {
+        self.inner.as_ref().unwrap()
+    }
+
+    /// Returns a reference to the async connection.
+    pub fn as_async(&self) -> &connection::Connection {
+        self.inner()
+    }
+
+    /// See [`connection::Connection::register_callback`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if fail to send registration command.
+    pub fn register_callback<F>(&self, callback: F) -> Result<()>
+    where
+        F: ConnectionCallback + Send + 'static,
+    {
+        self.runtime
+            .block_on(self.inner().register_callback(callback))
+    }
+
+    /// See [`connection::Connection::open_channel`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn open_channel(&self, channel_id: Option<AmqpChannelId>) -> Result<Channel> {
+        let inner = self
+            .runtime
+            .block_on(self.inner().open_channel(channel_id))?;
+        Ok(Channel {
+            inner: Some(inner),
+            runtime: self.runtime.clone(),
+        })
+    }
+
+    /// See [`connection::Connection::close`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn close(mut self) -> Result<()> {
+        let inner = self.inner.take().unwrap();
+        self.runtime.block_on(inner.close())
+    }
+
+    /// See [`connection::Connection::connection_name`].
+    pub fn connection_name(&self) -> &str {
+        self.inner().connection_name()
+    }
+    /// See [`connection::Connection::channel_max`].
+    pub fn channel_max(&self) -> u16 {
+        self.inner().channel_max()
+    }
+    /// See [`connection::Connection::frame_max`].
+    pub fn frame_max(&self) -> u32 {
+        self.inner().frame_max()
+    }
+    /// See [`connection::Connection::server_properties`].
+    pub fn server_properties(&self) -> &ServerProperties {
+        self.inner().server_properties()
+    }
+    /// Returns `true` if connection is open.
+    pub fn is_open(&self) -> bool {
+        self.inner().is_open()
+    }
+    /// See [`connection::Connection::state`].
+    pub fn state(&self) -> State {
+        self.inner().state()
+    }
+}
+
+impl Drop for Connection {
+    /// Gracefully shutdown the connection if it is still open, bounded by 5 seconds.
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            self.runtime.close_at_drop(inner.close());
+        }
+    }
+}
+
+impl fmt::Display for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.inner())
+    }
+}
+
+/// Blocking version of [`channel::Channel`].
+///
+/// See documentation of [module][`self`].
+pub struct Channel {
+    /// always `Some` until dropped.
+    inner: Option<channel::Channel>,
+    runtime: Arc<SharedRuntime>,
+}
+
+impl Channel {
+    fn inner(&self) -> &channel::Channel {
+        self.inner.as_ref().unwrap()
+    }
+
+    /// Returns a reference to the async channel.
+    pub fn as_async(&self) -> &channel::Channel {
+        self.inner()
+    }
+
+    /// See [`channel::Channel::register_callback`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if fail to send registration command.
+    pub fn register_callback<F>(&self, callback: F) -> Result<()>
+    where
+        F: ChannelCallback + Send + 'static,
+    {
+        self.runtime
+            .block_on(self.inner().register_callback(callback))
+    }
+
+    /// See [`channel::Channel::channel_id`].
+    pub fn channel_id(&self) -> AmqpChannelId {
+        self.inner().channel_id()
+    }
+    /// Returns `true` if channel is open.
+    pub fn is_open(&self) -> bool {
+        self.inner().is_open()
+    }
+    /// See [`channel::Channel::state`].
+    pub fn state(&self) -> State {
+        self.inner().state()
+    }
+
+    /// See [`channel::Channel::flow`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn flow(&self, active: bool) -> Result<bool> {
+        self.runtime.block_on(self.inner().flow(active))
+    }
+
+    /// See [`channel::Channel::exchange_declare`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn exchange_declare(&self, args: ExchangeDeclareArguments) -> Result<()> {
+        self.runtime.block_on(self.inner().exchange_declare(args))
+    }
+
+    /// See [`channel::Channel::exchange_delete`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn exchange_delete(&self, args: ExchangeDeleteArguments) -> Result<()> {
+        self.runtime.block_on(self.inner().exchange_delete(args))
+    }
+
+    /// See [`channel::Channel::exchange_bind`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn exchange_bind(&self, args: ExchangeBindArguments) -> Result<()> {
+        self.runtime.block_on(self.inner().exchange_bind(args))
+    }
+
+    /// See [`channel::Channel::exchange_unbind`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn exchange_unbind(&self, args: ExchangeUnbindArguments) -> Result<()> {
+        self.runtime.block_on(self.inner().exchange_unbind(args))
+    }
+
+    /// See [`channel::Channel::queue_declare`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn queue_declare(
+        &self,
+        args: QueueDeclareArguments,
+    ) -> Result<Option<(String, AmqpMessageCount, u32)>> {
+        self.runtime.block_on(self.inner().queue_declare(args))
+    }
+
+    /// See [`channel::Channel::queue_bind`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn queue_bind(&self, args: QueueBindArguments) -> Result<()> {
+        self.runtime.block_on(self.inner().queue_bind(args))
+    }
+
+    /// See [`channel::Channel::queue_purge`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn queue_purge(&self, args: QueuePurgeArguments) -> Result<Option<AmqpMessageCount>> {
+        self.runtime.block_on(self.inner().queue_purge(args))
+    }
+
+    /// See [`channel::Channel::queue_delete`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn queue_delete(&self, args: QueueDeleteArguments) -> Result<Option<AmqpMessageCount>> {
+        self.runtime.block_on(self.inner().queue_delete(args))
+    }
+
+    /// See [`channel::Channel::queue_unbind`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn queue_unbind(&self, args: QueueUnbindArguments) -> Result<()> {
+        self.runtime.block_on(self.inner().queue_unbind(args))
+    }
+
+    /// See [`channel::Channel::basic_qos`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn basic_qos(&self, args: BasicQosArguments) -> Result<()> {
+        self.runtime.block_on(self.inner().basic_qos(args))
+    }
+
+    /// Start a consumer, which runs in the blocking thread pool of the internal runtime.
+    ///
+    /// See [`channel::Channel::basic_consume_blocking`].
+    ///
+    /// Returns the consumer tag on success.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn basic_consume<F>(&self, consumer: F, args: BasicConsumeArguments) -> Result<String>
+    where
+        F: BlockingConsumer + Send + 'static,
+    {
+        self.runtime
+            .block_on(self.inner().basic_consume_blocking(consumer, args))
+    }
+
+    /// Start a consumer and returns its messages as a blocking iterator.
+    ///
+    /// See [`channel::Channel::basic_consume_stream`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn basic_consume_iter(&self, args: BasicConsumeArguments) -> Result<ConsumerIter> {
+        let stream = self
+            .runtime
+            .block_on(self.inner().basic_consume_stream(args))?;
+        Ok(ConsumerIter {
+            stream,
+            runtime: self.runtime.clone(),
+        })
+    }
+
+    /// See [`channel::Channel::basic_cancel`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn basic_cancel(&self, args: BasicCancelArguments) -> Result<String> {
+        self.runtime.block_on(self.inner().basic_cancel(args))
+    }
+
+    /// See [`channel::Channel::basic_get`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn basic_get(&self, args: BasicGetArguments) -> Result<Option<GetMessage>> {
+        self.runtime.block_on(self.inner().basic_get(args))
+    }
+
+    /// See [`channel::Channel::basic_ack`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn basic_ack(&self, args: BasicAckArguments) -> Result<()> {
+        self.runtime.block_on(self.inner().basic_ack(args))
+    }
+
+    /// See [`channel::Channel::basic_nack`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn basic_nack(&self, args: BasicNackArguments) -> Result<()> {
+        self.runtime.block_on(self.inner().basic_nack(args))
+    }
+
+    /// See [`channel::Channel::basic_reject`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn basic_reject(&self, args: BasicRejectArguments) -> Result<()> {
+        self.runtime.block_on(self.inner().basic_reject(args))
+    }
+
+    /// See [`channel::Channel::basic_recover`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn basic_recover(&self, requeue: bool) -> Result<()> {
+        self.runtime.block_on(self.inner().basic_recover(requeue))
+    }
+
+    /// See [`channel::Channel::basic_publish`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn basic_publish(
+        &self,
+        basic_properties: BasicProperties,
+        content: impl Into<Bytes>,
+        args: BasicPublishArguments,
+    ) -> Result<()> {
+        self.runtime
+            .block_on(self.inner().basic_publish(basic_properties, content, args))
+    }
+
+    /// See [`channel::Channel::confirm_select`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn confirm_select(&self, args: ConfirmSelectArguments) -> Result<()> {
+        self.runtime.block_on(self.inner().confirm_select(args))
+    }
+
+    /// See [`channel::Channel::tx_select`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn tx_select(&self) -> Result<()> {
+        self.runtime.block_on(self.inner().tx_select())
+    }
+
+    /// See [`channel::Channel::tx_commit`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn tx_commit(&self) -> Result<()> {
+        self.runtime.block_on(self.inner().tx_commit())
+    }
+
+    /// See [`channel::Channel::tx_rollback`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn tx_rollback(&self) -> Result<()> {
+        self.runtime.block_on(self.inner().tx_rollback())
+    }
+
+    /// See [`channel::Channel::close`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub fn close(mut self) -> Result<()> {
+        let inner = self.inner.take().unwrap();
+        self.runtime.block_on(inner.close())
+    }
+}
+
+impl Drop for Channel {
+    /// Gracefully shutdown the channel if it is still open, bounded by 5 seconds.
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            self.runtime.close_at_drop(inner.close());
+        }
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.inner())
+    }
+}
+
+/// Blocking iterator of messages delivered to a consumer.
+///
+/// The iteration ends when the consumer is cancelled or the channel is closed,
+/// see [`channel::ConsumerStream`].
+pub struct ConsumerIter {
+    stream: ConsumerStream,
+    runtime: Arc<SharedRuntime>,
+}
+
+impl ConsumerIter {
+    /// Returns the consumer tag.
+    pub fn consumer_tag(&self) -> &str {
+        self.stream.consumer_tag()
+    }
+}
+
+impl Iterator for ConsumerIter {
+    type Item = ConsumerMessage;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.stream.recv())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Builder, Connection, SharedRuntime};
+    use crate::{
+        api::{
+            callbacks::{DefaultChannelCallback, DefaultConnectionCallback},
+            channel::{
+                BasicCancelArguments, BasicConsumeArguments, BasicGetArguments,
+                BasicPublishArguments, QueueDeclareArguments,
+            },
+            connection::OpenConnectionArguments,
+        },
+        frame::BasicProperties,
+    };
+
+    #[test]
+    fn test_blocking_publish_and_get() {
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_blocking_publish_and_get")
+            .finish();
+        let connection = Connection::open(&args).unwrap();
+        connection
+            .register_callback(DefaultConnectionCallback)
+            .unwrap();
+
+        let channel = connection.open_channel(None).unwrap();
+        channel.register_callback(DefaultChannelCallback).unwrap();
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .unwrap()
+            .unwrap();
+        channel
+            .basic_publish(
+                BasicProperties::default(),
+                b"blocking".to_vec(),
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .unwrap();
+
+        let mut get_message = None;
+        for _ in 0..10 {
+            get_message = channel
+                .basic_get(BasicGetArguments::new(&queue_name).no_ack(true).finish())
+                .unwrap();
+            if get_message.is_some() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+        let (_, _, content) = get_message.unwrap();
+        assert_eq!(b"blocking".to_vec(), content);
+
+        channel.close().unwrap();
+        connection.close().unwrap();
+    }
+
+    #[test]
+    fn test_blocking_consume_iter() {
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_blocking_consume_iter")
+            .finish();
+        let connection = Connection::open(&args).unwrap();
+        let channel = connection.open_channel(None).unwrap();
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .unwrap()
+            .unwrap();
+        for _ in 0..3 {
+            channel
+                .basic_publish(
+                    BasicProperties::default(),
+                    b"blocking".to_vec(),
+                    BasicPublishArguments::new("", &queue_name),
+                )
+                .unwrap();
+        }
+
+        let messages = channel
+            .basic_consume_iter(
+                BasicConsumeArguments::new(&queue_name, "")
+                    .auto_ack(true)
+                    .finish(),
+            )
+            .unwrap();
+        let consumer_tag = messages.consumer_tag().to_owned();
+        for message in messages.take(3) {
+            assert_eq!(b"blocking".to_vec(), message.content.unwrap());
+        }
+        channel
+            .basic_cancel(BasicCancelArguments::new(&consumer_tag))
+            .unwrap();
+
+        channel.close().unwrap();
+        connection.close().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_drop_in_async_context() {
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .unwrap();
+        let runtime = SharedRuntime(Some(runtime));
+        // a close which never completes does not block the async context
+        runtime.close_at_drop(std::future::pending());
+        // nor does dropping the runtime panic
+        drop(runtime);
+    }
+}
@@ -20,7 +20,10 @@
 //! [`Channel`]: ../channel/struct.Channel.html
 //! [`Channel::register_callback`]: ../channel/struct.Channel.html#method.register_callback
 
-use super::{channel::Channel, connection::Connection};
+use super::{
+    channel::{Channel, QueueDeclareArguments},
+    connection::{Connection, ShutdownCause},
+};
 use crate::api::Result;
 use crate::frame::Cancel;
 use crate::{
@@ -28,6 +31,7 @@ use crate::{
     BasicProperties,
 };
 use async_trait::async_trait;
+use std::time::Duration;
 #[cfg(feature = "traces")]
 use tracing::{error, info, warn};
 
@@ -54,6 +58,16 @@ pub trait ConnectionCallback {
 
     /// Callback to handle connection `unblocked` indication from server
     async fn unblocked(&mut self, connection: &Connection);
+
+    /// Callback to handle shutdown of the connection, for whatever reason.
+    ///
+    /// Called once after the connection is closed, with the same cause as
+    /// [`Connection::shutdown_cause`].
+    ///
+    /// Default implementation does nothing.
+    ///
+    /// [`Connection::shutdown_cause`]: ../connection/struct.Connection.html#method.shutdown_cause
+    async fn on_close(&mut self, _connection: &Connection, _cause: &ShutdownCause) {}
 }
 
 /// Default type that implements `ConnectionCallback`.
@@ -87,6 +101,14 @@ impl ConnectionCallback for DefaultConnectionCallback {
             connection
         );
     }
+
+    async fn on_close(&mut self, connection: &Connection, cause: &ShutdownCause) {
+        #[cfg(feature = "traces")]
+        info!(
+            "handle shutdown of connection {}, cause: {}",
+            connection, cause
+        );
+    }
 }
 
 /////////////////////////////////////////////////////////////////////////////
@@ -138,6 +160,20 @@ pub trait ChannelCallback {
     /// of unhandled messages.
     async fn publish_nack(&mut self, channel: &Channel, nack: Nack);
 
+    /// Callback to handle a storm of `nack` from server.
+    ///
+    /// Called after [`publish_nack`] once the number of messages nacked within one second
+    /// reaches [`ConfirmSelectArguments::nack_storm_threshold`], at most once per second.
+    /// `nacked` is the number of messages nacked so far within the second.
+    ///
+    /// It usually indicates server fails to persist messages, so publisher should back off.
+    ///
+    /// Default implementation does nothing.
+    ///
+    /// [`publish_nack`]: trait.ChannelCallback.html#tymethod.publish_nack
+    /// [`ConfirmSelectArguments::nack_storm_threshold`]: ../channel/struct.ConfirmSelectArguments.html#structfield.nack_storm_threshold
+    async fn publish_nack_storm(&mut self, _channel: &Channel, _nacked: u64) {}
+
     /// Callback to handle `return` indication with undeliverable message from server.
     ///
     /// The [ret][`Return`] contains the reason why the message is returned.
@@ -156,6 +192,52 @@ pub trait ChannelCallback {
         basic_properties: BasicProperties,
         content: Vec<u8>,
     );
+
+    /// Callback to handle the number of unacked deliveries on the channel reaching the threshold
+    /// set by [`Channel::set_unacked_warning_threshold`].
+    ///
+    /// `unacked` is the number of deliveries not acked, nacked or rejected yet.
+    ///
+    /// Default implementation does nothing.
+    ///
+    /// [`Channel::set_unacked_warning_threshold`]: ../channel/struct.Channel.html#method.set_unacked_warning_threshold
+    async fn unacked_threshold_exceeded(&mut self, _channel: &Channel, _unacked: usize) {}
+
+    /// Callback to handle an unacked delivery on the channel reaching the age
+    /// set by [`Channel::set_ack_deadline_warning`].
+    ///
+    /// `age` is how long the delivery has waited for ack. It is called once per delivery.
+    ///
+    /// Default implementation does nothing.
+    ///
+    /// [`Channel::set_ack_deadline_warning`]: ../channel/struct.Channel.html#method.set_ack_deadline_warning
+    async fn ack_deadline_approaching(
+        &mut self,
+        _channel: &Channel,
+        _delivery_tag: u64,
+        _age: Duration,
+    ) {
+    }
+
+    /// Callback to handle removal of an exclusive or auto-delete queue declared on the channel.
+    ///
+    /// Server cancels the consumers of a queue when the queue is removed, so it is called
+    /// after [`cancel`] for the consumer of such a queue.
+    ///
+    /// The [args][`QueueDeclareArguments`] are the arguments used to declare the queue,
+    /// which can be used to declare the queue again.
+    ///
+    /// Default implementation does nothing.
+    ///
+    /// [`cancel`]: trait.ChannelCallback.html#tymethod.cancel
+    /// [`QueueDeclareArguments`]: ../channel/struct.QueueDeclareArguments.html
+    async fn transient_queue_removed(
+        &mut self,
+        _channel: &Channel,
+        _queue: String,
+        _args: QueueDeclareArguments,
+    ) {
+    }
 }
 
 /// Default type that implements `ChannelCallback`.
@@ -206,6 +288,13 @@ impl ChannelCallback for DefaultChannelCallback {
             channel
         );
     }
+    async fn publish_nack_storm(&mut self, channel: &Channel, nacked: u64) {
+        #[cfg(feature = "traces")]
+        error!(
+            "handle publish nack storm of {} messages on channel {}",
+            nacked, channel
+        );
+    }
     async fn publish_return(
         &mut self,
         channel: &Channel,
@@ -221,4 +310,35 @@ impl ChannelCallback for DefaultChannelCallback {
             content.len()
         );
     }
+    async fn unacked_threshold_exceeded(&mut self, channel: &Channel, unacked: usize) {
+        #[cfg(feature = "traces")]
+        warn!(
+            "handle {} unacked deliveries on channel {}",
+            unacked, channel
+        );
+    }
+    async fn ack_deadline_approaching(
+        &mut self,
+        channel: &Channel,
+        delivery_tag: u64,
+        age: Duration,
+    ) {
+        #[cfg(feature = "traces")]
+        warn!(
+            "handle delivery {} unacked for {:?} on channel {}",
+            delivery_tag, age, channel
+        );
+    }
+    async fn transient_queue_removed(
+        &mut self,
+        channel: &Channel,
+        queue: String,
+        _args: QueueDeclareArguments,
+    ) {
+        #[cfg(feature = "traces")]
+        warn!(
+            "handle removal of transient queue {} on channel {}",
+            queue, channel
+        );
+    }
 }
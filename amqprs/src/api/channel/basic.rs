@@ -1,13 +1,31 @@
-use amqp_serde::types::AmqpDeliveryTag;
-use tokio::sync::mpsc;
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
+
+use amqp_serde::types::{AmqpDeliveryTag, FieldName, FieldValue, ShortStr};
+use bytes::{Bytes, BytesMut};
+use tokio::{
+    io::AsyncRead,
+    sync::{mpsc, oneshot, Semaphore},
+};
 #[cfg(feature = "traces")]
-use tracing::{debug, trace};
+use tracing::{debug, error, trace};
 
 use crate::{
     api::{
-        channel::{ConsumerMessage, DispatcherManagementCommand, RegisterContentConsumer},
-        consumer::AsyncConsumer,
+        channel::{
+            ConfirmResponder, Confirmation, ConsumerMessage, DispatcherManagementCommand,
+            RegisterConfirmWaiter, RegisterContentConsumer, TrackedPublish, PUBLISH_TAG_HEADER,
+        },
+        consumer::{
+            panic_message, AsyncConsumer, CatchUnwind, ConsumerTermination, DispatchStrategy,
+            PanicPolicy,
+        },
         error::Error,
+        naming::{check_exchange_name, check_queue_name},
+        state::{CloseInitiator, State},
         FieldTable, Result,
     },
     consumer::BlockingConsumer,
@@ -16,12 +34,73 @@ use crate::{
         ContentHeaderCommon, Frame, Get, GetOk, Nack, Publish, Qos, QosOk, Recover, RecoverOk,
         Reject,
     },
+    net::serialize_frame,
+    rt,
 };
 
 #[cfg(feature = "compliance_assert")]
 use crate::api::compliance_asserts::{assert_exchange_name, assert_queue_name};
 
-use super::{Channel, DeregisterContentConsumer, RegisterGetContentResponder};
+use super::{
+    consumer_buffer, Channel, ChunkedMessage, ConsumerReceiver, ConsumerSender,
+    DeregisterContentConsumer, RegisterGetContentResponder, UnackedDelivery,
+};
+
+#[cfg(feature = "cancellation")]
+use tokio_util::sync::CancellationToken;
+
+#[cfg(feature = "compression")]
+use crate::api::compression::{decompress_message, Compression};
+
+/// Cancellation token of a consumer, `()` if feature "cancellation" is disabled.
+#[cfg(feature = "cancellation")]
+type ConsumerCancellation = Option<CancellationToken>;
+#[cfg(not(feature = "cancellation"))]
+type ConsumerCancellation = ();
+
+/// Pseudo-queue of RabbitMQ [Direct Reply-to].
+///
+/// A requester consumes from it in no-ack mode without declaring it, then publishes requests
+/// with `reply_to` property set to it on the same channel. Server routes replies, published to
+/// the default exchange with the `reply_to` value as routing key, directly to the consumer.
+///
+/// See [`BasicConsumeArguments::direct_reply_to`].
+///
+/// [Direct Reply-to]: https://www.rabbitmq.com/direct-reply-to.html
+/// [`BasicConsumeArguments::direct_reply_to`]: struct.BasicConsumeArguments.html#method.direct_reply_to
+pub const DIRECT_REPLY_TO_QUEUE: &str = "amq.rabbitmq.reply-to";
+
+////////////////////////////////////////////////////////////////////////////////
+/// Where to start consuming a stream queue, see [`BasicConsumeArguments::stream_offset`].
+///
+/// See [RabbitMQ stream consuming](https://www.rabbitmq.com/streams.html#consuming).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamOffset {
+    /// Start from the first available message in the stream.
+    First,
+    /// Start from the last written chunk of messages.
+    Last,
+    /// Start after the last message in the stream, server's default.
+    Next,
+    /// Start from the given absolute offset.
+    Offset(u64),
+    /// Start from the messages appended at or after the given time, in seconds since Unix epoch.
+    Timestamp(u64),
+}
+
+impl From<StreamOffset> for FieldValue {
+    fn from(offset: StreamOffset) -> Self {
+        match offset {
+            StreamOffset::First => "first".into(),
+            StreamOffset::Last => "last".into(),
+            StreamOffset::Next => "next".into(),
+            StreamOffset::Offset(offset) => {
+                FieldValue::l(i64::try_from(offset).unwrap_or(i64::MAX))
+            }
+            StreamOffset::Timestamp(timestamp) => FieldValue::T(timestamp),
+        }
+    }
+}
 ////////////////////////////////////////////////////////////////////////////////
 /// Arguments for [`basic_qos`]
 ///
@@ -34,7 +113,12 @@ pub struct BasicQosArguments {
     pub prefetch_size: u32,
     /// Default: 0.
     pub prefetch_count: u16,
-    /// Default: `false`.
+    /// Scope of the limits. Default: `false`.
+    ///
+    /// If server supports `per_consumer_qos` capability (e.g. RabbitMQ), `false` applies
+    /// the limits separately to each new consumer on the channel, `true` shares the limits
+    /// across all consumers on the channel.
+    /// See [RabbitMQ consumer prefetch](https://www.rabbitmq.com/consumer-prefetch.html).
     pub global: bool,
 }
 
@@ -47,6 +131,18 @@ impl BasicQosArguments {
             global,
         }
     }
+
+    /// Create new arguments which limit unacknowledged deliveries of each new consumer
+    /// on the channel to `prefetch_count`.
+    pub fn per_consumer(prefetch_count: u16) -> Self {
+        Self::new(0, prefetch_count, false)
+    }
+
+    /// Create new arguments which limit unacknowledged deliveries shared by all consumers
+    /// on the channel to `prefetch_count`.
+    pub fn per_channel(prefetch_count: u16) -> Self {
+        Self::new(0, prefetch_count, true)
+    }
     impl_chainable_setter! {
         /// Chainable setter method.
         prefetch_size, u32
@@ -84,7 +180,10 @@ impl BasicQosArguments {
 pub struct BasicConsumeArguments {
     /// Target queue name. Must be provided.
     pub queue: String,
-    /// Consumer identifier. Default: "" (server-generated).
+    /// Consumer identifier. Default: "" (generated by [`ConsumerTagGenerator`] of connection
+    /// if given, otherwise server-generated).
+    ///
+    /// [`ConsumerTagGenerator`]: ../consumer/trait.ConsumerTagGenerator.html
     pub consumer_tag: String,
     /// Ignored by modern RabbitMQ releases. Default: `false`.
     pub no_local: bool,
@@ -92,10 +191,22 @@ pub struct BasicConsumeArguments {
     pub no_ack: bool,
     /// Should this consumer be exclusive (the only one allowed on the target queue)? Default: `false`.
     pub exclusive: bool,
-    /// Default: `false`.
+    /// Do not wait for `consume-ok` from server. A consumer tag must be given
+    /// either in `consumer_tag` or by [`ConsumerTagGenerator`]. Default: `false`.
     pub no_wait: bool,
     /// Default: empty table.
     pub arguments: FieldTable,
+    /// How to handle the delivery if consumer panics. Default: [`PanicPolicy::Requeue`].
+    pub panic_policy: PanicPolicy,
+    /// How deliveries are dispatched to the consumer. Default: [`DispatchStrategy::Serial`].
+    pub dispatch_strategy: DispatchStrategy,
+    /// Maximum number of deliveries buffered for the consumer task. Default: [`None`] (unbounded).
+    ///
+    /// See [`buffer_size`](struct.BasicConsumeArguments.html#method.buffer_size).
+    pub buffer_size: Option<usize>,
+    /// Cancel the consumer when the token is cancelled. Default: [`None`].
+    #[cfg(feature = "cancellation")]
+    pub cancellation_token: Option<CancellationToken>,
 }
 
 impl BasicConsumeArguments {
@@ -112,8 +223,25 @@ impl BasicConsumeArguments {
             exclusive: false,
             no_wait: false,
             arguments: FieldTable::new(),
+            panic_policy: PanicPolicy::default(),
+            dispatch_strategy: DispatchStrategy::default(),
+            buffer_size: None,
+            #[cfg(feature = "cancellation")]
+            cancellation_token: None,
         }
     }
+    /// Create arguments to consume replies by [Direct Reply-to], see [`DIRECT_REPLY_TO_QUEUE`].
+    ///
+    /// The consumer must be in no-ack mode, so [`manual_ack`] should not be enabled.
+    ///
+    /// [Direct Reply-to]: https://www.rabbitmq.com/direct-reply-to.html
+    /// [`DIRECT_REPLY_TO_QUEUE`]: constant.DIRECT_REPLY_TO_QUEUE.html
+    /// [`manual_ack`]: struct.BasicConsumeArguments.html#method.manual_ack
+    pub fn direct_reply_to() -> Self {
+        let mut args = Self::new(DIRECT_REPLY_TO_QUEUE, "");
+        args.no_ack = true;
+        args
+    }
     impl_chainable_setter! {
         /// Chainable setter method.
         queue, String
@@ -155,6 +283,85 @@ impl BasicConsumeArguments {
         arguments, FieldTable
     }
 
+    /// Set consumer priority by `x-priority` argument, a consumer with higher priority
+    /// receives deliveries before the ones with lower priority.
+    ///
+    /// See [RabbitMQ consumer priorities](https://www.rabbitmq.com/consumer-priority.html).
+    pub fn priority(&mut self, priority: i32) -> &mut Self {
+        self.arguments
+            .insert("x-priority".try_into().unwrap(), FieldValue::I(priority));
+        self
+    }
+
+    /// Set where to start consuming a stream queue by `x-stream-offset` argument.
+    ///
+    /// Stream queues must be consumed with [`manual_ack`] enabled, and a prefetch count
+    /// must be set by [`basic_qos`] beforehand, otherwise consuming returns an error
+    /// without contacting the server.
+    ///
+    /// [`manual_ack`]: struct.BasicConsumeArguments.html#method.manual_ack
+    /// [`basic_qos`]: struct.Channel.html#method.basic_qos
+    pub fn stream_offset(&mut self, offset: StreamOffset) -> &mut Self {
+        self.arguments
+            .insert("x-stream-offset".try_into().unwrap(), offset.into());
+        self
+    }
+
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        panic_policy, PanicPolicy
+    }
+
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        dispatch_strategy, DispatchStrategy
+    }
+
+    /// Bound the number of deliveries buffered for the consumer task, [`None`] for unbounded.
+    ///
+    /// Once the buffer is full, the channel stops dispatching frames until the consumer
+    /// catches up. Meanwhile all frames of the channel wait, including responses to other
+    /// methods, so the consumer must not wait on a synchronous method of the same channel,
+    /// e.g. [`basic_cancel`], while its buffer may be full. Acknowledgements are fine.
+    ///
+    /// It does not apply backpressure to server: the connection keeps reading frames,
+    /// which are queued for the channel without bound. Only the prefetch count of
+    /// [`basic_qos`] bounds the number of deliveries held in memory, in manual ack mode.
+    ///
+    /// The occupancy is reported by [`Channel::consumer_buffered`].
+    /// It has no effect on [`basic_consume_rx`] and [`basic_consume_stream`].
+    ///
+    /// [`basic_cancel`]: struct.Channel.html#method.basic_cancel
+    /// [`basic_qos`]: struct.Channel.html#method.basic_qos
+    /// [`Channel::consumer_buffered`]: struct.Channel.html#method.consumer_buffered
+    /// [`basic_consume_rx`]: struct.Channel.html#method.basic_consume_rx
+    /// [`basic_consume_stream`]: struct.Channel.html#method.basic_consume_stream
+    pub fn buffer_size(&mut self, buffer_size: Option<usize>) -> &mut Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    /// Cancel the consumer when `token` is cancelled.
+    ///
+    /// The consumer is cancelled on server, and the consumer task exits after handling
+    /// all deliveries already received. It has no effect on [`basic_consume_rx`].
+    ///
+    /// [`basic_consume_rx`]: struct.Channel.html#method.basic_consume_rx
+    #[cfg(feature = "cancellation")]
+    pub fn cancellation_token(&mut self, token: CancellationToken) -> &mut Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// Take the cancellation token.
+    #[cfg(feature = "cancellation")]
+    fn take_cancellation(&mut self) -> ConsumerCancellation {
+        self.cancellation_token.take()
+    }
+
+    #[cfg(not(feature = "cancellation"))]
+    fn take_cancellation(&mut self) -> ConsumerCancellation {}
+
     /// Finish chained configuration and return new arguments.
     pub fn finish(&mut self) -> Self {
         #[cfg(feature = "compliance_assert")]
@@ -208,7 +415,7 @@ impl BasicCancelArguments {
 pub struct BasicGetArguments {
     /// Queue name. Default: "".
     pub queue: String,
-    /// Default: `false`.
+    /// Should automatic acknowledgements be used? Default: `false`.
     pub no_ack: bool,
 }
 
@@ -231,6 +438,17 @@ impl BasicGetArguments {
         /// Chainable setter method.
         no_ack, bool
     }
+    impl_chainable_alias_setter! {
+        /// Chainable setter method.
+        auto_ack, no_ack, bool
+    }
+    /// Chainable setter method, the opposite of [`auto_ack`].
+    ///
+    /// [`auto_ack`]: struct.BasicGetArguments.html#method.auto_ack
+    pub fn manual_ack(&mut self, value: bool) -> &mut Self {
+        self.no_ack = !value;
+        self
+    }
     /// Finish chained configuration and return new arguments.
     pub fn finish(&mut self) -> Self {
         #[cfg(feature = "compliance_assert")]
@@ -247,6 +465,191 @@ impl BasicGetArguments {
 /// [`Channel::basic_get`]: struct.Channel.html#method.basic_get
 pub type GetMessage = (GetOk, BasicProperties, Vec<u8>);
 
+/// Handle of a consumer started on a channel, returned by [`Channel::consumer`].
+///
+/// [`Channel::consumer`]: struct.Channel.html#method.consumer
+pub struct Consumer {
+    channel: Channel,
+    consumer_tag: String,
+}
+
+impl Consumer {
+    /// Returns the consumer tag.
+    pub fn consumer_tag(&self) -> &str {
+        &self.consumer_tag
+    }
+
+    /// Gracefully shutdown the consumer, see [`Channel::basic_cancel_graceful`].
+    ///
+    /// Returns `true` if the consumer finished handling all deliveries before `deadline`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in comunication with server.
+    ///
+    /// [`Channel::basic_cancel_graceful`]: struct.Channel.html#method.basic_cancel_graceful
+    pub async fn shutdown(self, deadline: Instant) -> Result<bool> {
+        self.channel
+            .basic_cancel_graceful(BasicCancelArguments::new(&self.consumer_tag), deadline)
+            .await
+    }
+}
+
+/// Stream of messages delivered to a consumer, returned by [`Channel::basic_consume_stream`].
+///
+/// The stream ends when the consumer is cancelled, either by [`basic_cancel`] or by server,
+/// or when the channel or connection is closed. Once ended, the reason is available
+/// by [`termination`](#method.termination).
+///
+/// If feature "stream" is enabled, it implements `futures_core::Stream`, so it composes
+/// with combinators of `futures` crate, for example, `for_each_concurrent` and `take_until`.
+///
+/// [`Channel::basic_consume_stream`]: struct.Channel.html#method.basic_consume_stream
+/// [`basic_cancel`]: struct.Channel.html#method.basic_cancel
+pub struct ConsumerStream {
+    consumer_tag: String,
+    rx: mpsc::UnboundedReceiver<ConsumerMessage>,
+    terminated_rx: oneshot::Receiver<ConsumerTermination>,
+    termination: Option<ConsumerTermination>,
+}
+
+impl ConsumerStream {
+    pub(crate) fn new(
+        consumer_tag: String,
+        rx: mpsc::UnboundedReceiver<ConsumerMessage>,
+        terminated_rx: oneshot::Receiver<ConsumerTermination>,
+    ) -> Self {
+        Self {
+            consumer_tag,
+            rx,
+            terminated_rx,
+            termination: None,
+        }
+    }
+
+    /// Returns the reason why the stream ends, [`None`] if it has not ended.
+    pub fn termination(&self) -> Option<&ConsumerTermination> {
+        self.termination.as_ref()
+    }
+
+    /// Record the termination reason once the stream ends.
+    fn on_message(&mut self, message: Option<ConsumerMessage>) -> Option<ConsumerMessage> {
+        if message.is_none() && self.termination.is_none() {
+            self.termination = Some(termination_reason(&mut self.terminated_rx));
+        }
+        message
+    }
+
+    /// Returns the consumer tag.
+    pub fn consumer_tag(&self) -> &str {
+        &self.consumer_tag
+    }
+
+    /// Receive next message, returns [`None`] if the stream ends.
+    pub async fn recv(&mut self) -> Option<ConsumerMessage> {
+        let message = self.rx.recv().await;
+        self.on_message(message)
+    }
+
+    /// Returns the consumer tag and the receiver half, same as [`basic_consume_rx`].
+    ///
+    /// [`basic_consume_rx`]: struct.Channel.html#method.basic_consume_rx
+    pub fn into_inner(self) -> (String, mpsc::UnboundedReceiver<ConsumerMessage>) {
+        (self.consumer_tag, self.rx)
+    }
+}
+
+#[cfg(feature = "stream")]
+impl futures_core::Stream for ConsumerStream {
+    type Item = ConsumerMessage;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        self.rx
+            .poll_recv(cx)
+            .map(|message| self.on_message(message))
+    }
+}
+
+/// Handle to publish messages with the same properties and arguments,
+/// returned by [`Channel::prepare_publish`].
+///
+/// The `Publish` method frame and the content header frame are encoded once when prepared,
+/// only the content body and its size are serialized per message, so repeated publishes
+/// skip encoding of exchange, routing key and properties.
+///
+/// If a [`MessageStamp`] is set on the channel, each message is stamped and encoded
+/// in full as by [`Channel::basic_publish`], so the benefit of pre-encoding is lost.
+///
+/// [`Channel::prepare_publish`]: struct.Channel.html#method.prepare_publish
+/// [`Channel::basic_publish`]: struct.Channel.html#method.basic_publish
+/// [`MessageStamp`]: struct.MessageStamp.html
+#[derive(Clone)]
+pub struct PreparedPublish {
+    channel: Channel,
+    /// properties and arguments with publish defaults applied, used if messages are stamped
+    basic_properties: BasicProperties,
+    args: BasicPublishArguments,
+    /// pre-encoded `Publish` method frame and content header frame with zero body size
+    encoded: Bytes,
+    /// `reply_to` is the Direct Reply-to pseudo-queue
+    direct_reply_to: bool,
+    #[cfg(feature = "compression")]
+    compression: Option<Compression>,
+}
+
+impl PreparedPublish {
+    /// Publish a message, same as [`basic_publish`] with the prepared properties and arguments.
+    ///
+    /// # Errors
+    ///
+    /// See [`basic_publish`].
+    ///
+    /// [`basic_publish`]: struct.Channel.html#method.basic_publish
+    pub async fn publish(&self, content: impl Into<Bytes>) -> Result<()> {
+        if self.channel.has_message_stamp() {
+            return self
+                .channel
+                .send_publish(
+                    self.basic_properties.clone(),
+                    content.into(),
+                    self.args.clone(),
+                    None,
+                )
+                .await
+                .map(|_| ());
+        }
+        if self.direct_reply_to && !self.channel.is_consuming(DIRECT_REPLY_TO_QUEUE) {
+            return Err(Error::ChannelUseError(format!(
+                "must consume from {} before publishing with it as reply_to",
+                DIRECT_REPLY_TO_QUEUE
+            )));
+        }
+        let content = content.into();
+        #[cfg(feature = "compression")]
+        let content = match self.compression {
+            Some(compression) => Bytes::from(compression.compress(&content).map_err(|err| {
+                Error::ChannelUseError(format!("failed to compress content, cause: {}", err))
+            })?),
+            None => content,
+        };
+        self.channel
+            .send_publish_frame(
+                Frame::PreparedPublishCombo(self.encoded.clone(), content),
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the channel to publish on.
+    pub fn channel(&self) -> &Channel {
+        &self.channel
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 /// Arguments for [`basic_ack`]
 ///
@@ -296,7 +699,6 @@ impl Default for BasicNackArguments {
 }
 impl BasicNackArguments {
     /// Create new arguments with defaults.
-
     pub fn new(delivery_tag: u64, multiple: bool, requeue: bool) -> Self {
         Self {
             delivery_tag,
@@ -352,26 +754,42 @@ pub struct BasicPublishArguments {
     pub mandatory: bool,
     /// Default: `false`.
     pub immediate: bool,
+    /// Compress the content and set `content_encoding` accordingly. Default: [`None`].
+    #[cfg(feature = "compression")]
+    pub compression: Option<Compression>,
+    /// Headers merged into the message properties, replacing the ones of same name.
+    /// Default: empty.
+    pub headers: FieldTable,
 }
 
 impl BasicPublishArguments {
     /// Create new arguments with defaults.
+    ///
+    /// Use the [`exchange`] and [`routing_key`] setters to move owned `String`s
+    /// into the arguments without allocation.
+    ///
+    /// [`exchange`]: #method.exchange
+    /// [`routing_key`]: #method.routing_key
     pub fn new(exchange: &str, routing_key: &str) -> Self {
+        let exchange = exchange.to_owned();
         #[cfg(feature = "compliance_assert")]
-        assert_exchange_name(exchange);
+        assert_exchange_name(&exchange);
 
         Self {
-            exchange: exchange.to_owned(),
+            exchange,
             routing_key: routing_key.to_owned(),
             mandatory: false,
             immediate: false,
+            #[cfg(feature = "compression")]
+            compression: None,
+            headers: FieldTable::new(),
         }
     }
-    impl_chainable_setter! {
+    impl_chainable_into_setter! {
         /// Chainable setter method.
         exchange, String
     }
-    impl_chainable_setter! {
+    impl_chainable_into_setter! {
         /// Chainable setter method.
         routing_key, String
     }
@@ -383,6 +801,19 @@ impl BasicPublishArguments {
         /// Chainable setter method.
         immediate, bool
     }
+    #[cfg(feature = "compression")]
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        compression, Option<Compression>
+    }
+    /// Add a header to the message, e.g. per message metadata like a request id,
+    /// without changing a shared [`BasicProperties`].
+    ///
+    /// [`BasicProperties`]: ../struct.BasicProperties.html
+    pub fn with_header(&mut self, name: FieldName, value: impl Into<FieldValue>) -> &mut Self {
+        self.headers.insert(name, value.into());
+        self
+    }
     /// Finish chained configuration and return new arguments.
     pub fn finish(&mut self) -> Self {
         #[cfg(feature = "compliance_assert")]
@@ -390,6 +821,17 @@ impl BasicPublishArguments {
 
         self.clone()
     }
+
+    /// Merge the headers into `basic_properties`.
+    pub(crate) fn apply_headers(&self, basic_properties: &mut BasicProperties) {
+        if !self.headers.is_empty() {
+            let mut headers = basic_properties.headers().cloned().unwrap_or_default();
+            for (name, value) in self.headers.iter() {
+                headers.insert(name.clone(), value.clone());
+            }
+            basic_properties.with_headers(headers);
+        }
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -397,20 +839,29 @@ impl BasicPublishArguments {
 impl Channel {
     /// See [AMQP_0-9-1 Reference](https://www.rabbitmq.com/amqp-0-9-1-reference.html#basic.qos)
     ///
+    /// The scope of the limits depends on [`BasicQosArguments::global`], check
+    /// [`ServerCapabilities::per_consumer_qos`] to know if per consumer limit is supported.
+    ///
     /// # Errors
     ///
     /// Returns error if any failure in comunication with server.
+    ///
+    /// [`ServerCapabilities::per_consumer_qos`]: ../connection/struct.ServerCapabilities.html#method.per_consumer_qos
     pub async fn basic_qos(&self, args: BasicQosArguments) -> Result<()> {
+        self.mark_stateful();
         let qos = Qos::new(args.prefetch_size, args.prefetch_count, args.global);
         let responder_rx = self.register_responder(QosOk::header()).await?;
 
         let _method = synchronous_request!(
-            self.shared.outgoing_tx,
+            self.shared,
             (self.shared.channel_id, qos.into_frame()),
             responder_rx,
             Frame::QosOk,
             Error::ChannelUseError
         )?;
+        self.shared
+            .prefetch_count
+            .store(args.prefetch_count, Ordering::Relaxed);
         Ok(())
     }
 
@@ -418,16 +869,107 @@ impl Channel {
     ///
     /// Returns the consumer tag on success.
     ///
+    /// Deliveries are dispatched to the consumer serially, use [`basic_consume_cloneable`]
+    /// for [`DispatchStrategy::Concurrent`].
+    ///
     /// # Errors
     ///
-    /// Returns an error if a failure occurs while comunicating with the server.
-    pub async fn basic_consume<F>(&self, consumer: F, args: BasicConsumeArguments) -> Result<String>
+    /// Returns an error if a failure occurs while comunicating with the server,
+    /// or if `args` requires [`DispatchStrategy::Concurrent`], or [`Error::InvalidName`]
+    /// if the queue name is longer than 255 bytes.
+    ///
+    /// [`basic_consume_cloneable`]: struct.Channel.html#method.basic_consume_cloneable
+    /// [`Error::InvalidName`]: ../error/enum.Error.html#variant.InvalidName
+    pub async fn basic_consume<F>(
+        &self,
+        consumer: F,
+        mut args: BasicConsumeArguments,
+    ) -> Result<String>
     where
         F: AsyncConsumer + Send + 'static,
     {
+        if let DispatchStrategy::Concurrent { .. } = args.dispatch_strategy {
+            return Err(Error::ChannelUseError(
+                "concurrent dispatch strategy requires a cloneable consumer".to_string(),
+            ));
+        }
+        let (no_ack, panic_policy, buffer_size, cancellation) = (
+            args.no_ack,
+            args.panic_policy,
+            args.buffer_size,
+            args.take_cancellation(),
+        );
+        let consumer_tag = self.request_basic_consume(args).await?;
+
+        let exited = self
+            .spawn_consumer(
+                consumer_tag.clone(),
+                consumer,
+                no_ack,
+                panic_policy,
+                buffer_size,
+            )
+            .await?;
+        self.cancel_consumer_on(&consumer_tag, cancellation, exited);
+
+        Ok(consumer_tag)
+    }
+
+    /// Similar as [`basic_consume`] but dispatch deliveries according to
+    /// [`BasicConsumeArguments::dispatch_strategy`].
+    ///
+    /// With [`DispatchStrategy::Concurrent`], each delivery is handled by a clone of `consumer`
+    /// in a new task spawned onto the runtime, so slow deliveries do not block others.
+    ///
+    /// Returns the consumer tag on success.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a failure occurs while comunicating with the server.
+    ///
+    /// [`basic_consume`]: struct.Channel.html#method.basic_consume
+    pub async fn basic_consume_cloneable<F>(
+        &self,
+        consumer: F,
+        mut args: BasicConsumeArguments,
+    ) -> Result<String>
+    where
+        F: AsyncConsumer + Clone + Send + 'static,
+    {
+        let (no_ack, panic_policy, dispatch_strategy, buffer_size, cancellation) = (
+            args.no_ack,
+            args.panic_policy,
+            args.dispatch_strategy,
+            args.buffer_size,
+            args.take_cancellation(),
+        );
         let consumer_tag = self.request_basic_consume(args).await?;
 
-        self.spawn_consumer(consumer_tag.clone(), consumer).await?;
+        let exited = match dispatch_strategy {
+            DispatchStrategy::Serial => {
+                self.spawn_consumer(
+                    consumer_tag.clone(),
+                    consumer,
+                    no_ack,
+                    panic_policy,
+                    buffer_size,
+                )
+                .await?
+            }
+            DispatchStrategy::Concurrent { max_in_flight } => {
+                self.spawn_concurrent_consumer(
+                    consumer_tag.clone(),
+                    consumer,
+                    no_ack,
+                    panic_policy,
+                    // the permits are acquired all at once when the consumer exits
+                    max_in_flight.clamp(1, Semaphore::MAX_PERMITS.min(u32::MAX as usize)),
+                    buffer_size,
+                )
+                .await?
+            }
+        };
+        self.cancel_consumer_on(&consumer_tag, cancellation, exited);
 
         Ok(consumer_tag)
     }
@@ -444,15 +986,34 @@ impl Channel {
     pub async fn basic_consume_blocking<F>(
         &self,
         consumer: F,
-        args: BasicConsumeArguments,
+        mut args: BasicConsumeArguments,
     ) -> Result<String>
     where
         F: BlockingConsumer + Send + 'static,
     {
+        if let DispatchStrategy::Concurrent { .. } = args.dispatch_strategy {
+            return Err(Error::ChannelUseError(
+                "concurrent dispatch strategy is not supported by blocking consumer".to_string(),
+            ));
+        }
+        let (no_ack, panic_policy, buffer_size, cancellation) = (
+            args.no_ack,
+            args.panic_policy,
+            args.buffer_size,
+            args.take_cancellation(),
+        );
         let consumer_tag = self.request_basic_consume(args).await?;
 
-        self.spawn_blocking_consumer(consumer_tag.clone(), consumer)
+        let exited = self
+            .spawn_blocking_consumer(
+                consumer_tag.clone(),
+                consumer,
+                no_ack,
+                panic_policy,
+                buffer_size,
+            )
             .await?;
+        self.cancel_consumer_on(&consumer_tag, cancellation, exited);
 
         Ok(consumer_tag)
     }
@@ -460,6 +1021,9 @@ impl Channel {
     /// Similar to [`basic_consume`] but returns the raw unbounded [`UnboundedReceiver`]
     ///
     /// Returns the consumer tag and the [`UnboundedReceiver`] on success.
+    /// Each received [`ConsumerMessage`] is one delivery, all of its fields are [`Some`].
+    /// The receiver returns [`None`] once the consumer is cancelled or its channel is closed,
+    /// use [`basic_consume_stream`] if the reason is needed.
     ///
     /// If you were to stop consuming before the channel has been closed internally,
     /// you must call [`basic_cancel`] to make sure resources are cleaned up properly.
@@ -548,8 +1112,10 @@ impl Channel {
     /// Returns an error if a failure occurs while comunicating with the server.
     ///
     /// [`basic_consume`]: struct.Channel.html#method.basic_consume
+    /// [`basic_consume_stream`]: struct.Channel.html#method.basic_consume_stream
     /// [`basic_cancel`]: struct.Channel.html#method.basic_cancel
     /// [`basic_qos`]: struct.Channel.html#method.basic_qos
+    /// [`ConsumerMessage`]: struct.ConsumerMessage.html
     /// [`UnboundedReceiver`]: https://docs.rs/tokio/latest/tokio/sync/mpsc/struct.UnboundedReceiver.html
     pub async fn basic_consume_rx(
         &self,
@@ -557,19 +1123,113 @@ impl Channel {
     ) -> Result<(String, mpsc::UnboundedReceiver<ConsumerMessage>)> {
         let consumer_tag = self.request_basic_consume(args).await?;
 
-        let (consumer_tx, consumer_rx): (
-            mpsc::UnboundedSender<ConsumerMessage>,
-            mpsc::UnboundedReceiver<ConsumerMessage>,
-        ) = mpsc::unbounded_channel();
+        let (consumer_tx, consumer_rx) = self.consumer_channel();
 
-        self.register_consumer(consumer_tag.clone(), consumer_tx)
+        self.register_consumer(consumer_tag.clone(), consumer_tx.into(), None, None)
             .await?;
 
         Ok((consumer_tag, consumer_rx))
     }
 
+    /// Start a consumer and returns its messages as a [`ConsumerStream`].
+    ///
+    /// Same as [`basic_consume_rx`], except that the stream ends once the consumer is
+    /// cancelled or the channel is closed, see [`ConsumerStream`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a failure occurs while comunicating with the server.
+    ///
+    /// [`basic_consume_rx`]: struct.Channel.html#method.basic_consume_rx
+    /// [`ConsumerStream`]: struct.ConsumerStream.html
+    pub async fn basic_consume_stream(
+        &self,
+        args: BasicConsumeArguments,
+    ) -> Result<ConsumerStream> {
+        let consumer_tag = self.request_basic_consume(args).await?;
+
+        let (consumer_tx, consumer_rx) = self.consumer_channel();
+        let (terminated_tx, terminated_rx) = oneshot::channel();
+        self.register_consumer(
+            consumer_tag.clone(),
+            consumer_tx.into(),
+            None,
+            Some(terminated_tx),
+        )
+        .await?;
+
+        Ok(ConsumerStream::new(
+            consumer_tag,
+            consumer_rx,
+            terminated_rx,
+        ))
+    }
+
+    /// Start a consumer whose messages are received with their body as a stream of
+    /// chunks, so that a very large message is not held in memory as a whole,
+    /// as long as the body is read as fast as it is received.
+    ///
+    /// Each [`ChunkedMessage`] is received once its content header arrives, and its
+    /// [`BodyStream`] yields the content body frames as they arrive, see [`BodyStream`]
+    /// for how a consumer should read it.
+    ///
+    /// Same as [`basic_consume_rx`] otherwise. Bodies of compressed messages are not
+    /// decompressed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a failure occurs while comunicating with the server.
+    ///
+    /// [`ChunkedMessage`]: struct.ChunkedMessage.html
+    /// [`BodyStream`]: struct.BodyStream.html
+    /// [`basic_consume_rx`]: struct.Channel.html#method.basic_consume_rx
+    pub async fn basic_consume_chunked(
+        &self,
+        mut args: BasicConsumeArguments,
+    ) -> Result<(String, mpsc::UnboundedReceiver<ChunkedMessage>)> {
+        if args.consumer_tag.is_empty() {
+            if let Some(consumer_tag) = self.connection.generate_consumer_tag(&args.queue) {
+                args.consumer_tag = consumer_tag;
+            }
+        }
+        let (consumer_tx, consumer_rx) = mpsc::unbounded_channel();
+        // deliveries may arrive before the consumer is registered, and such deliveries are
+        // received as a whole, so register it beforehand if possible
+        let early_tag = args.consumer_tag.clone();
+        let consumer_tx = if early_tag.is_empty() {
+            Some(consumer_tx)
+        } else {
+            self.register_consumer(early_tag.clone(), consumer_tx.into(), None, None)
+                .await?;
+            None
+        };
+
+        let consumer_tag = match self.request_basic_consume(args).await {
+            Ok(consumer_tag) => consumer_tag,
+            Err(err) => {
+                if consumer_tx.is_none() {
+                    let cmd = DeregisterContentConsumer {
+                        consumer_tag: early_tag,
+                        responder: None,
+                    };
+                    self.shared
+                        .dispatcher_mgmt_tx
+                        .send(DispatcherManagementCommand::DeregisterContentConsumer(cmd))?;
+                }
+                return Err(err);
+            }
+        };
+        if let Some(consumer_tx) = consumer_tx {
+            self.register_consumer(consumer_tag.clone(), consumer_tx.into(), None, None)
+                .await?;
+        }
+
+        Ok((consumer_tag, consumer_rx))
+    }
+
     /// Send basic consume request to server
     async fn request_basic_consume(&self, args: BasicConsumeArguments) -> Result<String> {
+        self.mark_stateful();
         let BasicConsumeArguments {
             queue,
             consumer_tag,
@@ -578,20 +1238,61 @@ impl Channel {
             exclusive,
             no_wait,
             arguments,
+            ..
         } = args;
+        check_queue_name(&queue)?;
+        if queue == DIRECT_REPLY_TO_QUEUE && !no_ack {
+            return Err(Error::ChannelUseError(format!(
+                "{} must be consumed in no-ack mode",
+                DIRECT_REPLY_TO_QUEUE
+            )));
+        }
+        if arguments
+            .get(&"x-stream-offset".try_into().unwrap())
+            .is_some()
+        {
+            if no_ack {
+                return Err(Error::ChannelUseError(
+                    "stream queue must be consumed with manual ack".to_string(),
+                ));
+            }
+            if self.shared.prefetch_count.load(Ordering::Relaxed) == 0 {
+                return Err(Error::ChannelUseError(
+                    "stream queue requires a prefetch count set by basic_qos".to_string(),
+                ));
+            }
+        }
+        let consumer_tag = if consumer_tag.is_empty() {
+            self.connection
+                .generate_consumer_tag(&queue)
+                .unwrap_or(consumer_tag)
+        } else {
+            consumer_tag
+        };
+        if consumer_tag.is_empty() && no_wait {
+            return Err(Error::ChannelUseError(
+                "server-generated consumer tag can not be used with no_wait".to_string(),
+            ));
+        }
+        let short_consumer_tag: ShortStr = consumer_tag.clone().try_into().map_err(|_| {
+            Error::ChannelUseError(format!("consumer tag too long: {}", consumer_tag))
+        })?;
+        // deliveries may arrive before the response is handled, track it beforehand if possible
+        if !consumer_tag.is_empty() {
+            self.track_consumer_queue(consumer_tag.clone(), queue.clone(), no_ack);
+        }
         let mut consume = Consume::new(
             0,
-            queue.try_into().unwrap(),
-            consumer_tag.clone().try_into().unwrap(),
+            queue.clone().try_into().unwrap(),
+            short_consumer_tag,
             arguments,
         );
         consume.set_no_local(no_local);
         consume.set_no_ack(no_ack);
         consume.set_exclusive(exclusive);
         consume.set_nowait(no_wait);
-        let consumer_tag = if args.no_wait {
+        let consumer_tag = if no_wait {
             self.shared
-                .outgoing_tx
                 .send((self.shared.channel_id, consume.into_frame()))
                 .await?;
             consumer_tag
@@ -599,7 +1300,7 @@ impl Channel {
             let responder_rx = self.register_responder(ConsumeOk::header()).await?;
 
             let method = synchronous_request!(
-                self.shared.outgoing_tx,
+                self.shared,
                 (self.shared.channel_id, consume.into_frame()),
                 responder_rx,
                 Frame::ConsumeOk,
@@ -607,24 +1308,34 @@ impl Channel {
             )?;
             method.consumer_tag.into()
         };
+        self.track_consumer_queue(consumer_tag.clone(), queue, no_ack);
         Ok(consumer_tag)
     }
 
     /// Spawn async consumer task
-    async fn spawn_consumer<F>(&self, consumer_tag: String, mut consumer: F) -> Result<()>
+    async fn spawn_consumer<F>(
+        &self,
+        consumer_tag: String,
+        mut consumer: F,
+        no_ack: bool,
+        panic_policy: PanicPolicy,
+        buffer_size: Option<usize>,
+    ) -> Result<oneshot::Receiver<()>>
     where
         F: AsyncConsumer + Send + 'static,
     {
-        let (consumer_tx, mut consumer_rx): (
-            mpsc::UnboundedSender<ConsumerMessage>,
-            mpsc::UnboundedReceiver<ConsumerMessage>,
-        ) = mpsc::unbounded_channel();
+        let (consumer_tx, mut consumer_rx) = self.new_consumer_buffer(&consumer_tag, buffer_size);
 
+        let (drained_tx, drained_rx) = oneshot::channel();
+        let (terminated_tx, mut terminated_rx) = oneshot::channel();
+        // dropped when consumer task exits
+        let (exit_tx, exit_rx) = oneshot::channel::<()>();
         let ctag = consumer_tag.clone();
         let channel = self.clone_as_secondary();
 
         // spawn consumer task
-        tokio::spawn(async move {
+        self.connection.spawn(async move {
+            let _exit_tx = exit_tx;
             #[cfg(feature = "traces")]
             trace!(
                 "starts task for async consumer {} on channel {}",
@@ -634,14 +1345,8 @@ impl Channel {
 
             loop {
                 match consumer_rx.recv().await {
-                    Some(mut msg) => {
-                        consumer
-                            .consume(
-                                &channel,
-                                msg.deliver.take().unwrap(),
-                                msg.basic_properties.take().unwrap(),
-                                msg.content.take().unwrap(),
-                            )
+                    Some(msg) => {
+                        handle_delivery(&mut consumer, &channel, &ctag, msg, no_ack, panic_policy)
                             .await;
                     }
                     None => {
@@ -651,27 +1356,123 @@ impl Channel {
                     }
                 }
             }
+            consumer
+                .on_terminated(&channel, termination_reason(&mut terminated_rx))
+                .await;
+            channel.remove_consumer_buffer(&ctag);
+            // notify all forwarded messages have been handled
+            let _ = drained_tx.send(());
         });
 
-        self.register_consumer(consumer_tag, consumer_tx).await?;
-        Ok(())
+        self.register_consumer(
+            consumer_tag,
+            consumer_tx,
+            Some(drained_rx),
+            Some(terminated_tx),
+        )
+        .await?;
+        Ok(exit_rx)
     }
 
-    /// Spawn blocking consumer task
-    async fn spawn_blocking_consumer<F>(&self, consumer_tag: String, mut consumer: F) -> Result<()>
+    /// Spawn consumer task which handles each delivery in a new task
+    async fn spawn_concurrent_consumer<F>(
+        &self,
+        consumer_tag: String,
+        mut consumer: F,
+        no_ack: bool,
+        panic_policy: PanicPolicy,
+        max_in_flight: usize,
+        buffer_size: Option<usize>,
+    ) -> Result<oneshot::Receiver<()>>
+    where
+        F: AsyncConsumer + Clone + Send + 'static,
+    {
+        let (consumer_tx, mut consumer_rx) = self.new_consumer_buffer(&consumer_tag, buffer_size);
+
+        let (drained_tx, drained_rx) = oneshot::channel();
+        let (terminated_tx, mut terminated_rx) = oneshot::channel();
+        // dropped when consumer task exits
+        let (exit_tx, exit_rx) = oneshot::channel::<()>();
+        let ctag = consumer_tag.clone();
+        let channel = self.clone_as_secondary();
+        let in_flight = Arc::new(Semaphore::new(max_in_flight));
+
+        // spawn consumer task
+        self.connection.spawn(async move {
+            let _exit_tx = exit_tx;
+            #[cfg(feature = "traces")]
+            trace!(
+                "starts task for concurrent consumer {} on channel {}",
+                ctag,
+                channel
+            );
+
+            while let Some(msg) = consumer_rx.recv().await {
+                // semaphore is never closed
+                let permit = in_flight.clone().acquire_owned().await.unwrap();
+                let mut consumer = consumer.clone();
+                let delivery_channel = channel.clone_as_secondary();
+                let ctag = ctag.clone();
+                channel.connection.spawn(async move {
+                    handle_delivery(
+                        &mut consumer,
+                        &delivery_channel,
+                        &ctag,
+                        msg,
+                        no_ack,
+                        panic_policy,
+                    )
+                    .await;
+                    drop(permit);
+                });
+            }
+            #[cfg(feature = "traces")]
+            debug!("exit task of concurrent consumer {}", ctag);
+
+            // wait for all in-flight deliveries to be handled
+            let _ = in_flight.acquire_many(max_in_flight as u32).await;
+            consumer
+                .on_terminated(&channel, termination_reason(&mut terminated_rx))
+                .await;
+            channel.remove_consumer_buffer(&ctag);
+            // notify all forwarded messages have been handled
+            let _ = drained_tx.send(());
+        });
+
+        self.register_consumer(
+            consumer_tag,
+            consumer_tx,
+            Some(drained_rx),
+            Some(terminated_tx),
+        )
+        .await?;
+        Ok(exit_rx)
+    }
+
+    /// Spawn blocking consumer task
+    async fn spawn_blocking_consumer<F>(
+        &self,
+        consumer_tag: String,
+        mut consumer: F,
+        no_ack: bool,
+        panic_policy: PanicPolicy,
+        buffer_size: Option<usize>,
+    ) -> Result<oneshot::Receiver<()>>
     where
         F: BlockingConsumer + Send + 'static,
     {
-        let (consumer_tx, mut consumer_rx): (
-            mpsc::UnboundedSender<ConsumerMessage>,
-            mpsc::UnboundedReceiver<ConsumerMessage>,
-        ) = mpsc::unbounded_channel();
+        let (consumer_tx, mut consumer_rx) = self.new_consumer_buffer(&consumer_tag, buffer_size);
 
+        let (drained_tx, drained_rx) = oneshot::channel();
+        let (terminated_tx, mut terminated_rx) = oneshot::channel();
+        // dropped when consumer task exits
+        let (exit_tx, exit_rx) = oneshot::channel::<()>();
         let ctag = consumer_tag.clone();
         let channel = self.clone_as_secondary();
 
         // spawn blocking consumer task
-        tokio::task::spawn_blocking(move || {
+        rt::spawn_blocking(move || {
+            let _exit_tx = exit_tx;
             #[cfg(feature = "traces")]
             trace!(
                 "starts task for blocking consumer {} on channel {}",
@@ -682,12 +1483,38 @@ impl Channel {
             loop {
                 match consumer_rx.blocking_recv() {
                     Some(mut msg) => {
-                        consumer.consume(
-                            &channel,
-                            msg.deliver.take().unwrap(),
-                            msg.basic_properties.take().unwrap(),
-                            msg.content.take().unwrap(),
-                        );
+                        let deliver = msg.deliver.take().unwrap();
+                        let delivery_tag = deliver.delivery_tag();
+                        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                            consumer.consume(
+                                &channel,
+                                deliver,
+                                msg.basic_properties.take().unwrap(),
+                                msg.content.take().unwrap(),
+                            )
+                        }));
+                        if let Err(payload) = result {
+                            let _reason = panic_message(payload.as_ref());
+                            #[cfg(feature = "traces")]
+                            error!(
+                                "blocking consumer {} panicked on delivery {}, cause: {}",
+                                ctag, delivery_tag, _reason
+                            );
+                            if !no_ack {
+                                let args = BasicNackArguments::new(
+                                    delivery_tag,
+                                    false,
+                                    panic_policy.requeue(),
+                                );
+                                if let Err(_err) = channel.basic_nack_blocking(args) {
+                                    #[cfg(feature = "traces")]
+                                    error!(
+                                        "failed to nack delivery {} of consumer {}, cause: {}",
+                                        delivery_tag, ctag, _err
+                                    );
+                                }
+                            }
+                        }
                     }
                     None => {
                         #[cfg(feature = "traces")]
@@ -696,22 +1523,152 @@ impl Channel {
                     }
                 }
             }
+            consumer.on_terminated(&channel, termination_reason(&mut terminated_rx));
+            channel.remove_consumer_buffer(&ctag);
+            // notify all forwarded messages have been handled
+            let _ = drained_tx.send(());
         });
 
-        self.register_consumer(consumer_tag, consumer_tx).await?;
-        Ok(())
+        self.register_consumer(
+            consumer_tag,
+            consumer_tx,
+            Some(drained_rx),
+            Some(terminated_tx),
+        )
+        .await?;
+        Ok(exit_rx)
+    }
+
+    /// Spawn a task to cancel the consumer once the cancellation token is cancelled.
+    ///
+    /// The task exits without cancelling if the consumer task exits before that.
+    #[cfg(feature = "cancellation")]
+    fn cancel_consumer_on(
+        &self,
+        consumer_tag: &str,
+        cancellation: ConsumerCancellation,
+        exited: oneshot::Receiver<()>,
+    ) {
+        let token = match cancellation {
+            Some(token) => token,
+            None => return,
+        };
+        let args = BasicCancelArguments::new(consumer_tag);
+        let channel = self.clone_as_secondary();
+        self.connection.spawn(async move {
+            tokio::select! {
+                _ = token.cancelled() => {
+                    #[cfg(feature = "traces")]
+                    debug!(
+                        "cancellation requested for consumer {} on channel {}",
+                        args.consumer_tag, channel
+                    );
+                    if let Err(_err) = channel.basic_cancel(args).await {
+                        #[cfg(feature = "traces")]
+                        error!("failed to cancel consumer on cancellation, cause: {}", _err);
+                    }
+                }
+                _ = exited => {}
+            }
+        });
+    }
+
+    #[cfg(not(feature = "cancellation"))]
+    fn cancel_consumer_on(
+        &self,
+        _consumer_tag: &str,
+        _cancellation: ConsumerCancellation,
+        _exited: oneshot::Receiver<()>,
+    ) {
+    }
+
+    /// Create the unbounded channel of deliveries for a consumer without a consumer task.
+    ///
+    /// With feature "compression", deliveries are relayed by a task which decompresses them,
+    /// so that decompression does not hold up the dispatcher of the channel.
+    fn consumer_channel(
+        &self,
+    ) -> (
+        mpsc::UnboundedSender<ConsumerMessage>,
+        mpsc::UnboundedReceiver<ConsumerMessage>,
+    ) {
+        let (consumer_tx, consumer_rx) = mpsc::unbounded_channel();
+        #[cfg(feature = "compression")]
+        let consumer_rx = {
+            let mut rx: mpsc::UnboundedReceiver<ConsumerMessage> = consumer_rx;
+            let (tx, consumer_rx) = mpsc::unbounded_channel();
+            let limit = self.connection.max_message_size();
+            self.connection.spawn(async move {
+                while let Some(mut message) = rx.recv().await {
+                    message.decompress(limit);
+                    if tx.send(message).is_err() {
+                        break;
+                    }
+                }
+            });
+            consumer_rx
+        };
+        (consumer_tx, consumer_rx)
+    }
+
+    /// Create the buffer of deliveries for a consumer task, and track its occupancy.
+    fn new_consumer_buffer(
+        &self,
+        consumer_tag: &str,
+        buffer_size: Option<usize>,
+    ) -> (ConsumerSender, ConsumerReceiver) {
+        let (consumer_tx, consumer_rx, buffered) = consumer_buffer(buffer_size);
+        #[cfg(feature = "compression")]
+        let consumer_rx = consumer_rx.decompress_limit(self.connection.max_message_size());
+        self.shared
+            .consumer_buffers
+            .lock()
+            .unwrap()
+            .insert(consumer_tag.to_owned(), buffered);
+        (consumer_tx, consumer_rx)
+    }
+
+    fn remove_consumer_buffer(&self, consumer_tag: &str) {
+        self.shared
+            .consumer_buffers
+            .lock()
+            .unwrap()
+            .remove(consumer_tag);
+    }
+
+    /// Returns number of deliveries buffered for the consumer task of `consumer_tag`,
+    /// which are received from server but not yet handled by the consumer.
+    ///
+    /// Returns [`None`] if no consumer task of `consumer_tag` is running on the channel,
+    /// e.g. consumers of [`basic_consume_rx`] and [`basic_consume_stream`].
+    /// See [`BasicConsumeArguments::buffer_size`] to bound it.
+    ///
+    /// [`basic_consume_rx`]: struct.Channel.html#method.basic_consume_rx
+    /// [`basic_consume_stream`]: struct.Channel.html#method.basic_consume_stream
+    /// [`BasicConsumeArguments::buffer_size`]: struct.BasicConsumeArguments.html#method.buffer_size
+    pub fn consumer_buffered(&self, consumer_tag: &str) -> Option<usize> {
+        self.shared
+            .consumer_buffers
+            .lock()
+            .unwrap()
+            .get(consumer_tag)
+            .map(|buffered| buffered.load(Ordering::Relaxed))
     }
 
     /// register consumer in dispatcher
     async fn register_consumer(
         &self,
         consumer_tag: String,
-        consumer_tx: mpsc::UnboundedSender<ConsumerMessage>,
+        consumer_tx: ConsumerSender,
+        drained_rx: Option<oneshot::Receiver<()>>,
+        terminated_tx: Option<oneshot::Sender<ConsumerTermination>>,
     ) -> Result<()> {
         self.shared.dispatcher_mgmt_tx.send(
             DispatcherManagementCommand::RegisterContentConsumer(RegisterContentConsumer {
                 consumer_tag,
                 consumer_tx,
+                drained_rx,
+                terminated_tx,
             }),
         )?;
         Ok(())
@@ -723,9 +1680,9 @@ impl Channel {
     ///
     /// Returns error if any failure in comunication with server.
     pub async fn basic_ack(&self, args: BasicAckArguments) -> Result<()> {
+        self.settle_unacked(args.delivery_tag, args.multiple);
         let ack = Ack::new(args.delivery_tag, args.multiple);
         self.shared
-            .outgoing_tx
             .send((self.shared.channel_id, ack.into_frame()))
             .await?;
         Ok(())
@@ -743,9 +1700,9 @@ impl Channel {
     ///
     /// [`basic_ack`]: struct.Channel.html#method.basic_ack
     pub fn basic_ack_blocking(&self, args: BasicAckArguments) -> Result<()> {
+        self.settle_unacked(args.delivery_tag, args.multiple);
         let ack = Ack::new(args.delivery_tag, args.multiple);
         self.shared
-            .outgoing_tx
             .blocking_send((self.shared.channel_id, ack.into_frame()))?;
         Ok(())
     }
@@ -756,11 +1713,11 @@ impl Channel {
     ///
     /// Returns error if any failure in comunication with server.
     pub async fn basic_nack(&self, args: BasicNackArguments) -> Result<()> {
+        self.settle_unacked(args.delivery_tag, args.multiple);
         let mut nack = Nack::new(args.delivery_tag);
         nack.set_multiple(args.multiple);
         nack.set_requeue(args.requeue);
         self.shared
-            .outgoing_tx
             .send((self.shared.channel_id, nack.into_frame()))
             .await?;
         Ok(())
@@ -778,11 +1735,11 @@ impl Channel {
     ///
     /// [`basic_nack`]: struct.Channel.html#method.basic_nack
     pub fn basic_nack_blocking(&self, args: BasicNackArguments) -> Result<()> {
+        self.settle_unacked(args.delivery_tag, args.multiple);
         let mut nack = Nack::new(args.delivery_tag);
         nack.set_multiple(args.multiple);
         nack.set_requeue(args.requeue);
         self.shared
-            .outgoing_tx
             .blocking_send((self.shared.channel_id, nack.into_frame()))?;
         Ok(())
     }
@@ -793,9 +1750,9 @@ impl Channel {
     ///
     /// Returns error if any failure in comunication with server.
     pub async fn basic_reject(&self, args: BasicRejectArguments) -> Result<()> {
+        self.settle_unacked(args.delivery_tag, false);
         let reject = Reject::new(args.delivery_tag, args.requeue);
         self.shared
-            .outgoing_tx
             .send((self.shared.channel_id, reject.into_frame()))
             .await?;
         Ok(())
@@ -807,9 +1764,9 @@ impl Channel {
     ///
     /// Returns error if any failure in comunication with server.
     pub fn basic_reject_blocking(&self, args: BasicRejectArguments) -> Result<()> {
+        self.settle_unacked(args.delivery_tag, false);
         let reject = Reject::new(args.delivery_tag, args.requeue);
         self.shared
-            .outgoing_tx
             .blocking_send((self.shared.channel_id, reject.into_frame()))?;
         Ok(())
     }
@@ -831,7 +1788,6 @@ impl Channel {
 
         let consumer_tag = if args.no_wait {
             self.shared
-                .outgoing_tx
                 .send((self.shared.channel_id, cancel.into_frame()))
                 .await?;
             consumer_tag
@@ -839,7 +1795,7 @@ impl Channel {
             let responder_rx = self.register_responder(CancelOk::header()).await?;
 
             let cancel_ok = synchronous_request!(
-                self.shared.outgoing_tx,
+                self.shared,
                 (self.shared.channel_id, cancel.into_frame()),
                 responder_rx,
                 Frame::CancelOk,
@@ -848,34 +1804,120 @@ impl Channel {
             cancel_ok.consumer_tag.into()
         };
 
+        self.untrack_consumer_queue(&consumer_tag);
         let consumer_tag2 = consumer_tag.clone();
-        let cmd = DeregisterContentConsumer { consumer_tag };
+        let cmd = DeregisterContentConsumer {
+            consumer_tag,
+            responder: None,
+        };
         self.shared
             .dispatcher_mgmt_tx
             .send(DispatcherManagementCommand::DeregisterContentConsumer(cmd))?;
         Ok(consumer_tag2)
     }
 
+    /// Returns a handle of the consumer `consumer_tag` started on this channel,
+    /// to gracefully shutdown it by [`Consumer::shutdown`].
+    ///
+    /// The handle does not keep the channel open, it is closed once this channel is dropped.
+    ///
+    /// [`Consumer::shutdown`]: struct.Consumer.html#method.shutdown
+    pub fn consumer(&self, consumer_tag: &str) -> Consumer {
+        Consumer {
+            channel: self.clone_as_secondary(),
+            consumer_tag: consumer_tag.to_owned(),
+        }
+    }
+
+    /// Gracefully shutdown a consumer.
+    ///
+    /// It sends `basic.cancel` and waits for `basic.cancel-ok`, so server stops delivering
+    /// new messages to the consumer. Then it waits until the consumer has handled all deliveries
+    /// already received from server, or until the `deadline` passes.
+    /// The consumer is expected to ack (or nack) each delivery before returning from `consume`,
+    /// so that no message is lost or redelivered to other consumers on rolling restarts.
+    ///
+    /// The `no_wait` field of `args` is ignored, because `basic.cancel-ok` is required
+    /// to know that server will not deliver more messages.
+    ///
+    /// Returns `true` if the consumer finished handling all deliveries before `deadline`,
+    /// otherwise returns `false`, and the consumer continues to handle the remaining deliveries
+    /// in background.
+    ///
+    /// For consumer started by [`basic_consume_rx`], the caller owns the receiver half, so
+    /// it returns `true` immediately after the consumer is cancelled.
+    ///
+    /// Same as [`Consumer::shutdown`] of the handle returned by [`consumer`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in comunication with server, or if the consumer tag
+    /// is longer than 255 bytes.
+    ///
+    /// [`basic_consume_rx`]: struct.Channel.html#method.basic_consume_rx
+    /// [`Consumer::shutdown`]: struct.Consumer.html#method.shutdown
+    /// [`consumer`]: struct.Channel.html#method.consumer
+    pub async fn basic_cancel_graceful(
+        &self,
+        args: BasicCancelArguments,
+        deadline: Instant,
+    ) -> Result<bool> {
+        let consumer_tag = args.consumer_tag;
+        let short_consumer_tag: ShortStr = consumer_tag.clone().try_into().map_err(|_| {
+            Error::ChannelUseError(format!("consumer tag too long: {}", consumer_tag))
+        })?;
+        let cancel = Cancel::new(short_consumer_tag, false);
+
+        let responder_rx = self.register_responder(CancelOk::header()).await?;
+        let cancel_ok = synchronous_request!(
+            self.shared,
+            (self.shared.channel_id, cancel.into_frame()),
+            responder_rx,
+            Frame::CancelOk,
+            Error::ChannelUseError
+        )?;
+
+        let consumer_tag: String = cancel_ok.consumer_tag.into();
+        self.untrack_consumer_queue(&consumer_tag);
+        let (tx, rx) = oneshot::channel();
+        let cmd = DeregisterContentConsumer {
+            consumer_tag,
+            responder: Some(tx),
+        };
+        self.shared
+            .dispatcher_mgmt_tx
+            .send(DispatcherManagementCommand::DeregisterContentConsumer(cmd))?;
+        match rx.await? {
+            // either notified or the consumer task has exited, both mean drained
+            Some(drained_rx) => Ok(rt::timeout_at(deadline, drained_rx).await.is_some()),
+            None => Ok(true),
+        }
+    }
+
     /// See [AMQP_0-9-1 Reference](https://www.rabbitmq.com/amqp-0-9-1-reference.html#basic.get)
     ///
     /// Either returns a tuple [`GetMessage`] or [`None`] if no message available.
     ///
+    /// If [`BasicGetArguments::no_ack`] is `true`, the message is considered acknowledged
+    /// once delivered, it must not be acked again.
+    ///
     /// # Errors
     ///
-    /// Returns error if any failure in comunication with server.
+    /// Returns error if any failure in comunication with server, or [`Error::InvalidName`]
+    /// if the queue name is longer than 255 bytes.
+    ///
+    /// [`Error::InvalidName`]: ../error/enum.Error.html#variant.InvalidName
     pub async fn basic_get(&self, args: BasicGetArguments) -> Result<Option<GetMessage>> {
+        check_queue_name(&args.queue)?;
         let get = Get::new(0, args.queue.try_into().unwrap(), args.no_ack);
 
+        // the dispatcher sends the request once the responder is registered, so that
+        // responses are routed in order of requests, even if called concurrently
         let (tx, mut rx) = mpsc::unbounded_channel();
-        let command = RegisterGetContentResponder { tx };
+        let command = RegisterGetContentResponder { tx, get };
         self.shared.dispatcher_mgmt_tx.send(
             DispatcherManagementCommand::RegisterGetContentResponder(command),
         )?;
-
-        self.shared
-            .outgoing_tx
-            .send((self.shared.channel_id, get.into_frame()))
-            .await?;
         let get_ok = match rx.recv().await.ok_or_else(|| {
             Error::InternalChannelError("failed to receive response to Get".to_string())
         })? {
@@ -883,6 +1925,9 @@ impl Channel {
             Frame::GetOk(_, get_ok) => get_ok,
             _ => unreachable!("expect GetOk or GetEmpty"),
         };
+        if !args.no_ack {
+            self.track_unacked(get_ok.delivery_tag());
+        }
 
         let basic_properties = match rx.recv().await.ok_or_else(|| {
             Error::InternalChannelError("failed to receive Get ContentHeader".to_string())
@@ -897,9 +1942,50 @@ impl Channel {
             Frame::ContentBody(content) => content.inner,
             _ => unreachable!("expect ContentBody"),
         };
+        #[cfg(feature = "compression")]
+        let (basic_properties, content) = {
+            let (mut basic_properties, mut content) = (basic_properties, content);
+            decompress_message(
+                &mut basic_properties,
+                &mut content,
+                self.connection.max_message_size(),
+            );
+            (basic_properties, content)
+        };
         Ok(Some((get_ok, basic_properties, content)))
     }
 
+    /// Drain messages from a queue by repeating [`basic_get`], until the queue is empty,
+    /// `max` messages are received, or the `deadline` passes.
+    ///
+    /// The deadline is checked before each `basic.get`, a request in flight is always completed,
+    /// so that no message is lost.
+    ///
+    /// Returns the messages received in order, which are empty if none is available.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in comunication with server.
+    /// Messages received before the failure are unacked and requeued by server
+    /// once the channel is closed, unless [`BasicGetArguments::no_ack`] is `true`.
+    ///
+    /// [`basic_get`]: struct.Channel.html#method.basic_get
+    pub async fn basic_get_many(
+        &self,
+        args: BasicGetArguments,
+        max: usize,
+        deadline: Instant,
+    ) -> Result<Vec<GetMessage>> {
+        let mut messages = Vec::new();
+        while messages.len() < max && Instant::now() < deadline {
+            match self.basic_get(args.clone()).await? {
+                Some(message) => messages.push(message),
+                None => break,
+            }
+        }
+        Ok(messages)
+    }
+
     /// See [AMQP_0-9-1 Reference](https://www.rabbitmq.com/amqp-0-9-1-reference.html#basic.recover)
     ///
     /// # Errors
@@ -911,83 +1997,734 @@ impl Channel {
         let responder_rx = self.register_responder(RecoverOk::header()).await?;
 
         let _method = synchronous_request!(
-            self.shared.outgoing_tx,
+            self.shared,
             (self.shared.channel_id, recover.into_frame()),
             responder_rx,
             Frame::RecoverOk,
             Error::ChannelUseError
         )?;
+        // all unacked deliveries are redelivered with new delivery tags
+        self.shared.unacked.lock().unwrap().clear();
         Ok(())
     }
 
+    /// Returns the number of deliveries received in manual ack mode on this channel,
+    /// which are not acked, nacked or rejected yet.
+    ///
+    /// RabbitMQ closes the channel if a delivery is not acked within the
+    /// [delivery acknowledgement timeout](https://www.rabbitmq.com/consumers.html#acknowledgement-timeout),
+    /// a growing count usually indicates a consumer which forgets to ack.
+    pub fn unacked_count(&self) -> usize {
+        self.shared.unacked.lock().unwrap().len()
+    }
+
+    /// Returns how long the oldest unacked delivery on this channel has waited for ack,
+    /// [`None`] if there is no unacked delivery. See [`unacked_count`].
+    ///
+    /// [`unacked_count`]: struct.Channel.html#method.unacked_count
+    pub fn oldest_unacked_age(&self) -> Option<Duration> {
+        self.shared
+            .unacked
+            .lock()
+            .unwrap()
+            .values()
+            .map(|delivery| delivery.received_at.elapsed())
+            .max()
+    }
+
+    /// Set the age of an unacked delivery, which triggers
+    /// [`ChannelCallback::ack_deadline_approaching`] once for the delivery when reached.
+    /// [`Duration::ZERO`] disables it.
+    ///
+    /// Set it below the server's `consumer_timeout`, so that a stuck consumer is noticed
+    /// before server closes the channel. Ages are checked about every second.
+    ///
+    /// # Default
+    ///
+    /// [`Duration::ZERO`], disabled.
+    ///
+    /// [`ChannelCallback::ack_deadline_approaching`]: ../callbacks/trait.ChannelCallback.html#method.ack_deadline_approaching
+    pub fn set_ack_deadline_warning(&self, deadline: Duration) {
+        self.shared
+            .ack_deadline_warning
+            .store(deadline.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Returns the ack deadline warning, [`None`] if disabled.
+    pub(crate) fn ack_deadline_warning(&self) -> Option<Duration> {
+        match self.shared.ack_deadline_warning.load(Ordering::Relaxed) {
+            0 => None,
+            millis => Some(Duration::from_millis(millis)),
+        }
+    }
+
+    /// Returns delivery tags and ages of unacked deliveries which just reach `deadline`,
+    /// each delivery is returned only once.
+    pub(crate) fn unacked_past_deadline(&self, deadline: Duration) -> Vec<(u64, Duration)> {
+        let mut unacked = self.shared.unacked.lock().unwrap();
+        let mut past = vec![];
+        for (delivery_tag, delivery) in unacked.iter_mut() {
+            let age = delivery.received_at.elapsed();
+            if !delivery.warned && age >= deadline {
+                delivery.warned = true;
+                past.push((*delivery_tag, age));
+            }
+        }
+        past
+    }
+
+    /// Set the number of unacked deliveries, which triggers
+    /// [`ChannelCallback::unacked_threshold_exceeded`] when reached. `0` disables it.
+    ///
+    /// # Default
+    ///
+    /// `0`, disabled.
+    ///
+    /// [`ChannelCallback::unacked_threshold_exceeded`]: ../callbacks/trait.ChannelCallback.html#method.unacked_threshold_exceeded
+    pub fn set_unacked_warning_threshold(&self, threshold: usize) {
+        self.shared
+            .unacked_warning_threshold
+            .store(threshold, Ordering::Relaxed);
+    }
+
+    /// Track an unacked delivery.
+    ///
+    /// Returns the number of unacked deliveries if it just reaches the warning threshold.
+    pub(crate) fn track_unacked(&self, delivery_tag: u64) -> Option<usize> {
+        let mut unacked = self.shared.unacked.lock().unwrap();
+        unacked.insert(
+            delivery_tag,
+            UnackedDelivery {
+                received_at: Instant::now(),
+                warned: false,
+            },
+        );
+        let threshold = self
+            .shared
+            .unacked_warning_threshold
+            .load(Ordering::Relaxed);
+        if threshold > 0 && unacked.len() == threshold {
+            Some(threshold)
+        } else {
+            None
+        }
+    }
+
+    /// Forget deliveries settled by ack, nack or reject.
+    fn settle_unacked(&self, delivery_tag: u64, multiple: bool) {
+        let mut unacked = self.shared.unacked.lock().unwrap();
+        if multiple {
+            // delivery tag `0` with `multiple` settles all outstanding deliveries
+            if delivery_tag == 0 {
+                unacked.clear();
+            } else {
+                *unacked = unacked.split_off(&(delivery_tag + 1));
+            }
+        } else {
+            unacked.remove(&delivery_tag);
+        }
+    }
+
     /// See [AMQP_0-9-1 Reference](https://www.rabbitmq.com/amqp-0-9-1-reference.html#basic.publish)
     ///
+    /// `content` can be any type convertible to [`Bytes`], e.g. `Vec<u8>`, `String`,
+    /// `&'static [u8]` or a clone of [`Bytes`], which avoids copying the content.
+    ///
     /// # Errors
     ///
     /// Returns error in case of a network I/O failure. For data safety, use
     /// [publisher confirms](https://rabbitmq.com/publishers.html#data-safety).
+    ///
+    /// Returns [`Error::ChannelUseError`] without sending anything if `content` is larger
+    /// than [`Connection::max_message_size`], or [`Error::InvalidName`] if the exchange name
+    /// is longer than 255 bytes.
+    ///
+    /// [`Bytes`]: https://docs.rs/bytes/latest/bytes/struct.Bytes.html
+    /// [`Error::ChannelUseError`]: ../error/enum.Error.html#variant.ChannelUseError
+    /// [`Error::InvalidName`]: ../error/enum.Error.html#variant.InvalidName
+    /// [`Connection::max_message_size`]: ../connection/struct.Connection.html#method.max_message_size
     pub async fn basic_publish(
         &self,
         basic_properties: BasicProperties,
-        content: Vec<u8>,
+        content: impl Into<Bytes>,
         args: BasicPublishArguments,
     ) -> Result<()> {
-        let mut publish = Publish::new(
-            0,
-            args.exchange.try_into().unwrap(),
-            args.routing_key.try_into().unwrap(),
-        );
-        publish.set_mandatory(args.mandatory);
-        publish.set_immediate(args.immediate);
-
-        let content_header = ContentHeader::new(
-            ContentHeaderCommon {
-                class: 60, // basic class
-                weight: 0,
-                body_size: content.len() as u64,
-            },
-            basic_properties,
-        );
+        self.send_publish(basic_properties, content.into(), args, None)
+            .await
+            .map(|_| ())
+    }
 
-        let publish_combo =
-            Frame::PublishCombo(publish, Box::new(content_header), ContentBody::new(content));
-        self.shared
-            .outgoing_tx
-            .send((self.shared.channel_id, publish_combo))
+    /// Same as [`basic_publish`], but the content of `body_size` bytes is read from `reader`
+    /// and sent in body frames incrementally, so that a very large message is not buffered
+    /// as a whole. Reading waits if the connection's outgoing buffer is full.
+    ///
+    /// Other frames of the channel wait until the message is sent, including other
+    /// messages, acknowledgements, requests of other methods and responses to server.
+    /// So `reader` must not depend on the progress of the same channel, e.g. on a body
+    /// received by [`basic_consume_chunked`] on it.
+    ///
+    /// Compression by [`BasicPublishArguments::compression`] is not supported.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ChannelUseError`] if `body_size` exceeds [`Connection::max_message_size`],
+    /// or if fail to read from `reader`. In the latter case, the message is incomplete, and
+    /// the channel is marked closed, because server would close the connection once another
+    /// frame is sent on the channel. Its channel id is not reused until the connection is closed.
+    /// The same applies if the returned future is dropped before the message is sent.
+    ///
+    /// Returns error in case of a network I/O failure.
+    ///
+    /// [`basic_publish`]: struct.Channel.html#method.basic_publish
+    /// [`basic_consume_chunked`]: struct.Channel.html#method.basic_consume_chunked
+    /// [`BasicPublishArguments::compression`]: struct.BasicPublishArguments.html#method.compression
+    /// [`Error::ChannelUseError`]: ../error/enum.Error.html#variant.ChannelUseError
+    /// [`Connection::max_message_size`]: ../connection/struct.Connection.html#method.max_message_size
+    pub async fn basic_publish_stream<R>(
+        &self,
+        mut basic_properties: BasicProperties,
+        body_size: u64,
+        reader: R,
+        mut args: BasicPublishArguments,
+    ) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+    {
+        self.prepare_message(&mut basic_properties, &mut args)?;
+        #[cfg(feature = "compression")]
+        if args.compression.is_some() {
+            return Err(Error::ChannelUseError(
+                "compression is not supported by streaming publish".to_string(),
+            ));
+        }
+        self.check_message_size(body_size)?;
+        self.acquire_rate_limit(body_size as usize).await;
+        let mut chunks = self.body_chunks(reader, body_size);
+        let (publish, content_header) = build_publish(basic_properties, args, body_size);
+
+        let channel_id = self.shared.channel_id;
+        let shared = &self.shared;
+        // no other frame of the channel is sent amid the body frames
+        let mut publish_seq = self.shared.publish_seq.lock().await;
+        shared
+            .send_locked(&publish_seq, (channel_id, publish.into_frame()))
+            .await?;
+        // server expects the rest of the message from now on
+        let mut incomplete = IncompleteMessageGuard(Some(self));
+        shared
+            .send_locked(&publish_seq, (channel_id, content_header.into_frame()))
             .await?;
+        loop {
+            let chunk = match chunks.next_chunk().await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
+                Err(err) => {
+                    let text = format!("failed to read message body, cause: {}", err);
+                    self.set_closed(State::Closed {
+                        initiator: CloseInitiator::Client,
+                        code: 0,
+                        text: text.clone(),
+                    });
+                    return Err(Error::ChannelUseError(text));
+                }
+            };
+            shared
+                .send_locked(
+                    &publish_seq,
+                    (channel_id, ContentBody::new(chunk.to_vec()).into_frame()),
+                )
+                .await?;
+        }
+        incomplete.0 = None;
+        if self.is_confirm_mode() {
+            *publish_seq += 1;
+        }
         Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::callbacks::{DefaultChannelCallback, DefaultConnectionCallback};
-    use crate::test_utils::setup_logging;
-    use crate::{
-        api::{
-            channel::{QueueBindArguments, QueueDeclareArguments},
-            connection::{Connection, OpenConnectionArguments},
-            consumer::DefaultConsumer,
-        },
-        frame::BasicProperties,
-        DELIVERY_MODE_TRANSIENT,
-    };
-    use tokio::time;
+    /// Prepare to publish messages with the same `basic_properties` and `args` repeatedly,
+    /// which avoids encoding them for each message.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use amqprs::{BasicProperties, channel::{BasicPublishArguments, Channel}};
+    /// # async fn publish(channel: &Channel) {
+    /// let prepared = channel
+    ///     .prepare_publish(
+    ///         BasicProperties::default(),
+    ///         BasicPublishArguments::new("amq.topic", "metrics"),
+    ///     )
+    ///     .unwrap();
+    /// for i in 0..1000 {
+    ///     prepared.publish(i.to_string()).await.unwrap();
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns error if fail to encode the properties and arguments, or [`Error::InvalidName`]
+    /// if the exchange name is longer than 255 bytes.
+    ///
+    /// [`Error::InvalidName`]: ../error/enum.Error.html#variant.InvalidName
+    pub fn prepare_publish(
+        &self,
+        mut basic_properties: BasicProperties,
+        mut args: BasicPublishArguments,
+    ) -> Result<PreparedPublish> {
+        args.apply_headers(&mut basic_properties);
+        self.apply_publish_defaults(&mut basic_properties, &mut args);
+        check_exchange_name(&args.exchange)?;
+        let direct_reply_to =
+            basic_properties.reply_to().map(String::as_str) == Some(DIRECT_REPLY_TO_QUEUE);
+        #[cfg(feature = "compression")]
+        let compression = args.compression;
+        let (publish, content_header) = build_publish(basic_properties.clone(), args.clone(), 0);
 
-    use super::{BasicConsumeArguments, BasicPublishArguments, BasicQosArguments};
+        let mut encoded = BytesMut::new();
+        serialize_frame(&mut encoded, self.shared.channel_id, &publish.into_frame())?;
+        serialize_frame(
+            &mut encoded,
+            self.shared.channel_id,
+            &content_header.into_frame(),
+        )?;
+        Ok(PreparedPublish {
+            channel: self.clone(),
+            basic_properties,
+            args,
+            encoded: encoded.freeze(),
+            direct_reply_to,
+            #[cfg(feature = "compression")]
+            compression,
+        })
+    }
 
-    #[tokio::test(flavor = "multi_thread", worker_threads = 5)]
-    async fn test_basic_consume_auto_ack() {
-        setup_logging();
+    /// Same as [`basic_publish`], but gives up if the message can not be handed over to
+    /// the connection within `timeout`.
+    ///
+    /// If the channel is in publisher confirm mode (see [`confirm_select`]), it also waits
+    /// for the server to confirm the message within the same `timeout`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::TimeoutError`] if `timeout` elapses. Note that the message may still
+    /// be delivered after timeout, the publish is not revoked.
+    ///
+    /// Returns [`Error::PublishNacked`] if the message is nacked by server in confirm mode.
+    ///
+    /// Returns error in case of a network I/O failure.
+    ///
+    /// [`basic_publish`]: struct.Channel.html#method.basic_publish
+    /// [`confirm_select`]: struct.Channel.html#method.confirm_select
+    pub async fn basic_publish_timeout(
+        &self,
+        basic_properties: BasicProperties,
+        content: impl Into<Bytes>,
+        args: BasicPublishArguments,
+        timeout: Duration,
+    ) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let publish = async {
+            let (responder, responder_rx) = oneshot::channel();
+            let delivery_tag = self
+                .send_publish(
+                    basic_properties,
+                    content.into(),
+                    args,
+                    Some(ConfirmResponder::Confirmation(responder)),
+                )
+                .await?;
+            match delivery_tag {
+                // not in confirm mode
+                None => Ok(()),
+                Some(delivery_tag) => match responder_rx.await? {
+                    Confirmation::Ack => Ok(()),
+                    Confirmation::Nack => Err(Error::PublishNacked(format!(
+                        "message {} nacked by server on channel {}",
+                        delivery_tag, self
+                    ))),
+                },
+            }
+        };
+        match rt::timeout_at(deadline, publish).await {
+            Some(result) => result,
+            None => Err(Error::TimeoutError(format!(
+                "publish not completed within {:?} on channel {}",
+                timeout, self
+            ))),
+        }
+    }
 
-        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
-            .connection_name("test_basic_consume_auto_ack")
-            .finish();
-        let connection = Connection::open(&args).await.unwrap();
+    /// Publish a message and wait for server to confirm it.
+    ///
+    /// The channel must be in publisher confirm mode, see [`confirm_select`].
+    ///
+    /// Returns [`Confirmation::Nack`] if server nacks the message, for example, failed
+    /// to persist it. Server may nack multiple messages at once, each of the publishes
+    /// covered is resolved as nacked.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ChannelUseError`] if the channel is not in publisher confirm mode.
+    ///
+    /// Returns error in case of a network I/O failure.
+    ///
+    /// [`confirm_select`]: struct.Channel.html#method.confirm_select
+    pub async fn basic_publish_confirm(
+        &self,
+        basic_properties: BasicProperties,
+        content: impl Into<Bytes>,
+        args: BasicPublishArguments,
+    ) -> Result<Confirmation> {
+        if !self.is_confirm_mode() {
+            return Err(Error::ChannelUseError(format!(
+                "channel {} is not in publisher confirm mode",
+                self
+            )));
+        }
+        let (responder, responder_rx) = oneshot::channel();
+        self.send_publish(
+            basic_properties,
+            content.into(),
+            args,
+            Some(ConfirmResponder::Confirmation(responder)),
+        )
+        .await?;
+        Ok(responder_rx.await?)
+    }
 
-        {
-            let channel = connection.open_channel(None).await.unwrap();
-            let (queue_name, ..) = channel
+    /// Publish a message in publisher confirm mode, and track it with an opaque `token`,
+    /// e.g. the id of a business record, which is handed back with the outcome of the message.
+    ///
+    /// If the message is published with [`BasicPublishArguments::mandatory`] and server
+    /// returns it as unroutable, the outcome is [`PublishOutcome::Returned`]. To correlate
+    /// the return, the message is published with header [`PUBLISH_TAG_HEADER`], which
+    /// consumers of the message see if it is routed. The return is also passed to
+    /// [`ChannelCallback::publish_return`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use amqprs::{BasicProperties, channel::{BasicPublishArguments, Channel}};
+    /// # async fn publish(channel: &Channel, order_id: u64) {
+    /// let tracked = channel
+    ///     .basic_publish_tracked(
+    ///         BasicProperties::default(),
+    ///         b"order".to_vec(),
+    ///         BasicPublishArguments::new("orders", "created").mandatory(true).finish(),
+    ///         order_id,
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    /// let (order_id, outcome) = tracked.outcome().await.unwrap();
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ChannelUseError`] if the channel is not in publisher confirm mode.
+    ///
+    /// Returns error in case of a network I/O failure.
+    ///
+    /// [`PublishOutcome::Returned`]: enum.PublishOutcome.html#variant.Returned
+    /// [`PUBLISH_TAG_HEADER`]: constant.PUBLISH_TAG_HEADER.html
+    /// [`ChannelCallback::publish_return`]: ../callbacks/trait.ChannelCallback.html#tymethod.publish_return
+    pub async fn basic_publish_tracked<T>(
+        &self,
+        basic_properties: BasicProperties,
+        content: impl Into<Bytes>,
+        args: BasicPublishArguments,
+        token: T,
+    ) -> Result<TrackedPublish<T>> {
+        if !self.is_confirm_mode() {
+            return Err(Error::ChannelUseError(format!(
+                "channel {} is not in publisher confirm mode",
+                self
+            )));
+        }
+        let (responder, responder_rx) = oneshot::channel();
+        let delivery_tag = self
+            .send_publish(
+                basic_properties,
+                content.into(),
+                args,
+                Some(ConfirmResponder::Outcome(responder)),
+            )
+            .await?
+            .ok_or_else(|| {
+                Error::ChannelUseError(format!("channel {} is not in publisher confirm mode", self))
+            })?;
+        Ok(TrackedPublish::new(token, delivery_tag, responder_rx))
+    }
+
+    /// Send publish frames to connection.
+    ///
+    /// In publisher confirm mode, the sequence number of the message is tracked and
+    /// returned, `confirm_responder` will be resolved when server confirms the message.
+    async fn send_publish(
+        &self,
+        mut basic_properties: BasicProperties,
+        content: Bytes,
+        mut args: BasicPublishArguments,
+        confirm_responder: Option<ConfirmResponder>,
+    ) -> Result<Option<u64>> {
+        self.prepare_message(&mut basic_properties, &mut args)?;
+        #[cfg(feature = "compression")]
+        let content = match args.compression {
+            Some(compression) => Bytes::from(compression.compress(&content).map_err(|err| {
+                Error::ChannelUseError(format!("failed to compress content, cause: {}", err))
+            })?),
+            None => content,
+        };
+
+        let (publish, content_header) = build_publish(basic_properties, args, content.len() as u64);
+        let publish_combo = Frame::PublishCombo(publish, Box::new(content_header), content);
+        self.send_publish_frame(publish_combo, confirm_responder)
+            .await
+    }
+
+    /// Apply publish defaults and message stamp to a message to publish,
+    /// and check that it can be published.
+    fn prepare_message(
+        &self,
+        basic_properties: &mut BasicProperties,
+        args: &mut BasicPublishArguments,
+    ) -> Result<()> {
+        args.apply_headers(basic_properties);
+        self.apply_publish_defaults(basic_properties, args);
+        check_exchange_name(&args.exchange)?;
+        self.stamp_message(basic_properties);
+        // server closes the channel if there is no Direct Reply-to consumer
+        if basic_properties.reply_to().map(String::as_str) == Some(DIRECT_REPLY_TO_QUEUE)
+            && !self.is_consuming(DIRECT_REPLY_TO_QUEUE)
+        {
+            return Err(Error::ChannelUseError(format!(
+                "must consume from {} before publishing with it as reply_to",
+                DIRECT_REPLY_TO_QUEUE
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns error if a message of `content_size` bytes exceeds the max message size.
+    fn check_message_size(&self, content_size: u64) -> Result<()> {
+        let max_message_size = self.connection.max_message_size();
+        if content_size > max_message_size as u64 {
+            return Err(Error::ChannelUseError(format!(
+                "message of {} bytes exceeds max message size {}",
+                content_size, max_message_size
+            )));
+        }
+        Ok(())
+    }
+
+    /// Send a `PublishCombo` or `PreparedPublishCombo` frame to connection,
+    /// see [`send_publish`](#method.send_publish).
+    pub(crate) async fn send_publish_frame(
+        &self,
+        mut publish_combo: Frame,
+        confirm_responder: Option<ConfirmResponder>,
+    ) -> Result<Option<u64>> {
+        let content_size = match &publish_combo {
+            Frame::PublishCombo(_, _, content) | Frame::PreparedPublishCombo(_, content) => {
+                content.len()
+            }
+            _ => 0,
+        };
+        self.check_message_size(content_size as u64)?;
+        self.acquire_rate_limit(content_size).await;
+
+        // hold the lock until the message is sent, so that server's sequence number
+        // of messages matches the local one, and no message is sent amid a streamed one
+        let mut publish_seq = self.shared.publish_seq.lock().await;
+        if !self.is_confirm_mode() {
+            self.shared
+                .send_locked(&publish_seq, (self.shared.channel_id, publish_combo))
+                .await?;
+            return Ok(None);
+        }
+
+        let delivery_tag = *publish_seq;
+        if let Some(responder) = confirm_responder {
+            // tag the tracked message to correlate its return
+            if let (ConfirmResponder::Outcome(_), Frame::PublishCombo(publish, content_header, _)) =
+                (&responder, &mut publish_combo)
+            {
+                if publish.mandatory() {
+                    tag_publish(&mut content_header.basic_properties, delivery_tag);
+                }
+            }
+            self.shared.dispatcher_mgmt_tx.send(
+                DispatcherManagementCommand::RegisterConfirmWaiter(RegisterConfirmWaiter {
+                    delivery_tag,
+                    responder,
+                }),
+            )?;
+        }
+        self.shared
+            .send_locked(&publish_seq, (self.shared.channel_id, publish_combo))
+            .await?;
+        *publish_seq += 1;
+        Ok(Some(delivery_tag))
+    }
+}
+
+/// Marks the channel closed when dropped amid a streamed message, i.e. the message is
+/// incomplete because it fails or is cancelled. Server would close the connection once
+/// another frame is sent on the channel, so the channel is not usable.
+struct IncompleteMessageGuard<'a>(Option<&'a Channel>);
+
+impl Drop for IncompleteMessageGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(channel) = self.0 {
+            channel.set_closed(State::Closed {
+                initiator: CloseInitiator::Client,
+                code: 0,
+                text: "streamed message is incomplete".to_string(),
+            });
+        }
+    }
+}
+
+/// Returns the reason of consumer termination, once the consumer's tx half is dropped.
+fn termination_reason(
+    terminated_rx: &mut oneshot::Receiver<ConsumerTermination>,
+) -> ConsumerTermination {
+    // the reason is sent before the tx half of consumer is dropped,
+    // it is missing only if the channel's dispatcher is gone
+    terminated_rx
+        .try_recv()
+        .unwrap_or_else(|_| ConsumerTermination::ChannelClosed("dispatcher exited".to_string()))
+}
+
+/// Add [`PUBLISH_TAG_HEADER`] with `delivery_tag` to the headers of a message.
+fn tag_publish(basic_properties: &mut BasicProperties, delivery_tag: u64) {
+    let mut headers = basic_properties.headers().cloned().unwrap_or_default();
+    headers.insert(
+        PUBLISH_TAG_HEADER.try_into().unwrap(),
+        FieldValue::l(delivery_tag as i64),
+    );
+    basic_properties.with_headers(headers);
+}
+
+/// Remove [`PUBLISH_TAG_HEADER`] from a returned message, and returns the delivery tag in it.
+pub(crate) fn take_publish_tag(basic_properties: &mut BasicProperties) -> Option<u64> {
+    match basic_properties.remove_header(&PUBLISH_TAG_HEADER.try_into().unwrap()) {
+        Some(FieldValue::l(delivery_tag)) => u64::try_from(delivery_tag).ok(),
+        _ => None,
+    }
+}
+
+/// Build `Publish` method and content header of a message with `body_size`.
+fn build_publish(
+    basic_properties: BasicProperties,
+    args: BasicPublishArguments,
+    body_size: u64,
+) -> (Publish, ContentHeader) {
+    let mut publish = Publish::new(
+        0,
+        args.exchange.try_into().unwrap(),
+        args.routing_key.try_into().unwrap(),
+    );
+    publish.set_mandatory(args.mandatory);
+    publish.set_immediate(args.immediate);
+
+    #[cfg(feature = "compression")]
+    let basic_properties = match args.compression {
+        Some(compression) => {
+            let mut basic_properties = basic_properties;
+            basic_properties.with_content_encoding(compression.content_encoding());
+            basic_properties
+        }
+        None => basic_properties,
+    };
+
+    let content_header = ContentHeader::new(
+        ContentHeaderCommon {
+            class: 60, // basic class
+            weight: 0,
+            body_size,
+        },
+        basic_properties,
+    );
+    (publish, content_header)
+}
+
+/// Handle one delivery by async consumer, `nack` the delivery if the consumer panics.
+async fn handle_delivery<F>(
+    consumer: &mut F,
+    channel: &Channel,
+    _consumer_tag: &str,
+    mut msg: ConsumerMessage,
+    no_ack: bool,
+    panic_policy: PanicPolicy,
+) where
+    F: AsyncConsumer + Send,
+{
+    let deliver = msg.deliver.take().unwrap();
+    let delivery_tag = deliver.delivery_tag();
+    let result = CatchUnwind::new(consumer.consume(
+        channel,
+        deliver,
+        msg.basic_properties.take().unwrap(),
+        msg.content.take().unwrap(),
+    ))
+    .await;
+    if let Err(payload) = result {
+        let _reason = panic_message(payload.as_ref());
+        #[cfg(feature = "traces")]
+        error!(
+            "async consumer {} panicked on delivery {}, cause: {}",
+            _consumer_tag, delivery_tag, _reason
+        );
+        if !no_ack {
+            let args = BasicNackArguments::new(delivery_tag, false, panic_policy.requeue());
+            if let Err(_err) = channel.basic_nack(args).await {
+                #[cfg(feature = "traces")]
+                error!(
+                    "failed to nack delivery {} of consumer {}, cause: {}",
+                    delivery_tag, _consumer_tag, _err
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::callbacks::{DefaultChannelCallback, DefaultConnectionCallback};
+    use crate::test_utils::setup_logging;
+    use crate::{
+        api::{
+            channel::{ConfirmSelectArguments, QueueBindArguments, QueueDeclareArguments},
+            connection::{Connection, OpenConnectionArguments},
+            consumer::DefaultConsumer,
+            error::Error,
+        },
+        frame::BasicProperties,
+        DELIVERY_MODE_TRANSIENT,
+    };
+    use tokio::time;
+
+    use super::{
+        BasicCancelArguments, BasicConsumeArguments, BasicPublishArguments, BasicQosArguments,
+    };
+    use crate::api::consumer::{ConsumerTermination, DispatchStrategy};
+    use crate::FieldValue;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 5)]
+    async fn test_basic_consume_auto_ack() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_basic_consume_auto_ack")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+
+        {
+            let channel = connection.open_channel(None).await.unwrap();
+            let (queue_name, ..) = channel
                 .queue_declare(QueueDeclareArguments::default())
                 .await
                 .unwrap()
@@ -1052,6 +2789,57 @@ mod tests {
         time::sleep(time::Duration::from_secs(1)).await;
     }
 
+    #[test]
+    fn test_basic_consume_arguments() {
+        let args = BasicConsumeArguments::new("queue", "ctag")
+            .no_local(true)
+            .manual_ack(true)
+            .exclusive(true)
+            .no_wait(true)
+            .priority(10)
+            .finish();
+        assert_eq!("ctag", args.consumer_tag);
+        assert!(args.no_local && !args.no_ack && args.exclusive && args.no_wait);
+        assert!(matches!(
+            args.arguments.get(&"x-priority".try_into().unwrap()),
+            Some(FieldValue::I(10))
+        ));
+    }
+
+    #[test]
+    fn test_basic_publish_headers() {
+        let args = BasicPublishArguments::new("", "queue")
+            .with_header("x-request-id".try_into().unwrap(), "req-1")
+            .with_header("x-shared".try_into().unwrap(), "per-message")
+            .finish();
+        let shared = BasicProperties::default()
+            .with_headers({
+                let mut headers = crate::FieldTable::new();
+                headers.insert("x-shared".try_into().unwrap(), "shared".into());
+                headers.insert("x-service".try_into().unwrap(), "billing".into());
+                headers
+            })
+            .finish();
+
+        let mut basic_properties = shared.clone();
+        args.apply_headers(&mut basic_properties);
+        assert_eq!(3, basic_properties.headers().unwrap().len());
+        assert_eq!(
+            Some(&FieldValue::from("req-1")),
+            basic_properties.header("x-request-id")
+        );
+        assert_eq!(
+            Some(&FieldValue::from("per-message")),
+            basic_properties.header("x-shared")
+        );
+        assert_eq!(
+            Some(&FieldValue::from("billing")),
+            basic_properties.header("x-service")
+        );
+        // the shared properties are unchanged
+        assert_eq!(Some(&FieldValue::from("shared")), shared.header("x-shared"));
+    }
+
     #[tokio::test]
     async fn test_basic_publish() {
         setup_logging();
@@ -1081,7 +2869,17 @@ mod tests {
             ).into_bytes();
 
             channel
-                .basic_publish(basic_properties, content, args)
+                .basic_publish(basic_properties.clone(), content, args.clone())
+                .await
+                .unwrap();
+            // static and shared contents are published without copy
+            channel
+                .basic_publish(basic_properties.clone(), "static content", args.clone())
+                .await
+                .unwrap();
+            let shared = bytes::Bytes::from_static(b"shared content");
+            channel
+                .basic_publish(basic_properties, shared.clone(), args)
                 .await
                 .unwrap();
             time::sleep(time::Duration::from_secs(1)).await;
@@ -1132,6 +2930,18 @@ mod tests {
             .basic_qos(BasicQosArguments::new(0, 100, false))
             .await
             .unwrap();
+        assert!(connection
+            .server_properties()
+            .capabilities()
+            .per_consumer_qos());
+        channel
+            .basic_qos(BasicQosArguments::per_consumer(10))
+            .await
+            .unwrap();
+        channel
+            .basic_qos(BasicQosArguments::per_channel(50))
+            .await
+            .unwrap();
 
         // try recover
         channel.basic_recover(true).await.unwrap();
@@ -1139,4 +2949,1149 @@ mod tests {
         channel.close().await.unwrap();
         connection.close().await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_basic_cancel_graceful() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_basic_cancel_graceful")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let args = BasicConsumeArguments::new(&queue_name, "test_basic_cancel_graceful");
+        let consumer_tag = channel
+            .basic_consume(DefaultConsumer::new(args.no_ack), args)
+            .await
+            .unwrap();
+
+        for _ in 0..10 {
+            channel
+                .basic_publish(
+                    BasicProperties::default(),
+                    b"graceful".to_vec(),
+                    BasicPublishArguments::new("", &queue_name),
+                )
+                .await
+                .unwrap();
+        }
+
+        let deadline = std::time::Instant::now() + time::Duration::from_secs(5);
+        let consumer = channel.consumer(&consumer_tag);
+        assert!(consumer.shutdown(deadline).await.unwrap());
+
+        // too long consumer tag is an error, not a panic
+        let res = channel
+            .basic_cancel_graceful(BasicCancelArguments::new(&"t".repeat(256)), deadline)
+            .await;
+        assert!(matches!(res, Err(Error::ChannelUseError(_))));
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_basic_consume_concurrent() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_basic_consume_concurrent")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let args = BasicConsumeArguments::new(&queue_name, "test_basic_consume_concurrent")
+            .dispatch_strategy(DispatchStrategy::Concurrent { max_in_flight: 4 })
+            .finish();
+        // not supported by non-cloneable consumer
+        assert!(channel
+            .basic_consume(DefaultConsumer::new(args.no_ack), args.clone())
+            .await
+            .is_err());
+
+        let consumer_tag = channel
+            .basic_consume_cloneable(DefaultConsumer::new(args.no_ack), args)
+            .await
+            .unwrap();
+
+        for _ in 0..10 {
+            channel
+                .basic_publish(
+                    BasicProperties::default(),
+                    b"concurrent".to_vec(),
+                    BasicPublishArguments::new("", &queue_name),
+                )
+                .await
+                .unwrap();
+        }
+
+        let deadline = std::time::Instant::now() + time::Duration::from_secs(5);
+        let drained = channel
+            .basic_cancel_graceful(BasicCancelArguments::new(&consumer_tag), deadline)
+            .await
+            .unwrap();
+        assert!(drained);
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_basic_publish_timeout() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_basic_publish_timeout")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+
+        // not in confirm mode, only wait for the message to be sent
+        channel
+            .basic_publish_timeout(
+                BasicProperties::default(),
+                b"no confirm".to_vec(),
+                BasicPublishArguments::new("", &queue_name),
+                time::Duration::from_secs(5),
+            )
+            .await
+            .unwrap();
+
+        // in confirm mode, wait for server to ack
+        channel
+            .confirm_select(ConfirmSelectArguments::default())
+            .await
+            .unwrap();
+        for _ in 0..3 {
+            channel
+                .basic_publish_timeout(
+                    BasicProperties::default(),
+                    b"confirm".to_vec(),
+                    BasicPublishArguments::new("", &queue_name),
+                    time::Duration::from_secs(5),
+                )
+                .await
+                .unwrap();
+        }
+
+        // server can not confirm in zero time
+        let result = channel
+            .basic_publish_timeout(
+                BasicProperties::default(),
+                b"timeout".to_vec(),
+                BasicPublishArguments::new("", &queue_name),
+                time::Duration::from_secs(0),
+            )
+            .await;
+        assert!(matches!(result, Err(Error::TimeoutError(_))));
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_direct_reply_to() {
+        use super::{BasicGetArguments, DIRECT_REPLY_TO_QUEUE};
+
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_direct_reply_to")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let requester = connection.open_channel(None).await.unwrap();
+        let responder = connection.open_channel(None).await.unwrap();
+
+        let (queue_name, ..) = responder
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+        let request_properties = BasicProperties::default()
+            .with_reply_to(DIRECT_REPLY_TO_QUEUE)
+            .finish();
+
+        // rejected without channel error
+        let result = requester
+            .basic_publish(
+                request_properties.clone(),
+                b"request".to_vec(),
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .await;
+        assert!(matches!(result, Err(Error::ChannelUseError(_))));
+        let result = requester
+            .basic_consume_rx(
+                BasicConsumeArguments::direct_reply_to()
+                    .manual_ack(true)
+                    .finish(),
+            )
+            .await;
+        assert!(matches!(result, Err(Error::ChannelUseError(_))));
+
+        let (_, mut replies) = requester
+            .basic_consume_rx(BasicConsumeArguments::direct_reply_to())
+            .await
+            .unwrap();
+        requester
+            .basic_publish(
+                request_properties,
+                b"request".to_vec(),
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .await
+            .unwrap();
+
+        time::sleep(time::Duration::from_millis(100)).await;
+        let (_, basic_properties, _) = responder
+            .basic_get(BasicGetArguments::new(&queue_name).auto_ack(true).finish())
+            .await
+            .unwrap()
+            .unwrap();
+        // server replaces the pseudo-queue name by a routable one
+        let reply_to = basic_properties.reply_to().unwrap().clone();
+        assert!(reply_to.starts_with(DIRECT_REPLY_TO_QUEUE));
+        responder
+            .basic_publish(
+                BasicProperties::default(),
+                b"reply".to_vec(),
+                BasicPublishArguments::new("", &reply_to),
+            )
+            .await
+            .unwrap();
+
+        let reply = time::timeout(time::Duration::from_secs(1), replies.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(b"reply".to_vec(), reply.content.unwrap());
+
+        requester.close().await.unwrap();
+        responder.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_basic_consume_stream() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_basic_consume_stream")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+        channel
+            .basic_publish(
+                BasicProperties::default(),
+                b"stream".to_vec(),
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .await
+            .unwrap();
+
+        let mut messages = channel
+            .basic_consume_stream(
+                BasicConsumeArguments::new(&queue_name, "")
+                    .auto_ack(true)
+                    .finish(),
+            )
+            .await
+            .unwrap();
+        let message = time::timeout(time::Duration::from_secs(1), messages.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(b"stream".to_vec(), message.content.unwrap());
+
+        // stream ends after consumer is cancelled
+        channel
+            .basic_cancel(BasicCancelArguments::new(messages.consumer_tag()))
+            .await
+            .unwrap();
+        let end = time::timeout(time::Duration::from_secs(1), messages.recv())
+            .await
+            .unwrap();
+        assert!(end.is_none());
+        assert_eq!(
+            Some(&ConsumerTermination::Cancelled),
+            messages.termination()
+        );
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[cfg(feature = "stream")]
+    #[tokio::test]
+    async fn test_consumer_stream_combinators() {
+        use futures_util::StreamExt;
+
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_consumer_stream_combinators")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+        for _ in 0..5 {
+            channel
+                .basic_publish(
+                    BasicProperties::default(),
+                    b"stream".to_vec(),
+                    BasicPublishArguments::new("", &queue_name),
+                )
+                .await
+                .unwrap();
+        }
+
+        let messages = channel
+            .basic_consume_stream(
+                BasicConsumeArguments::new(&queue_name, "")
+                    .auto_ack(true)
+                    .finish(),
+            )
+            .await
+            .unwrap();
+        let consumer_tag = messages.consumer_tag().to_owned();
+        let contents: Vec<Vec<u8>> = messages
+            .take(5)
+            .map(|message| message.content.unwrap())
+            .collect()
+            .await;
+        assert_eq!(vec![b"stream".to_vec(); 5], contents);
+
+        channel
+            .basic_cancel(BasicCancelArguments::new(&consumer_tag))
+            .await
+            .unwrap();
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[cfg(feature = "cancellation")]
+    #[tokio::test]
+    async fn test_cancellation_token() {
+        use tokio_util::sync::CancellationToken;
+
+        setup_logging();
+
+        let token = CancellationToken::new();
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_cancellation_token")
+            .cancellation_token(token.child_token())
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+
+        // consumer is cancelled by its own token
+        let consumer_token = CancellationToken::new();
+        let args = BasicConsumeArguments::new(&queue_name, "test_cancellation_token")
+            .cancellation_token(consumer_token.clone())
+            .finish();
+        channel
+            .basic_consume(DefaultConsumer::new(args.no_ack), args)
+            .await
+            .unwrap();
+        consumer_token.cancel();
+        time::sleep(time::Duration::from_millis(100)).await;
+        let (_, _, consumer_count) = channel
+            .queue_declare(
+                QueueDeclareArguments::new(&queue_name)
+                    .passive(true)
+                    .finish(),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(0, consumer_count);
+
+        // connection is closed by application-wide token
+        token.cancel();
+        time::sleep(time::Duration::from_millis(100)).await;
+        assert!(!connection.is_open());
+        assert!(!channel.is_connection_open());
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn test_basic_publish_compression() {
+        use super::BasicGetArguments;
+        use crate::api::compression::Compression;
+
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_basic_publish_compression")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let content = b"compressed content, compressed content".to_vec();
+        channel
+            .basic_publish(
+                BasicProperties::default(),
+                content.clone(),
+                BasicPublishArguments::new("", &queue_name)
+                    .compression(Some(Compression::Zstd))
+                    .finish(),
+            )
+            .await
+            .unwrap();
+
+        let (_, basic_properties, received) = channel
+            .basic_get(BasicGetArguments::new(&queue_name).auto_ack(true).finish())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(content, received);
+        assert_eq!(None, basic_properties.content_encoding());
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unacked_count() {
+        use super::{BasicAckArguments, BasicGetArguments};
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_unacked_count")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+        for _ in 0..3 {
+            channel
+                .basic_publish(
+                    BasicProperties::default(),
+                    b"unacked".to_vec(),
+                    BasicPublishArguments::new("", &queue_name),
+                )
+                .await
+                .unwrap();
+        }
+        time::sleep(time::Duration::from_millis(100)).await;
+
+        let mut delivery_tags = vec![];
+        for _ in 0..3 {
+            let (get_ok, ..) = channel
+                .basic_get(BasicGetArguments::new(&queue_name))
+                .await
+                .unwrap()
+                .unwrap();
+            delivery_tags.push(get_ok.delivery_tag());
+        }
+        assert_eq!(3, channel.unacked_count());
+
+        channel
+            .basic_ack(BasicAckArguments::new(delivery_tags[0], false))
+            .await
+            .unwrap();
+        assert_eq!(2, channel.unacked_count());
+        channel
+            .basic_ack(BasicAckArguments::new(delivery_tags[2], true))
+            .await
+            .unwrap();
+        assert_eq!(0, channel.unacked_count());
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_basic_get_many() {
+        use super::BasicGetArguments;
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_basic_get_many")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+        for i in 0..5u8 {
+            channel
+                .basic_publish(
+                    BasicProperties::default(),
+                    vec![i],
+                    BasicPublishArguments::new("", &queue_name),
+                )
+                .await
+                .unwrap();
+        }
+        time::sleep(time::Duration::from_millis(100)).await;
+
+        let get_args = BasicGetArguments::new(&queue_name).no_ack(true).finish();
+        let deadline = std::time::Instant::now() + time::Duration::from_secs(5);
+        let messages = channel
+            .basic_get_many(get_args.clone(), 3, deadline)
+            .await
+            .unwrap();
+        let contents: Vec<_> = messages.into_iter().map(|(_, _, body)| body).collect();
+        assert_eq!(vec![vec![0], vec![1], vec![2]], contents);
+
+        // stops once the queue is empty
+        let messages = channel
+            .basic_get_many(get_args.clone(), 10, deadline)
+            .await
+            .unwrap();
+        assert_eq!(2, messages.len());
+        assert_eq!(0, messages[1].0.message_count());
+
+        // nothing is requested once the deadline passes
+        channel
+            .basic_publish(
+                BasicProperties::default(),
+                vec![5],
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .await
+            .unwrap();
+        let messages = channel
+            .basic_get_many(get_args, 10, std::time::Instant::now())
+            .await
+            .unwrap();
+        assert!(messages.is_empty());
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_basic_get_concurrent() {
+        use super::BasicGetArguments;
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_basic_get_concurrent")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        // each getter gets from its own queue
+        let mut queues = Vec::new();
+        for i in 0..4u8 {
+            let (queue_name, ..) = channel
+                .queue_declare(QueueDeclareArguments::default())
+                .await
+                .unwrap()
+                .unwrap();
+            for _ in 0..10 {
+                channel
+                    .basic_publish(
+                        BasicProperties::default(),
+                        vec![i],
+                        BasicPublishArguments::new("", &queue_name),
+                    )
+                    .await
+                    .unwrap();
+            }
+            queues.push(queue_name);
+        }
+        time::sleep(time::Duration::from_millis(100)).await;
+
+        let getters: Vec<_> = queues
+            .iter()
+            .enumerate()
+            .map(|(i, queue_name)| {
+                let channel = channel.clone();
+                let get_args = BasicGetArguments::new(queue_name).no_ack(true).finish();
+                tokio::spawn(async move {
+                    let mut received = 0;
+                    while let Some((_, _, content)) =
+                        channel.basic_get(get_args.clone()).await.unwrap()
+                    {
+                        assert_eq!(vec![i as u8], content);
+                        received += 1;
+                    }
+                    received
+                })
+            })
+            .collect();
+        for getter in getters {
+            assert_eq!(10, getter.await.unwrap());
+        }
+
+        // response to a cancelled get is not forwarded to the next one
+        channel
+            .basic_publish(
+                BasicProperties::default(),
+                vec![0],
+                BasicPublishArguments::new("", &queues[0]),
+            )
+            .await
+            .unwrap();
+        time::sleep(time::Duration::from_millis(100)).await;
+        let get_args = BasicGetArguments::new(&queues[0]).no_ack(true).finish();
+        assert!(
+            time::timeout(time::Duration::ZERO, channel.basic_get(get_args))
+                .await
+                .is_err()
+        );
+        let get_args = BasicGetArguments::new(&queues[1]).no_ack(true).finish();
+        assert!(channel.basic_get(get_args).await.unwrap().is_none());
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_prepared_publish() {
+        use super::BasicGetArguments;
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_prepared_publish")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+        let prepared = channel
+            .prepare_publish(
+                BasicProperties::default()
+                    .with_content_type("text/plain")
+                    .finish(),
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .unwrap();
+        let contents = ["first", "", "third message"];
+        for content in contents {
+            prepared.publish(content.as_bytes().to_vec()).await.unwrap();
+        }
+        time::sleep(time::Duration::from_millis(100)).await;
+
+        for content in contents {
+            let (_, basic_properties, body) = channel
+                .basic_get(BasicGetArguments::new(&queue_name).no_ack(true).finish())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!("text/plain", basic_properties.content_type().unwrap());
+            assert_eq!(content.as_bytes(), body);
+        }
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_consumer_on_terminated() {
+        use super::{AsyncConsumer, Channel};
+        use crate::{api::channel::QueueDeleteArguments, frame::Deliver};
+        use async_trait::async_trait;
+        use tokio::sync::mpsc;
+
+        struct TerminationConsumer(mpsc::UnboundedSender<ConsumerTermination>);
+
+        #[async_trait]
+        impl AsyncConsumer for TerminationConsumer {
+            async fn consume(
+                &mut self,
+                _channel: &Channel,
+                _deliver: Deliver,
+                _basic_properties: BasicProperties,
+                _content: Vec<u8>,
+            ) {
+            }
+
+            async fn on_terminated(&mut self, _channel: &Channel, reason: ConsumerTermination) {
+                self.0.send(reason).unwrap();
+            }
+        }
+
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_consumer_on_terminated")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+        channel
+            .register_callback(DefaultChannelCallback)
+            .await
+            .unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        // cancelled by server when its queue is deleted
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+        channel
+            .basic_consume(
+                TerminationConsumer(tx.clone()),
+                BasicConsumeArguments::new(&queue_name, "").finish(),
+            )
+            .await
+            .unwrap();
+        channel
+            .queue_delete(QueueDeleteArguments::new(&queue_name))
+            .await
+            .unwrap();
+        let reason = time::timeout(time::Duration::from_secs(1), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(ConsumerTermination::CancelledByServer, reason);
+
+        // channel closed by client
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+        channel
+            .basic_consume(
+                TerminationConsumer(tx),
+                BasicConsumeArguments::new(&queue_name, "").finish(),
+            )
+            .await
+            .unwrap();
+        channel.close().await.unwrap();
+        let reason = time::timeout(time::Duration::from_secs(1), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(reason, ConsumerTermination::ChannelClosed(_)));
+
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_basic_publish_max_message_size() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_basic_publish_max_message_size")
+            .max_message_size(16)
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        assert_eq!(16, connection.max_message_size());
+        let channel = connection.open_channel(None).await.unwrap();
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let publish = |content: Vec<u8>| {
+            channel.basic_publish(
+                BasicProperties::default(),
+                content,
+                BasicPublishArguments::new("", &queue_name),
+            )
+        };
+        publish(vec![0; 16]).await.unwrap();
+        match publish(vec![0; 17]).await {
+            Err(Error::ChannelUseError(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // the connection is still usable
+        assert!(channel.is_open());
+        publish(vec![0; 8]).await.unwrap();
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_basic_publish_stream() {
+        use super::BasicGetArguments;
+
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_basic_publish_stream")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+
+        // body spanning several frames
+        let body: Vec<u8> = (0..channel.frame_max() as usize * 3)
+            .map(|i| i as u8)
+            .collect();
+        channel
+            .basic_publish_stream(
+                BasicProperties::default(),
+                body.len() as u64,
+                &body[..],
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .await
+            .unwrap();
+        // nothing is sent if the body is too large
+        let result = channel
+            .basic_publish_stream(
+                BasicProperties::default(),
+                connection.max_message_size() as u64 + 1,
+                &body[..],
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .await;
+        assert!(matches!(result, Err(Error::ChannelUseError(_))));
+
+        time::sleep(time::Duration::from_millis(100)).await;
+        let (_, _, content) = channel
+            .basic_get(BasicGetArguments::new(&queue_name).auto_ack(true).finish())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(body, content);
+
+        // the channel is not usable once the body ends early
+        let result = channel
+            .basic_publish_stream(
+                BasicProperties::default(),
+                body.len() as u64 + 1,
+                &body[..],
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .await;
+        assert!(matches!(result, Err(Error::ChannelUseError(_))));
+        assert!(!channel.is_open());
+        assert!(matches!(
+            channel
+                .basic_publish(
+                    BasicProperties::default(),
+                    b"after".to_vec(),
+                    BasicPublishArguments::new("", &queue_name),
+                )
+                .await,
+            Err(Error::ChannelUseError(_))
+        ));
+        assert!(connection.is_open());
+
+        // the channel is not usable once the publish is cancelled amid the body
+        let channel2 = connection.open_channel(None).await.unwrap();
+        let (_writer, reader) = tokio::io::duplex(64);
+        let result = time::timeout(
+            time::Duration::from_millis(100),
+            channel2.basic_publish_stream(
+                BasicProperties::default(),
+                body.len() as u64,
+                reader,
+                BasicPublishArguments::new("", &queue_name),
+            ),
+        )
+        .await;
+        assert!(result.is_err());
+        assert!(!channel2.is_open());
+        assert!(connection.is_open());
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_no_wait_topology() {
+        use crate::channel::{
+            ExchangeDeclareArguments, ExchangeDeleteArguments, QueueDeleteArguments,
+            QueuePurgeArguments,
+        };
+
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_no_wait_topology")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let exchange_name = "amqprs.test.no_wait";
+        let queue_name = "amqprs.test.no_wait";
+        channel
+            .exchange_declare(
+                ExchangeDeclareArguments::new(exchange_name, "direct")
+                    .no_wait(true)
+                    .finish(),
+            )
+            .await
+            .unwrap();
+        let declared = channel
+            .queue_declare(
+                QueueDeclareArguments::new(queue_name)
+                    .no_wait(true)
+                    .finish(),
+            )
+            .await
+            .unwrap();
+        assert!(declared.is_none());
+        channel
+            .queue_bind(
+                QueueBindArguments::new(queue_name, exchange_name, "key")
+                    .no_wait(true)
+                    .finish(),
+            )
+            .await
+            .unwrap();
+
+        // server-generated consumer tag would be unknown
+        let args = BasicConsumeArguments::new(queue_name, "")
+            .no_wait(true)
+            .finish();
+        assert!(channel.basic_consume_rx(args).await.is_err());
+        let args = BasicConsumeArguments::new(queue_name, "test_no_wait_topology")
+            .auto_ack(true)
+            .no_wait(true)
+            .finish();
+        let (consumer_tag, mut rx) = channel.basic_consume_rx(args).await.unwrap();
+        assert_eq!("test_no_wait_topology", consumer_tag);
+
+        channel
+            .basic_publish(
+                BasicProperties::default(),
+                b"no_wait".to_vec(),
+                BasicPublishArguments::new(exchange_name, "key"),
+            )
+            .await
+            .unwrap();
+        let message = time::timeout(time::Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(b"no_wait".to_vec(), message.content.unwrap());
+
+        channel
+            .basic_cancel(BasicCancelArguments::new(&consumer_tag))
+            .await
+            .unwrap();
+        let purged = channel
+            .queue_purge(QueuePurgeArguments::new(queue_name).no_wait(true).finish())
+            .await
+            .unwrap();
+        assert!(purged.is_none());
+        let deleted = channel
+            .queue_delete(QueueDeleteArguments::new(queue_name).no_wait(true).finish())
+            .await
+            .unwrap();
+        assert!(deleted.is_none());
+        channel
+            .exchange_delete(
+                ExchangeDeleteArguments::new(exchange_name)
+                    .no_wait(true)
+                    .finish(),
+            )
+            .await
+            .unwrap();
+
+        // the queue is gone once the fire-and-forget requests are handled by server
+        let args = QueueDeclareArguments::new(queue_name)
+            .passive(true)
+            .finish();
+        assert!(channel.queue_declare(args).await.is_err());
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_basic_consume_buffer_size() {
+        use super::{AsyncConsumer, Channel};
+        use crate::frame::Deliver;
+        use async_trait::async_trait;
+        use tokio::sync::mpsc;
+
+        struct SlowConsumer(mpsc::UnboundedSender<u64>);
+
+        #[async_trait]
+        impl AsyncConsumer for SlowConsumer {
+            async fn consume(
+                &mut self,
+                _channel: &Channel,
+                deliver: Deliver,
+                _basic_properties: BasicProperties,
+                _content: Vec<u8>,
+            ) {
+                time::sleep(time::Duration::from_millis(20)).await;
+                self.0.send(deliver.delivery_tag()).unwrap();
+            }
+        }
+
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_basic_consume_buffer_size")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+        for _ in 0..20 {
+            channel
+                .basic_publish(
+                    BasicProperties::default(),
+                    b"slow".to_vec(),
+                    BasicPublishArguments::new("", &queue_name),
+                )
+                .await
+                .unwrap();
+        }
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let args = BasicConsumeArguments::new(&queue_name, "test_basic_consume_buffer_size")
+            .auto_ack(true)
+            .buffer_size(Some(2))
+            .finish();
+        let consumer_tag = channel.basic_consume(SlowConsumer(tx), args).await.unwrap();
+
+        // all deliveries are handled in order, none is dropped
+        for expected in 1..=20 {
+            let buffered = channel.consumer_buffered(&consumer_tag).unwrap();
+            assert!(buffered <= 2, "buffered {} deliveries", buffered);
+            let delivery_tag = time::timeout(time::Duration::from_secs(5), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(expected, delivery_tag);
+        }
+        assert_eq!(Some(0), channel.consumer_buffered(&consumer_tag));
+        assert_eq!(None, channel.consumer_buffered("not_exist"));
+
+        channel
+            .basic_cancel(BasicCancelArguments::new(&consumer_tag))
+            .await
+            .unwrap();
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_consume_stream_queue_from_offset() {
+        use super::{BasicAckArguments, StreamOffset};
+        use crate::channel::{QueueDeleteArguments, QueueType};
+
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_consume_stream_queue_from_offset")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let queue_name = "amqprs.test.stream";
+        channel
+            .queue_declare(
+                QueueDeclareArguments::durable_client_named(queue_name)
+                    .queue_type(QueueType::Stream)
+                    .finish(),
+            )
+            .await
+            .unwrap();
+        for i in 0..3u8 {
+            channel
+                .basic_publish(
+                    BasicProperties::default(),
+                    vec![i],
+                    BasicPublishArguments::new("", queue_name),
+                )
+                .await
+                .unwrap();
+        }
+
+        // rejected by client without prefetch count or in no-ack mode
+        let args = BasicConsumeArguments::new(queue_name, "")
+            .stream_offset(StreamOffset::First)
+            .finish();
+        assert!(channel.basic_consume_rx(args.clone()).await.is_err());
+        channel
+            .basic_qos(BasicQosArguments::per_consumer(10))
+            .await
+            .unwrap();
+        let no_ack_args = BasicConsumeArguments::new(queue_name, "")
+            .stream_offset(StreamOffset::First)
+            .manual_ack(false)
+            .finish();
+        assert!(channel.basic_consume_rx(no_ack_args).await.is_err());
+
+        // consume from the first message
+        let (_ctag, mut rx) = channel.basic_consume_rx(args).await.unwrap();
+        for i in 0..3u8 {
+            let msg = time::timeout(time::Duration::from_secs(5), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(vec![i], msg.content.unwrap());
+            channel
+                .basic_ack(BasicAckArguments::new(
+                    msg.deliver.unwrap().delivery_tag(),
+                    false,
+                ))
+                .await
+                .unwrap();
+        }
+
+        channel
+            .queue_delete(QueueDeleteArguments::new(queue_name))
+            .await
+            .unwrap();
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
 }
@@ -0,0 +1,225 @@
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{Bytes, BytesMut};
+use tokio::io::{AsyncRead, ReadBuf};
+
+use super::Channel;
+use crate::frame::FRAME_HEADER_SIZE;
+
+/// Adapter which reads a message body of known size from an [`AsyncRead`] source
+/// in chunks, each of which fits the payload of one content body frame.
+///
+/// Only one chunk is held in memory at a time, so that a very large body
+/// is not materialized as a whole.
+///
+/// Create it by [`Channel::body_chunks`] to use the `frame_max` negotiated
+/// by the connection, or by [`BodyChunks::new`].
+///
+/// With feature "stream", it also implements `futures_core::Stream`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use amqprs::channel::Channel;
+/// # async fn read(channel: &Channel, body: &[u8]) -> std::io::Result<()> {
+/// // any `AsyncRead`, e.g. a file or a socket
+/// let mut chunks = channel.body_chunks(body, body.len() as u64);
+/// while let Some(chunk) = chunks.next_chunk().await? {
+///     assert!(chunk.len() <= chunks.chunk_size());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`AsyncRead`]: https://docs.rs/tokio/latest/tokio/io/trait.AsyncRead.html
+/// [`Channel::body_chunks`]: struct.Channel.html#method.body_chunks
+/// [`BodyChunks::new`]: struct.BodyChunks.html#method.new
+pub struct BodyChunks<R> {
+    reader: R,
+    /// max size of a chunk, i.e. the max payload size of a frame
+    chunk_size: usize,
+    /// number of bytes not read from `reader` yet
+    remaining: u64,
+    /// the chunk being read
+    buffer: BytesMut,
+    /// number of bytes of the chunk read so far
+    filled: usize,
+}
+
+impl<R: AsyncRead + Unpin> BodyChunks<R> {
+    /// Returns chunks of a body of `body_size` bytes read from `reader`,
+    /// sized to fit frames of `frame_max` bytes, `0` for no limit.
+    pub fn new(reader: R, body_size: u64, frame_max: u32) -> Self {
+        let frame_max = match frame_max {
+            0 => usize::MAX,
+            frame_max => frame_max as usize,
+        };
+        Self {
+            reader,
+            chunk_size: frame_max.saturating_sub(FRAME_HEADER_SIZE + 1).max(1),
+            remaining: body_size,
+            buffer: BytesMut::new(),
+            filled: 0,
+        }
+    }
+
+    /// Returns the max size of a chunk.
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Returns the number of bytes of the body which are not returned yet.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Read the next chunk, returns [`None`] once the whole body is read.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if fail to read, or [`io::ErrorKind::UnexpectedEof`] if the reader ends
+    /// before the whole body is read.
+    pub async fn next_chunk(&mut self) -> io::Result<Option<Bytes>> {
+        NextChunk(self).await
+    }
+
+    /// Poll version of [`next_chunk`].
+    ///
+    /// [`next_chunk`]: struct.BodyChunks.html#method.next_chunk
+    pub fn poll_next_chunk(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Option<Bytes>>> {
+        if self.remaining == 0 {
+            return Poll::Ready(Ok(None));
+        }
+        let chunk_len = usize::try_from(self.remaining)
+            .unwrap_or(usize::MAX)
+            .min(self.chunk_size);
+        if self.buffer.len() < chunk_len {
+            self.buffer.resize(chunk_len, 0);
+        }
+        while self.filled < chunk_len {
+            let mut read_buf = ReadBuf::new(&mut self.buffer[self.filled..chunk_len]);
+            match Pin::new(&mut self.reader).poll_read(cx, &mut read_buf) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+            let read = read_buf.filled().len();
+            if read == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("body ends with {} bytes not read", self.remaining),
+                )));
+            }
+            self.filled += read;
+        }
+        self.filled = 0;
+        self.remaining -= chunk_len as u64;
+        Poll::Ready(Ok(Some(self.buffer.split_to(chunk_len).freeze())))
+    }
+}
+
+/// Future returned by `BodyChunks::next_chunk`.
+struct NextChunk<'a, R>(&'a mut BodyChunks<R>);
+
+impl<'a, R: AsyncRead + Unpin> Future for NextChunk<'a, R> {
+    type Output = io::Result<Option<Bytes>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.poll_next_chunk(cx)
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<R: AsyncRead + Unpin> futures_core::Stream for BodyChunks<R> {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.poll_next_chunk(cx).map(Result::transpose)
+    }
+}
+
+impl Channel {
+    /// Returns the largest frame size negotiated by the connection,
+    /// see [`Connection::frame_max`].
+    ///
+    /// [`Connection::frame_max`]: ../connection/struct.Connection.html#method.frame_max
+    pub fn frame_max(&self) -> u32 {
+        self.connection.frame_max()
+    }
+
+    /// Returns chunks of a body of `body_size` bytes read from `reader`,
+    /// sized by [`frame_max`] of the channel. See [`BodyChunks`].
+    ///
+    /// [`frame_max`]: struct.Channel.html#method.frame_max
+    /// [`BodyChunks`]: struct.BodyChunks.html
+    pub fn body_chunks<R: AsyncRead + Unpin>(&self, reader: R, body_size: u64) -> BodyChunks<R> {
+        BodyChunks::new(reader, body_size, self.frame_max())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::BodyChunks;
+    use crate::{
+        connection::{Connection, OpenConnectionArguments},
+        test_utils::setup_logging,
+    };
+
+    #[tokio::test]
+    async fn test_body_chunks() {
+        let body: Vec<u8> = (0..20).collect();
+        // payload of 16 bytes frame is 8 bytes
+        let mut chunks = BodyChunks::new(&body[..], 20, 16);
+        assert_eq!(8, chunks.chunk_size());
+        let mut received = Vec::new();
+        while let Some(chunk) = chunks.next_chunk().await.unwrap() {
+            received.push(chunk);
+        }
+        assert_eq!(vec![&body[0..8], &body[8..16], &body[16..]], received);
+        assert_eq!(0, chunks.remaining());
+
+        // bytes beyond the body size are not read
+        let mut chunks = BodyChunks::new(&body[..], 4, 0);
+        let chunk = chunks.next_chunk().await.unwrap().unwrap();
+        assert_eq!(&body[..4], &chunk[..]);
+        assert!(chunks.next_chunk().await.unwrap().is_none());
+
+        // reader ends before the whole body is read
+        let mut chunks = BodyChunks::new(&body[..], 30, 16);
+        chunks.next_chunk().await.unwrap().unwrap();
+        chunks.next_chunk().await.unwrap().unwrap();
+        let err = chunks.next_chunk().await.unwrap_err();
+        assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+    }
+
+    #[tokio::test]
+    async fn test_channel_frame_max() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_channel_frame_max")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+        assert_eq!(connection.frame_max(), channel.frame_max());
+
+        let body = vec![0u8; channel.frame_max() as usize * 2];
+        let mut chunks = channel.body_chunks(&body[..], body.len() as u64);
+        let mut size = 0;
+        while let Some(chunk) = chunks.next_chunk().await.unwrap() {
+            assert!(chunk.len() + 8 <= channel.frame_max() as usize);
+            size += chunk.len();
+        }
+        assert_eq!(body.len(), size);
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+}
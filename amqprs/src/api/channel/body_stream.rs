@@ -0,0 +1,231 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use tokio::sync::mpsc;
+
+use super::ConsumerMessage;
+use crate::frame::{BasicProperties, Deliver};
+
+/// Max number of body chunks buffered for a [`BodyStream`] before the dispatcher
+/// waits for the consumer to read them.
+const BODY_STREAM_BUFFER: usize = 16;
+
+/// Message delivered to a consumer started by [`Channel::basic_consume_chunked`].
+///
+/// It is forwarded once its content header is received, its body is received
+/// as chunks by [`body`](#structfield.body).
+///
+/// [`Channel::basic_consume_chunked`]: struct.Channel.html#method.basic_consume_chunked
+pub struct ChunkedMessage {
+    pub deliver: Deliver,
+    pub basic_properties: BasicProperties,
+    pub body: BodyStream,
+}
+
+/// Body of a [`ChunkedMessage`], received as chunks of one content body frame each.
+///
+/// Only a bounded number of chunks is buffered for the stream. Once it is full,
+/// the channel does not dispatch any frame, including messages to its other consumers
+/// and responses to its other methods, until the body is read or the stream is dropped.
+/// So a consumer should read or drop the body of each message before receiving the
+/// next one, and a slow reader stalls the whole channel.
+///
+/// It does not apply backpressure to server: the connection keeps reading frames, and
+/// the rest of the body is queued for the channel without bound meanwhile. Use a
+/// prefetch count by [`Channel::basic_qos`] to bound the number of messages in flight.
+///
+/// With feature "stream", it also implements `futures_core::Stream`.
+///
+/// [`ChunkedMessage`]: struct.ChunkedMessage.html
+/// [`Channel::basic_qos`]: struct.Channel.html#method.basic_qos
+pub struct BodyStream {
+    rx: mpsc::Receiver<Bytes>,
+    /// total size given by the content header
+    body_size: u64,
+    /// number of bytes not received yet
+    remaining: u64,
+}
+
+impl BodyStream {
+    /// Returns the tx half to send chunks of a body of `body_size` bytes, and the stream.
+    pub(crate) fn new(body_size: u64) -> (mpsc::Sender<Bytes>, Self) {
+        let (tx, rx) = mpsc::channel(BODY_STREAM_BUFFER);
+        (
+            tx,
+            Self {
+                rx,
+                body_size,
+                remaining: body_size,
+            },
+        )
+    }
+
+    /// Returns the total size of the body given by the content header.
+    pub fn body_size(&self) -> u64 {
+        self.body_size
+    }
+
+    /// Returns the number of bytes of the body which are not received yet.
+    ///
+    /// If it is not `0` after the stream ends, the body is incomplete because
+    /// the channel or connection is closed.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Receive the next chunk, returns [`None`] once the whole body is received,
+    /// or if the channel is closed.
+    pub async fn next_chunk(&mut self) -> Option<Bytes> {
+        NextChunk(self).await
+    }
+
+    /// Poll version of [`next_chunk`].
+    ///
+    /// [`next_chunk`]: struct.BodyStream.html#method.next_chunk
+    pub fn poll_next_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<Bytes>> {
+        let chunk = match self.rx.poll_recv(cx) {
+            Poll::Ready(chunk) => chunk,
+            Poll::Pending => return Poll::Pending,
+        };
+        if let Some(chunk) = &chunk {
+            self.remaining = self.remaining.saturating_sub(chunk.len() as u64);
+        }
+        Poll::Ready(chunk)
+    }
+
+    /// Receive the whole body into memory.
+    pub async fn collect(mut self) -> Vec<u8> {
+        let mut content = Vec::with_capacity(self.body_size.try_into().unwrap_or(0));
+        while let Some(chunk) = self.next_chunk().await {
+            content.extend_from_slice(&chunk);
+        }
+        content
+    }
+}
+
+/// Future returned by `BodyStream::next_chunk`.
+struct NextChunk<'a>(&'a mut BodyStream);
+
+impl<'a> Future for NextChunk<'a> {
+    type Output = Option<Bytes>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.poll_next_chunk(cx)
+    }
+}
+
+#[cfg(feature = "stream")]
+impl futures_core::Stream for BodyStream {
+    type Item = Bytes;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.poll_next_chunk(cx)
+    }
+}
+
+impl From<ConsumerMessage> for ChunkedMessage {
+    /// Convert a message received as a whole, e.g. before the consumer is registered,
+    /// to a message whose body has a single chunk.
+    fn from(message: ConsumerMessage) -> Self {
+        let content = message.content.unwrap_or_default();
+        let (tx, body) = BodyStream::new(content.len() as u64);
+        if !content.is_empty() {
+            // the buffer is empty, never fails
+            tx.try_send(content.into()).unwrap();
+        }
+        Self {
+            deliver: message.deliver.unwrap(),
+            basic_properties: message.basic_properties.unwrap(),
+            body,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time;
+
+    use super::BodyStream;
+    use crate::{
+        channel::{BasicConsumeArguments, BasicPublishArguments, QueueDeclareArguments},
+        connection::{Connection, OpenConnectionArguments},
+        test_utils::setup_logging,
+        BasicProperties,
+    };
+
+    #[tokio::test]
+    async fn test_body_stream() {
+        let (tx, mut body) = BodyStream::new(6);
+        tx.send(b"abc".to_vec().into()).await.unwrap();
+        assert_eq!(&b"abc"[..], &body.next_chunk().await.unwrap()[..]);
+        assert_eq!(3, body.remaining());
+
+        // incomplete once tx half is dropped
+        drop(tx);
+        assert!(body.next_chunk().await.is_none());
+        assert_eq!(6, body.body_size());
+        assert_eq!(3, body.remaining());
+    }
+
+    #[tokio::test]
+    async fn test_basic_consume_chunked() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_basic_consume_chunked")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+
+        // body spanning several frames, and an empty body
+        let content: Vec<u8> = (0..channel.frame_max() as usize * 3)
+            .map(|i| i as u8)
+            .collect();
+        for content in [content.clone(), Vec::new()] {
+            channel
+                .basic_publish(
+                    BasicProperties::default(),
+                    content,
+                    BasicPublishArguments::new("", &queue_name),
+                )
+                .await
+                .unwrap();
+        }
+
+        let args = BasicConsumeArguments::new(&queue_name, "test_basic_consume_chunked")
+            .auto_ack(true)
+            .finish();
+        let (_, mut rx) = channel.basic_consume_chunked(args).await.unwrap();
+
+        let mut message = time::timeout(time::Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(content.len() as u64, message.body.body_size());
+        let mut received = Vec::new();
+        while let Some(chunk) = message.body.next_chunk().await {
+            assert!(chunk.len() < channel.frame_max() as usize);
+            received.extend_from_slice(&chunk);
+        }
+        assert_eq!(0, message.body.remaining());
+        assert_eq!(content, received);
+
+        let message = time::timeout(time::Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(message.body.collect().await.is_empty());
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+}
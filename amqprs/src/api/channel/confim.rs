@@ -1,8 +1,12 @@
 use crate::{
     api::error::Error,
-    frame::{Frame, Select, SelectOk},
+    frame::{Frame, Return, Select, SelectOk},
 };
 
+use std::sync::atomic::Ordering;
+
+use tokio::sync::oneshot;
+
 use super::{Channel, Result};
 
 /// Arguments for [`confirm_select`]
@@ -14,12 +18,118 @@ use super::{Channel, Result};
 pub struct ConfirmSelectArguments {
     /// Default: `false`
     pub no_wait: bool,
+    /// Number of messages nacked by server within one second, which triggers
+    /// [`ChannelCallback::publish_nack_storm`]. Default: `0`, disabled.
+    ///
+    /// [`ChannelCallback::publish_nack_storm`]: ../callbacks/trait.ChannelCallback.html#method.publish_nack_storm
+    pub nack_storm_threshold: u64,
 }
 
 impl ConfirmSelectArguments {
     /// Create new arguments with defaults.
     pub fn new(no_wait: bool) -> Self {
-        Self { no_wait }
+        Self {
+            no_wait,
+            nack_storm_threshold: 0,
+        }
+    }
+}
+
+/// Outcome of a message published in publisher confirm mode.
+///
+/// See [publisher confirms](https://www.rabbitmq.com/confirms.html#publisher-confirms).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confirmation {
+    /// Server has taken responsibility for the message.
+    Ack,
+    /// Server could not handle the message, for example, failed to persist it.
+    Nack,
+}
+
+impl Confirmation {
+    /// Returns `true` if the message is acked by server.
+    pub fn is_ack(&self) -> bool {
+        matches!(self, Confirmation::Ack)
+    }
+}
+
+/// Header added to a message published by [`basic_publish_tracked`] with
+/// [`BasicPublishArguments::mandatory`], its value is the delivery tag of the message.
+///
+/// It correlates the message returned by server with its [`TrackedPublish`], because
+/// `basic.return` does not carry the delivery tag of the message.
+///
+/// The header is part of the message on the wire, so consumers of a routed message see it.
+/// It is removed from a returned message before the return is handed to
+/// [`ChannelCallback::publish_return`] and publisher event subscribers.
+///
+/// [`basic_publish_tracked`]: struct.Channel.html#method.basic_publish_tracked
+/// [`BasicPublishArguments::mandatory`]: struct.BasicPublishArguments.html#structfield.mandatory
+/// [`ChannelCallback::publish_return`]: ../callbacks/trait.ChannelCallback.html#tymethod.publish_return
+pub const PUBLISH_TAG_HEADER: &str = "x-amqprs-publish-tag";
+
+/// Outcome of a message published by [`basic_publish_tracked`].
+///
+/// [`basic_publish_tracked`]: struct.Channel.html#method.basic_publish_tracked
+#[derive(Debug, Clone)]
+pub enum PublishOutcome {
+    /// Server has taken responsibility for the message.
+    Ack,
+    /// Server could not handle the message, for example, failed to persist it.
+    Nack,
+    /// Server returned the message as unroutable, then acked it.
+    Returned(Return),
+}
+
+impl PublishOutcome {
+    /// Returns `true` if the message is acked by server and not returned.
+    pub fn is_ack(&self) -> bool {
+        matches!(self, PublishOutcome::Ack)
+    }
+}
+
+/// Handle of a message published by [`basic_publish_tracked`], which carries
+/// the user token until the outcome of the message is resolved.
+///
+/// [`basic_publish_tracked`]: struct.Channel.html#method.basic_publish_tracked
+#[derive(Debug)]
+pub struct TrackedPublish<T> {
+    token: T,
+    delivery_tag: u64,
+    responder_rx: oneshot::Receiver<PublishOutcome>,
+}
+
+impl<T> TrackedPublish<T> {
+    pub(crate) fn new(
+        token: T,
+        delivery_tag: u64,
+        responder_rx: oneshot::Receiver<PublishOutcome>,
+    ) -> Self {
+        Self {
+            token,
+            delivery_tag,
+            responder_rx,
+        }
+    }
+
+    /// Returns the user token of the message.
+    pub fn token(&self) -> &T {
+        &self.token
+    }
+
+    /// Returns the delivery tag of the message, i.e. its sequence number in confirm mode.
+    pub fn delivery_tag(&self) -> u64 {
+        self.delivery_tag
+    }
+
+    /// Wait for server to confirm the message, returns the user token with the outcome.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the channel is closed before the message is confirmed.
+    pub async fn outcome(self) -> Result<(T, PublishOutcome)> {
+        let outcome = self.responder_rx.await?;
+        Ok((self.token, outcome))
     }
 }
 
@@ -31,25 +141,33 @@ impl Channel {
     ///
     /// Returns error if any failure in comunication with server.
     pub async fn confirm_select(&self, args: ConfirmSelectArguments) -> Result<()> {
+        self.mark_stateful();
         let select = Select::new(args.no_wait);
         if args.no_wait {
             self.shared
-                .outgoing_tx
                 .send((self.shared.channel_id, select.into_frame()))
                 .await?;
-            Ok(())
         } else {
             let responder_rx = self.register_responder(SelectOk::header()).await?;
 
             let _method = synchronous_request!(
-                self.shared.outgoing_tx,
+                self.shared,
                 (self.shared.channel_id, select.into_frame()),
                 responder_rx,
                 Frame::SelectOk,
                 Error::ChannelUseError
             )?;
-            Ok(())
         }
+        self.shared
+            .nack_storm_threshold
+            .store(args.nack_storm_threshold, Ordering::Relaxed);
+        self.shared.confirm_mode.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Returns `true` if channel is in publisher confirm mode.
+    pub fn is_confirm_mode(&self) -> bool {
+        self.shared.confirm_mode.load(Ordering::Relaxed)
     }
 }
 
@@ -67,7 +185,7 @@ mod tests {
         BasicProperties, DELIVERY_MODE_TRANSIENT,
     };
 
-    use super::ConfirmSelectArguments;
+    use super::{ConfirmSelectArguments, Confirmation, PublishOutcome};
 
     #[tokio::test]
     async fn test_publish_confirm_mode() {
@@ -110,4 +228,126 @@ mod tests {
         channel.close().await.unwrap();
         connection.close().await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_basic_publish_confirm() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami");
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let args = BasicPublishArguments::new("amq.topic", "amqprs.test.confirm");
+        // not in confirm mode
+        assert!(channel
+            .basic_publish_confirm(
+                BasicProperties::default(),
+                b"confirm".to_vec(),
+                args.clone()
+            )
+            .await
+            .is_err());
+
+        channel
+            .confirm_select(ConfirmSelectArguments {
+                nack_storm_threshold: 10,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        for _ in 0..3 {
+            let confirmation = channel
+                .basic_publish_confirm(
+                    BasicProperties::default(),
+                    b"confirm".to_vec(),
+                    args.clone(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(Confirmation::Ack, confirmation);
+        }
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_basic_publish_tracked() {
+        use crate::channel::{QueueBindArguments, QueueDeclareArguments};
+
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_basic_publish_tracked")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+        channel
+            .register_callback(DefaultChannelCallback)
+            .await
+            .unwrap();
+
+        // not in confirm mode
+        assert!(channel
+            .basic_publish_tracked(
+                BasicProperties::default(),
+                b"tracked".to_vec(),
+                BasicPublishArguments::new("amq.topic", "amqprs.test.tracked"),
+                0,
+            )
+            .await
+            .is_err());
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+        channel
+            .queue_bind(QueueBindArguments::new(
+                &queue_name,
+                "amq.topic",
+                "amqprs.test.tracked",
+            ))
+            .await
+            .unwrap();
+        channel
+            .confirm_select(ConfirmSelectArguments::default())
+            .await
+            .unwrap();
+
+        let routed = channel
+            .basic_publish_tracked(
+                BasicProperties::default(),
+                b"tracked".to_vec(),
+                BasicPublishArguments::new("amq.topic", "amqprs.test.tracked")
+                    .mandatory(true)
+                    .finish(),
+                "routed",
+            )
+            .await
+            .unwrap();
+        let unroutable = channel
+            .basic_publish_tracked(
+                BasicProperties::default(),
+                b"tracked".to_vec(),
+                BasicPublishArguments::new("amq.topic", "amqprs.test.unroutable")
+                    .mandatory(true)
+                    .finish(),
+                "unroutable",
+            )
+            .await
+            .unwrap();
+        assert_eq!(routed.delivery_tag() + 1, unroutable.delivery_tag());
+
+        let (token, outcome) = routed.outcome().await.unwrap();
+        assert_eq!("routed", token);
+        assert!(outcome.is_ack());
+        let (token, outcome) = unroutable.outcome().await.unwrap();
+        assert_eq!("unroutable", token);
+        assert!(matches!(outcome, PublishOutcome::Returned(_)));
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
 }
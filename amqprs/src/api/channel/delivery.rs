@@ -0,0 +1,292 @@
+use super::{
+    BasicAckArguments, BasicNackArguments, BasicRejectArguments, Channel, ConsumerMessage, Result,
+};
+use crate::{frame::Deliver, rt, BasicProperties};
+#[cfg(feature = "traces")]
+use tracing::{error, warn};
+
+/// Action taken if a [`Delivery`] is dropped without being acked, nacked or rejected.
+///
+/// See [`Delivery::on_drop`].
+///
+/// [`Delivery`]: struct.Delivery.html
+/// [`Delivery::on_drop`]: struct.Delivery.html#method.on_drop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// `basic.nack` the delivery with `requeue = true`, so it will be redelivered.
+    Requeue,
+    /// Only log the unsettled delivery, it stays unacked until the channel is closed.
+    Log,
+}
+
+impl Default for DropPolicy {
+    fn default() -> Self {
+        DropPolicy::Requeue
+    }
+}
+
+/// Message delivered to a consumer, which is settled when dropped.
+///
+/// If it is dropped without an explicit [`ack`], [`nack`] or [`reject`], e.g. because
+/// the handler returns early by `?`, the [`DropPolicy`] applies, which by default
+/// requeues it, so that no message is silently left unacked.
+///
+/// Nothing is done on drop if the delivery is received by a consumer in auto ack mode.
+///
+/// Create it from a [`ConsumerMessage`] received by [`Channel::basic_consume_rx`]
+/// or [`Channel::basic_consume_stream`], by [`Channel::delivery`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use amqprs::channel::{Channel, ConsumerMessage};
+/// # use amqprs::error::Error;
+/// # async fn handle(channel: &Channel, message: ConsumerMessage) -> Result<(), Error> {
+/// let delivery = channel.delivery(message);
+/// // requeued if returns early
+/// let text = std::str::from_utf8(delivery.content())
+///     .map_err(|err| Error::ChannelUseError(err.to_string()))?;
+/// println!("{}", text);
+/// delivery.ack().await
+/// # }
+/// ```
+///
+/// [`ack`]: struct.Delivery.html#method.ack
+/// [`nack`]: struct.Delivery.html#method.nack
+/// [`reject`]: struct.Delivery.html#method.reject
+/// [`DropPolicy`]: enum.DropPolicy.html
+/// [`ConsumerMessage`]: struct.ConsumerMessage.html
+/// [`Channel::basic_consume_rx`]: struct.Channel.html#method.basic_consume_rx
+/// [`Channel::basic_consume_stream`]: struct.Channel.html#method.basic_consume_stream
+/// [`Channel::delivery`]: struct.Channel.html#method.delivery
+pub struct Delivery {
+    channel: Channel,
+    deliver: Deliver,
+    basic_properties: BasicProperties,
+    content: Vec<u8>,
+    drop_policy: DropPolicy,
+    /// `true` once settled, or if the delivery needs no settlement
+    settled: bool,
+}
+
+impl Delivery {
+    /// Set the action taken if the delivery is dropped unsettled.
+    ///
+    /// # Default
+    ///
+    /// [`DropPolicy::Requeue`].
+    ///
+    /// [`DropPolicy::Requeue`]: enum.DropPolicy.html#variant.Requeue
+    pub fn on_drop(mut self, drop_policy: DropPolicy) -> Self {
+        self.drop_policy = drop_policy;
+        self
+    }
+
+    pub fn deliver(&self) -> &Deliver {
+        &self.deliver
+    }
+
+    pub fn delivery_tag(&self) -> u64 {
+        self.deliver.delivery_tag()
+    }
+
+    pub fn basic_properties(&self) -> &BasicProperties {
+        &self.basic_properties
+    }
+
+    pub fn content(&self) -> &[u8] {
+        &self.content
+    }
+
+    /// Take the content out of the delivery.
+    pub fn take_content(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.content)
+    }
+
+    /// Ack the delivery, see [`Channel::basic_ack`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in comunication with server.
+    ///
+    /// [`Channel::basic_ack`]: struct.Channel.html#method.basic_ack
+    pub async fn ack(mut self) -> Result<()> {
+        self.settled = true;
+        self.channel
+            .basic_ack(BasicAckArguments::new(self.delivery_tag(), false))
+            .await
+    }
+
+    /// Nack the delivery, see [`Channel::basic_nack`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in comunication with server.
+    ///
+    /// [`Channel::basic_nack`]: struct.Channel.html#method.basic_nack
+    pub async fn nack(mut self, requeue: bool) -> Result<()> {
+        self.settled = true;
+        self.channel
+            .basic_nack(BasicNackArguments::new(self.delivery_tag(), false, requeue))
+            .await
+    }
+
+    /// Reject the delivery, see [`Channel::basic_reject`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in comunication with server.
+    ///
+    /// [`Channel::basic_reject`]: struct.Channel.html#method.basic_reject
+    pub async fn reject(mut self, requeue: bool) -> Result<()> {
+        self.settled = true;
+        self.channel
+            .basic_reject(BasicRejectArguments::new(self.delivery_tag(), requeue))
+            .await
+    }
+}
+
+impl Drop for Delivery {
+    /// Apply the drop policy if the delivery is not settled.
+    /// Nothing is done if the channel is closed, or if dropped outside of a runtime.
+    fn drop(&mut self) {
+        if self.settled || !self.channel.is_open() {
+            return;
+        }
+        let delivery_tag = self.delivery_tag();
+        match self.drop_policy {
+            DropPolicy::Requeue => {
+                let channel = self.channel.clone();
+                let spawned = rt::try_spawn(async move {
+                    let args = BasicNackArguments::new(delivery_tag, false, true);
+                    if let Err(_err) = channel.basic_nack(args).await {
+                        #[cfg(feature = "traces")]
+                        error!(
+                            "failed to requeue dropped delivery {} on channel {}, cause: {}",
+                            delivery_tag, channel, _err
+                        );
+                    }
+                });
+                if !spawned {
+                    #[cfg(feature = "traces")]
+                    error!(
+                        "failed to requeue dropped delivery {} on channel {}, no runtime",
+                        delivery_tag, self.channel
+                    );
+                }
+            }
+            DropPolicy::Log => {
+                #[cfg(feature = "traces")]
+                warn!(
+                    "delivery {} on channel {} is dropped without ack",
+                    delivery_tag, self.channel
+                );
+            }
+        }
+    }
+}
+
+impl Channel {
+    /// Returns a [`Delivery`] of `message`, which applies a [`DropPolicy`] if dropped unsettled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `message` is not a complete delivery as received by a consumer.
+    ///
+    /// [`Delivery`]: struct.Delivery.html
+    /// [`DropPolicy`]: enum.DropPolicy.html
+    pub fn delivery(&self, message: ConsumerMessage) -> Delivery {
+        let deliver = message.deliver.expect("delivered message has deliver");
+        let settled = !self.is_manual_ack_consumer(deliver.consumer_tag());
+        Delivery {
+            // does not keep the channel open
+            channel: self.clone_as_secondary(),
+            deliver,
+            basic_properties: message
+                .basic_properties
+                .expect("delivered message has properties"),
+            content: message.content.expect("delivered message has content"),
+            drop_policy: DropPolicy::default(),
+            settled,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time;
+
+    use super::DropPolicy;
+    use crate::{
+        channel::{
+            BasicConsumeArguments, BasicPublishArguments, QueueDeclareArguments,
+            QueueDeleteArguments,
+        },
+        connection::{Connection, OpenConnectionArguments},
+        test_utils::setup_logging,
+        BasicProperties,
+    };
+
+    #[tokio::test]
+    async fn test_delivery_requeue_on_drop() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_delivery_requeue_on_drop")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+        channel
+            .basic_publish(
+                BasicProperties::default(),
+                b"requeue on drop".to_vec(),
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .await
+            .unwrap();
+
+        let args = BasicConsumeArguments::new(&queue_name, "test_delivery_requeue_on_drop")
+            .manual_ack(true)
+            .finish();
+        let (_, mut rx) = channel.basic_consume_rx(args).await.unwrap();
+        let timeout = time::Duration::from_secs(5);
+
+        // dropped unsettled, so it is redelivered
+        let message = time::timeout(timeout, rx.recv()).await.unwrap().unwrap();
+        let delivery = channel.delivery(message);
+        assert!(!delivery.deliver().redelivered());
+        drop(delivery);
+
+        let message = time::timeout(timeout, rx.recv()).await.unwrap().unwrap();
+        let delivery = channel.delivery(message);
+        assert!(delivery.deliver().redelivered());
+        assert_eq!(b"requeue on drop", delivery.content());
+        delivery.ack().await.unwrap();
+
+        // only logged
+        channel
+            .basic_publish(
+                BasicProperties::default(),
+                b"log on drop".to_vec(),
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .await
+            .unwrap();
+        let message = time::timeout(timeout, rx.recv()).await.unwrap().unwrap();
+        drop(channel.delivery(message).on_drop(DropPolicy::Log));
+        let not_redelivered = time::Duration::from_millis(500);
+        assert!(time::timeout(not_redelivered, rx.recv()).await.is_err());
+
+        channel
+            .queue_delete(QueueDeleteArguments::new(&queue_name))
+            .await
+            .unwrap();
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+}
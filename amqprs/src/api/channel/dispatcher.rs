@@ -1,22 +1,35 @@
-use std::collections::{HashMap, VecDeque};
-
-use tokio::{
-    sync::{mpsc, oneshot},
-    task::yield_now,
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::atomic::Ordering,
     time,
 };
 
+use tokio::sync::{mpsc, oneshot};
+
 use crate::{
-    api::{callbacks::ChannelCallback, channel::ReturnMessage},
+    api::{
+        callbacks::ChannelCallback,
+        channel::ReturnMessage,
+        consumer::ConsumerTermination,
+        state::{CloseInitiator, State as ChannelState},
+        Result,
+    },
     channel::GetOkMessage,
-    frame::{CancelOk, CloseChannelOk, ContentBody, FlowOk, Frame, MethodHeader},
-    net::IncomingMessage,
-    BasicProperties, Return,
+    frame::{
+        CancelOk, CloseChannel, CloseChannelOk, ContentBody, FlowOk, Frame, MethodHeader, Select,
+    },
+    net::{ConnManagementCommand, IncomingMessage},
+    rt, BasicProperties, Return,
 };
 #[cfg(feature = "traces")]
-use tracing::{debug, error, info, trace};
+use tracing::{debug, error, info, trace, warn};
+
+use super::{
+    take_publish_tag, BodyStream, Channel, ChunkedMessage, ConfirmResponder, Confirmation,
+    ConsumerMessage, ConsumerSender, DispatcherManagementCommand, PublishOutcome, PublisherEvent,
+    ReturnedMessage,
+};
 
-use super::{Channel, ConsumerMessage, DispatcherManagementCommand};
 
 /// Assumption:
 /// Depends on total number of consumers per channel, a reasonable value
@@ -30,15 +43,25 @@ const CONSUMER_PURGE_INTERVAL: time::Duration = time::Duration::from_secs(10);
 /// After consumer is canceled, all on-the-fly messages should be received within `5` seconds
 const CONSUMER_EXPIRY_PERIOD: time::Duration = time::Duration::from_secs(5);
 
+/// Interval to check ages of unacked deliveries against the ack deadline warning.
+const ACK_DEADLINE_CHECK_INTERVAL: time::Duration = time::Duration::from_secs(1);
+
+/// Window to count messages nacked by server for detecting a nack storm.
+const NACK_STORM_WINDOW: time::Duration = time::Duration::from_secs(1);
+
 /// Resource for handling consumer messages.
 struct ConsumerResource {
     /// FIFO buffer for a delivery = `deliver + content`.
     fifo: VecDeque<ConsumerMessage>,
     /// tx channel to forward a delivery to a consumer task.
     /// dispatcher task holds the tx half, and the consumer task holds the rx half.
-    tx: Option<mpsc::UnboundedSender<ConsumerMessage>>,
+    tx: Option<ConsumerSender>,
     /// expiry time of fifo buffer
     expiration: Option<time::Instant>,
+    /// resolved when the consumer task exits after all forwarded messages are handled.
+    drained_rx: Option<oneshot::Receiver<()>>,
+    /// notified with the reason once the consumer stops receiving deliveries.
+    terminated_tx: Option<oneshot::Sender<ConsumerTermination>>,
 }
 
 impl ConsumerResource {
@@ -47,19 +70,27 @@ impl ConsumerResource {
             fifo: VecDeque::new(),
             tx: None,
             expiration: Some(time::Instant::now() + CONSUMER_EXPIRY_PERIOD),
+            drained_rx: None,
+            terminated_tx: None,
         }
     }
 
-    fn register_tx(
-        &mut self,
-        tx: mpsc::UnboundedSender<ConsumerMessage>,
-    ) -> Option<mpsc::UnboundedSender<ConsumerMessage>> {
+    /// Notify the consumer of termination, the tx half is dropped, so the consumer
+    /// receives the reason after all forwarded messages.
+    fn terminate(mut self, reason: ConsumerTermination) {
+        if let Some(terminated_tx) = self.terminated_tx.take() {
+            // the consumer may have gone, ignore error
+            let _ = terminated_tx.send(reason);
+        }
+    }
+
+    fn register_tx(&mut self, tx: ConsumerSender) -> Option<ConsumerSender> {
         // once consumer's tx half is registered, clear the expiry timer
         self.expiration.take();
         self.tx.replace(tx)
     }
 
-    fn get_tx(&self) -> Option<&mpsc::UnboundedSender<ConsumerMessage>> {
+    fn get_tx(&self) -> Option<&ConsumerSender> {
         self.tx.as_ref()
     }
 
@@ -76,6 +107,62 @@ impl ConsumerResource {
     }
 }
 
+/// Counter of messages nacked by server within current window.
+struct NackStormCounter {
+    window_start: time::Instant,
+    nacked: u64,
+    /// storm is notified at most once per window
+    notified: bool,
+    /// highest delivery tag acked or nacked by server
+    last_confirmed: u64,
+}
+
+impl NackStormCounter {
+    fn new() -> Self {
+        Self {
+            window_start: time::Instant::now(),
+            nacked: 0,
+            notified: false,
+            last_confirmed: 0,
+        }
+    }
+
+    /// Record an ack or nack of `delivery_tag`, returns the number of messages it covers.
+    ///
+    /// A `multiple` confirm covers all messages since the last confirm, regardless of
+    /// whether they are waited for.
+    fn confirm(&mut self, delivery_tag: u64, multiple: bool) -> u64 {
+        let covered = if multiple {
+            delivery_tag.saturating_sub(self.last_confirmed).max(1)
+        } else {
+            1
+        };
+        self.last_confirmed = self.last_confirmed.max(delivery_tag);
+        covered
+    }
+
+    /// Count `nacked` messages, returns total number of nacked messages in current window
+    /// if it reaches `threshold` for the first time. `threshold` of `0` disables detection.
+    fn count(&mut self, nacked: u64, threshold: u64) -> Option<u64> {
+        if threshold == 0 {
+            return None;
+        }
+        let now = time::Instant::now();
+        if now.duration_since(self.window_start) >= NACK_STORM_WINDOW {
+            self.window_start = now;
+            self.nacked = 0;
+            self.notified = false;
+        }
+        self.nacked += nacked;
+        if self.nacked >= threshold && !self.notified {
+            self.notified = true;
+            Some(self.nacked)
+        } else {
+            None
+        }
+    }
+}
+
 enum State {
     Initial,
     Deliver,
@@ -94,9 +181,16 @@ pub(crate) struct ChannelDispatcher {
     dispatcher_rx: mpsc::UnboundedReceiver<IncomingMessage>,
     dispatcher_mgmt_rx: mpsc::UnboundedReceiver<DispatcherManagementCommand>,
     consumer_resources: HashMap<String, ConsumerResource>,
-    get_content_responder: Option<mpsc::UnboundedSender<IncomingMessage>>,
+    /// responders of `get` requests in flight, in order of the requests
+    get_content_responders: VecDeque<mpsc::UnboundedSender<IncomingMessage>>,
     responders: HashMap<&'static MethodHeader, oneshot::Sender<IncomingMessage>>,
     callback: Option<Box<dyn ChannelCallback + Send + 'static>>,
+    /// waiters of publisher confirms, keyed by delivery tag
+    confirm_waiters: BTreeMap<u64, ConfirmResponder>,
+    /// tracked messages returned by server and waiting for confirms, keyed by delivery tag
+    returned: HashMap<u64, Return>,
+    /// messages nacked by server in current window
+    nack_storm: NackStormCounter,
     state: State,
 }
 /////////////////////////////////////////////////////////////////////////////
@@ -111,9 +205,12 @@ impl ChannelDispatcher {
             dispatcher_rx,
             dispatcher_mgmt_rx,
             consumer_resources: HashMap::new(),
-            get_content_responder: None,
+            get_content_responders: VecDeque::new(),
             responders: HashMap::new(),
             callback: None,
+            confirm_waiters: BTreeMap::new(),
+            returned: HashMap::new(),
+            nack_storm: NackStormCounter::new(),
             state: State::Initial,
         }
     }
@@ -128,6 +225,47 @@ impl ChannelDispatcher {
         self.consumer_resources.get_mut(consumer_tag).unwrap()
     }
 
+    /// resolve waiters of publisher confirms covered by an ack or nack from server.
+    fn resolve_confirm_waiters(
+        &mut self,
+        delivery_tag: u64,
+        multiple: bool,
+        confirmation: Confirmation,
+    ) {
+        if multiple {
+            let remains = self.confirm_waiters.split_off(&(delivery_tag + 1));
+            let resolved = std::mem::replace(&mut self.confirm_waiters, remains);
+            for (delivery_tag, responder) in resolved {
+                self.resolve_confirm_waiter(delivery_tag, responder, confirmation);
+            }
+        } else if let Some(responder) = self.confirm_waiters.remove(&delivery_tag) {
+            self.resolve_confirm_waiter(delivery_tag, responder, confirmation);
+        }
+    }
+
+    fn resolve_confirm_waiter(
+        &mut self,
+        delivery_tag: u64,
+        responder: ConfirmResponder,
+        confirmation: Confirmation,
+    ) {
+        let returned = self.returned.remove(&delivery_tag);
+        // the publisher may have given up waiting, ignore error
+        match responder {
+            ConfirmResponder::Confirmation(responder) => {
+                let _ = responder.send(confirmation);
+            }
+            ConfirmResponder::Outcome(responder) => {
+                let outcome = match (confirmation, returned) {
+                    (Confirmation::Nack, _) => PublishOutcome::Nack,
+                    (Confirmation::Ack, Some(ret)) => PublishOutcome::Returned(ret),
+                    (Confirmation::Ack, None) => PublishOutcome::Ack,
+                };
+                let _ = responder.send(outcome);
+            }
+        }
+    }
+
     /// purge expired consumer resource
     fn purge_consumer_resource(&mut self) {
         // find all resources that are expired
@@ -171,7 +309,9 @@ impl ChannelDispatcher {
         let consumer = self.get_or_new_consumer_resource(&consumer_tag);
         match consumer.get_tx() {
             Some(consumer_tx) => {
-                if (consumer_tx.send(consumer_message)).is_err() {
+                // waits for room if the consumer's buffer is bounded and full,
+                // which stops reading from the channel until the consumer catches up
+                if !consumer_tx.send(consumer_message).await {
                     #[cfg(feature = "traces")]
                     error!(
                         "failed to dispatch message to consumer {} on channel {}",
@@ -185,7 +325,7 @@ impl ChannelDispatcher {
                 consumer.push_message(consumer_message);
                 // try to yield for expected consumer registration command,
                 // it might reduceas buffering
-                yield_now().await;
+                rt::yield_now().await;
             }
         };
     }
@@ -193,9 +333,23 @@ impl ChannelDispatcher {
     async fn handle_return(
         &mut self,
         ret: Return,
-        basic_properties: BasicProperties,
+        mut basic_properties: BasicProperties,
         content: Vec<u8>,
     ) {
+        // server sends the return before the ack of the message
+        if let Some(delivery_tag) = take_publish_tag(&mut basic_properties) {
+            if let Some(ConfirmResponder::Outcome(_)) = self.confirm_waiters.get(&delivery_tag) {
+                self.returned.insert(delivery_tag, ret.clone());
+            }
+        }
+        if self.channel.has_publisher_events() {
+            self.channel
+                .notify_publisher_event(PublisherEvent::Return(Box::new(ReturnedMessage {
+                    ret: ret.clone(),
+                    basic_properties: basic_properties.clone(),
+                    content: content.clone(),
+                })));
+        }
         if let Some(ref mut cb) = self.callback {
             cb.publish_return(&self.channel, ret, basic_properties, content)
                 .await;
@@ -204,9 +358,90 @@ impl ChannelDispatcher {
             error!("callback not registered on channel {}", self.channel);
         }
     }
+    /// Notify unacked deliveries which reach the ack deadline warning.
+    async fn check_ack_deadline(&mut self) {
+        let deadline = match self.channel.ack_deadline_warning() {
+            Some(deadline) => deadline,
+            None => return,
+        };
+        for (_delivery_tag, _age) in self.channel.unacked_past_deadline(deadline) {
+            if let Some(ref mut cb) = self.callback {
+                cb.ack_deadline_approaching(&self.channel, _delivery_tag, _age)
+                    .await;
+            } else {
+                #[cfg(feature = "traces")]
+                error!("callback not registered on channel {}", self.channel);
+            }
+        }
+    }
+
+    /// Drop consumers and responders of the channel once it is closed,
+    /// so that consumers stop with `termination`, and pending requests error out
+    /// instead of waiting forever.
+    fn drop_pending(&mut self, termination: ConsumerTermination) {
+        #[cfg(feature = "traces")]
+        debug!(
+            "drop {} consumers, {} responders and {} confirm waiters of closed channel {}",
+            self.consumer_resources.len(),
+            self.responders.len(),
+            self.confirm_waiters.len(),
+            self.channel
+        );
+        for (_, consumer) in self.consumer_resources.drain() {
+            consumer.terminate(termination.clone());
+        }
+        self.responders.clear();
+        self.confirm_waiters.clear();
+        self.returned.clear();
+        self.get_content_responders.clear();
+    }
+
+    /// Reopen the channel closed by server with `close_channel`.
+    ///
+    /// The pending requests fail with the reason, and other pending resources are dropped.
+    async fn reopen(&mut self, close_channel: &CloseChannel) -> Result<()> {
+        // the dispatcher holds a secondary channel, so a clone does not affect drop
+        let channel = self.channel.clone();
+        let _publish_seq = channel.reset_for_reopen().await;
+        let channel_id = channel.channel_id();
+        // the lock of the publish sequence is held, so the frames are sent directly
+        channel
+            .shared
+            .outgoing_tx
+            .send((channel_id, CloseChannelOk.into_frame()))
+            .await?;
+
+        for (_, responder) in self.responders.drain() {
+            let close = CloseChannel::new(
+                close_channel.reply_code(),
+                close_channel.reply_text().clone().try_into().unwrap(),
+            );
+            // the requester may have given up waiting, ignore error
+            let _ = responder.send(close.into_frame());
+        }
+        self.drop_pending(ConsumerTermination::ChannelClosed(format!(
+            "closed by server, {}",
+            close_channel
+        )));
+        self.state = State::Initial;
+        // delivery tags restart from 1 on the reopened channel
+        self.nack_storm.last_confirmed = 0;
+
+        channel.connection.reopen_channel(channel_id).await?;
+        if channel.is_confirm_mode() {
+            channel
+                .shared
+                .outgoing_tx
+                .send((channel_id, Select::new(true).into_frame()))
+                .await?;
+        }
+        Ok(())
+    }
+
     /// Spawn dispatcher task.
     pub(in crate::api) async fn spawn(mut self) {
-        tokio::spawn(async move {
+        let connection = self.channel.connection.clone_no_drop_guard();
+        connection.spawn(async move {
             // aggregation buffer for `deliver + content` messages to a consumer
             let mut message_buffer = ConsumerMessage {
                 deliver: None,
@@ -214,6 +449,8 @@ impl ChannelDispatcher {
                 content: None,
                 remaining: 0,
             };
+            // tx half of the body of a `deliver + content` message to a chunked consumer
+            let mut body_tx = None;
             // buffer for `return + content` messages due to publish failure.
             let mut return_buffer = ReturnMessage {
                 ret: None,
@@ -230,8 +467,10 @@ impl ChannelDispatcher {
             #[cfg(feature = "traces")]
             trace!("starts up dispatcher task of channel {}", self.channel);
 
-            let mut purge_timer = time::interval(CONSUMER_PURGE_INTERVAL);
-            purge_timer.tick().await;
+            let mut purge_deadline = time::Instant::now() + CONSUMER_PURGE_INTERVAL;
+            let mut ack_deadline_check = time::Instant::now() + ACK_DEADLINE_CHECK_INTERVAL;
+            // reason of channel close, `None` if the dispatcher exits due to connection close
+            let mut close_reason: Option<String> = None;
             // main loop of dispatcher
             loop {
                 tokio::select! {
@@ -254,27 +493,58 @@ impl ChannelDispatcher {
                                 info!("register consumer {}", cmd.consumer_tag);
                                 let consumer = self.get_or_new_consumer_resource(&cmd.consumer_tag);
                                 consumer.register_tx(cmd.consumer_tx);
+                                consumer.drained_rx = cmd.drained_rx;
+                                consumer.terminated_tx = cmd.terminated_tx;
                                 // forward buffered messages
                                 while !consumer.fifo.is_empty() {
                                     #[cfg(feature="traces")]
                                     trace!("consumer {} total buffered messages: {}", cmd.consumer_tag, consumer.fifo.len());
                                     let msg = consumer.pop_message().unwrap();
-                                    if let Err(_err) = consumer.get_tx().unwrap().send(msg) {
+                                    if !consumer.get_tx().unwrap().send(msg).await {
                                         #[cfg(feature="traces")]
                                         error!("failed to forward message to consumer {}", cmd.consumer_tag);
                                     }
                                 }
                             },
                             DispatcherManagementCommand::DeregisterContentConsumer(cmd) => {
-                                if let Some(consumer) = self.remove_consumer_resource(&cmd.consumer_tag) {
-                                    #[cfg(feature="traces")]
-                                    info!("deregister consumer {}, total buffered messages: {}",
-                                        cmd.consumer_tag, consumer.fifo.len()
-                                    );
+                                let drained_rx = match self.remove_consumer_resource(&cmd.consumer_tag) {
+                                    Some(mut consumer) => {
+                                        #[cfg(feature="traces")]
+                                        info!("deregister consumer {}, total buffered messages: {}",
+                                            cmd.consumer_tag, consumer.fifo.len()
+                                        );
+                                        // messages buffered before the consumer is registered are
+                                        // forwarded, so that they are handled before it terminates
+                                        if let Some(consumer_tx) = consumer.tx.as_ref() {
+                                            for msg in consumer.fifo.drain(..) {
+                                                if !consumer_tx.send(msg).await {
+                                                    #[cfg(feature="traces")]
+                                                    error!("failed to forward message to consumer {}", cmd.consumer_tag);
+                                                }
+                                            }
+                                        }
+                                        let drained_rx = consumer.drained_rx.take();
+                                        consumer.terminate(ConsumerTermination::Cancelled);
+                                        drained_rx
+                                    }
+                                    None => None,
+                                };
+                                if let Some(responder) = cmd.responder {
+                                    // the requester may have given up waiting, ignore error
+                                    let _ = responder.send(drained_rx);
                                 }
                             },
                             DispatcherManagementCommand::RegisterGetContentResponder(cmd) => {
-                                self.get_content_responder.replace(cmd.tx);
+                                // send request after registration, so that responses are routed
+                                // in order of requests, even if requesters give up waiting
+                                self.get_content_responders.push_back(cmd.tx);
+                                let channel_id = self.channel.channel_id();
+                                if let Err(_err) = self.channel.shared.send((channel_id, cmd.get.into_frame())).await {
+                                    #[cfg(feature="traces")]
+                                    error!("failed to send get request on channel {}, cause: {}", self.channel, _err);
+                                    // the requester is notified by dropping its responder
+                                    self.get_content_responders.pop_back();
+                                }
                             }
                             DispatcherManagementCommand::RegisterOneshotResponder(cmd) => {
                                 self.responders.insert(cmd.method_header, cmd.responder);
@@ -285,6 +555,9 @@ impl ChannelDispatcher {
                                 #[cfg(feature="traces")]
                                 debug!("callback registered on channel {}", self.channel);
                             }
+                            DispatcherManagementCommand::RegisterConfirmWaiter(cmd) => {
+                                self.confirm_waiters.insert(cmd.delivery_tag, cmd.responder);
+                            }
                         }
                     }
                     // only one tx half held by connection handler, once the tx half dorp
@@ -306,23 +579,67 @@ impl ChannelDispatcher {
                             // frames for closing channel
                             // channel.close-ok response from server
                             Frame::CloseChannelOk(method_header, close_channel_ok) => {
-                                self.channel.set_is_open(false);
+                                self.channel.set_closed(ChannelState::closed_by_client());
+                                close_reason = Some("closed by client".to_string());
+                                // release the channel id before responding, so that it can be
+                                // reused once the close request returns
+                                let cmd = ConnManagementCommand::DeregisterChannelResource(self.channel.channel_id());
+                                if let Err(_err) = self.channel.shared.conn_mgmt_tx.send(cmd).await {
+                                    #[cfg(feature="traces")]
+                                    error!("failed to release channel id of channel {}, cause: {}", self.channel, _err);
+                                }
 
                                 match self.responders.remove(method_header) {
-                                    Some(responder) => responder.send(close_channel_ok.into_frame()).unwrap(),
-                                    None => unreachable!("responder must be registered for {} on channel {}",
-                                    close_channel_ok.into_frame(), self.channel),
+                                    Some(responder) => {
+                                        // the close request may have been dropped
+                                        let _ = responder.send(close_channel_ok.into_frame());
+                                    }
+                                    None => {
+                                        #[cfg(feature="traces")]
+                                        warn!("discard {} on channel {}, no responder registered",
+                                        close_channel_ok.into_frame(), self.channel);
+                                    }
                                 }
                                 // exit
                                 break;
                             }
                             // channel.close request from server
                             Frame::CloseChannel(_, close_channel) => {
+                                if self.channel.should_reopen(close_channel.reply_code()) {
+                                    match self.reopen(&close_channel).await {
+                                        Ok(()) => {
+                                            #[cfg(feature="traces")]
+                                            info!("reopened channel {} closed by server, {}", self.channel, close_channel);
+                                            body_tx = None;
+                                            continue;
+                                        }
+                                        Err(_err) => {
+                                            #[cfg(feature="traces")]
+                                            error!("failed to reopen channel {}, cause: {}", self.channel, _err);
+                                            self.channel.set_closed(ChannelState::Closed {
+                                                initiator: CloseInitiator::Server,
+                                                code: close_channel.reply_code(),
+                                                text: close_channel.reply_text().to_string(),
+                                            });
+                                            close_reason = Some(format!("closed by server, {}", close_channel));
+                                            let cmd = ConnManagementCommand::DeregisterChannelResource(self.channel.channel_id());
+                                            let _ = self.channel.shared.conn_mgmt_tx.send(cmd).await;
+                                            break;
+                                        }
+                                    }
+                                }
+                                close_reason = Some(format!("closed by server, {}", close_channel));
+                                let closed = ChannelState::Closed {
+                                    initiator: CloseInitiator::Server,
+                                    code: close_channel.reply_code(),
+                                    text: close_channel.reply_text().to_string(),
+                                };
                                 // callback
                                 if let Some(ref mut cb) = self.callback {
                                     if let Err(err) = cb.close(&self.channel, close_channel).await {
                                       #[cfg(feature="traces")]
                                       error!("close callback returns error on channel {}, cause: {}", self.channel, err);
+                                      self.channel.set_closed(closed);
                                       // exit immediately, no response to server
                                       break;
                                     };
@@ -330,12 +647,18 @@ impl ChannelDispatcher {
                                     #[cfg(feature="traces")]
                                     error!("callback not registered on channel {}", self.channel);
                                 }
-                                self.channel.set_is_open(false);
+                                self.channel.set_closed(closed);
 
                                 // implictly respond OK to server
                                 self.channel.shared.outgoing_tx
-                                .send((self.channel.channel_id(), CloseChannelOk::default().into_frame()))
+                                .send((self.channel.channel_id(), CloseChannelOk.into_frame()))
                                 .await.unwrap();
+                                // release the channel id, as no one else will close the channel
+                                let cmd = ConnManagementCommand::DeregisterChannelResource(self.channel.channel_id());
+                                if let Err(_err) = self.channel.shared.conn_mgmt_tx.send(cmd).await {
+                                    #[cfg(feature="traces")]
+                                    error!("failed to release channel id of channel {}, cause: {}", self.channel, _err);
+                                }
                                 // exit
                                 break;
                             }
@@ -344,16 +667,17 @@ impl ChannelDispatcher {
                             Frame::GetEmpty(_, get_empty) => {
                                 self.state = State::GetEmpty;
 
-                                self.get_content_responder.take()
+                                // the requester may have given up waiting, ignore error
+                                let _ = self.get_content_responders.pop_front()
                                 .expect("get responder must be registered")
-                                .send(get_empty.into_frame()).unwrap();
+                                .send(get_empty.into_frame());
                             }
                             Frame::GetOk(_, get_ok) => {
                                 self.state = State::GetOk;
 
-                                self.get_content_responder.as_ref()
+                                let _ = self.get_content_responders.front()
                                 .expect("get responder must be registered")
-                                .send(get_ok.into_frame()).unwrap();
+                                .send(get_ok.into_frame());
                             }
                             Frame::Return(_, ret) => {
                                 self.state = State::Return;
@@ -361,14 +685,44 @@ impl ChannelDispatcher {
                             }
                             Frame::Deliver(_, deliver) => {
                                 self.state = State::Deliver;
+                                if self.channel.is_manual_ack_consumer(deliver.consumer_tag()) {
+                                    if let Some(unacked) =
+                                        self.channel.track_unacked(deliver.delivery_tag())
+                                    {
+                                        if let Some(ref mut cb) = self.callback {
+                                            cb.unacked_threshold_exceeded(&self.channel, unacked)
+                                                .await;
+                                        }
+                                    }
+                                }
                                 message_buffer.deliver = Some(deliver);
                             }
                             Frame::ContentHeader(header) => {
                                 match self.state {
                                     State::Deliver => {
                                         message_buffer.remaining = header.common.body_size.try_into().unwrap();
-                                        // do not wait for content body frame if content body size is zero
-                                        if message_buffer.remaining == 0 {
+                                        // forward to a chunked consumer without waiting for content body frames
+                                        let chunked_tx = message_buffer.deliver.as_ref()
+                                            .and_then(|deliver| self.consumer_resources.get(deliver.consumer_tag()))
+                                            .and_then(|consumer| consumer.get_tx())
+                                            .and_then(ConsumerSender::chunked_tx)
+                                            .cloned();
+                                        if let Some(chunked_tx) = chunked_tx {
+                                            let (tx, body) = BodyStream::new(header.common.body_size);
+                                            let chunked_message = ChunkedMessage {
+                                                deliver: message_buffer.deliver.take().unwrap(),
+                                                basic_properties: header.basic_properties,
+                                                body,
+                                            };
+                                            if chunked_tx.send(chunked_message).is_err() {
+                                                #[cfg(feature="traces")]
+                                                error!("failed to dispatch message to chunked consumer on channel {}", self.channel);
+                                            }
+                                            if message_buffer.remaining != 0 {
+                                                body_tx = Some(tx);
+                                            }
+                                        } else if message_buffer.remaining == 0 {
+                                            // do not wait for content body frame if content body size is zero
                                             let consumer_message  = ConsumerMessage {
                                                 deliver: message_buffer.deliver.take(),
                                                 basic_properties: Some(header.basic_properties),
@@ -384,11 +738,13 @@ impl ChannelDispatcher {
                                     State::GetOk => {
                                         getok_content_buffer.remaining = header.common.body_size.try_into().unwrap();
 
-                                        let responder = self.get_content_responder.as_ref().expect("get responder must be registered");
-                                        responder.send(header.into_frame()).unwrap();
+                                        let responder = self.get_content_responders.front().expect("get responder must be registered");
+                                        let _ = responder.send(header.into_frame());
                                         // do not wait for content body frame if content body size is zero
                                         if getok_content_buffer.remaining  == 0 {
-                                            responder.send(ContentBody::new(Vec::new()).into_frame()).unwrap();
+                                            let _ = self.get_content_responders.pop_front()
+                                            .expect("get responder must be registered")
+                                            .send(ContentBody::new(Vec::new()).into_frame());
                                         } else {
                                             getok_content_buffer.content = Some(Vec::new());
                                         }
@@ -410,20 +766,30 @@ impl ChannelDispatcher {
                             Frame::ContentBody(body) => {
                                 match self.state {
                                     State::Deliver => {
-                                        let mut content_buffer = message_buffer.content.take().unwrap();
-                                        content_buffer.extend_from_slice(&body.inner);
-                                        message_buffer.content.replace(content_buffer);
-                                        // calculate remaining size of content body
-                                        message_buffer.remaining = message_buffer.remaining.checked_sub(body.inner.len()).expect("should never overflow");
+                                        if let Some(tx) = body_tx.as_ref() {
+                                            message_buffer.remaining = message_buffer.remaining.checked_sub(body.inner.len()).expect("should never overflow");
+                                            // waits for room if the consumer is reading slower,
+                                            // the body may have been dropped, ignore error
+                                            let _ = tx.send(body.inner.into()).await;
+                                            if message_buffer.remaining == 0 {
+                                                body_tx = None;
+                                            }
+                                        } else {
+                                            let mut content_buffer = message_buffer.content.take().unwrap();
+                                            content_buffer.extend_from_slice(&body.inner);
+                                            message_buffer.content.replace(content_buffer);
+                                            // calculate remaining size of content body
+                                            message_buffer.remaining = message_buffer.remaining.checked_sub(body.inner.len()).expect("should never overflow");
 
-                                        if message_buffer.remaining == 0 {
-                                            let consumer_message  = ConsumerMessage {
-                                                deliver: message_buffer.deliver.take(),
-                                                basic_properties: message_buffer.basic_properties.take(),
-                                                content: message_buffer.content.take(),
-                                                remaining: message_buffer.remaining,
-                                            };
-                                            self.forward_deliver(consumer_message).await;
+                                            if message_buffer.remaining == 0 {
+                                                let consumer_message  = ConsumerMessage {
+                                                    deliver: message_buffer.deliver.take(),
+                                                    basic_properties: message_buffer.basic_properties.take(),
+                                                    content: message_buffer.content.take(),
+                                                    remaining: message_buffer.remaining,
+                                                };
+                                                self.forward_deliver(consumer_message).await;
+                                            }
                                         }
                                     }
                                     State::GetOk => {
@@ -433,9 +799,9 @@ impl ChannelDispatcher {
                                         getok_content_buffer.remaining = getok_content_buffer.remaining.checked_sub(body.inner.len()).expect("should never overflow");
                                         if getok_content_buffer.remaining == 0 {
                                             let content = getok_content_buffer.content.take().unwrap();
-                                            self.get_content_responder.take()
+                                            let _ = self.get_content_responders.pop_front()
                                             .expect("get responder must be registered")
-                                            .send(ContentBody::new(content).into_frame()).unwrap();
+                                            .send(ContentBody::new(content).into_frame());
                                         }
                                     },
                                     State::Return => {
@@ -487,15 +853,20 @@ impl ChannelDispatcher {
                                             );
                                         }
                                     }
-                                    None => unreachable!(
-                                        "responder must be registered for {} on channel {}",
-                                        frame, self.channel
-                                    ),
+                                    None => {
+                                        #[cfg(feature="traces")]
+                                        warn!(
+                                            "discard {} on channel {}, no responder registered",
+                                            frame, self.channel
+                                        );
+                                    }
                                 }
                             }
                             //////////////////////////////////////////////////////////
                             // asynchronous request frames
                             Frame::Flow(_, flow) => {
+                                self.channel.set_blocked(!flow.active);
+                                self.channel.notify_publisher_event(PublisherEvent::FlowChanged(flow.active));
                                 // callback
                                 if let Some(ref mut cb) = self.callback {
                                     match cb.flow(&self.channel, flow.active).await {
@@ -505,9 +876,12 @@ impl ChannelDispatcher {
                                       }
                                       Ok(active) => {
                                          // respond to server that we have handled the request
-                                         self.channel.shared.outgoing_tx
+                                         if let Err(_err) = self.channel.shared
                                          .send((self.channel.channel_id(), FlowOk::new(active).into_frame()))
-                                         .await.unwrap();
+                                         .await {
+                                            #[cfg(feature="traces")]
+                                            error!("failed to respond flow on channel {}, cause: {}", self.channel, _err);
+                                         }
                                       }
                                     };
                                 } else {
@@ -516,6 +890,8 @@ impl ChannelDispatcher {
                                 }
                             }
                             Frame::Cancel(_, cancel) => {
+                                // transient queue consumed by the cancelled consumer may have been removed by server
+                                let removed_queue = self.channel.take_transient_queue_of(cancel.consumer_tag());
                                 // callback
                                 if let Some(ref mut cb) = self.callback {
                                     let consumer_tag = cancel.consumer_tag().clone();
@@ -526,13 +902,18 @@ impl ChannelDispatcher {
                                         error!("cancel callback error on channel {}, cause: '{}'.", self.channel, err);
                                       }
                                       Ok(_) => {
-                                        self.remove_consumer_resource(&consumer_tag);
+                                        if let Some(consumer) = self.remove_consumer_resource(&consumer_tag) {
+                                            consumer.terminate(ConsumerTermination::CancelledByServer);
+                                        }
 
                                         // respond to server that we have handled the request
                                         if !no_wait  {
-                                            self.channel.shared.outgoing_tx
+                                            if let Err(_err) = self.channel.shared
                                             .send((self.channel.channel_id(), CancelOk::new(consumer_tag.try_into().unwrap()).into_frame()))
-                                            .await.unwrap();
+                                            .await {
+                                                #[cfg(feature="traces")]
+                                                error!("failed to respond cancel on channel {}, cause: {}", self.channel, _err);
+                                            }
                                         }
                                       }
                                     };
@@ -540,9 +921,18 @@ impl ChannelDispatcher {
                                     #[cfg(feature="traces")]
                                     error!("callback not registered on channel {}", self.channel);
                                 }
+                                if let (Some((queue, args)), Some(cb)) = (removed_queue, self.callback.as_mut()) {
+                                    cb.transient_queue_removed(&self.channel, queue, args).await;
+                                }
                             }
                             // in confirmed mode
                             Frame::Ack(_, ack) => {
+                                self.resolve_confirm_waiters(ack.delivery_tag(), ack.mutiple(), Confirmation::Ack);
+                                self.nack_storm.confirm(ack.delivery_tag(), ack.mutiple());
+                                self.channel.notify_publisher_event(PublisherEvent::Ack {
+                                    delivery_tag: ack.delivery_tag(),
+                                    multiple: ack.mutiple(),
+                                });
                                 if let Some(ref mut cb) = self.callback {
                                     cb.publish_ack(&self.channel, ack).await;
                                 } else {
@@ -551,8 +941,19 @@ impl ChannelDispatcher {
                                 }
                             }
                             Frame::Nack(_, nack) => {
+                                self.resolve_confirm_waiters(nack.delivery_tag(), nack.multiple(), Confirmation::Nack);
+                                let nacked = self.nack_storm.confirm(nack.delivery_tag(), nack.multiple());
+                                let threshold = self.channel.shared.nack_storm_threshold.load(Ordering::Relaxed);
+                                let storm = self.nack_storm.count(nacked, threshold);
+                                self.channel.notify_publisher_event(PublisherEvent::Nack {
+                                    delivery_tag: nack.delivery_tag(),
+                                    multiple: nack.multiple(),
+                                });
                                 if let Some(ref mut cb) = self.callback {
                                     cb.publish_nack(&self.channel, nack).await;
+                                    if let Some(nacked) = storm {
+                                        cb.publish_nack_storm(&self.channel, nacked).await;
+                                    }
                                 } else {
                                     #[cfg(feature="traces")]
                                     error!("callback not registered on channel {}", self.channel);
@@ -561,15 +962,42 @@ impl ChannelDispatcher {
                         }
                     }
                     // purge stale consumer resource
-                    _ = purge_timer.tick() => {
+                    _ = rt::sleep_until(purge_deadline) => {
                         self.purge_consumer_resource();
+                        purge_deadline = time::Instant::now() + CONSUMER_PURGE_INTERVAL;
+                    }
+                    // check ages of unacked deliveries
+                    _ = rt::sleep_until(ack_deadline_check) => {
+                        self.check_ack_deadline().await;
+                        ack_deadline_check = time::Instant::now() + ACK_DEADLINE_CHECK_INTERVAL;
                     }
                     else => {
                         break;
                     }
                 }
             }
-            self.channel.set_is_open(false);
+            // closed along with the connection if not closed by client or server
+            if close_reason.is_none() {
+                match self.channel.connection.state() {
+                    closed @ ChannelState::Closed { .. } => self.channel.set_closed(closed),
+                    _ => self.channel.set_closed(ChannelState::Closed {
+                        initiator: CloseInitiator::Network,
+                        code: 0,
+                        text: "shutdown".to_string(),
+                    }),
+                }
+            }
+            let termination = match close_reason {
+                Some(reason) => ConsumerTermination::ChannelClosed(reason),
+                None => ConsumerTermination::ConnectionClosed(
+                    match self.channel.connection.state() {
+                        ChannelState::Closed { text, .. } => text,
+                        _ => "shutdown".to_string(),
+                    },
+                ),
+            };
+            self.drop_pending(termination);
+            self.channel.close_publisher_events();
 
             #[cfg(feature = "traces")]
             info!("exit dispatcher of channel {}", self.channel);
@@ -592,7 +1020,36 @@ mod tests {
         BasicProperties,
     };
 
-    use super::{CONSUMER_EXPIRY_PERIOD, CONSUMER_PURGE_INTERVAL};
+    use super::{NackStormCounter, CONSUMER_EXPIRY_PERIOD, CONSUMER_PURGE_INTERVAL};
+
+    #[test]
+    fn test_nack_storm_counter() {
+        let mut counter = NackStormCounter::new();
+        // disabled
+        assert_eq!(None, counter.count(100, 0));
+
+        assert_eq!(None, counter.count(5, 10));
+        assert_eq!(Some(12), counter.count(7, 10));
+        // notified once per window
+        assert_eq!(None, counter.count(1, 10));
+
+        // new window
+        counter.window_start -= super::NACK_STORM_WINDOW;
+        assert_eq!(None, counter.count(1, 10));
+        assert_eq!(Some(10), counter.count(9, 10));
+    }
+
+    #[test]
+    fn test_nack_storm_counter_multiple() {
+        let mut counter = NackStormCounter::new();
+        assert_eq!(1, counter.confirm(1, false));
+        // multiple confirm covers all messages since the last confirm
+        assert_eq!(9, counter.confirm(10, true));
+        assert_eq!(1, counter.confirm(12, false));
+        assert_eq!(3, counter.confirm(15, true));
+        // a stale multiple confirm still counts as one message
+        assert_eq!(1, counter.confirm(15, true));
+    }
 
     #[tokio::test]
     async fn test_purge_consumer_resource() {
@@ -1,7 +1,12 @@
+use amqp_serde::types::{FieldName, FieldValue};
 use std::borrow::ToOwned;
 use std::fmt::{Debug, Display, Formatter};
 use crate::{
-    api::{error::Error, FieldTable},
+    api::{
+        error::Error,
+        naming::{check_exchange_declare, check_exchange_name},
+        FieldTable,
+    },
     frame::{Bind, BindOk, Declare, DeclareOk, Delete, DeleteOk, Frame, Unbind, UnbindOk},
 };
 
@@ -103,6 +108,112 @@ impl Display for ExchangeType {
     }
 }
 
+/// How a headers exchange matches the headers of a message against a [`HeadersBinding`].
+///
+/// [`HeadersBinding`]: struct.HeadersBinding.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeadersMatch {
+    /// All headers of the binding must match.
+    All,
+    /// At least one header of the binding must match.
+    Any,
+}
+
+impl Display for HeadersMatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeadersMatch::All => f.write_str("all"),
+            HeadersMatch::Any => f.write_str("any"),
+        }
+    }
+}
+
+/// Binding arguments of a headers exchange, i.e. `x-match` and the headers to match.
+///
+/// Use it by `headers_binding` method of [`QueueBindArguments`], [`ExchangeBindArguments`]
+/// and their unbind counterparts. Headers whose name starts with `x-` are not matched by server.
+///
+/// # Support chainable methods to build arguments
+/// ```
+/// # use amqprs::channel::{HeadersBinding, QueueBindArguments};
+///
+/// let args = QueueBindArguments::new("q", "amq.headers", "")
+///     .headers_binding(
+///         HeadersBinding::all()
+///             .header("format".try_into().unwrap(), "pdf")
+///             .header("type".try_into().unwrap(), "report")
+///             .finish(),
+///     )
+///     .finish();
+/// ```
+///
+/// See [RabbitMQ headers exchange](https://www.rabbitmq.com/tutorials/amqp-concepts.html#exchange-headers).
+///
+/// [`QueueBindArguments`]: struct.QueueBindArguments.html
+/// [`ExchangeBindArguments`]: struct.ExchangeBindArguments.html
+#[derive(Debug, Clone)]
+pub struct HeadersBinding {
+    /// How the headers are matched.
+    pub x_match: HeadersMatch,
+    /// Headers to match. Default: empty table.
+    pub headers: FieldTable,
+}
+
+impl HeadersBinding {
+    /// Create new binding with `x_match` and no header.
+    pub fn new(x_match: HeadersMatch) -> Self {
+        Self {
+            x_match,
+            headers: FieldTable::new(),
+        }
+    }
+
+    /// Create new binding which matches all headers.
+    pub fn all() -> Self {
+        Self::new(HeadersMatch::All)
+    }
+
+    /// Create new binding which matches any header.
+    pub fn any() -> Self {
+        Self::new(HeadersMatch::Any)
+    }
+
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        x_match, HeadersMatch
+    }
+
+    /// Add a header to match.
+    pub fn header(&mut self, name: FieldName, value: impl Into<FieldValue>) -> &mut Self {
+        self.headers.insert(name, value.into());
+        self
+    }
+
+    /// Finish chaining and returns a new argument according to chained configurations.
+    pub fn finish(&mut self) -> Self {
+        self.clone()
+    }
+
+    /// Insert `x-match` and the headers into binding `arguments`.
+    pub(crate) fn insert_into(self, arguments: &mut FieldTable) {
+        arguments.insert(
+            "x-match".try_into().unwrap(),
+            self.x_match.to_string().into(),
+        );
+        for (name, value) in self.headers.iter() {
+            arguments.insert(name.clone(), value.clone());
+        }
+    }
+}
+
+impl From<HeadersBinding> for FieldTable {
+    fn from(binding: HeadersBinding) -> Self {
+        let mut arguments = FieldTable::new();
+        binding.insert_into(&mut arguments);
+        arguments
+    }
+}
+
 /// Arguments for [`exchange_declare`]
 ///
 /// # Support chainable methods to build arguments
@@ -207,6 +318,20 @@ impl ExchangeDeclareArguments {
         /// Chainable setter method.
         arguments, FieldTable
     }
+
+    /// Route messages which can't be routed by this exchange to `exchange`
+    /// by `alternate-exchange` argument.
+    ///
+    /// See [RabbitMQ alternate exchanges](https://www.rabbitmq.com/ae.html).
+    pub fn alternate_exchange(&mut self, exchange: &str) -> &mut Self {
+        #[cfg(feature = "compliance_assert")]
+        assert_exchange_name(exchange);
+
+        self.arguments
+            .insert("alternate-exchange".try_into().unwrap(), exchange.into());
+        self
+    }
+
     /// Finish chained configuration and return new arguments.
     pub fn finish(&mut self) -> Self {
         #[cfg(feature = "compliance_assert")]
@@ -319,6 +444,13 @@ impl ExchangeBindArguments {
         /// Chainable setter method.
         arguments, FieldTable
     }
+    /// Insert the arguments of a headers exchange binding, see [`HeadersBinding`].
+    ///
+    /// [`HeadersBinding`]: struct.HeadersBinding.html
+    pub fn headers_binding(&mut self, binding: HeadersBinding) -> &mut Self {
+        binding.insert_into(&mut self.arguments);
+        self
+    }
     /// Finish chained configuration and return new arguments.
     pub fn finish(&mut self) -> Self {
         #[cfg(feature = "compliance_assert")]
@@ -385,6 +517,13 @@ impl ExchangeUnbindArguments {
         /// Chainable setter method.
         arguments, FieldTable
     }
+    /// Insert the arguments of a headers exchange binding, see [`HeadersBinding`].
+    ///
+    /// [`HeadersBinding`]: struct.HeadersBinding.html
+    pub fn headers_binding(&mut self, binding: HeadersBinding) -> &mut Self {
+        binding.insert_into(&mut self.arguments);
+        self
+    }
     /// Finish chained configuration and return new arguments.
     pub fn finish(&mut self) -> Self {
         #[cfg(feature = "compliance_assert")]
@@ -402,8 +541,13 @@ impl Channel {
     ///
     /// # Errors
     ///
-    /// Returns error if any failure in comunication with server.
+    /// Returns error if any failure in comunication with server, or [`Error::InvalidName`]
+    /// if the exchange name is longer than 255 bytes, or starts with reserved prefix `amq.`
+    /// without `passive`.
+    ///
+    /// [`Error::InvalidName`]: ../error/enum.Error.html#variant.InvalidName
     pub async fn exchange_declare(&self, args: ExchangeDeclareArguments) -> Result<()> {
+        check_exchange_declare(&args.exchange, args.passive)?;
         let mut declare = Declare::new(
             0,
             args.exchange.try_into().unwrap(),
@@ -419,7 +563,6 @@ impl Channel {
 
         if args.no_wait {
             self.shared
-                .outgoing_tx
                 .send((self.shared.channel_id, declare.into_frame()))
                 .await?;
             Ok(())
@@ -427,7 +570,7 @@ impl Channel {
             let responder_rx = self.register_responder(DeclareOk::header()).await?;
 
             let _method = synchronous_request!(
-                self.shared.outgoing_tx,
+                self.shared,
                 (self.shared.channel_id, declare.into_frame()),
                 responder_rx,
                 Frame::DeclareOk,
@@ -440,14 +583,17 @@ impl Channel {
     ///
     /// # Errors
     ///
-    /// Returns error if any failure in comunication with server.
+    /// Returns error if any failure in comunication with server, or [`Error::InvalidName`]
+    /// if the exchange name is longer than 255 bytes.
+    ///
+    /// [`Error::InvalidName`]: ../error/enum.Error.html#variant.InvalidName
     pub async fn exchange_delete(&self, args: ExchangeDeleteArguments) -> Result<()> {
+        check_exchange_name(&args.exchange)?;
         let mut delete = Delete::new(0, args.exchange.try_into().unwrap());
         delete.set_if_unused(args.if_unused);
         delete.set_no_wait(args.no_wait);
         if args.no_wait {
             self.shared
-                .outgoing_tx
                 .send((self.shared.channel_id, delete.into_frame()))
                 .await?;
             Ok(())
@@ -455,7 +601,7 @@ impl Channel {
             let responder_rx = self.register_responder(DeleteOk::header()).await?;
 
             let _method = synchronous_request!(
-                self.shared.outgoing_tx,
+                self.shared,
                 (self.shared.channel_id, delete.into_frame()),
                 responder_rx,
                 Frame::DeleteOk,
@@ -468,8 +614,13 @@ impl Channel {
     ///
     /// # Errors
     ///
-    /// Returns error if any failure in comunication with server.
+    /// Returns error if any failure in comunication with server, or [`Error::InvalidName`]
+    /// if an exchange name is longer than 255 bytes.
+    ///
+    /// [`Error::InvalidName`]: ../error/enum.Error.html#variant.InvalidName
     pub async fn exchange_bind(&self, args: ExchangeBindArguments) -> Result<()> {
+        check_exchange_name(&args.destination)?;
+        check_exchange_name(&args.source)?;
         let bind = Bind::new(
             0,
             args.destination.try_into().unwrap(),
@@ -480,7 +631,6 @@ impl Channel {
         );
         if args.no_wait {
             self.shared
-                .outgoing_tx
                 .send((self.shared.channel_id, bind.into_frame()))
                 .await?;
             Ok(())
@@ -488,7 +638,7 @@ impl Channel {
             let responder_rx = self.register_responder(BindOk::header()).await?;
 
             synchronous_request!(
-                self.shared.outgoing_tx,
+                self.shared,
                 (self.shared.channel_id, bind.into_frame()),
                 responder_rx,
                 Frame::BindOk,
@@ -501,8 +651,13 @@ impl Channel {
     ///
     /// # Errors
     ///
-    /// Returns error if any failure in comunication with server.
+    /// Returns error if any failure in comunication with server, or [`Error::InvalidName`]
+    /// if an exchange name is longer than 255 bytes.
+    ///
+    /// [`Error::InvalidName`]: ../error/enum.Error.html#variant.InvalidName
     pub async fn exchange_unbind(&self, args: ExchangeUnbindArguments) -> Result<()> {
+        check_exchange_name(&args.destination)?;
+        check_exchange_name(&args.source)?;
         let unbind = Unbind::new(
             0,
             args.destination.try_into().unwrap(),
@@ -513,7 +668,6 @@ impl Channel {
         );
         if args.no_wait {
             self.shared
-                .outgoing_tx
                 .send((self.shared.channel_id, unbind.into_frame()))
                 .await?;
             Ok(())
@@ -521,7 +675,7 @@ impl Channel {
             let responder_rx = self.register_responder(UnbindOk::header()).await?;
 
             synchronous_request!(
-                self.shared.outgoing_tx,
+                self.shared,
                 (self.shared.channel_id, unbind.into_frame()),
                 responder_rx,
                 Frame::UnbindOk,
@@ -534,9 +688,11 @@ impl Channel {
 
 #[cfg(test)]
 mod tests {
+    use amqp_serde::types::{FieldTable, FieldValue};
+
     use super::{
         ExchangeBindArguments, ExchangeDeclareArguments, ExchangeDeleteArguments,
-        ExchangeUnbindArguments, ExchangeType,
+        ExchangeUnbindArguments, ExchangeType, HeadersBinding, HeadersMatch,
     };
     use crate::{
         api::connection::{Connection, OpenConnectionArguments},
@@ -605,6 +761,86 @@ mod tests {
         connection.close().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_exchange_declare_alternate_exchange() {
+        use crate::{
+            channel::{
+                BasicGetArguments, BasicPublishArguments, QueueBindArguments, QueueDeclareArguments,
+            },
+            BasicProperties,
+        };
+
+        test_utils::setup_logging();
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_exchange_declare_alternate_exchange")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let args = ExchangeDeclareArguments::of_type("amqprs.test.main", ExchangeType::Direct)
+            .auto_delete(true)
+            .alternate_exchange("amqprs.test.ae")
+            .finish();
+        assert_eq!(
+            Some(&FieldValue::from("amqprs.test.ae")),
+            args.arguments
+                .get(&"alternate-exchange".try_into().unwrap())
+        );
+        channel.exchange_declare(args).await.unwrap();
+        channel
+            .exchange_declare(
+                ExchangeDeclareArguments::of_type("amqprs.test.ae", ExchangeType::Fanout)
+                    .auto_delete(true)
+                    .finish(),
+            )
+            .await
+            .unwrap();
+
+        let (routed, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+        channel
+            .queue_bind(QueueBindArguments::new(
+                &routed,
+                "amqprs.test.main",
+                "routed",
+            ))
+            .await
+            .unwrap();
+        let (unroutable, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+        channel
+            .queue_bind(QueueBindArguments::new(&unroutable, "amqprs.test.ae", ""))
+            .await
+            .unwrap();
+
+        for routing_key in ["routed", "unknown"] {
+            channel
+                .basic_publish(
+                    BasicProperties::default(),
+                    routing_key.as_bytes().to_vec(),
+                    BasicPublishArguments::new("amqprs.test.main", routing_key),
+                )
+                .await
+                .unwrap();
+        }
+
+        for (queue, expected) in [(&routed, "routed"), (&unroutable, "unknown")] {
+            let get_args = BasicGetArguments::new(queue).no_ack(true).finish();
+            let (_, _, content) = channel.basic_get(get_args.clone()).await.unwrap().unwrap();
+            assert_eq!(expected.as_bytes(), content);
+            assert!(channel.basic_get(get_args).await.unwrap().is_none());
+        }
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
     #[tokio::test]
     #[should_panic = "InternalChannelError(\"channel closed\")"]
     async fn test_exchange_delete() {
@@ -675,4 +911,108 @@ mod tests {
             .await
             .unwrap();
     }
+
+    #[test]
+    fn test_headers_binding_arguments() {
+        let arguments: FieldTable = HeadersBinding::any()
+            .header("format".try_into().unwrap(), "pdf")
+            .header("draft".try_into().unwrap(), true)
+            .x_match(HeadersMatch::All)
+            .finish()
+            .into();
+        assert_eq!(3, arguments.len());
+        assert_eq!(
+            Some(&FieldValue::from("all")),
+            arguments.get(&"x-match".try_into().unwrap())
+        );
+        assert_eq!(
+            Some(&FieldValue::from(true)),
+            arguments.get(&"draft".try_into().unwrap())
+        );
+
+        // merged into existing arguments
+        let mut args = ExchangeBindArguments::new("dest", "amq.headers", "");
+        args.arguments
+            .insert("x-custom".try_into().unwrap(), FieldValue::from("kept"));
+        let args = args
+            .headers_binding(
+                HeadersBinding::any()
+                    .header("format".try_into().unwrap(), "pdf")
+                    .finish(),
+            )
+            .finish();
+        assert_eq!(3, args.arguments.len());
+        assert_eq!(
+            Some(&FieldValue::from("any")),
+            args.arguments.get(&"x-match".try_into().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_queue_bind_headers_binding() {
+        use crate::{
+            channel::{
+                BasicGetArguments, BasicPublishArguments, QueueBindArguments,
+                QueueDeclareArguments, QueueUnbindArguments,
+            },
+            BasicProperties,
+        };
+
+        test_utils::setup_logging();
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_queue_bind_headers_binding")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+        let binding = HeadersBinding::all()
+            .header("format".try_into().unwrap(), "pdf")
+            .header("type".try_into().unwrap(), "report")
+            .finish();
+        channel
+            .queue_bind(
+                QueueBindArguments::new(&queue_name, "amq.headers", "")
+                    .headers_binding(binding.clone())
+                    .finish(),
+            )
+            .await
+            .unwrap();
+
+        let publish = |headers: &[(&str, &str)]| {
+            let mut table = FieldTable::new();
+            for (name, value) in headers {
+                table.insert((*name).try_into().unwrap(), (*value).into());
+            }
+            channel.basic_publish(
+                BasicProperties::default().with_headers(table).finish(),
+                b"headers".to_vec(),
+                BasicPublishArguments::new("amq.headers", ""),
+            )
+        };
+        publish(&[("format", "pdf")]).await.unwrap();
+        publish(&[("format", "pdf"), ("type", "report")])
+            .await
+            .unwrap();
+
+        let get_args = BasicGetArguments::new(&queue_name).no_ack(true).finish();
+        let (_, basic_properties, _) = channel.basic_get(get_args.clone()).await.unwrap().unwrap();
+        assert_eq!(2, basic_properties.headers().unwrap().len());
+        assert!(channel.basic_get(get_args).await.unwrap().is_none());
+
+        channel
+            .queue_unbind(
+                QueueUnbindArguments::new(&queue_name, "amq.headers", "")
+                    .headers_binding(binding)
+                    .finish(),
+            )
+            .await
+            .unwrap();
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
 }
@@ -20,26 +20,43 @@
 //! [`close`]: struct.Channel.html#method.close
 //!
 use std::{
+    collections::{BTreeMap, HashMap},
     fmt,
+    future::Future,
+    pin::Pin,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU16, AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
+    task::{Context, Poll},
+    time::{Duration, Instant},
 };
 
 use amqp_serde::types::AmqpChannelId;
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{mpsc, oneshot, Mutex, MutexGuard, OwnedMutexGuard};
 
 use super::callbacks::ChannelCallback;
 use crate::{
-    api::{error::Error, Result},
+    api::{
+        consumer::ConsumerTermination,
+        error::Error,
+        state::{SharedState, State},
+        Result,
+    },
     connection::Connection,
-    frame::{CloseChannel, CloseChannelOk, Deliver, Flow, FlowOk, Frame, MethodHeader, Return},
+    frame::{
+        CloseChannel, CloseChannelOk, Deliver, Flow, FlowOk, Frame, Get, MethodHeader, Return,
+    },
     net::{ConnManagementCommand, IncomingMessage, OutgoingMessage},
-    BasicProperties,
+    rt, BasicProperties,
 };
+#[cfg(feature = "compression")]
+use crate::api::compression::decompress_message;
 #[cfg(feature = "traces")]
-use tracing::{error, info, trace};
+use tracing::{debug, error, info, trace};
+
+/// Time limit of the close handshake when a channel is dropped.
+const DROP_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Combined message received by a consumer
 ///
@@ -53,6 +70,18 @@ pub struct ConsumerMessage {
     remaining: usize,
 }
 
+#[cfg(feature = "compression")]
+impl ConsumerMessage {
+    /// Decompress the content if `content_encoding` is known, see [`decompress_message`].
+    fn decompress(&mut self, limit: usize) {
+        if let (Some(basic_properties), Some(content)) =
+            (self.basic_properties.as_mut(), self.content.as_mut())
+        {
+            decompress_message(basic_properties, content, limit);
+        }
+    }
+}
+
 /// Message buffer for a `Return + content` sequence from server.
 pub(crate) struct ReturnMessage {
     ret: Option<Return>,
@@ -67,10 +96,174 @@ pub(crate) struct GetOkMessage {
     remaining: usize,
 }
 
+/// Sender half of the buffer of deliveries forwarded by dispatcher to a consumer.
+pub(crate) struct ConsumerSender {
+    tx: BufferTx,
+    /// number of buffered deliveries, `None` if the receiver half is owned by user
+    buffered: Option<Arc<AtomicUsize>>,
+}
+
+enum BufferTx {
+    Unbounded(mpsc::UnboundedSender<ConsumerMessage>),
+    /// dispatcher waits for room once the buffer is full
+    Bounded(mpsc::Sender<ConsumerMessage>),
+    /// messages are forwarded once their content header is received, see `basic_consume_chunked`
+    Chunked(mpsc::UnboundedSender<ChunkedMessage>),
+}
+
+/// Receiver half of the buffer of deliveries, held by a consumer task.
+pub(crate) struct ConsumerReceiver {
+    rx: BufferRx,
+    buffered: Arc<AtomicUsize>,
+    /// max size of decompressed content, deliveries are decompressed once received
+    #[cfg(feature = "compression")]
+    decompress_limit: usize,
+}
+
+enum BufferRx {
+    Unbounded(mpsc::UnboundedReceiver<ConsumerMessage>),
+    Bounded(mpsc::Receiver<ConsumerMessage>),
+}
+
+/// Create the buffer of deliveries for a consumer task, bounded by `capacity` if given.
+///
+/// Returns both halves and the number of buffered deliveries.
+pub(crate) fn consumer_buffer(
+    capacity: Option<usize>,
+) -> (ConsumerSender, ConsumerReceiver, Arc<AtomicUsize>) {
+    let (tx, rx) = match capacity {
+        Some(capacity) => {
+            let (tx, rx) = mpsc::channel(capacity.max(1));
+            (BufferTx::Bounded(tx), BufferRx::Bounded(rx))
+        }
+        None => {
+            let (tx, rx) = mpsc::unbounded_channel();
+            (BufferTx::Unbounded(tx), BufferRx::Unbounded(rx))
+        }
+    };
+    let buffered = Arc::new(AtomicUsize::new(0));
+    (
+        ConsumerSender {
+            tx,
+            buffered: Some(buffered.clone()),
+        },
+        ConsumerReceiver {
+            rx,
+            buffered: buffered.clone(),
+            #[cfg(feature = "compression")]
+            decompress_limit: usize::MAX,
+        },
+        buffered,
+    )
+}
+
+impl ConsumerSender {
+    /// Forward `message` to the consumer, wait if the buffer is full.
+    ///
+    /// Returns `false` if the consumer has gone.
+    async fn send(&self, message: ConsumerMessage) -> bool {
+        // count before the message is visible to the receiver, which uncounts it
+        let count = || {
+            if let Some(buffered) = &self.buffered {
+                buffered.fetch_add(1, Ordering::Relaxed);
+            }
+        };
+        match &self.tx {
+            BufferTx::Unbounded(tx) => {
+                count();
+                if tx.send(message).is_ok() {
+                    return true;
+                }
+                if let Some(buffered) = &self.buffered {
+                    buffered.fetch_sub(1, Ordering::Relaxed);
+                }
+                false
+            }
+            BufferTx::Bounded(tx) => match tx.reserve().await {
+                Ok(permit) => {
+                    count();
+                    permit.send(message);
+                    true
+                }
+                Err(_) => false,
+            },
+            // received as a whole before the consumer is registered
+            BufferTx::Chunked(tx) => tx.send(message.into()).is_ok(),
+        }
+    }
+
+    /// Returns the tx half of a chunked consumer, `None` if the consumer is not chunked.
+    fn chunked_tx(&self) -> Option<&mpsc::UnboundedSender<ChunkedMessage>> {
+        match &self.tx {
+            BufferTx::Chunked(tx) => Some(tx),
+            _ => None,
+        }
+    }
+}
+
+impl From<mpsc::UnboundedSender<ChunkedMessage>> for ConsumerSender {
+    fn from(tx: mpsc::UnboundedSender<ChunkedMessage>) -> Self {
+        Self {
+            tx: BufferTx::Chunked(tx),
+            buffered: None,
+        }
+    }
+}
+
+impl From<mpsc::UnboundedSender<ConsumerMessage>> for ConsumerSender {
+    fn from(tx: mpsc::UnboundedSender<ConsumerMessage>) -> Self {
+        Self {
+            tx: BufferTx::Unbounded(tx),
+            buffered: None,
+        }
+    }
+}
+
+impl ConsumerReceiver {
+    /// Decompress deliveries once received, up to `limit` bytes of decompressed content.
+    #[cfg(feature = "compression")]
+    fn decompress_limit(mut self, limit: usize) -> Self {
+        self.decompress_limit = limit;
+        self
+    }
+
+    async fn recv(&mut self) -> Option<ConsumerMessage> {
+        let message = match &mut self.rx {
+            BufferRx::Unbounded(rx) => rx.recv().await,
+            BufferRx::Bounded(rx) => rx.recv().await,
+        };
+        self.on_received(message)
+    }
+
+    fn blocking_recv(&mut self) -> Option<ConsumerMessage> {
+        let message = match &mut self.rx {
+            BufferRx::Unbounded(rx) => rx.blocking_recv(),
+            BufferRx::Bounded(rx) => rx.blocking_recv(),
+        };
+        self.on_received(message)
+    }
+
+    fn on_received(&self, message: Option<ConsumerMessage>) -> Option<ConsumerMessage> {
+        if message.is_some() {
+            self.buffered.fetch_sub(1, Ordering::Relaxed);
+        }
+        #[cfg(feature = "compression")]
+        let message = message.map(|mut message| {
+            message.decompress(self.decompress_limit);
+            message
+        });
+        message
+    }
+}
+
 /// Command to register consumer of asynchronous delivered contents.
 pub(crate) struct RegisterContentConsumer {
     consumer_tag: String,
-    consumer_tx: mpsc::UnboundedSender<ConsumerMessage>,
+    consumer_tx: ConsumerSender,
+    /// Resolved when the consumer task exits, `None` if there is no consumer task.
+    drained_rx: Option<oneshot::Receiver<()>>,
+    /// Notified with the reason once the consumer stops receiving deliveries.
+    terminated_tx: Option<oneshot::Sender<ConsumerTermination>>,
 }
 
 /// Command to deregister consumer of asynchronous delivered contents.
@@ -78,14 +271,18 @@ pub(crate) struct RegisterContentConsumer {
 /// Consumer should be deregistered when it is cancelled or the channel is closed.
 pub(crate) struct DeregisterContentConsumer {
     consumer_tag: String,
+    /// oneshot sender to return the consumer task's `drained_rx`, if requested.
+    responder: Option<oneshot::Sender<Option<oneshot::Receiver<()>>>>,
 }
 
-/// Command to register sender to forward server's response to `get` request.
+/// Command to register sender to forward server's response to `get` request,
+/// and to send the request.
 ///
 /// Server will respond `get-ok` + `message propertities` + `content body` in sequence,
 /// so the sender should be mpsc instead of oneshot.
 pub(crate) struct RegisterGetContentResponder {
     tx: mpsc::UnboundedSender<IncomingMessage>,
+    get: Get,
 }
 
 /// Command to register oneshot sender for response from server.
@@ -97,6 +294,20 @@ pub(crate) struct RegisterOneshotResponder {
     pub acker: oneshot::Sender<()>,
 }
 
+/// Command to register oneshot sender for publisher confirm of a message.
+pub(crate) struct RegisterConfirmWaiter {
+    pub delivery_tag: u64,
+    pub responder: ConfirmResponder,
+}
+
+/// Oneshot sender resolved when server confirms a message.
+pub(crate) enum ConfirmResponder {
+    /// Resolves whether the message is acked or nacked.
+    Confirmation(oneshot::Sender<Confirmation>),
+    /// Resolves the outcome including return of the message, see `basic_publish_tracked`.
+    Outcome(oneshot::Sender<PublishOutcome>),
+}
+
 /// Command to register channel callbacks
 pub(crate) struct RegisterChannelCallback {
     pub callback: Box<dyn ChannelCallback + Send + 'static>,
@@ -109,6 +320,7 @@ pub(crate) enum DispatcherManagementCommand {
     RegisterGetContentResponder(RegisterGetContentResponder),
     RegisterOneshotResponder(RegisterOneshotResponder),
     RegisterChannelCallback(RegisterChannelCallback),
+    RegisterConfirmWaiter(RegisterConfirmWaiter),
 }
 
 /// Type represents an AMQP Channel.
@@ -127,8 +339,14 @@ pub(crate) enum DispatcherManagementCommand {
 ///
 /// See detailed explanation in [`Java Client`], it applies to the library also.
 ///
+/// If a channel is shared anyway, synchronous methods such as [`Channel::queue_declare`]
+/// are serialized, a method waits for the response to the one in flight before sending
+/// its request, so that responses are always matched to their requests. This holds even if
+/// a method is cancelled after sending its request, the next one waits for the response anyway.
+///
 /// [`Connection::open_channel`]: ../connection/struct.Connection.html#method.open_channel
 /// [`Channel::register_callback`]: struct.Channel.html#method.register_callback
+/// [`Channel::queue_declare`]: struct.Channel.html#method.queue_declare
 /// [`Java Client`]: https://www.rabbitmq.com/api-guide.html#concurrency
 #[derive(Clone)]
 pub struct Channel {
@@ -143,7 +361,7 @@ struct DropGuard(Arc<SharedChannelInner>);
 
 pub(crate) struct SharedChannelInner {
     /// open state
-    is_open: AtomicBool,
+    state: SharedState,
     /// channel id
     channel_id: AmqpChannelId,
     /// tx half to send message to `WriteHandler` task
@@ -152,16 +370,116 @@ pub(crate) struct SharedChannelInner {
     conn_mgmt_tx: mpsc::Sender<ConnManagementCommand>,
     /// tx half to send management command to `ChannelDispatcher` task
     dispatcher_mgmt_tx: mpsc::UnboundedSender<DispatcherManagementCommand>,
+    /// `true` if channel is in publisher confirm mode
+    confirm_mode: AtomicBool,
+    /// sequence number of next published message in confirm mode,
+    /// the lock also ensures messages are sent in the order of their sequence numbers,
+    /// and every frame of the channel is sent with it held, so that no frame is sent
+    /// amid the frames of a streamed message.
+    publish_seq: Mutex<u64>,
+    /// number of messages nacked within a second to notify a nack storm, `0` if disabled
+    nack_storm_threshold: AtomicU64,
+    /// exclusive or auto-delete queues declared on the channel, keyed by queue name
+    transient_queues: std::sync::Mutex<HashMap<String, QueueDeclareArguments>>,
+    /// queue name and `no_ack` of each consumer started on the channel, keyed by consumer tag
+    consumer_queues: std::sync::Mutex<HashMap<String, (String, bool)>>,
+    /// deliveries received in manual ack mode, which are not acked yet, keyed by delivery tag
+    unacked: std::sync::Mutex<BTreeMap<u64, UnackedDelivery>>,
+    /// number of unacked deliveries to notify, `0` if disabled
+    unacked_warning_threshold: AtomicUsize,
+    /// age of an unacked delivery to notify in milliseconds, `0` if disabled
+    ack_deadline_warning: AtomicU64,
+    /// prefetch count of the last `basic_qos` on the channel, `0` if unlimited
+    prefetch_count: AtomicU16,
+    /// properties stamped on every published message if enabled
+    message_stamp: std::sync::Mutex<Option<Arc<MessageStamp>>>,
+    /// options applied to every published message if enabled
+    publish_defaults: std::sync::Mutex<Option<Arc<PublishDefaults>>>,
+    /// publish rate limit of the channel if enabled
+    rate_limiter: std::sync::Mutex<Option<Arc<RateLimiter>>>,
+    /// subscribers of publisher events
+    publisher_event_txs: std::sync::Mutex<Vec<mpsc::UnboundedSender<PublisherEvent>>>,
+    /// number of buffered deliveries of each consumer task, keyed by consumer tag
+    consumer_buffers: std::sync::Mutex<HashMap<String, Arc<AtomicUsize>>>,
+    /// held while a synchronous request is waiting for its response
+    rpc_lock: Arc<Mutex<()>>,
+    /// `true` if the channel is reopened once server closes it due to a soft error
+    reopen_on_soft_error: AtomicBool,
+    /// `true` once consumers, publisher confirm or transaction mode, QoS, flow or callbacks
+    /// are set on the channel, which must not be carried over to another user of a pool
+    stateful: AtomicBool,
+}
+
+/// Receiver of the response to a synchronous request.
+///
+/// It holds the channel's RPC lock until the response is received. If it is dropped
+/// after the request is sent, e.g. the request is cancelled or times out, the lock is
+/// held until the response is received and discarded, or the channel is closed.
+pub(crate) struct ResponderReceiver {
+    /// `None` once the response is received
+    rx: Option<oneshot::Receiver<IncomingMessage>>,
+    rpc_guard: Option<OwnedMutexGuard<()>>,
+    /// `true` once polled, it is polled only after the request is sent
+    sent: bool,
+}
+
+impl Future for ResponderReceiver {
+    type Output = std::result::Result<IncomingMessage, oneshot::error::RecvError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.sent = true;
+        let rx = self
+            .rx
+            .as_mut()
+            .expect("responder receiver polled after completion");
+        match Pin::new(rx).poll(cx) {
+            Poll::Ready(response) => {
+                self.rx = None;
+                self.rpc_guard = None;
+                Poll::Ready(response)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for ResponderReceiver {
+    fn drop(&mut self) {
+        if let (true, Some(rx)) = (self.sent, self.rx.take()) {
+            // the response is still expected, so the next request must not register
+            // its responder before it is received, otherwise it would take the response.
+            let rpc_guard = self.rpc_guard.take();
+            rt::try_spawn(async move {
+                if let Ok(_response) = rx.await {
+                    #[cfg(feature = "traces")]
+                    debug!("discard {} to a cancelled request", _response);
+                }
+                drop(rpc_guard);
+            });
+        }
+    }
+}
+
+/// A delivery which is not acked yet.
+pub(crate) struct UnackedDelivery {
+    received_at: Instant,
+    /// `true` if it has been notified for reaching the ack deadline warning
+    warned: bool,
 }
 
 impl SharedChannelInner {
     /// Register oneshot responder for single message.
     ///
     /// Used for synchronous request/response protocol.
+    ///
+    /// It waits until no other synchronous request is in flight on the channel,
+    /// and the returned receiver blocks the next one until it resolves or is dropped,
+    /// so that a response is never forwarded to the responder of another request.
     async fn register_responder(
         &self,
         method_header: &'static MethodHeader,
-    ) -> Result<oneshot::Receiver<IncomingMessage>> {
+    ) -> Result<ResponderReceiver> {
+        let rpc_guard = self.rpc_lock.clone().lock_owned().await;
         let (responder, responder_rx) = oneshot::channel();
         let (acker, acker_rx) = oneshot::channel();
         let cmd = RegisterOneshotResponder {
@@ -172,21 +490,77 @@ impl SharedChannelInner {
         self.dispatcher_mgmt_tx
             .send(DispatcherManagementCommand::RegisterOneshotResponder(cmd))?;
         acker_rx.await?;
-        Ok(responder_rx)
+        Ok(ResponderReceiver {
+            rx: Some(responder_rx),
+            rpc_guard: Some(rpc_guard),
+            sent: false,
+        })
+    }
+    /// Send a frame of the channel to server.
+    ///
+    /// It waits for the lock of the publish sequence, so that it is not sent amid
+    /// the frames of a message being published.
+    ///
+    /// Returns [`Error::ChannelUseError`] if the channel is closed, because its channel id
+    /// may already be released and reused by another channel.
+    async fn send(&self, message: OutgoingMessage) -> Result<()> {
+        let publish_seq = self.publish_seq.lock().await;
+        self.send_locked(&publish_seq, message).await
+    }
+
+    /// Same as [`send`](#method.send), with the lock of the publish sequence held by caller.
+    async fn send_locked(
+        &self,
+        _publish_seq: &MutexGuard<'_, u64>,
+        message: OutgoingMessage,
+    ) -> Result<()> {
+        self.check_not_closed()?;
+        self.outgoing_tx.send(message).await?;
+        Ok(())
     }
-    async fn close_handshake(&self) -> Result<()> {
+
+    /// Blocking version of [`send`](#method.send).
+    fn blocking_send(&self, message: OutgoingMessage) -> Result<()> {
+        let _publish_seq = self.publish_seq.blocking_lock();
+        self.check_not_closed()?;
+        self.outgoing_tx.blocking_send(message)?;
+        Ok(())
+    }
+
+    fn check_not_closed(&self) -> Result<()> {
+        if self.state.get().is_closed() {
+            return Err(Error::ChannelUseError(format!(
+                "channel {} is closed",
+                self.channel_id
+            )));
+        }
+        Ok(())
+    }
+
+    /// Mark the channel closed by client and detach its dispatcher without `CloseOk`,
+    /// so that the dispatcher exits and drops pending consumers and responders.
+    ///
+    /// The channel id stays reserved until server responds `CloseOk` or the connection
+    /// is closed, so that a late `CloseOk` is not taken by a new channel with the same id.
+    async fn release(&self) {
+        self.state.close(State::closed_by_client());
+        let cmd = ConnManagementCommand::DetachChannelDispatcher(self.channel_id);
+        let _ = self.conn_mgmt_tx.send(cmd).await;
+    }
+
+    /// Send `close` and wait for `CloseOk` from server.
+    ///
+    /// Once `CloseOk` is received, the dispatcher releases the channel id and exits,
+    /// and drops pending consumers and responders of the channel, so they error out.
+    async fn close_handshake(&self, close: CloseChannel) -> Result<()> {
         let responder_rx = self.register_responder(CloseChannelOk::header()).await?;
         synchronous_request!(
-            self.outgoing_tx,
-            (self.channel_id, CloseChannel::default().into_frame()),
+            self,
+            (self.channel_id, close.into_frame()),
             responder_rx,
             Frame::CloseChannelOk,
             Error::ChannelCloseError
         )?;
-        // deregister channel resource from connection handler,
-        // so that dispatcher will exit automatically.
-        let cmd = ConnManagementCommand::DeregisterChannelResource(self.channel_id);
-        self.conn_mgmt_tx.send(cmd).await?;
         Ok(())
     }
 }
@@ -199,7 +573,7 @@ impl Channel {
     ///
     /// [`Connection::open_channel`]: ../connection/struct.Connection.html#method.open_channel
     pub(in crate::api) fn new(
-        is_open: AtomicBool,
+        state: State,
         connection: Connection,
         channel_id: AmqpChannelId,
         outgoing_tx: mpsc::Sender<OutgoingMessage>,
@@ -207,11 +581,13 @@ impl Channel {
         dispatcher_mgmt_tx: mpsc::UnboundedSender<DispatcherManagementCommand>,
     ) -> Self {
         let shared = Arc::new(SharedChannelInner::new(
-            is_open,
+            state,
             channel_id,
             outgoing_tx,
             conn_mgmt_tx,
             dispatcher_mgmt_tx,
+            connection.message_stamp(),
+            connection.publish_defaults(),
         ));
         let guard = Some(Arc::new(DropGuard(shared.clone())));
         Self {
@@ -235,6 +611,7 @@ impl Channel {
     where
         F: ChannelCallback + Send + 'static,
     {
+        self.mark_stateful();
         let cmd = RegisterChannelCallback {
             callback: Box::new(callback),
         };
@@ -250,19 +627,8 @@ impl Channel {
     async fn register_responder(
         &self,
         method_header: &'static MethodHeader,
-    ) -> Result<oneshot::Receiver<IncomingMessage>> {
-        let (responder, responder_rx) = oneshot::channel();
-        let (acker, acker_rx) = oneshot::channel();
-        let cmd = RegisterOneshotResponder {
-            method_header,
-            responder,
-            acker,
-        };
-        self.shared
-            .dispatcher_mgmt_tx
-            .send(DispatcherManagementCommand::RegisterOneshotResponder(cmd))?;
-        acker_rx.await?;
-        Ok(responder_rx)
+    ) -> Result<ResponderReceiver> {
+        self.shared.register_responder(method_header).await
     }
 
     pub fn channel_id(&self) -> AmqpChannelId {
@@ -274,12 +640,88 @@ impl Channel {
     pub fn is_connection_open(&self) -> bool {
         self.connection.is_open()
     }
-    /// Returns `true` if channel is open.
+    /// Returns `true` if channel is open, see [`State::is_open`].
+    ///
+    /// [`State::is_open`]: ../state/enum.State.html#method.is_open
     pub fn is_open(&self) -> bool {
-        self.shared.is_open.load(Ordering::Relaxed)
+        self.shared.state.is_open()
+    }
+    /// Returns current state of the channel, including the reason once closed.
+    pub fn state(&self) -> State {
+        self.shared.state.get()
+    }
+    /// Wait until the channel is closed, returns [`State::Closed`] with the reason.
+    ///
+    /// Resolves immediately if already closed. The channel is also closed once its
+    /// connection is closed. Supervisory tasks can `select!` on it instead of polling [`state`].
+    ///
+    /// [`State::Closed`]: ../state/enum.State.html#variant.Closed
+    /// [`state`]: struct.Channel.html#method.state
+    pub async fn closed(&self) -> State {
+        self.shared.state.closed().await
+    }
+    pub(crate) fn set_blocked(&self, blocked: bool) {
+        self.shared.state.set_blocked(blocked);
+    }
+    /// Update state to closed, unless it is already closed.
+    pub(crate) fn set_closed(&self, closed: State) {
+        self.shared.state.close(closed);
     }
-    pub(crate) fn set_is_open(&self, is_open: bool) {
-        self.shared.is_open.store(is_open, Ordering::Relaxed);
+
+    /// Set whether to reopen the channel once server closes it due to a soft error,
+    /// i.e. reply code `311` to `313` or `403` to `406`, e.g. `NOT_FOUND` of a passive
+    /// declare or `PRECONDITION_FAILED` of an inequivalent declare.
+    ///
+    /// If enabled, the channel stays open and usable, only the request pending at the time
+    /// fails with the reason. The channel is opened afresh with the same channel id:
+    /// * its consumers are terminated as if the channel is closed,
+    /// * its unacked deliveries and pending publisher confirms are dropped,
+    /// * the prefetch count of [`basic_qos`] is reset,
+    /// * the publisher confirm mode is restored, the transaction mode is not.
+    ///
+    /// The [`ChannelCallback::close`] callback is not invoked for a reopened channel.
+    ///
+    /// # Default
+    ///
+    /// `false`, the channel is closed.
+    ///
+    /// [`basic_qos`]: struct.Channel.html#method.basic_qos
+    /// [`ChannelCallback::close`]: ../callbacks/trait.ChannelCallback.html#tymethod.close
+    pub fn set_reopen_on_soft_error(&self, enabled: bool) {
+        self.shared
+            .reopen_on_soft_error
+            .store(enabled, Ordering::Relaxed);
+    }
+
+    /// Record that the channel has state which must not be carried over to another user,
+    /// see [`is_stateful`](#method.is_stateful).
+    pub(crate) fn mark_stateful(&self) {
+        self.shared.stateful.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if consumers, publisher confirm or transaction mode, QoS, flow
+    /// or callbacks have been set on the channel, so that it is not reused by a pool.
+    pub(crate) fn is_stateful(&self) -> bool {
+        self.shared.stateful.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if the channel is reopened once server closes it with `reply_code`.
+    pub(crate) fn should_reopen(&self, reply_code: u16) -> bool {
+        self.shared.reopen_on_soft_error.load(Ordering::Relaxed)
+            && matches!(reply_code, 311..=313 | 403..=406)
+    }
+
+    /// Forget the state of the channel closed by server, before it is reopened.
+    ///
+    /// Returns the lock of the publish sequence, which is held until the channel is reopened
+    /// so that no message is published before the confirm mode is restored.
+    pub(crate) async fn reset_for_reopen(&self) -> MutexGuard<'_, u64> {
+        let mut publish_seq = self.shared.publish_seq.lock().await;
+        *publish_seq = 1;
+        self.shared.consumer_queues.lock().unwrap().clear();
+        self.shared.unacked.lock().unwrap().clear();
+        self.shared.prefetch_count.store(0, Ordering::Relaxed);
+        publish_seq
     }
 
     /// Asks the server to pause or restart the flow of content data.
@@ -293,9 +735,10 @@ impl Channel {
     ///
     /// Returns error if any failure in communication with server.
     pub async fn flow(&self, active: bool) -> Result<bool> {
+        self.mark_stateful();
         let responder_rx = self.register_responder(FlowOk::header()).await?;
         let flow_ok = synchronous_request!(
-            self.shared.outgoing_tx,
+            self.shared,
             (self.shared.channel_id, Flow::new(active).into_frame()),
             responder_rx,
             Frame::FlowOk,
@@ -317,23 +760,47 @@ impl Channel {
     /// Returns error if any failure in communication with server.
     /// Fail to close the channel may result in `channel leak` in server.
     pub async fn close(self) -> Result<()> {
+        self.close_channel(CloseChannel::default()).await
+    }
+
+    /// Ask the server to close the channel with `reply_code` and `reply_text`,
+    /// e.g. to indicate an error of the client.
+    ///
+    /// Same as [`close`], waits for server's `CloseOk`, then pending consumers and
+    /// requests on the channel error out, and the channel id is released for new channels.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if `reply_text` is longer than 255 bytes, or any failure in
+    /// communication with server.
+    ///
+    /// [`close`]: struct.Channel.html#method.close
+    pub async fn close_with(self, reply_code: u16, reply_text: &str) -> Result<()> {
+        let reply_text = reply_text.try_into().map_err(|_| {
+            Error::ChannelCloseError(format!("reply text is too long: {}", reply_text))
+        })?;
+        self.close_channel(CloseChannel::new(reply_code, reply_text))
+            .await
+    }
+
+    async fn close_channel(self, close: CloseChannel) -> Result<()> {
         // if connection closed, no need to close channel
         if self.is_connection_open() {
             // check if channel is open
-            if let Ok(true) = self.shared.is_open.compare_exchange(
-                true,
-                false,
-                Ordering::Acquire,
-                Ordering::Relaxed,
-            ) {
+            if self.shared.state.begin_close() {
                 #[cfg(feature = "traces")]
                 info!("close channel {}", self);
-                self.shared.close_handshake().await?;
+                self.shared.close_handshake(close).await?;
             }
         }
         Ok(())
     }
 
+    /// Returns `true` if both are handles of the same channel instance.
+    pub(crate) fn is_same(&self, other: &Channel) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
+    }
+
     pub(crate) fn clone_as_secondary(&self) -> Self {
         Self {
             shared: self.shared.clone(),
@@ -348,35 +815,50 @@ impl Drop for DropGuard {
     /// It is not guaranteed to succeed in a clean way because the connection
     /// may already be closed.
     ///
+    /// The close handshake is bounded by 5 seconds. If it fails or times out,
+    /// the dispatcher resources are released anyway, but the channel id is not
+    /// reused until server responds `CloseOk` or the connection is closed.
+    /// Nothing is done if it is dropped outside of a runtime.
+    ///
     /// User is recommended to explictly call the [`close`] method.
     ///
     /// [`close`]: struct.Channel.html#method.close
     fn drop(&mut self) {
-        if let Ok(true) =
-            self.0
-                .is_open
-                .compare_exchange(true, false, Ordering::Acquire, Ordering::Relaxed)
-        {
+        if self.0.state.begin_close() {
             #[cfg(feature = "traces")]
             trace!("drop channel {}", self.0.channel_id);
 
             let inner = self.0.clone();
-            tokio::spawn(async move {
+            let spawned = rt::try_spawn(async move {
                 #[cfg(feature = "traces")]
                 info!("try to close channel {} at drop", inner.channel_id);
-                if let Err(err) = inner.close_handshake().await {
-                    // Compliance: A peer that detects a socket closure without having received a Channel.Close-Ok
-                    // handshake method SHOULD log the error.
-                    #[cfg(feature = "traces")]
-                    error!(
-                        "failed to gracefully close channel {} at drop, cause: '{}'",
-                        inner.channel_id, err,
-                    );
-                } else {
-                    #[cfg(feature = "traces")]
-                    info!("channel {} is closed OK after drop", inner.channel_id);
-                }
+                let deadline = Instant::now() + DROP_CLOSE_TIMEOUT;
+                let close = inner.close_handshake(CloseChannel::default());
+                let _cause = match rt::timeout_at(deadline, close).await {
+                    Some(Ok(())) => {
+                        #[cfg(feature = "traces")]
+                        info!("channel {} is closed OK after drop", inner.channel_id);
+                        return;
+                    }
+                    Some(Err(err)) => err.to_string(),
+                    None => "timeout".to_string(),
+                };
+                // Compliance: A peer that detects a socket closure without having received a Channel.Close-Ok
+                // handshake method SHOULD log the error.
+                #[cfg(feature = "traces")]
+                error!(
+                    "failed to gracefully close channel {} at drop, cause: '{}'",
+                    inner.channel_id, _cause,
+                );
+                inner.release().await;
             });
+            if !spawned {
+                #[cfg(feature = "traces")]
+                error!(
+                    "failed to close channel {} at drop, no runtime",
+                    self.0.channel_id
+                );
+            }
         }
     }
 }
@@ -387,7 +869,7 @@ impl fmt::Display for Channel {
             f,
             "{} [{}] of connection {}",
             self.channel_id(),
-            if self.is_open() { "open" } else { "closed" },
+            self.state(),
             self.connection,
         )
     }
@@ -395,18 +877,37 @@ impl fmt::Display for Channel {
 ///////////////////////////////////////////////////////////////////////////////
 impl SharedChannelInner {
     fn new(
-        is_open: AtomicBool,
+        state: State,
         channel_id: AmqpChannelId,
         outgoing_tx: mpsc::Sender<OutgoingMessage>,
         conn_mgmt_tx: mpsc::Sender<ConnManagementCommand>,
         dispatcher_mgmt_tx: mpsc::UnboundedSender<DispatcherManagementCommand>,
+        message_stamp: Option<Arc<MessageStamp>>,
+        publish_defaults: Option<Arc<PublishDefaults>>,
     ) -> Self {
         Self {
-            is_open,
+            state: SharedState::new(state),
             channel_id,
             outgoing_tx,
             conn_mgmt_tx,
             dispatcher_mgmt_tx,
+            confirm_mode: AtomicBool::new(false),
+            publish_seq: Mutex::new(1),
+            nack_storm_threshold: AtomicU64::new(0),
+            transient_queues: std::sync::Mutex::new(HashMap::new()),
+            consumer_queues: std::sync::Mutex::new(HashMap::new()),
+            unacked: std::sync::Mutex::new(BTreeMap::new()),
+            unacked_warning_threshold: AtomicUsize::new(0),
+            ack_deadline_warning: AtomicU64::new(0),
+            prefetch_count: AtomicU16::new(0),
+            message_stamp: std::sync::Mutex::new(message_stamp),
+            publish_defaults: std::sync::Mutex::new(publish_defaults),
+            rate_limiter: std::sync::Mutex::new(None),
+            publisher_event_txs: std::sync::Mutex::new(Vec::new()),
+            consumer_buffers: std::sync::Mutex::new(HashMap::new()),
+            rpc_lock: Arc::new(Mutex::new(())),
+            reopen_on_soft_error: AtomicBool::new(false),
+            stateful: AtomicBool::new(false),
         }
     }
 }
@@ -416,11 +917,17 @@ mod tests {
     use tokio::time;
 
     use crate::{
-        channel::Channel,
+        channel::{
+            BasicAckArguments, BasicConsumeArguments, BasicPublishArguments, Channel,
+            QueueDeclareArguments, QueueDeleteArguments,
+        },
         connection::{Connection, OpenConnectionArguments},
-        test_utils::setup_logging,
+        error::Error,
+        test_utils::{setup_logging, ChannelEvent, ForwardingChannelCallback},
+        BasicProperties,
     };
     use std::marker::PhantomData;
+    use tokio::sync::mpsc;
 
     #[ignore = "https://github.com/gftea/amqprs/issues/69"]
     #[tokio::test]
@@ -463,6 +970,310 @@ mod tests {
         conn.close().await.unwrap();
         time::sleep(time::Duration::from_millis(100)).await;
     }
+
+    #[test]
+    fn test_channel_drop_outside_runtime() {
+        setup_logging();
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (conn, channel) = runtime.block_on(async {
+            let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+                .connection_name("test_channel_drop_outside_runtime")
+                .finish();
+            let conn = Connection::open(&args).await.unwrap();
+            let channel = conn.open_channel(None).await.unwrap();
+            (conn, channel)
+        });
+        drop(runtime);
+        // must not panic without a runtime
+        drop(channel);
+        drop(conn);
+    }
+
+    #[tokio::test]
+    async fn test_channel_concurrent_synchronous_requests() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_channel_concurrent_synchronous_requests")
+            .finish();
+        let conn = Connection::open(&args).await.unwrap();
+        let channel = conn.open_channel(None).await.unwrap();
+
+        let declare = |channel: Channel, queue_name: String| async move {
+            let args = QueueDeclareArguments::new(&queue_name)
+                .auto_delete(true)
+                .finish();
+            let (declared, ..) = channel.queue_declare(args).await.unwrap().unwrap();
+            assert_eq!(queue_name, declared);
+            channel
+                .queue_delete(QueueDeleteArguments::new(&queue_name))
+                .await
+                .unwrap();
+        };
+        let tasks: Vec<_> = (0..10)
+            .map(|i| {
+                tokio::spawn(declare(
+                    channel.clone(),
+                    format!("amqprs.test.concurrent.{}", i),
+                ))
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        channel.close().await.unwrap();
+        conn.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_channel_callback_publish_return() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami");
+        let conn = Connection::open(&args).await.unwrap();
+        let channel = conn.open_channel(None).await.unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        channel
+            .register_callback(ForwardingChannelCallback(tx))
+            .await
+            .unwrap();
+
+        // mandatory message to an unroutable key is returned by server
+        let args = BasicPublishArguments::new("amq.direct", "amqprs.test.unroutable")
+            .mandatory(true)
+            .finish();
+        channel
+            .basic_publish(BasicProperties::default(), b"returned".to_vec(), args)
+            .await
+            .unwrap();
+
+        let event = time::timeout(time::Duration::from_secs(1), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        // NO_ROUTE
+        assert_eq!(ChannelEvent::PublishReturn(312), event);
+
+        channel.close().await.unwrap();
+        conn.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_channel_callback_transient_queue_removed() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami");
+        let conn = Connection::open(&args).await.unwrap();
+        let channel = conn.open_channel(None).await.unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        channel
+            .register_callback(ForwardingChannelCallback(tx))
+            .await
+            .unwrap();
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::exclusive_server_named())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(vec![queue_name.clone()], channel.transient_queues());
+        channel
+            .basic_consume_rx(BasicConsumeArguments::new(&queue_name, ""))
+            .await
+            .unwrap();
+
+        // delete the queue on another channel, server cancels the consumer
+        let other = conn.open_channel(None).await.unwrap();
+        other
+            .queue_delete(QueueDeleteArguments::new(&queue_name))
+            .await
+            .unwrap();
+
+        let event = time::timeout(time::Duration::from_secs(1), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(ChannelEvent::TransientQueueRemoved(queue_name), event);
+        assert!(channel.transient_queues().is_empty());
+
+        other.close().await.unwrap();
+        channel.close().await.unwrap();
+        conn.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_channel_close_with_releases_id() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_channel_close_with_releases_id")
+            .finish();
+        let conn = Connection::open(&args).await.unwrap();
+
+        // closed by client with reply code and text
+        let channel = conn.open_channel(Some(7)).await.unwrap();
+        channel.close_with(200, "done").await.unwrap();
+
+        // closed by server, because the queue does not exist
+        let stale = conn.open_channel(Some(7)).await.unwrap();
+        let mut args = QueueDeclareArguments::new("amqprs.test.close_with.not_exist");
+        args.passive(true);
+        assert!(stale.queue_declare(args).await.is_err());
+        time::sleep(time::Duration::from_millis(100)).await;
+        assert!(!stale.is_open());
+
+        let channel = conn.open_channel(Some(7)).await.unwrap();
+        assert!(channel.is_open());
+        // the stale handle must not send frames over the reused channel id
+        match stale.basic_ack(BasicAckArguments::new(1, false)).await {
+            Err(Error::ChannelUseError(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        time::sleep(time::Duration::from_millis(100)).await;
+        assert!(channel.is_open());
+        channel.close().await.unwrap();
+        conn.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_channel_ack_deadline_warning() {
+        use crate::channel::BasicGetArguments;
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_channel_ack_deadline_warning")
+            .finish();
+        let conn = Connection::open(&args).await.unwrap();
+        let channel = conn.open_channel(None).await.unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        channel
+            .register_callback(ForwardingChannelCallback(tx))
+            .await
+            .unwrap();
+        channel.set_ack_deadline_warning(time::Duration::from_millis(200));
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+        channel
+            .basic_publish(
+                BasicProperties::default(),
+                b"stuck".to_vec(),
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .await
+            .unwrap();
+        time::sleep(time::Duration::from_millis(100)).await;
+        let (get_ok, ..) = channel
+            .basic_get(BasicGetArguments::new(&queue_name))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let event = time::timeout(time::Duration::from_secs(3), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            ChannelEvent::AckDeadlineApproaching(get_ok.delivery_tag()),
+            event
+        );
+        assert!(channel.oldest_unacked_age().unwrap() >= time::Duration::from_millis(200));
+
+        channel.close().await.unwrap();
+        conn.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_channel_reopen_on_soft_error() {
+        use crate::channel::{ConfirmSelectArguments, Confirmation};
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_channel_reopen_on_soft_error")
+            .finish();
+        let conn = Connection::open(&args).await.unwrap();
+        let channel = conn.open_channel(None).await.unwrap();
+        channel.set_reopen_on_soft_error(true);
+        channel
+            .confirm_select(ConfirmSelectArguments::default())
+            .await
+            .unwrap();
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+
+        // NOT_FOUND fails the declare only
+        let args = QueueDeclareArguments::new("amqprs.test.reopen_on_soft_error.missing")
+            .passive(true)
+            .finish();
+        match channel.queue_declare(args).await {
+            Err(Error::ChannelUseError(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(channel.is_open());
+
+        // still in confirm mode
+        let confirmation = channel
+            .basic_publish_confirm(
+                BasicProperties::default(),
+                b"reopened".to_vec(),
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .await
+            .unwrap();
+        assert_eq!(Confirmation::Ack, confirmation);
+
+        channel.close().await.unwrap();
+        conn.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_request_holds_rpc_lock_until_response() {
+        use super::ResponderReceiver;
+        use crate::frame::CloseChannelOk;
+        use std::sync::Arc;
+        use tokio::sync::{oneshot, Mutex};
+
+        let rpc_lock = Arc::new(Mutex::new(()));
+
+        // dropped before the request is sent, lock is released immediately
+        let (_responder, rx) = oneshot::channel();
+        let receiver = ResponderReceiver {
+            rx: Some(rx),
+            rpc_guard: Some(rpc_lock.clone().lock_owned().await),
+            sent: false,
+        };
+        drop(receiver);
+        assert!(rpc_lock.try_lock().is_ok());
+
+        // dropped after the request is sent, lock is held until the response is received
+        let (responder, rx) = oneshot::channel();
+        let mut receiver = ResponderReceiver {
+            rx: Some(rx),
+            rpc_guard: Some(rpc_lock.clone().lock_owned().await),
+            sent: false,
+        };
+        assert!(time::timeout(time::Duration::from_millis(10), &mut receiver)
+            .await
+            .is_err());
+        drop(receiver);
+        time::sleep(time::Duration::from_millis(10)).await;
+        assert!(rpc_lock.try_lock().is_err());
+
+        responder.send(CloseChannelOk.into_frame()).unwrap();
+        let _guard = time::timeout(time::Duration::from_secs(1), rpc_lock.lock())
+            .await
+            .unwrap();
+    }
 }
 
 /////////////////////////////////////////////////////////////////////////////
@@ -470,14 +1281,29 @@ mod dispatcher;
 pub(crate) use dispatcher::*;
 
 mod basic;
+mod body_chunks;
+mod body_stream;
 mod confim;
+mod delivery;
 mod exchange;
+mod publish_defaults;
+mod publisher_events;
 mod queue;
+mod rate_limit;
+mod roles;
+mod stamp;
 mod tx;
 
 // public APIs
 pub use basic::*;
+pub use body_chunks::*;
+pub use body_stream::*;
 pub use confim::*;
+pub use delivery::*;
 pub use exchange::*;
+pub use publish_defaults::*;
+pub use publisher_events::*;
 pub use queue::*;
-pub use tx::*;
+pub use rate_limit::*;
+pub use roles::*;
+pub use stamp::*;
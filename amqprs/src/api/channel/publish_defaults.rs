@@ -0,0 +1,212 @@
+use super::{BasicPublishArguments, Channel};
+use crate::{frame::BasicProperties, DELIVERY_MODE_PERSISTENT};
+
+/// Options applied to every message published on a channel, unless the publisher
+/// has set them already.
+///
+/// Set it for all channels of a connection by [`OpenConnectionArguments::publish_defaults`],
+/// or for a channel by [`Channel::set_publish_defaults`].
+/// For [`PreparedPublish`], they are applied once when the message is prepared.
+///
+/// # Support chainable methods to build arguments
+/// ```
+/// # use amqprs::channel::PublishDefaults;
+///
+/// let defaults = PublishDefaults::new()
+///     .persistent()
+///     .app_id("billing")
+///     .content_type("application/json")
+///     .finish();
+/// ```
+///
+/// [`OpenConnectionArguments::publish_defaults`]: ../connection/struct.OpenConnectionArguments.html#method.publish_defaults
+/// [`Channel::set_publish_defaults`]: struct.Channel.html#method.set_publish_defaults
+/// [`PreparedPublish`]: struct.PreparedPublish.html
+#[derive(Debug, Clone, Default)]
+pub struct PublishDefaults {
+    /// Publish with `mandatory` flag. A publisher can't clear it per message.
+    /// Default: `false`.
+    pub mandatory: bool,
+    /// Default: [`None`].
+    pub delivery_mode: Option<u8>,
+    /// Default: [`None`].
+    pub app_id: Option<String>,
+    /// Default: [`None`].
+    pub content_type: Option<String>,
+}
+
+impl PublishDefaults {
+    /// Create new defaults which change nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        mandatory, bool
+    }
+
+    /// `delivery_mode`: either [`DELIVERY_MODE_TRANSIENT`] or [`DELIVERY_MODE_PERSISTENT`].
+    ///
+    /// [`DELIVERY_MODE_TRANSIENT`]: ../constant.DELIVERY_MODE_TRANSIENT.html
+    /// [`DELIVERY_MODE_PERSISTENT`]: ../constant.DELIVERY_MODE_PERSISTENT.html
+    pub fn delivery_mode(&mut self, delivery_mode: u8) -> &mut Self {
+        self.delivery_mode = Some(delivery_mode);
+        self
+    }
+
+    /// Publish persistent messages by default.
+    pub fn persistent(&mut self) -> &mut Self {
+        self.delivery_mode(DELIVERY_MODE_PERSISTENT)
+    }
+
+    /// Chainable setter method.
+    pub fn app_id(&mut self, app_id: &str) -> &mut Self {
+        self.app_id = Some(app_id.to_owned());
+        self
+    }
+
+    /// Chainable setter method.
+    pub fn content_type(&mut self, content_type: &str) -> &mut Self {
+        self.content_type = Some(content_type.to_owned());
+        self
+    }
+
+    /// Finish chaining and returns a new argument according to chained configurations.
+    pub fn finish(&mut self) -> Self {
+        self.clone()
+    }
+
+    /// Set the configured options which are not set by the publisher.
+    pub(crate) fn apply(
+        &self,
+        basic_properties: &mut BasicProperties,
+        args: &mut BasicPublishArguments,
+    ) {
+        args.mandatory |= self.mandatory;
+        if let (Some(delivery_mode), None) = (self.delivery_mode, basic_properties.delivery_mode())
+        {
+            basic_properties.with_delivery_mode(delivery_mode);
+        }
+        if let (Some(app_id), None) = (&self.app_id, basic_properties.app_id()) {
+            basic_properties.with_app_id(app_id);
+        }
+        if let (Some(content_type), None) = (&self.content_type, basic_properties.content_type()) {
+            basic_properties.with_content_type(content_type);
+        }
+    }
+}
+
+impl Channel {
+    /// Set the options applied to every message published on the channel,
+    /// [`None`] to disable. See [`PublishDefaults`].
+    ///
+    /// It overrides the one given by [`OpenConnectionArguments::publish_defaults`].
+    ///
+    /// [`PublishDefaults`]: struct.PublishDefaults.html
+    /// [`OpenConnectionArguments::publish_defaults`]: ../connection/struct.OpenConnectionArguments.html#method.publish_defaults
+    pub fn set_publish_defaults(&self, defaults: Option<PublishDefaults>) {
+        *self.shared.publish_defaults.lock().unwrap() = defaults.map(Into::into);
+    }
+
+    /// Apply the publish defaults of the channel if any.
+    pub(crate) fn apply_publish_defaults(
+        &self,
+        basic_properties: &mut BasicProperties,
+        args: &mut BasicPublishArguments,
+    ) {
+        let defaults = self.shared.publish_defaults.lock().unwrap().clone();
+        if let Some(defaults) = defaults {
+            defaults.apply(basic_properties, args);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PublishDefaults;
+    use crate::{
+        channel::{BasicGetArguments, BasicPublishArguments, QueueDeclareArguments},
+        connection::{Connection, OpenConnectionArguments},
+        test_utils::setup_logging,
+        BasicProperties, DELIVERY_MODE_PERSISTENT, DELIVERY_MODE_TRANSIENT,
+    };
+
+    #[test]
+    fn test_publish_defaults_keep_given_options() {
+        let defaults = PublishDefaults::new()
+            .mandatory(true)
+            .persistent()
+            .app_id("billing")
+            .content_type("application/json")
+            .finish();
+
+        let mut basic_properties = BasicProperties::default();
+        let mut args = BasicPublishArguments::new("", "q");
+        defaults.apply(&mut basic_properties, &mut args);
+        assert!(args.mandatory);
+        assert_eq!(
+            Some(DELIVERY_MODE_PERSISTENT),
+            basic_properties.delivery_mode()
+        );
+        assert_eq!("billing", basic_properties.app_id().unwrap());
+        assert_eq!("application/json", basic_properties.content_type().unwrap());
+
+        let mut basic_properties = BasicProperties::default()
+            .with_delivery_mode(DELIVERY_MODE_TRANSIENT)
+            .with_app_id("orders")
+            .with_content_type("text/plain")
+            .finish();
+        defaults.apply(&mut basic_properties, &mut args);
+        assert_eq!(
+            Some(DELIVERY_MODE_TRANSIENT),
+            basic_properties.delivery_mode()
+        );
+        assert_eq!("orders", basic_properties.app_id().unwrap());
+        assert_eq!("text/plain", basic_properties.content_type().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_publish_defaults() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_publish_defaults")
+            .publish_defaults(PublishDefaults::new().app_id("test").persistent().finish())
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let publish = || {
+            channel.basic_publish(
+                BasicProperties::default(),
+                "defaults",
+                BasicPublishArguments::new("", &queue_name),
+            )
+        };
+        publish().await.unwrap();
+        channel.set_publish_defaults(Some(
+            PublishDefaults::new().content_type("text/plain").finish(),
+        ));
+        publish().await.unwrap();
+
+        let get_args = BasicGetArguments::new(&queue_name).no_ack(true).finish();
+        let (_, basic_properties, _) = channel.basic_get(get_args.clone()).await.unwrap().unwrap();
+        assert_eq!("test", basic_properties.app_id().unwrap());
+        assert_eq!(
+            Some(DELIVERY_MODE_PERSISTENT),
+            basic_properties.delivery_mode()
+        );
+        let (_, basic_properties, _) = channel.basic_get(get_args).await.unwrap().unwrap();
+        assert!(basic_properties.app_id().is_none());
+        assert_eq!("text/plain", basic_properties.content_type().unwrap());
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+}
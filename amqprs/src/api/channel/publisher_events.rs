@@ -0,0 +1,183 @@
+use tokio::sync::mpsc;
+
+use super::Channel;
+use crate::frame::{BasicProperties, Return};
+
+/// A message returned by server, because it is published with `mandatory`
+/// but can't be routed to any queue.
+#[derive(Debug, Clone)]
+pub struct ReturnedMessage {
+    /// The `basic.return` method, with the reply code and the exchange and routing key
+    /// of the message.
+    pub ret: Return,
+    /// Properties of the message.
+    pub basic_properties: BasicProperties,
+    /// Content of the message.
+    pub content: Vec<u8>,
+}
+
+/// Event of publishing on a channel, see [`Channel::publisher_events`].
+///
+/// [`Channel::publisher_events`]: struct.Channel.html#method.publisher_events
+#[derive(Debug, Clone)]
+pub enum PublisherEvent {
+    /// Server has confirmed the message of `delivery_tag` in publisher confirm mode,
+    /// and all messages up to it if `multiple` is `true`.
+    Ack {
+        /// sequence number of the message.
+        delivery_tag: u64,
+        /// `true` if all messages up to `delivery_tag` are confirmed.
+        multiple: bool,
+    },
+    /// Server has rejected the message of `delivery_tag` in publisher confirm mode,
+    /// and all messages up to it if `multiple` is `true`.
+    Nack {
+        /// sequence number of the message.
+        delivery_tag: u64,
+        /// `true` if all messages up to `delivery_tag` are rejected.
+        multiple: bool,
+    },
+    /// Server has returned an unroutable message.
+    /// In publisher confirm mode, it comes before the ack of the message.
+    Return(Box<ReturnedMessage>),
+    /// Server has asked to stop (`false`) or restart (`true`) publishing by `channel.flow`.
+    FlowChanged(bool),
+}
+
+/// Stream of [`PublisherEvent`]s of a channel, returned by [`Channel::publisher_events`].
+///
+/// The events are in the order they are received from server.
+/// The stream ends when the channel is closed, it survives the channel being
+/// reopened after a soft error.
+///
+/// If feature "stream" is enabled, it implements `futures_core::Stream`.
+///
+/// [`Channel::publisher_events`]: struct.Channel.html#method.publisher_events
+pub struct PublisherEvents {
+    rx: mpsc::UnboundedReceiver<PublisherEvent>,
+}
+
+impl PublisherEvents {
+    /// Receive next event, returns [`None`] if the stream ends.
+    pub async fn recv(&mut self) -> Option<PublisherEvent> {
+        self.rx.recv().await
+    }
+}
+
+#[cfg(feature = "stream")]
+impl futures_core::Stream for PublisherEvents {
+    type Item = PublisherEvent;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+impl Channel {
+    /// Returns a stream of acks, nacks, returned messages and flow changes of the channel,
+    /// so that an outbox processor can drive its state from one ordered source of events,
+    /// instead of multiple [`ChannelCallback`] methods.
+    ///
+    /// Only events which occur after subscription are received. Events are buffered
+    /// without bound until received, so a subscriber must keep receiving or drop the stream.
+    /// The callback registered on the channel is still called for each event.
+    ///
+    /// [`ChannelCallback`]: ../callbacks/trait.ChannelCallback.html
+    pub fn publisher_events(&self) -> PublisherEvents {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.shared.publisher_event_txs.lock().unwrap().push(tx);
+        PublisherEvents { rx }
+    }
+
+    /// Returns `true` if any stream of publisher events is subscribed.
+    pub(crate) fn has_publisher_events(&self) -> bool {
+        !self.shared.publisher_event_txs.lock().unwrap().is_empty()
+    }
+
+    /// Send `event` to subscribers, and forget the dropped ones.
+    pub(crate) fn notify_publisher_event(&self, event: PublisherEvent) {
+        self.shared
+            .publisher_event_txs
+            .lock()
+            .unwrap()
+            .retain(|tx| tx.send(event.clone()).is_ok());
+    }
+
+    /// End the streams of publisher events once the channel is closed.
+    pub(crate) fn close_publisher_events(&self) {
+        self.shared.publisher_event_txs.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time;
+
+    use super::PublisherEvent;
+    use crate::{
+        channel::{BasicPublishArguments, ConfirmSelectArguments},
+        connection::{Connection, OpenConnectionArguments},
+        test_utils::setup_logging,
+        BasicProperties,
+    };
+
+    #[tokio::test]
+    async fn test_publisher_events() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_publisher_events")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+        channel
+            .confirm_select(ConfirmSelectArguments::default())
+            .await
+            .unwrap();
+        let mut events = channel.publisher_events();
+
+        // unroutable message is returned, then acked
+        channel
+            .basic_publish(
+                BasicProperties::default(),
+                b"unroutable".to_vec(),
+                BasicPublishArguments::new("amq.topic", "amqprs.test.publisher_events")
+                    .mandatory(true)
+                    .finish(),
+            )
+            .await
+            .unwrap();
+        let timeout = time::Duration::from_secs(5);
+        match time::timeout(timeout, events.recv())
+            .await
+            .unwrap()
+            .unwrap()
+        {
+            PublisherEvent::Return(returned) => {
+                assert_eq!(b"unroutable".to_vec(), returned.content);
+                assert_eq!("amqprs.test.publisher_events", returned.ret.routing_key());
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        assert!(matches!(
+            time::timeout(timeout, events.recv())
+                .await
+                .unwrap()
+                .unwrap(),
+            PublisherEvent::Ack {
+                delivery_tag: 1,
+                ..
+            }
+        ));
+
+        channel.close().await.unwrap();
+        assert!(time::timeout(timeout, events.recv())
+            .await
+            .unwrap()
+            .is_none());
+        connection.close().await.unwrap();
+    }
+}
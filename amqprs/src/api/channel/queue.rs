@@ -1,8 +1,14 @@
-use amqp_serde::types::AmqpMessageCount;
+use std::fmt;
 
-use super::Channel;
+use amqp_serde::types::{AmqpMessageCount, FieldValue};
+
+use super::{Channel, HeadersBinding, DIRECT_REPLY_TO_QUEUE};
 use crate::{
-    api::{error::Error, FieldTable, Result},
+    api::{
+        error::Error,
+        naming::{check_exchange_name, check_queue_declare, check_queue_name},
+        FieldTable, Result,
+    },
     frame::{
         BindQueue, BindQueueOk, DeclareQueue, DeclareQueueOk, DeleteQueue, DeleteQueueOk, Frame,
         PurgeQueue, PurgeQueueOk, UnbindQueue, UnbindQueueOk,
@@ -12,6 +18,83 @@ use crate::{
 #[cfg(feature = "compliance_assert")]
 use crate::api::compliance_asserts::{assert_exchange_name, assert_queue_name};
 
+////////////////////////////////////////////////////////////////////////////////
+/// Behaviour of a queue when its length limit is reached,
+/// see [`QueueDeclareArguments::overflow`].
+///
+/// See [RabbitMQ queue length limit](https://www.rabbitmq.com/maxlength.html#overflow-behaviour).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueOverflow {
+    /// Drop or dead-letter messages from the front of the queue, server's default.
+    DropHead,
+    /// Discard the most recently published messages, publishers are nacked in
+    /// publisher confirm mode.
+    RejectPublish,
+    /// Same as [`RejectPublish`](#variant.RejectPublish), and dead-letter the rejected messages.
+    RejectPublishDlx,
+}
+
+impl QueueOverflow {
+    /// Returns the value of `x-overflow` argument.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueueOverflow::DropHead => "drop-head",
+            QueueOverflow::RejectPublish => "reject-publish",
+            QueueOverflow::RejectPublishDlx => "reject-publish-dlx",
+        }
+    }
+}
+
+impl fmt::Display for QueueOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+/// Type of a queue, see [`QueueDeclareArguments::queue_type`].
+///
+/// See [RabbitMQ queue types](https://www.rabbitmq.com/queues.html#optional-arguments).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueType {
+    /// Classic queue, server's default.
+    Classic,
+    /// Replicated queue based on Raft, must be durable, non-exclusive and non-autodelete.
+    ///
+    /// See [RabbitMQ quorum queues](https://www.rabbitmq.com/quorum-queues.html).
+    Quorum,
+    /// Replicated append-only log, must be durable, non-exclusive and non-autodelete.
+    ///
+    /// See [RabbitMQ streams](https://www.rabbitmq.com/streams.html).
+    Stream,
+}
+
+impl QueueType {
+    /// Returns the value of `x-queue-type` argument.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueueType::Classic => "classic",
+            QueueType::Quorum => "quorum",
+            QueueType::Stream => "stream",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "classic" => Some(QueueType::Classic),
+            "quorum" => Some(QueueType::Quorum),
+            "stream" => Some(QueueType::Stream),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for QueueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 /// Arguments for [`queue_declare`]
 ///
@@ -138,6 +221,108 @@ impl QueueDeclareArguments {
         /// Chainable setter method.
         arguments, FieldTable
     }
+
+    /// Limit the number of ready messages in the queue by `x-max-length` argument.
+    ///
+    /// See [RabbitMQ queue length limit](https://www.rabbitmq.com/maxlength.html).
+    pub fn max_length(&mut self, max_length: u64) -> &mut Self {
+        self.arguments.insert(
+            "x-max-length".try_into().unwrap(),
+            FieldValue::l(i64::try_from(max_length).unwrap_or(i64::MAX)),
+        );
+        self
+    }
+
+    /// Limit the total body size of ready messages in the queue by `x-max-length-bytes` argument.
+    ///
+    /// See [RabbitMQ queue length limit](https://www.rabbitmq.com/maxlength.html).
+    pub fn max_length_bytes(&mut self, max_length_bytes: u64) -> &mut Self {
+        self.arguments.insert(
+            "x-max-length-bytes".try_into().unwrap(),
+            FieldValue::l(i64::try_from(max_length_bytes).unwrap_or(i64::MAX)),
+        );
+        self
+    }
+
+    /// Set the behaviour when the length limit is reached by `x-overflow` argument.
+    ///
+    /// See [`QueueOverflow`].
+    pub fn overflow(&mut self, overflow: QueueOverflow) -> &mut Self {
+        self.arguments
+            .insert("x-overflow".try_into().unwrap(), overflow.as_str().into());
+        self
+    }
+
+    /// Enable message priorities on a classic queue by `x-max-priority` argument,
+    /// messages with higher [`BasicProperties::priority`] are delivered first.
+    ///
+    /// See [RabbitMQ priority queues](https://www.rabbitmq.com/priority.html).
+    ///
+    /// [`BasicProperties::priority`]: ../struct.BasicProperties.html#method.priority
+    pub fn max_priority(&mut self, max_priority: u8) -> &mut Self {
+        self.arguments.insert(
+            "x-max-priority".try_into().unwrap(),
+            FieldValue::I(max_priority.into()),
+        );
+        self
+    }
+
+    /// Enable single active consumer by `x-single-active-consumer` argument, only one
+    /// consumer of the queue receives deliveries at a time, the others are waiting and
+    /// the next one is activated when the active consumer is cancelled or its channel closes.
+    ///
+    /// The server does not notify an AMQP 0-9-1 consumer whether it is active or waiting,
+    /// a consumer becomes active when it receives its first delivery.
+    ///
+    /// See [RabbitMQ single active consumer](https://www.rabbitmq.com/consumers.html#single-active-consumer).
+    pub fn single_active_consumer(&mut self, enabled: bool) -> &mut Self {
+        self.arguments.insert(
+            "x-single-active-consumer".try_into().unwrap(),
+            FieldValue::t(enabled),
+        );
+        self
+    }
+
+    /// Set the type of the queue by `x-queue-type` argument.
+    ///
+    /// [`QueueType::Quorum`] and [`QueueType::Stream`] queues must be durable,
+    /// non-exclusive and non-autodelete, otherwise [`queue_declare`] returns an error
+    /// without contacting the server.
+    ///
+    /// [`queue_declare`]: struct.Channel.html#method.queue_declare
+    pub fn queue_type(&mut self, queue_type: QueueType) -> &mut Self {
+        self.arguments.insert(
+            "x-queue-type".try_into().unwrap(),
+            queue_type.as_str().into(),
+        );
+        self
+    }
+
+    /// Limit the number of redeliveries of a message by `x-delivery-limit` argument,
+    /// the message is dropped or dead-lettered once the limit is exceeded.
+    /// Only applies to [`QueueType::Quorum`] queues.
+    ///
+    /// See [RabbitMQ poison message handling](https://www.rabbitmq.com/quorum-queues.html#poison-message-handling).
+    pub fn delivery_limit(&mut self, limit: u32) -> &mut Self {
+        self.arguments.insert(
+            "x-delivery-limit".try_into().unwrap(),
+            FieldValue::l(limit.into()),
+        );
+        self
+    }
+
+    /// Set the initial number of replicas by `x-quorum-initial-group-size` argument.
+    /// Only applies to [`QueueType::Quorum`] queues.
+    ///
+    /// See [RabbitMQ quorum queue replication](https://www.rabbitmq.com/quorum-queues.html#replication-factor).
+    pub fn quorum_initial_group_size(&mut self, size: u32) -> &mut Self {
+        self.arguments.insert(
+            "x-quorum-initial-group-size".try_into().unwrap(),
+            FieldValue::l(size.into()),
+        );
+        self
+    }
+
     /// Finish chained configuration and return new arguments.
     pub fn finish(&mut self) -> Self {
         #[cfg(feature = "compliance_assert")]
@@ -145,6 +330,38 @@ impl QueueDeclareArguments {
 
         self.clone()
     }
+
+    /// Returns the queue type set by `x-queue-type` argument, if recognized.
+    fn declared_queue_type(&self) -> Option<QueueType> {
+        match self.arguments.get(&"x-queue-type".try_into().unwrap()) {
+            Some(FieldValue::S(s)) => QueueType::parse(s.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Check that the flags are compatible with the queue type.
+    fn validate_queue_type(&self) -> Result<()> {
+        if self.passive {
+            return Ok(());
+        }
+        let queue_type = match self.declared_queue_type() {
+            Some(queue_type @ (QueueType::Quorum | QueueType::Stream)) => queue_type,
+            _ => return Ok(()),
+        };
+        let incompatible = if !self.durable {
+            "non-durable"
+        } else if self.exclusive {
+            "exclusive"
+        } else if self.auto_delete {
+            "autodelete"
+        } else {
+            return Ok(());
+        };
+        Err(Error::ChannelUseError(format!(
+            "{} queue can not be {}",
+            queue_type, incompatible
+        )))
+    }
 }
 ////////////////////////////////////////////////////////////////////////////////
 /// Arguments for [`queue_bind`]
@@ -162,7 +379,8 @@ pub struct QueueBindArguments {
     pub routing_key: String,
     /// Default: `false`.
     pub no_wait: bool,
-    /// Default: empty table.
+    /// Binding arguments for headers exchanges and plugins, e.g. `x-match`,
+    /// see [`headers_binding`](#method.headers_binding). Default: empty table.
     pub arguments: FieldTable,
 }
 
@@ -204,6 +422,13 @@ impl QueueBindArguments {
         /// Chainable setter method.
         arguments, FieldTable
     }
+    /// Insert the arguments of a headers exchange binding, see [`HeadersBinding`].
+    ///
+    /// [`HeadersBinding`]: struct.HeadersBinding.html
+    pub fn headers_binding(&mut self, binding: HeadersBinding) -> &mut Self {
+        binding.insert_into(&mut self.arguments);
+        self
+    }
     /// Finish chained configuration and return new arguments.
     pub fn finish(&mut self) -> Self {
         #[cfg(feature = "compliance_assert")]
@@ -240,6 +465,21 @@ impl QueuePurgeArguments {
             no_wait: false,
         }
     }
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        queue, String
+    }
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        no_wait, bool
+    }
+    /// Finish chained configuration and return new arguments.
+    pub fn finish(&mut self) -> Self {
+        #[cfg(feature = "compliance_assert")]
+        assert_queue_name(&self.queue);
+
+        self.clone()
+    }
 }
 ////////////////////////////////////////////////////////////////////////////////
 /// Arguments for [`queue_delete`]
@@ -346,6 +586,13 @@ impl QueueUnbindArguments {
         /// Chainable setter method.
         arguments, FieldTable
     }
+    /// Insert the arguments of a headers exchange binding, see [`HeadersBinding`].
+    ///
+    /// [`HeadersBinding`]: struct.HeadersBinding.html
+    pub fn headers_binding(&mut self, binding: HeadersBinding) -> &mut Self {
+        binding.insert_into(&mut self.arguments);
+        self
+    }
     /// Finish chained configuration and return new arguments.
     pub fn finish(&mut self) -> Self {
         #[cfg(feature = "compliance_assert")]
@@ -368,13 +615,41 @@ impl Channel {
     /// Returns a tuple `(queue_name, message_count, consumer_count)`
     /// if `no_wait` argument is `false`, otherwise returns [`None`].
     ///
+    /// If queue name is empty, server generates a unique queue name, which is returned
+    /// as `queue_name`. See [`QueueDeclareArguments::exclusive_server_named`].
+    ///
     /// # Errors
     ///
-    /// Returns error if any failure in comunication with server.
+    /// Returns error if any failure in comunication with server, or if queue name is empty
+    /// and `no_wait` is `true`, because the server-generated name would be unknown.
+    /// Also returns error if declaring [`DIRECT_REPLY_TO_QUEUE`] without `passive`,
+    /// or [`Error::InvalidName`] if the queue name is longer than 255 bytes, or starts with
+    /// reserved prefix `amq.` without `passive`.
+    ///
+    /// [`DIRECT_REPLY_TO_QUEUE`]: constant.DIRECT_REPLY_TO_QUEUE.html
+    /// [`Error::InvalidName`]: ../error/enum.Error.html#variant.InvalidName
     pub async fn queue_declare(
         &self,
         args: QueueDeclareArguments,
     ) -> Result<Option<(String, AmqpMessageCount, u32)>> {
+        if args.queue.is_empty() && args.no_wait {
+            return Err(Error::ChannelUseError(
+                "server-named queue can not be declared with no_wait".to_string(),
+            ));
+        }
+        if args.queue == DIRECT_REPLY_TO_QUEUE && !args.passive {
+            return Err(Error::ChannelUseError(format!(
+                "{} is a pseudo-queue and can not be declared",
+                DIRECT_REPLY_TO_QUEUE
+            )));
+        }
+        check_queue_declare(&args.queue, args.passive)?;
+        args.validate_queue_type()?;
+        let transient = if (args.exclusive || args.auto_delete) && !args.passive {
+            Some(args.clone())
+        } else {
+            None
+        };
         let mut declare = DeclareQueue::new(0, args.queue.try_into().unwrap(), args.arguments);
         declare.set_passive(args.passive);
         declare.set_durable(args.durable);
@@ -383,21 +658,27 @@ impl Channel {
         declare.set_no_wait(args.no_wait);
         if args.no_wait {
             self.shared
-                .outgoing_tx
                 .send((self.channel_id(), declare.into_frame()))
                 .await?;
+            if let Some(transient) = transient {
+                self.track_transient_queue(transient.queue.clone(), transient);
+            }
             Ok(None)
         } else {
             let responder_rx = self.register_responder(DeclareQueueOk::header()).await?;
             let delcare_ok = synchronous_request!(
-                self.shared.outgoing_tx,
+                self.shared,
                 (self.channel_id(), declare.into_frame()),
                 responder_rx,
                 Frame::DeclareQueueOk,
                 Error::ChannelUseError
             )?;
+            let queue_name: String = delcare_ok.queue.into();
+            if let Some(transient) = transient {
+                self.track_transient_queue(queue_name.clone(), transient);
+            }
             Ok(Some((
-                delcare_ok.queue.into(),
+                queue_name,
                 delcare_ok.message_count,
                 delcare_ok.consumer_count,
             )))
@@ -408,8 +689,13 @@ impl Channel {
     ///
     /// # Errors
     ///
-    /// Returns error if any failure in comunication with server.
+    /// Returns error if any failure in comunication with server, or [`Error::InvalidName`]
+    /// if the queue or exchange name is longer than 255 bytes.
+    ///
+    /// [`Error::InvalidName`]: ../error/enum.Error.html#variant.InvalidName
     pub async fn queue_bind(&self, args: QueueBindArguments) -> Result<()> {
+        check_queue_name(&args.queue)?;
+        check_exchange_name(&args.exchange)?;
         let bind = BindQueue::new(
             0,
             args.queue.try_into().unwrap(),
@@ -421,14 +707,13 @@ impl Channel {
 
         if args.no_wait {
             self.shared
-                .outgoing_tx
                 .send((self.channel_id(), bind.into_frame()))
                 .await?;
         } else {
             let responder_rx = self.register_responder(BindQueueOk::header()).await?;
 
             synchronous_request!(
-                self.shared.outgoing_tx,
+                self.shared,
                 (self.channel_id(), bind.into_frame()),
                 responder_rx,
                 Frame::BindQueueOk,
@@ -446,13 +731,16 @@ impl Channel {
     ///
     /// # Errors
     ///
-    /// Returns error if any failure in comunication with server.
+    /// Returns error if any failure in comunication with server, or [`Error::InvalidName`]
+    /// if the queue name is longer than 255 bytes.
+    ///
+    /// [`Error::InvalidName`]: ../error/enum.Error.html#variant.InvalidName
     pub async fn queue_purge(&self, args: QueuePurgeArguments) -> Result<Option<AmqpMessageCount>> {
+        check_queue_name(&args.queue)?;
         let purge = PurgeQueue::new(0, args.queue.try_into().unwrap(), args.no_wait);
 
         if args.no_wait {
             self.shared
-                .outgoing_tx
                 .send((self.channel_id(), purge.into_frame()))
                 .await?;
             Ok(None)
@@ -460,7 +748,7 @@ impl Channel {
             let responder_rx = self.register_responder(PurgeQueueOk::header()).await?;
 
             let purge_ok = synchronous_request!(
-                self.shared.outgoing_tx,
+                self.shared,
                 (self.channel_id(), purge.into_frame()),
                 responder_rx,
                 Frame::PurgeQueueOk,
@@ -477,18 +765,22 @@ impl Channel {
     ///
     /// # Errors
     ///
-    /// Returns error if any failure in comunication with server.
+    /// Returns error if any failure in comunication with server, or [`Error::InvalidName`]
+    /// if the queue name is longer than 255 bytes.
+    ///
+    /// [`Error::InvalidName`]: ../error/enum.Error.html#variant.InvalidName
     pub async fn queue_delete(
         &self,
         args: QueueDeleteArguments,
     ) -> Result<Option<AmqpMessageCount>> {
+        check_queue_name(&args.queue)?;
+        self.untrack_transient_queue(&args.queue);
         let mut delete = DeleteQueue::new(0, args.queue.try_into().unwrap());
         delete.set_if_unused(args.if_unused);
         delete.set_if_empty(args.if_empty);
         delete.set_no_wait(args.no_wait);
         if args.no_wait {
             self.shared
-                .outgoing_tx
                 .send((self.channel_id(), delete.into_frame()))
                 .await?;
             Ok(None)
@@ -496,7 +788,7 @@ impl Channel {
             let responder_rx = self.register_responder(DeleteQueueOk::header()).await?;
 
             let delete_ok = synchronous_request!(
-                self.shared.outgoing_tx,
+                self.shared,
                 (self.channel_id(), delete.into_frame()),
                 responder_rx,
                 Frame::DeleteQueueOk,
@@ -509,8 +801,13 @@ impl Channel {
     ///
     /// # Errors
     ///
-    /// Returns error if any failure in comunication with server.
+    /// Returns error if any failure in comunication with server, or [`Error::InvalidName`]
+    /// if the queue or exchange name is longer than 255 bytes.
+    ///
+    /// [`Error::InvalidName`]: ../error/enum.Error.html#variant.InvalidName
     pub async fn queue_unbind(&self, args: QueueUnbindArguments) -> Result<()> {
+        check_queue_name(&args.queue)?;
+        check_exchange_name(&args.exchange)?;
         let unbind = UnbindQueue::new(
             0,
             args.queue.try_into().unwrap(),
@@ -522,7 +819,7 @@ impl Channel {
         let responder_rx = self.register_responder(UnbindQueueOk::header()).await?;
 
         synchronous_request!(
-            self.shared.outgoing_tx,
+            self.shared,
             (self.channel_id(), unbind.into_frame()),
             responder_rx,
             Frame::UnbindQueueOk,
@@ -530,6 +827,101 @@ impl Channel {
         )?;
         Ok(())
     }
+
+    /// Returns names of the exclusive or auto-delete queues declared on this channel,
+    /// which have not been deleted by [`queue_delete`] or reported removed by server.
+    ///
+    /// Server removes such queues when the declaring connection closes, or when they
+    /// are deleted by another client. If a consumer is started on the queue, the removal
+    /// is notified by [`ChannelCallback::transient_queue_removed`], so that application
+    /// can declare the queue again.
+    ///
+    /// [`queue_delete`]: struct.Channel.html#method.queue_delete
+    /// [`ChannelCallback::transient_queue_removed`]: ../callbacks/trait.ChannelCallback.html#method.transient_queue_removed
+    pub fn transient_queues(&self) -> Vec<String> {
+        self.shared
+            .transient_queues
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    fn track_transient_queue(&self, queue: String, args: QueueDeclareArguments) {
+        self.shared
+            .transient_queues
+            .lock()
+            .unwrap()
+            .insert(queue, args);
+    }
+
+    fn untrack_transient_queue(&self, queue: &str) {
+        self.shared.transient_queues.lock().unwrap().remove(queue);
+    }
+
+    /// Record the queue consumed by consumer `consumer_tag`, and whether it is in no-ack mode.
+    pub(crate) fn track_consumer_queue(&self, consumer_tag: String, queue: String, no_ack: bool) {
+        self.shared
+            .consumer_queues
+            .lock()
+            .unwrap()
+            .insert(consumer_tag, (queue, no_ack));
+    }
+
+    /// Returns `true` if consumer `consumer_tag` is known to be in manual ack mode.
+    pub(crate) fn is_manual_ack_consumer(&self, consumer_tag: &str) -> bool {
+        matches!(
+            self.shared
+                .consumer_queues
+                .lock()
+                .unwrap()
+                .get(consumer_tag),
+            Some((_, false))
+        )
+    }
+
+    /// Returns `true` if any consumer on this channel consumes from `queue`.
+    pub(crate) fn is_consuming(&self, queue: &str) -> bool {
+        self.shared
+            .consumer_queues
+            .lock()
+            .unwrap()
+            .values()
+            .any(|(consumed, _)| consumed == queue)
+    }
+
+    /// Forget the queue consumed by consumer `consumer_tag` cancelled by client.
+    pub(crate) fn untrack_consumer_queue(&self, consumer_tag: &str) {
+        self.shared
+            .consumer_queues
+            .lock()
+            .unwrap()
+            .remove(consumer_tag);
+    }
+
+    /// Forget the queue consumed by consumer `consumer_tag` cancelled by server.
+    ///
+    /// Returns the queue name and its declare arguments if it is a tracked transient queue,
+    /// because server cancels consumers of a queue when the queue is removed.
+    pub(crate) fn take_transient_queue_of(
+        &self,
+        consumer_tag: &str,
+    ) -> Option<(String, QueueDeclareArguments)> {
+        let (queue, _) = self
+            .shared
+            .consumer_queues
+            .lock()
+            .unwrap()
+            .remove(consumer_tag)?;
+        let args = self
+            .shared
+            .transient_queues
+            .lock()
+            .unwrap()
+            .remove(&queue)?;
+        Some((queue, args))
+    }
 }
 
 #[cfg(test)]
@@ -541,8 +933,8 @@ mod tests {
     };
 
     use super::{
-        QueueBindArguments, QueueDeclareArguments, QueueDeleteArguments, QueuePurgeArguments,
-        QueueUnbindArguments,
+        QueueBindArguments, QueueDeclareArguments, QueueDeleteArguments, QueueOverflow,
+        QueuePurgeArguments, QueueType, QueueUnbindArguments,
     };
 
     #[tokio::test]
@@ -566,6 +958,12 @@ mod tests {
             .await
             .unwrap()
             .unwrap();
+        // server-generated name is returned
+        assert!(queue_name.starts_with("amq.gen-"));
+        assert!(channel
+            .queue_declare(QueueDeclareArguments::default().no_wait(true).finish())
+            .await
+            .is_err());
         channel
             .queue_bind(QueueBindArguments::new(
                 &queue_name,
@@ -600,4 +998,266 @@ mod tests {
         channel.close().await.unwrap();
         connection.close().await.unwrap();
     }
+
+    #[test]
+    fn test_queue_declare_length_limit_arguments() {
+        use amqp_serde::types::FieldValue;
+
+        let args = QueueDeclareArguments::new("queue")
+            .max_length(100)
+            .max_length_bytes(1024)
+            .overflow(QueueOverflow::RejectPublishDlx)
+            .finish();
+        assert_eq!(
+            Some(&FieldValue::l(100)),
+            args.arguments.get(&"x-max-length".try_into().unwrap())
+        );
+        assert_eq!(
+            Some(&FieldValue::l(1024)),
+            args.arguments
+                .get(&"x-max-length-bytes".try_into().unwrap())
+        );
+        assert_eq!(
+            Some(&FieldValue::from("reject-publish-dlx")),
+            args.arguments.get(&"x-overflow".try_into().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_queue_declare_length_limit() {
+        use crate::{channel::BasicPublishArguments, BasicProperties};
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_queue_declare_length_limit")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let args = QueueDeclareArguments::default()
+            .max_length(2)
+            .overflow(QueueOverflow::RejectPublish)
+            .finish();
+        let (queue_name, ..) = channel.queue_declare(args).await.unwrap().unwrap();
+        for _ in 0..3 {
+            channel
+                .basic_publish(
+                    BasicProperties::default(),
+                    b"bounded".to_vec(),
+                    BasicPublishArguments::new("", &queue_name),
+                )
+                .await
+                .unwrap();
+        }
+        // declare passively to get the number of messages
+        let args = QueueDeclareArguments::new(&queue_name)
+            .passive(true)
+            .finish();
+        let (_, message_count, _) = channel.queue_declare(args).await.unwrap().unwrap();
+        assert_eq!(2, message_count);
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[test]
+    fn test_queue_declare_queue_type_validation() {
+        let args = QueueDeclareArguments::durable_client_named("quorum")
+            .queue_type(QueueType::Quorum)
+            .delivery_limit(5)
+            .finish();
+        assert_eq!(Some(QueueType::Quorum), args.declared_queue_type());
+        assert!(args.validate_queue_type().is_ok());
+
+        let args = QueueDeclareArguments::exclusive_server_named()
+            .queue_type(QueueType::Quorum)
+            .finish();
+        assert!(args.validate_queue_type().is_err());
+
+        let args = QueueDeclareArguments::transient_autodelete("stream")
+            .queue_type(QueueType::Stream)
+            .finish();
+        assert!(args.validate_queue_type().is_err());
+
+        // passive declaration does not check flags
+        let args = QueueDeclareArguments::new("stream")
+            .queue_type(QueueType::Stream)
+            .passive(true)
+            .finish();
+        assert!(args.validate_queue_type().is_ok());
+
+        let args = QueueDeclareArguments::exclusive_server_named()
+            .queue_type(QueueType::Classic)
+            .finish();
+        assert!(args.validate_queue_type().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_queue_declare_quorum() {
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_queue_declare_quorum")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let args = QueueDeclareArguments::durable_client_named("amqprs.test.quorum")
+            .queue_type(QueueType::Quorum)
+            .delivery_limit(3)
+            .finish();
+        channel.queue_declare(args).await.unwrap();
+        channel
+            .queue_delete(QueueDeleteArguments::new("amqprs.test.quorum"))
+            .await
+            .unwrap();
+
+        // rejected by client, channel is still open
+        let args = QueueDeclareArguments::exclusive_server_named()
+            .queue_type(QueueType::Quorum)
+            .finish();
+        assert!(channel.queue_declare(args).await.is_err());
+        assert!(channel.is_open());
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_queue_declare_max_priority() {
+        use crate::{
+            channel::{BasicGetArguments, BasicPublishArguments},
+            BasicProperties,
+        };
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_queue_declare_max_priority")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let args = QueueDeclareArguments::default().max_priority(10).finish();
+        let (queue_name, ..) = channel.queue_declare(args).await.unwrap().unwrap();
+        for priority in [1, 5, 9] {
+            channel
+                .basic_publish(
+                    BasicProperties::default().with_priority(priority).finish(),
+                    vec![priority],
+                    BasicPublishArguments::new("", &queue_name),
+                )
+                .await
+                .unwrap();
+        }
+        // confirm all messages are enqueued before getting any of them
+        let args = QueueDeclareArguments::new(&queue_name)
+            .passive(true)
+            .finish();
+        let (_, message_count, _) = channel.queue_declare(args).await.unwrap().unwrap();
+        assert_eq!(3, message_count);
+
+        // high-priority messages arrive first
+        for priority in [9, 5, 1] {
+            let (_, basic_properties, content) = channel
+                .basic_get(BasicGetArguments::new(&queue_name).auto_ack(true).finish())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(Some(priority), basic_properties.priority());
+            assert_eq!(vec![priority], content);
+        }
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_queue_declare_single_active_consumer() {
+        use crate::channel::{BasicCancelArguments, BasicConsumeArguments, BasicPublishArguments};
+        use crate::BasicProperties;
+        use tokio::time;
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_queue_declare_single_active_consumer")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let args = QueueDeclareArguments::default()
+            .single_active_consumer(true)
+            .finish();
+        let (queue_name, ..) = channel.queue_declare(args).await.unwrap().unwrap();
+
+        let args = BasicConsumeArguments::new(&queue_name, "active")
+            .manual_ack(false)
+            .finish();
+        let (active_tag, mut active_rx) = channel.basic_consume_rx(args).await.unwrap();
+        let args = BasicConsumeArguments::new(&queue_name, "waiting")
+            .manual_ack(false)
+            .finish();
+        let (_, mut waiting_rx) = channel.basic_consume_rx(args).await.unwrap();
+
+        let publish = || {
+            channel.basic_publish(
+                BasicProperties::default(),
+                b"sac".to_vec(),
+                BasicPublishArguments::new("", &queue_name),
+            )
+        };
+        publish().await.unwrap();
+        time::timeout(time::Duration::from_secs(1), active_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(waiting_rx.try_recv().is_err());
+
+        // the waiting consumer is activated once the active one is cancelled
+        channel
+            .basic_cancel(BasicCancelArguments::new(&active_tag))
+            .await
+            .unwrap();
+        publish().await.unwrap();
+        time::timeout(time::Duration::from_secs(1), waiting_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_queue_invalid_names() {
+        use crate::api::{channel::BasicGetArguments, error::Error};
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami");
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        for name in ["amq.amqprs-test", "q".repeat(256).as_str()] {
+            let res = channel
+                .queue_declare(QueueDeclareArguments::new(name))
+                .await;
+            assert!(matches!(res, Err(Error::InvalidName(_))), "{}", name);
+        }
+        // invalid names are rejected locally, channel is still open
+        assert!(channel.is_open());
+
+        // reserved name is allowed by passive declare, which only checks existence
+        let res = channel
+            .queue_declare(
+                QueueDeclareArguments::new("amq.amqprs-test")
+                    .passive(true)
+                    .finish(),
+            )
+            .await;
+        assert!(!matches!(res, Err(Error::InvalidName(_))));
+
+        // too long name is rejected by any request, not only by declare
+        let long_name = "q".repeat(256);
+        let res = channel
+            .queue_purge(QueuePurgeArguments::new(&long_name))
+            .await;
+        assert!(matches!(res, Err(Error::InvalidName(_))));
+        let res = channel.basic_get(BasicGetArguments::new(&long_name)).await;
+        assert!(matches!(res, Err(Error::InvalidName(_))));
+
+        connection.close().await.unwrap();
+    }
 }
@@ -0,0 +1,248 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use super::Channel;
+use crate::rt;
+
+/// Limit of publish rate by token buckets, which allow a burst of one second's worth
+/// of messages and bytes.
+///
+/// Set it for all channels of a connection by [`OpenConnectionArguments::rate_limit`],
+/// which is shared by the channels, or for a channel by [`Channel::set_rate_limit`].
+/// A publisher exceeding the limit waits before its message is sent.
+///
+/// # Support chainable methods to build arguments
+/// ```
+/// # use amqprs::channel::RateLimit;
+///
+/// let limit = RateLimit::new()
+///     .messages_per_sec(1000)
+///     .bytes_per_sec(1024 * 1024)
+///     .finish();
+/// ```
+///
+/// [`OpenConnectionArguments::rate_limit`]: ../connection/struct.OpenConnectionArguments.html#method.rate_limit
+/// [`Channel::set_rate_limit`]: struct.Channel.html#method.set_rate_limit
+#[derive(Debug, Clone, Default)]
+pub struct RateLimit {
+    /// Max number of messages published per second, `0` for unlimited.
+    /// Default: `0`.
+    pub messages_per_sec: u32,
+    /// Max bytes of message content published per second, `0` for unlimited.
+    /// Default: `0`.
+    pub bytes_per_sec: u64,
+}
+
+impl RateLimit {
+    /// Create new limit which limits nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        messages_per_sec, u32
+    }
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        bytes_per_sec, u64
+    }
+
+    /// Finish chaining and returns a new argument according to chained configurations.
+    pub fn finish(&mut self) -> Self {
+        self.clone()
+    }
+}
+
+/// Token bucket refilled at `rate` tokens per second, up to `rate` tokens.
+///
+/// Tokens can go negative, so that a message larger than the bucket is sent
+/// after waiting for the missing tokens, instead of never.
+#[derive(Debug)]
+struct Bucket {
+    rate: f64,
+    tokens: f64,
+}
+
+impl Bucket {
+    fn new(rate: f64) -> Self {
+        Self { rate, tokens: rate }
+    }
+
+    /// Refill for `elapsed` time, take `amount` tokens,
+    /// and returns how long to wait for the bucket to be out of debt.
+    fn take(&mut self, elapsed: Duration, amount: f64) -> Duration {
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.rate);
+        self.tokens -= amount;
+        if self.tokens < 0.0 {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Buckets {
+    last_refill: Instant,
+    messages: Option<Bucket>,
+    bytes: Option<Bucket>,
+}
+
+/// Rate limiter of publishes, see [`RateLimit`].
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limit: &RateLimit) -> Self {
+        let bucket = |rate: f64| {
+            if rate > 0.0 {
+                Some(Bucket::new(rate))
+            } else {
+                None
+            }
+        };
+        Self {
+            buckets: Mutex::new(Buckets {
+                last_refill: Instant::now(),
+                messages: bucket(limit.messages_per_sec as f64),
+                bytes: bucket(limit.bytes_per_sec as f64),
+            }),
+        }
+    }
+
+    /// Take tokens for a message of `content_size` bytes,
+    /// returns the time to wait until it can be sent, [`None`] if it can be sent now.
+    fn reserve(&self, content_size: usize) -> Option<Instant> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.saturating_duration_since(buckets.last_refill);
+        buckets.last_refill = now;
+        let wait_messages = buckets
+            .messages
+            .as_mut()
+            .map_or(Duration::ZERO, |bucket| bucket.take(elapsed, 1.0));
+        let wait_bytes = buckets.bytes.as_mut().map_or(Duration::ZERO, |bucket| {
+            bucket.take(elapsed, content_size as f64)
+        });
+        let wait = wait_messages.max(wait_bytes);
+        if wait.is_zero() {
+            None
+        } else {
+            Some(now + wait)
+        }
+    }
+
+    /// Wait until a message of `content_size` bytes can be sent within the limit.
+    pub(crate) async fn acquire(&self, content_size: usize) {
+        if let Some(deadline) = self.reserve(content_size) {
+            rt::sleep_until(deadline).await;
+        }
+    }
+}
+
+impl Channel {
+    /// Limit the publish rate of the channel, [`None`] to disable. See [`RateLimit`].
+    ///
+    /// It applies in addition to the limit shared by all channels of the connection,
+    /// given by [`OpenConnectionArguments::rate_limit`].
+    ///
+    /// [`RateLimit`]: struct.RateLimit.html
+    /// [`OpenConnectionArguments::rate_limit`]: ../connection/struct.OpenConnectionArguments.html#method.rate_limit
+    pub fn set_rate_limit(&self, limit: Option<RateLimit>) {
+        *self.shared.rate_limiter.lock().unwrap() =
+            limit.map(|limit| Arc::new(RateLimiter::new(&limit)));
+    }
+
+    /// Wait until a message of `content_size` bytes can be published within
+    /// the rate limits of the channel and the connection.
+    pub(crate) async fn acquire_rate_limit(&self, content_size: usize) {
+        let limiter = self.shared.rate_limiter.lock().unwrap().clone();
+        if let Some(limiter) = limiter {
+            limiter.acquire(content_size).await;
+        }
+        if let Some(limiter) = self.connection.rate_limiter() {
+            limiter.acquire(content_size).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::{RateLimit, RateLimiter};
+    use crate::{
+        channel::{BasicPublishArguments, QueueDeclareArguments},
+        connection::{Connection, OpenConnectionArguments},
+        test_utils::setup_logging,
+        BasicProperties,
+    };
+
+    #[test]
+    fn test_rate_limiter_reserve() {
+        let limiter = RateLimiter::new(&RateLimit::new().messages_per_sec(10).finish());
+        // burst of one second
+        for _ in 0..10 {
+            assert!(limiter.reserve(1024).is_none());
+        }
+        assert!(limiter.reserve(1024).is_some());
+
+        let limiter = RateLimiter::new(&RateLimit::new().bytes_per_sec(1000).finish());
+        assert!(limiter.reserve(600).is_none());
+        // larger than the rest of the bucket, waits for the missing bytes
+        let deadline = limiter.reserve(600).unwrap();
+        assert!(deadline > Instant::now());
+
+        let limiter = RateLimiter::new(&RateLimit::new());
+        for _ in 0..1000 {
+            assert!(limiter.reserve(usize::MAX).is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_rate_limit")
+            .rate_limit(RateLimit::new().messages_per_sec(20).finish())
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+        let publish = || {
+            channel.basic_publish(
+                BasicProperties::default(),
+                "rate limited",
+                BasicPublishArguments::new("", &queue_name),
+            )
+        };
+
+        // burst of 20 messages, then 10 messages at 20 per second
+        let start = Instant::now();
+        for _ in 0..30 {
+            publish().await.unwrap();
+        }
+        assert!(start.elapsed().as_millis() >= 450);
+
+        // the lower limit of channel also applies
+        channel.set_rate_limit(Some(RateLimit::new().messages_per_sec(5).finish()));
+        let start = Instant::now();
+        for _ in 0..7 {
+            publish().await.unwrap();
+        }
+        assert!(start.elapsed().as_millis() >= 350);
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+}
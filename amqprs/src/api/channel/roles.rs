@@ -0,0 +1,307 @@
+use std::{fmt, time::Duration};
+
+use amqp_serde::types::AmqpChannelId;
+use bytes::Bytes;
+
+use super::{
+    BasicAckArguments, BasicCancelArguments, BasicConsumeArguments, BasicNackArguments,
+    BasicPublishArguments, BasicQosArguments, BasicRejectArguments, Channel,
+    ConfirmSelectArguments, Confirmation, ConsumerStream, PreparedPublish, PublisherEvents,
+    RateLimit, Result, TrackedPublish,
+};
+use crate::{consumer::AsyncConsumer, frame::BasicProperties};
+
+/// A channel only for publishing, created by [`Channel::into_publisher`].
+///
+/// RabbitMQ advises to publish and consume on separate channels, so that a consumer
+/// busy with deliveries does not hold back acks of publisher confirms, and flow control
+/// of a publisher does not hold back acks of deliveries.
+/// It exposes only the publishing APIs, so that consuming on it by accident doesn't compile.
+///
+/// It can be cloned to publish from multiple tasks.
+///
+/// [`Channel::into_publisher`]: struct.Channel.html#method.into_publisher
+#[derive(Clone)]
+pub struct PublisherChannel {
+    channel: Channel,
+}
+
+/// A channel only for consuming, created by [`Channel::into_consumer`].
+///
+/// It exposes only the consuming APIs, see [`PublisherChannel`] for why.
+///
+/// [`Channel::into_consumer`]: struct.Channel.html#method.into_consumer
+/// [`PublisherChannel`]: struct.PublisherChannel.html
+#[derive(Clone)]
+pub struct ConsumerChannel {
+    channel: Channel,
+}
+
+impl Channel {
+    /// Returns the channel restricted to publishing APIs, see [`PublisherChannel`].
+    ///
+    /// [`PublisherChannel`]: struct.PublisherChannel.html
+    pub fn into_publisher(self) -> PublisherChannel {
+        PublisherChannel { channel: self }
+    }
+
+    /// Returns the channel restricted to consuming APIs, see [`ConsumerChannel`].
+    ///
+    /// [`ConsumerChannel`]: struct.ConsumerChannel.html
+    pub fn into_consumer(self) -> ConsumerChannel {
+        ConsumerChannel { channel: self }
+    }
+}
+
+impl PublisherChannel {
+    /// See [`Channel::channel_id`](struct.Channel.html#method.channel_id).
+    pub fn channel_id(&self) -> AmqpChannelId {
+        self.channel.channel_id()
+    }
+
+    /// See [`Channel::is_open`](struct.Channel.html#method.is_open).
+    pub fn is_open(&self) -> bool {
+        self.channel.is_open()
+    }
+
+    /// See [`Channel::confirm_select`](struct.Channel.html#method.confirm_select).
+    pub async fn confirm_select(&self, args: ConfirmSelectArguments) -> Result<()> {
+        self.channel.confirm_select(args).await
+    }
+
+    /// See [`Channel::is_confirm_mode`](struct.Channel.html#method.is_confirm_mode).
+    pub fn is_confirm_mode(&self) -> bool {
+        self.channel.is_confirm_mode()
+    }
+
+    /// See [`Channel::basic_publish`](struct.Channel.html#method.basic_publish).
+    pub async fn basic_publish(
+        &self,
+        basic_properties: BasicProperties,
+        content: impl Into<Bytes>,
+        args: BasicPublishArguments,
+    ) -> Result<()> {
+        self.channel
+            .basic_publish(basic_properties, content, args)
+            .await
+    }
+
+    /// See [`Channel::basic_publish_timeout`](struct.Channel.html#method.basic_publish_timeout).
+    pub async fn basic_publish_timeout(
+        &self,
+        basic_properties: BasicProperties,
+        content: impl Into<Bytes>,
+        args: BasicPublishArguments,
+        timeout: Duration,
+    ) -> Result<()> {
+        self.channel
+            .basic_publish_timeout(basic_properties, content, args, timeout)
+            .await
+    }
+
+    /// See [`Channel::basic_publish_confirm`](struct.Channel.html#method.basic_publish_confirm).
+    pub async fn basic_publish_confirm(
+        &self,
+        basic_properties: BasicProperties,
+        content: impl Into<Bytes>,
+        args: BasicPublishArguments,
+    ) -> Result<Confirmation> {
+        self.channel
+            .basic_publish_confirm(basic_properties, content, args)
+            .await
+    }
+
+    /// See [`Channel::basic_publish_tracked`](struct.Channel.html#method.basic_publish_tracked).
+    pub async fn basic_publish_tracked<T>(
+        &self,
+        basic_properties: BasicProperties,
+        content: impl Into<Bytes>,
+        args: BasicPublishArguments,
+        token: T,
+    ) -> Result<TrackedPublish<T>> {
+        self.channel
+            .basic_publish_tracked(basic_properties, content, args, token)
+            .await
+    }
+
+    /// See [`Channel::prepare_publish`](struct.Channel.html#method.prepare_publish).
+    pub fn prepare_publish(
+        &self,
+        basic_properties: BasicProperties,
+        args: BasicPublishArguments,
+    ) -> Result<PreparedPublish> {
+        self.channel.prepare_publish(basic_properties, args)
+    }
+
+    /// See [`Channel::publisher_events`](struct.Channel.html#method.publisher_events).
+    pub fn publisher_events(&self) -> PublisherEvents {
+        self.channel.publisher_events()
+    }
+
+    /// See [`Channel::set_rate_limit`](struct.Channel.html#method.set_rate_limit).
+    pub fn set_rate_limit(&self, limit: Option<RateLimit>) {
+        self.channel.set_rate_limit(limit)
+    }
+
+    /// See [`Channel::close`](struct.Channel.html#method.close).
+    pub async fn close(self) -> Result<()> {
+        self.channel.close().await
+    }
+
+    /// Returns the unrestricted channel.
+    pub fn into_inner(self) -> Channel {
+        self.channel
+    }
+}
+
+impl ConsumerChannel {
+    /// See [`Channel::channel_id`](struct.Channel.html#method.channel_id).
+    pub fn channel_id(&self) -> AmqpChannelId {
+        self.channel.channel_id()
+    }
+
+    /// See [`Channel::is_open`](struct.Channel.html#method.is_open).
+    pub fn is_open(&self) -> bool {
+        self.channel.is_open()
+    }
+
+    /// See [`Channel::basic_qos`](struct.Channel.html#method.basic_qos).
+    pub async fn basic_qos(&self, args: BasicQosArguments) -> Result<()> {
+        self.channel.basic_qos(args).await
+    }
+
+    /// See [`Channel::basic_consume`](struct.Channel.html#method.basic_consume).
+    pub async fn basic_consume<F>(&self, consumer: F, args: BasicConsumeArguments) -> Result<String>
+    where
+        F: AsyncConsumer + Send + 'static,
+    {
+        self.channel.basic_consume(consumer, args).await
+    }
+
+    /// See [`Channel::basic_consume_stream`](struct.Channel.html#method.basic_consume_stream).
+    pub async fn basic_consume_stream(
+        &self,
+        args: BasicConsumeArguments,
+    ) -> Result<ConsumerStream> {
+        self.channel.basic_consume_stream(args).await
+    }
+
+    /// See [`Channel::basic_cancel`](struct.Channel.html#method.basic_cancel).
+    pub async fn basic_cancel(&self, args: BasicCancelArguments) -> Result<String> {
+        self.channel.basic_cancel(args).await
+    }
+
+    /// See [`Channel::basic_ack`](struct.Channel.html#method.basic_ack).
+    pub async fn basic_ack(&self, args: BasicAckArguments) -> Result<()> {
+        self.channel.basic_ack(args).await
+    }
+
+    /// See [`Channel::basic_nack`](struct.Channel.html#method.basic_nack).
+    pub async fn basic_nack(&self, args: BasicNackArguments) -> Result<()> {
+        self.channel.basic_nack(args).await
+    }
+
+    /// See [`Channel::basic_reject`](struct.Channel.html#method.basic_reject).
+    pub async fn basic_reject(&self, args: BasicRejectArguments) -> Result<()> {
+        self.channel.basic_reject(args).await
+    }
+
+    /// See [`Channel::unacked_count`](struct.Channel.html#method.unacked_count).
+    pub fn unacked_count(&self) -> usize {
+        self.channel.unacked_count()
+    }
+
+    /// See [`Channel::close`](struct.Channel.html#method.close).
+    pub async fn close(self) -> Result<()> {
+        self.channel.close().await
+    }
+
+    /// Returns the unrestricted channel.
+    pub fn into_inner(self) -> Channel {
+        self.channel
+    }
+}
+
+impl fmt::Display for PublisherChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "publisher channel {}", self.channel)
+    }
+}
+
+impl fmt::Display for ConsumerChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "consumer channel {}", self.channel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time;
+
+    use crate::{
+        channel::{
+            BasicAckArguments, BasicConsumeArguments, BasicPublishArguments,
+            ConfirmSelectArguments, QueueDeclareArguments,
+        },
+        connection::{Connection, OpenConnectionArguments},
+        test_utils::setup_logging,
+        BasicProperties,
+    };
+
+    #[tokio::test]
+    async fn test_publisher_and_consumer_channels() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_publisher_and_consumer_channels")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let publisher = channel.into_publisher();
+        let consumer = connection.open_channel(None).await.unwrap().into_consumer();
+        assert_ne!(publisher.channel_id(), consumer.channel_id());
+
+        let mut stream = consumer
+            .basic_consume_stream(BasicConsumeArguments::new(
+                &queue_name,
+                "test_publisher_and_consumer_channels",
+            ))
+            .await
+            .unwrap();
+        publisher
+            .confirm_select(ConfirmSelectArguments::default())
+            .await
+            .unwrap();
+        let confirmation = publisher
+            .basic_publish_confirm(
+                BasicProperties::default(),
+                b"split".to_vec(),
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .await
+            .unwrap();
+        assert!(confirmation.is_ack());
+
+        let message = time::timeout(time::Duration::from_secs(5), stream.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(b"split".to_vec(), message.content.unwrap());
+        let delivery_tag = message.deliver.unwrap().delivery_tag();
+        consumer
+            .basic_ack(BasicAckArguments::new(delivery_tag, false))
+            .await
+            .unwrap();
+        assert_eq!(0, consumer.unacked_count());
+
+        publisher.close().await.unwrap();
+        consumer.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+}
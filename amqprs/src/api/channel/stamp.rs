@@ -0,0 +1,271 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hash, Hasher},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use amqp_serde::types::{FieldName, FieldTable, FieldValue};
+
+use super::Channel;
+use crate::frame::BasicProperties;
+
+/// Properties stamped on every message published on a channel, unless the publisher
+/// has set them already.
+///
+/// Set it for all channels of a connection by [`OpenConnectionArguments::message_stamp`],
+/// or for a channel by [`Channel::set_message_stamp`].
+/// Messages of [`PreparedPublish`] are stamped too, at the cost of encoding their
+/// properties per message.
+///
+/// # Support chainable methods to build arguments
+/// ```
+/// # use amqprs::channel::MessageStamp;
+///
+/// let stamp = MessageStamp::new()
+///     .header("x-service".try_into().unwrap(), "billing")
+///     .finish();
+/// ```
+///
+/// [`OpenConnectionArguments::message_stamp`]: ../connection/struct.OpenConnectionArguments.html#method.message_stamp
+/// [`Channel::set_message_stamp`]: struct.Channel.html#method.set_message_stamp
+/// [`PreparedPublish`]: struct.PreparedPublish.html
+#[derive(Debug, Clone)]
+pub struct MessageStamp {
+    /// Set `message_id` to a random UUID.
+    /// Default: `true`.
+    pub message_id: bool,
+    /// Set `timestamp` to current time in seconds since UNIX epoch.
+    /// Default: `true`.
+    pub timestamp: bool,
+    /// Headers to set, e.g. correlation headers identifying the service.
+    /// Default: empty.
+    pub headers: FieldTable,
+}
+
+impl MessageStamp {
+    /// Create new stamp of `message_id` and `timestamp`.
+    pub fn new() -> Self {
+        Self {
+            message_id: true,
+            timestamp: true,
+            headers: FieldTable::new(),
+        }
+    }
+
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        message_id, bool
+    }
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        timestamp, bool
+    }
+
+    /// Add a header to set.
+    pub fn header(&mut self, name: FieldName, value: impl Into<FieldValue>) -> &mut Self {
+        self.headers.insert(name, value.into());
+        self
+    }
+
+    /// Finish chaining and returns a new argument according to chained configurations.
+    pub fn finish(&mut self) -> Self {
+        self.clone()
+    }
+
+    /// Set the configured properties which are not set in `basic_properties`.
+    pub(crate) fn apply(&self, basic_properties: &mut BasicProperties) {
+        if self.message_id && basic_properties.message_id().is_none() {
+            basic_properties.with_message_id(&generate_uuid());
+        }
+        if self.timestamp && basic_properties.timestamp().is_none() {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs());
+            basic_properties.with_timestamp(now);
+        }
+        if !self.headers.is_empty() {
+            let mut headers = basic_properties.headers().cloned().unwrap_or_default();
+            for (name, value) in self.headers.iter() {
+                if headers.get(name).is_none() {
+                    headers.insert(name.clone(), value.clone());
+                }
+            }
+            basic_properties.with_headers(headers);
+        }
+    }
+}
+
+impl Default for MessageStamp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Generate a random (version 4) UUID in its hyphenated form.
+///
+/// Randomness comes from the randomly seeded keys of std's `RandomState`,
+/// mixed with a process wide counter so that UUIDs never repeat within a process.
+fn generate_uuid() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let seq = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let random = |salt: u8| {
+        let mut hasher = RandomState::new().build_hasher();
+        (seq, salt, SystemTime::now()).hash(&mut hasher);
+        hasher.finish()
+    };
+    let (high, low) = (random(0), random(1));
+    // version 4 and RFC 4122 variant
+    let high = (high & !0xf000) | 0x4000;
+    let low = (low & !(0xc000 << 48)) | (0x8000 << 48);
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xffff,
+        low >> 48,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+impl Channel {
+    /// Set the properties stamped on every message published on the channel,
+    /// [`None`] to disable. See [`MessageStamp`].
+    ///
+    /// It overrides the one given by [`OpenConnectionArguments::message_stamp`].
+    ///
+    /// [`MessageStamp`]: struct.MessageStamp.html
+    /// [`OpenConnectionArguments::message_stamp`]: ../connection/struct.OpenConnectionArguments.html#method.message_stamp
+    pub fn set_message_stamp(&self, stamp: Option<MessageStamp>) {
+        *self.shared.message_stamp.lock().unwrap() = stamp.map(Into::into);
+    }
+
+    /// Returns `true` if message stamping is enabled on the channel.
+    pub(crate) fn has_message_stamp(&self) -> bool {
+        self.shared.message_stamp.lock().unwrap().is_some()
+    }
+
+    /// Stamp `basic_properties` if enabled on the channel.
+    pub(crate) fn stamp_message(&self, basic_properties: &mut BasicProperties) {
+        let stamp = self.shared.message_stamp.lock().unwrap().clone();
+        if let Some(stamp) = stamp {
+            stamp.apply(basic_properties);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use amqp_serde::types::FieldValue;
+
+    use super::{generate_uuid, MessageStamp};
+    use crate::{
+        channel::{BasicGetArguments, BasicPublishArguments, QueueDeclareArguments},
+        connection::{Connection, OpenConnectionArguments},
+        test_utils::setup_logging,
+        BasicProperties,
+    };
+
+    #[test]
+    fn test_generate_uuid() {
+        let uuids: HashSet<_> = (0..1000).map(|_| generate_uuid()).collect();
+        assert_eq!(1000, uuids.len());
+        for uuid in uuids {
+            assert_eq!(36, uuid.len());
+            assert_eq!(Some('4'), uuid.chars().nth(14));
+            assert!(matches!(uuid.chars().nth(19), Some('8' | '9' | 'a' | 'b')));
+        }
+    }
+
+    #[test]
+    fn test_message_stamp_keeps_given_properties() {
+        let stamp = MessageStamp::new()
+            .header("x-service".try_into().unwrap(), "billing")
+            .finish();
+
+        let mut basic_properties = BasicProperties::default();
+        stamp.apply(&mut basic_properties);
+        assert!(basic_properties.message_id().is_some());
+        assert!(basic_properties.timestamp().is_some());
+        assert_eq!(
+            Some(&FieldValue::from("billing")),
+            basic_properties.header("x-service")
+        );
+
+        let mut basic_properties = BasicProperties::default()
+            .with_message_id("given")
+            .with_timestamp(1)
+            .finish();
+        let mut headers = amqp_serde::types::FieldTable::new();
+        headers.insert("x-service".try_into().unwrap(), "orders".into());
+        basic_properties.with_headers(headers);
+        stamp.apply(&mut basic_properties);
+        assert_eq!("given", basic_properties.message_id().unwrap());
+        assert_eq!(Some(1), basic_properties.timestamp());
+        assert_eq!(
+            Some(&FieldValue::from("orders")),
+            basic_properties.header("x-service")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_message_stamp() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_message_stamp")
+            .message_stamp(
+                MessageStamp::new()
+                    .header("x-service".try_into().unwrap(), "test")
+                    .finish(),
+            )
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let publish = || {
+            channel.basic_publish(
+                BasicProperties::default(),
+                "stamped",
+                BasicPublishArguments::new("", &queue_name),
+            )
+        };
+        publish().await.unwrap();
+        // prepared publishes are stamped per message
+        let prepared = channel
+            .prepare_publish(
+                BasicProperties::default(),
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .unwrap();
+        prepared.publish("stamped").await.unwrap();
+        channel.set_message_stamp(None);
+        publish().await.unwrap();
+
+        let get_args = BasicGetArguments::new(&queue_name).no_ack(true).finish();
+        let mut message_ids = HashSet::new();
+        for _ in 0..2 {
+            let (_, basic_properties, _) =
+                channel.basic_get(get_args.clone()).await.unwrap().unwrap();
+            assert!(message_ids.insert(basic_properties.message_id().cloned().unwrap()));
+            assert!(basic_properties.timestamp().is_some());
+            assert_eq!(
+                Some(&FieldValue::from("test")),
+                basic_properties.header("x-service")
+            );
+        }
+        let (_, basic_properties, _) = channel.basic_get(get_args).await.unwrap().unwrap();
+        assert!(basic_properties.message_id().is_none());
+        assert!(basic_properties.header("x-service").is_none());
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+}
@@ -29,12 +29,13 @@ impl Channel {
     /// [`tx_commit`]: struct.Channel.html#method.tx_commit
     /// [`tx_rollback`]: struct.Channel.html#method.tx_rollback
     pub async fn tx_select(&self) -> Result<()> {
+        self.mark_stateful();
         let select = TxSelect;
 
         let responder_rx = self.register_responder(TxSelectOk::header()).await?;
 
         let _method = synchronous_request!(
-            self.shared.outgoing_tx,
+            self.shared,
             (self.shared.channel_id, select.into_frame()),
             responder_rx,
             Frame::TxSelectOk,
@@ -55,7 +56,7 @@ impl Channel {
         let responder_rx = self.register_responder(TxCommitOk::header()).await?;
 
         let _method = synchronous_request!(
-            self.shared.outgoing_tx,
+            self.shared,
             (self.shared.channel_id, select.into_frame()),
             responder_rx,
             Frame::TxCommitOk,
@@ -79,7 +80,7 @@ impl Channel {
         let responder_rx = self.register_responder(TxRollbackOk::header()).await?;
 
         let _method = synchronous_request!(
-            self.shared.outgoing_tx,
+            self.shared,
             (self.shared.channel_id, select.into_frame()),
             responder_rx,
             Frame::TxRollbackOk,
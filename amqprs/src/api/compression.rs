@@ -0,0 +1,221 @@
+//! Transparent payload compression according to `content_encoding` property.
+//!
+//! Publishing with [`BasicPublishArguments::compression`] compresses the content and sets
+//! `content_encoding` of the message. Deliveries to consumers and messages of
+//! [`Channel::basic_get`] are decompressed if `content_encoding` is a known encoding,
+//! in which case `content_encoding` is cleared before the message is handed over to user.
+//! Deliveries are decompressed on the consumer side, so that a large message does not
+//! hold up the other consumers of the channel.
+//!
+//! Supported encodings: "gzip", "deflate" and "zstd".
+//!
+//! # Example
+//! ```
+//! # use amqprs::{channel::BasicPublishArguments, compression::Compression};
+//! let args = BasicPublishArguments::new("amq.topic", "amqprs.example")
+//!     .compression(Some(Compression::Gzip))
+//!     .finish();
+//! ```
+//!
+//! [`BasicPublishArguments::compression`]: ../channel/struct.BasicPublishArguments.html#method.compression
+//! [`Channel::basic_get`]: ../channel/struct.Channel.html#method.basic_get
+use std::io::{self, Read, Write};
+
+use flate2::{
+    read::{GzDecoder, ZlibDecoder},
+    write::{GzEncoder, ZlibEncoder},
+};
+
+use crate::frame::BasicProperties;
+#[cfg(feature = "traces")]
+use tracing::error;
+
+/// Compression algorithm of message content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// "gzip" encoding.
+    Gzip,
+    /// "deflate" encoding, i.e. zlib format.
+    Deflate,
+    /// "zstd" encoding.
+    Zstd,
+}
+
+impl Compression {
+    /// Returns the value of `content_encoding` property.
+    pub fn content_encoding(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Deflate => "deflate",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    /// Returns the compression of a `content_encoding` property, [`None`] if unknown.
+    pub fn from_content_encoding(content_encoding: &str) -> Option<Self> {
+        match content_encoding {
+            "gzip" => Some(Compression::Gzip),
+            "deflate" => Some(Compression::Deflate),
+            "zstd" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Compress `content`.
+    pub fn compress(&self, content: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(content)?;
+                encoder.finish()
+            }
+            Compression::Deflate => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(content)?;
+                encoder.finish()
+            }
+            Compression::Zstd => zstd::encode_all(content, 0),
+        }
+    }
+
+    /// Decompress `content`.
+    pub fn decompress(&self, content: &[u8]) -> io::Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        match self {
+            Compression::Gzip => {
+                GzDecoder::new(content).read_to_end(&mut decoded)?;
+            }
+            Compression::Deflate => {
+                ZlibDecoder::new(content).read_to_end(&mut decoded)?;
+            }
+            Compression::Zstd => decoded = zstd::decode_all(content)?,
+        }
+        Ok(decoded)
+    }
+
+    /// Decompress `content`, but fail with [`io::ErrorKind::InvalidData`] if the
+    /// decompressed content is larger than `limit` bytes.
+    pub fn decompress_with_limit(&self, content: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+        // read one more byte than the limit to detect an oversized content
+        let take = (limit as u64).saturating_add(1);
+        let mut decoded = Vec::new();
+        match self {
+            Compression::Gzip => {
+                GzDecoder::new(content)
+                    .take(take)
+                    .read_to_end(&mut decoded)?;
+            }
+            Compression::Deflate => {
+                ZlibDecoder::new(content)
+                    .take(take)
+                    .read_to_end(&mut decoded)?;
+            }
+            Compression::Zstd => {
+                zstd::Decoder::new(content)?
+                    .take(take)
+                    .read_to_end(&mut decoded)?;
+            }
+        }
+        if decoded.len() > limit {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("decompressed content exceeds {} bytes", limit),
+            ));
+        }
+        Ok(decoded)
+    }
+}
+
+/// Decompress `content` in place if `content_encoding` is known, and clear `content_encoding`.
+///
+/// If decompression fails or the decompressed content is larger than `limit` bytes,
+/// the message is left untouched.
+pub(crate) fn decompress_message(
+    basic_properties: &mut BasicProperties,
+    content: &mut Vec<u8>,
+    limit: usize,
+) {
+    let compression = match basic_properties
+        .content_encoding()
+        .and_then(|encoding| Compression::from_content_encoding(encoding.as_str()))
+    {
+        Some(compression) => compression,
+        None => return,
+    };
+    match compression.decompress_with_limit(content, limit) {
+        Ok(decoded) => {
+            *content = decoded;
+            basic_properties.clear_content_encoding();
+        }
+        Err(_err) => {
+            #[cfg(feature = "traces")]
+            error!(
+                "failed to decompress content with encoding {}, cause: {}",
+                compression.content_encoding(),
+                _err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress_message, Compression};
+    use crate::BasicProperties;
+
+    #[test]
+    fn test_compression_round_trip() {
+        let content = b"hello, amqprs! hello, amqprs! hello, amqprs!".to_vec();
+        for compression in [Compression::Gzip, Compression::Deflate, Compression::Zstd] {
+            let compressed = compression.compress(&content).unwrap();
+            assert_ne!(content, compressed);
+
+            let mut props = BasicProperties::default()
+                .with_content_encoding(compression.content_encoding())
+                .finish();
+            let mut decoded = compressed;
+            decompress_message(&mut props, &mut decoded, usize::MAX);
+            assert_eq!(content, decoded);
+            assert_eq!(None, props.content_encoding());
+        }
+    }
+
+    #[test]
+    fn test_decompress_limit() {
+        let content = vec![0u8; 1024];
+        for compression in [Compression::Gzip, Compression::Deflate, Compression::Zstd] {
+            let compressed = compression.compress(&content).unwrap();
+            assert_eq!(
+                content,
+                compression
+                    .decompress_with_limit(&compressed, 1024)
+                    .unwrap()
+            );
+            assert!(compression
+                .decompress_with_limit(&compressed, 1023)
+                .is_err());
+
+            let mut props = BasicProperties::default()
+                .with_content_encoding(compression.content_encoding())
+                .finish();
+            let mut decoded = compressed.clone();
+            decompress_message(&mut props, &mut decoded, 1023);
+            assert_eq!(compressed, decoded);
+            assert_eq!(
+                Some(&compression.content_encoding().to_string()),
+                props.content_encoding()
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_encoding() {
+        let mut props = BasicProperties::default()
+            .with_content_encoding("utf8")
+            .finish();
+        let mut content = b"plain".to_vec();
+        decompress_message(&mut props, &mut content, usize::MAX);
+        assert_eq!(b"plain".to_vec(), content);
+        assert_eq!(Some(&"utf8".to_string()), props.content_encoding());
+    }
+}
@@ -10,12 +10,225 @@
 //! [`Channel::basic_consume`]: ../channel/struct.Channel.html#method.basic_consume
 //! [`Channel::basic_consume_blocking`]: ../channel/struct.Channel.html#method.basic_consume_blocking
 //!
-use super::channel::{BasicAckArguments, Channel};
+use std::{
+    any::Any,
+    collections::{HashMap, VecDeque},
+    fmt,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    process,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use super::channel::{BasicAckArguments, BasicRejectArguments, Channel};
 use crate::frame::{BasicProperties, Deliver};
 
+use amqp_serde::types::FieldValue;
 use async_trait::async_trait;
 #[cfg(feature = "traces")]
-use tracing::info;
+use tracing::{error, info};
+
+/// Policy applied to a delivery if the consumer panics while handling it.
+///
+/// The panic is caught and the consumer keeps running. If the consumer uses
+/// manual acknowledgement, the offending delivery is rejected by `basic.nack`
+/// according to the policy, otherwise the panic is only logged.
+///
+/// See [`BasicConsumeArguments::panic_policy`].
+///
+/// [`BasicConsumeArguments::panic_policy`]: ../channel/struct.BasicConsumeArguments.html#method.panic_policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanicPolicy {
+    /// `basic.nack` the delivery with `requeue = true`, so it will be redelivered.
+    Requeue,
+    /// `basic.nack` the delivery with `requeue = false`, so it will be dropped or
+    /// dead-lettered if the queue has a dead letter exchange.
+    DeadLetter,
+}
+
+impl Default for PanicPolicy {
+    fn default() -> Self {
+        PanicPolicy::Requeue
+    }
+}
+
+impl PanicPolicy {
+    /// Returns the `requeue` flag of `basic.nack` for this policy.
+    pub(crate) fn requeue(&self) -> bool {
+        matches!(self, PanicPolicy::Requeue)
+    }
+}
+
+/// Strategy to dispatch deliveries to a consumer.
+///
+/// See [`BasicConsumeArguments::dispatch_strategy`].
+///
+/// [`BasicConsumeArguments::dispatch_strategy`]: ../channel/struct.BasicConsumeArguments.html#method.dispatch_strategy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DispatchStrategy {
+    /// Deliveries are handled one after another by a single consumer task,
+    /// so the delivery order is preserved.
+    Serial,
+    /// Each delivery is handled by a new task spawned onto the runtime, using a clone of the consumer.
+    /// At most `max_in_flight` deliveries are handled at the same time,
+    /// the delivery order is not preserved.
+    ///
+    /// Only supported by [`Channel::basic_consume_cloneable`].
+    ///
+    /// [`Channel::basic_consume_cloneable`]: ../channel/struct.Channel.html#method.basic_consume_cloneable
+    Concurrent {
+        /// Maximum number of deliveries handled at the same time, `0` is treated as `1`.
+        ///
+        /// Values above [`u32::MAX`] or tokio's `Semaphore::MAX_PERMITS` are capped to the smaller one.
+        max_in_flight: usize,
+    },
+}
+
+impl Default for DispatchStrategy {
+    fn default() -> Self {
+        DispatchStrategy::Serial
+    }
+}
+
+/// Reason why a consumer stops receiving deliveries.
+///
+/// See [`AsyncConsumer::on_terminated`], [`BlockingConsumer::on_terminated`] and
+/// [`ConsumerStream::termination`].
+///
+/// [`ConsumerStream::termination`]: ../channel/struct.ConsumerStream.html#method.termination
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConsumerTermination {
+    /// The consumer is cancelled by client.
+    Cancelled,
+    /// The consumer is cancelled by server, e.g. its queue is deleted.
+    CancelledByServer,
+    /// The channel of the consumer is closed, with the reason.
+    ChannelClosed(String),
+    /// The connection of the consumer is closed, with the reason.
+    ConnectionClosed(String),
+}
+
+impl fmt::Display for ConsumerTermination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsumerTermination::Cancelled => write!(f, "cancelled by client"),
+            ConsumerTermination::CancelledByServer => write!(f, "cancelled by server"),
+            ConsumerTermination::ChannelClosed(reason) => {
+                write!(f, "channel closed: {}", reason)
+            }
+            ConsumerTermination::ConnectionClosed(reason) => {
+                write!(f, "connection closed: {}", reason)
+            }
+        }
+    }
+}
+
+/// Generator of consumer tags, used if no consumer tag is given to `basic.consume`.
+///
+/// By default, server generates an opaque consumer tag, a custom generator can encode
+/// information such as host, process or purpose into the tag for debuggability.
+///
+/// It is implemented for closures `Fn(&str) -> String`, the input is the queue name.
+///
+/// See [`OpenConnectionArguments::consumer_tag_generator`].
+///
+/// [`OpenConnectionArguments::consumer_tag_generator`]: ../connection/struct.OpenConnectionArguments.html#method.consumer_tag_generator
+pub trait ConsumerTagGenerator: Send + Sync {
+    /// Returns a new consumer tag for a consumer of `queue`.
+    ///
+    /// The tag must be unique within the channel.
+    fn generate(&self, queue: &str) -> String;
+}
+
+impl<F> ConsumerTagGenerator for F
+where
+    F: Fn(&str) -> String + Send + Sync,
+{
+    fn generate(&self, queue: &str) -> String {
+        self(queue)
+    }
+}
+
+/// Consumer tag generator using format `<prefix>.<pid>.<queue>.<sequence number>`.
+///
+/// The queue name is truncated if needed to keep the tag within 255 bytes,
+/// the maximum length of a consumer tag.
+#[derive(Debug)]
+pub struct PrefixedConsumerTag {
+    prefix: String,
+    seq: AtomicU64,
+}
+
+/// Maximum length in bytes of a consumer tag, which is a short string.
+const MAX_CONSUMER_TAG_LEN: usize = 255;
+
+impl PrefixedConsumerTag {
+    /// Returns a new generator with the given `prefix`.
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_owned(),
+            seq: AtomicU64::new(0),
+        }
+    }
+}
+
+impl ConsumerTagGenerator for PrefixedConsumerTag {
+    fn generate(&self, queue: &str) -> String {
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        let pid = process::id().to_string();
+        let seq = seq.to_string();
+        let fixed_len = self.prefix.len() + pid.len() + seq.len() + 3;
+        let mut queue_len = queue
+            .len()
+            .min(MAX_CONSUMER_TAG_LEN.saturating_sub(fixed_len));
+        while !queue.is_char_boundary(queue_len) {
+            queue_len -= 1;
+        }
+        format!("{}.{}.{}.{}", self.prefix, pid, &queue[..queue_len], seq)
+    }
+}
+
+/// Future wrapper which catches a panic raised when polling the inner future.
+pub(crate) struct CatchUnwind<F> {
+    inner: F,
+}
+
+impl<F> CatchUnwind<F> {
+    pub(crate) fn new(inner: F) -> Self {
+        Self { inner }
+    }
+}
+
+impl<F: Future + Unpin> Future for CatchUnwind<F> {
+    type Output = std::thread::Result<F::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = &mut self.get_mut().inner;
+        match panic::catch_unwind(AssertUnwindSafe(|| Pin::new(inner).poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+/// Extract a readable message from a panic payload.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
 
 /// Trait defines the callback interfaces for consuming asynchronous content data from server.
 ///
@@ -54,7 +267,11 @@ pub trait AsyncConsumer {
     ///    to start consuming message in a blocking context.
     ///
     /// [`Channel::basic_consume_blocking`]: ../channel/struct.Channel.html#method.basic_consume_blocking
-
+    ///
+    /// # Panics
+    ///
+    /// A panic raised by the implementation is caught by the library, the delivery is handled according
+    /// to the [`PanicPolicy`] of the consumer, and the consumer continues with next delivery.
     async fn consume(
         &mut self, // use `&mut self` to make trait object to be `Sync`
         channel: &Channel,
@@ -62,11 +279,18 @@ pub trait AsyncConsumer {
         basic_properties: BasicProperties,
         content: Vec<u8>,
     );
+
+    /// Called once the consumer stops receiving deliveries, after all received
+    /// deliveries are consumed, with the reason.
+    ///
+    /// Default implementation does nothing.
+    async fn on_terminated(&mut self, _channel: &Channel, _reason: ConsumerTermination) {}
 }
 
 /// Default type implements the [`AsyncConsumer`].
 ///
 /// It is used for demo and debugging purposes only.
+#[derive(Clone)]
 pub struct DefaultConsumer {
     no_ack: bool,
 }
@@ -109,6 +333,362 @@ impl AsyncConsumer for DefaultConsumer {
             channel.basic_ack(args).await.unwrap();
         }
     }
+
+    async fn on_terminated(&mut self, _channel: &Channel, _reason: ConsumerTermination) {
+        #[cfg(feature = "traces")]
+        info!(
+            "consumer on channel {} is terminated, {}",
+            _channel, _reason
+        );
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+/// Middleware wrapped around the handling of each delivery by an [`AsyncConsumer`],
+/// e.g. for logging, metrics, tracing, deadline enforcement or retry.
+///
+/// It is called with the delivery and [`Next`], which runs the rest of the chain,
+/// so it can act before and after, skip or repeat the handling.
+/// Stack middlewares on a consumer by [`MiddlewareConsumer`].
+///
+/// # Example
+///
+/// ```
+/// # use amqprs::{channel::Channel, consumer::{ConsumerMiddleware, Next}, BasicProperties, Deliver};
+/// # use async_trait::async_trait;
+/// struct Elapsed;
+///
+/// #[async_trait]
+/// impl ConsumerMiddleware for Elapsed {
+///     async fn handle(
+///         &mut self,
+///         mut next: Next<'_>,
+///         channel: &Channel,
+///         deliver: Deliver,
+///         basic_properties: BasicProperties,
+///         content: Vec<u8>,
+///     ) {
+///         let start = std::time::Instant::now();
+///         next.run(channel, deliver, basic_properties, content).await;
+///         println!("handled in {:?}", start.elapsed());
+///     }
+/// }
+/// ```
+///
+/// [`MiddlewareConsumer`]: struct.MiddlewareConsumer.html
+#[async_trait]
+pub trait ConsumerMiddleware {
+    /// Handle a delivery, call [`Next::run`] to pass it to the rest of the chain.
+    ///
+    /// [`Next::run`]: struct.Next.html#method.run
+    async fn handle(
+        &mut self,
+        next: Next<'_>,
+        channel: &Channel,
+        deliver: Deliver,
+        basic_properties: BasicProperties,
+        content: Vec<u8>,
+    );
+}
+
+/// The rest of a middleware chain, see [`ConsumerMiddleware`].
+pub struct Next<'a> {
+    middlewares: &'a mut [Box<dyn ConsumerMiddleware + Send>],
+    consumer: &'a mut (dyn AsyncConsumer + Send),
+}
+
+impl<'a> Next<'a> {
+    /// Pass the delivery to the next middleware, or to the consumer at the end of the chain.
+    ///
+    /// It can be called more than once, e.g. to retry.
+    pub async fn run(
+        &mut self,
+        channel: &Channel,
+        deliver: Deliver,
+        basic_properties: BasicProperties,
+        content: Vec<u8>,
+    ) {
+        match self.middlewares.split_first_mut() {
+            Some((middleware, middlewares)) => {
+                let next = Next {
+                    middlewares,
+                    consumer: &mut *self.consumer,
+                };
+                middleware
+                    .handle(next, channel, deliver, basic_properties, content)
+                    .await
+            }
+            None => {
+                self.consumer
+                    .consume(channel, deliver, basic_properties, content)
+                    .await
+            }
+        }
+    }
+}
+
+/// An [`AsyncConsumer`] whose deliveries go through a chain of [`ConsumerMiddleware`].
+///
+/// The middleware added first is the outermost one, i.e. it is called first.
+///
+/// # Example
+///
+/// ```
+/// # use amqprs::consumer::{DefaultConsumer, MiddlewareConsumer};
+/// # fn wrap<M1, M2>(logging: M1, retry: M2) -> MiddlewareConsumer<DefaultConsumer>
+/// # where
+/// #     M1: amqprs::consumer::ConsumerMiddleware + Send + 'static,
+/// #     M2: amqprs::consumer::ConsumerMiddleware + Send + 'static,
+/// # {
+/// // logging is called first, then retry, then the consumer
+/// MiddlewareConsumer::new(DefaultConsumer::new(false))
+///     .layer(logging)
+///     .layer(retry)
+/// # }
+/// ```
+pub struct MiddlewareConsumer<C> {
+    middlewares: Vec<Box<dyn ConsumerMiddleware + Send>>,
+    consumer: C,
+}
+
+impl<C> MiddlewareConsumer<C> {
+    /// Returns `consumer` without middleware.
+    pub fn new(consumer: C) -> Self {
+        Self {
+            middlewares: Vec::new(),
+            consumer,
+        }
+    }
+
+    /// Add `middleware` inside of the middlewares added before.
+    pub fn layer<M>(mut self, middleware: M) -> Self
+    where
+        M: ConsumerMiddleware + Send + 'static,
+    {
+        self.middlewares.push(Box::new(middleware));
+        self
+    }
+
+    /// Returns the wrapped consumer.
+    pub fn into_inner(self) -> C {
+        self.consumer
+    }
+}
+
+#[async_trait]
+impl<C> AsyncConsumer for MiddlewareConsumer<C>
+where
+    C: AsyncConsumer + Send,
+{
+    async fn consume(
+        &mut self,
+        channel: &Channel,
+        deliver: Deliver,
+        basic_properties: BasicProperties,
+        content: Vec<u8>,
+    ) {
+        let mut next = Next {
+            middlewares: &mut self.middlewares,
+            consumer: &mut self.consumer,
+        };
+        next.run(channel, deliver, basic_properties, content).await
+    }
+
+    async fn on_terminated(&mut self, channel: &Channel, reason: ConsumerTermination) {
+        self.consumer.on_terminated(channel, reason).await
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+/// Header added to a duplicate delivery by [`DuplicatePolicy::Flag`], its value is `true`.
+///
+/// [`DuplicatePolicy::Flag`]: enum.DuplicatePolicy.html#variant.Flag
+pub const DUPLICATE_HEADER: &str = "x-amqprs-duplicate";
+
+/// What [`Deduplicate`] does with a delivery whose `message_id` has been seen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Pass the delivery on, with header [`DUPLICATE_HEADER`] added.
+    ///
+    /// [`DUPLICATE_HEADER`]: constant.DUPLICATE_HEADER.html
+    Flag,
+    /// Drop the delivery. For a consumer in manual ack mode, the delivery is
+    /// `basic.reject`ed with `requeue = false`, so it will be dropped or dead-lettered
+    /// if the queue has a dead letter exchange.
+    Drop,
+    /// `basic.ack` and drop the delivery, for a consumer in manual ack mode.
+    /// For a consumer in auto ack mode, the delivery is only dropped.
+    AckAndDrop,
+}
+
+/// [`ConsumerMiddleware`] detecting duplicate deliveries by `message_id`,
+/// e.g. redeliveries after reconnecting, or messages published again by a retry.
+///
+/// A `message_id` is remembered only once its delivery is handled by the rest of the chain
+/// without panic, for at most `ttl`, and only the `capacity` most recently seen ones are remembered.
+/// Deliveries without `message_id` are passed on as they are.
+///
+/// Clones share the remembered ids, so that a consumer started again after
+/// reconnecting keeps detecting duplicates of the messages handled before.
+///
+/// # Example
+///
+/// ```
+/// # use std::time::Duration;
+/// # use amqprs::consumer::{Deduplicate, DefaultConsumer, DuplicatePolicy, MiddlewareConsumer};
+/// let dedup = Deduplicate::new(10_000, Duration::from_secs(600), DuplicatePolicy::AckAndDrop);
+/// let consumer = MiddlewareConsumer::new(DefaultConsumer::new(false)).layer(dedup.clone());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Deduplicate {
+    policy: DuplicatePolicy,
+    seen: Arc<Mutex<SeenIds>>,
+}
+
+impl Deduplicate {
+    /// Remember up to `capacity` message ids for `ttl` each, and apply `policy` to duplicates.
+    pub fn new(capacity: usize, ttl: Duration, policy: DuplicatePolicy) -> Self {
+        Self {
+            policy,
+            seen: Arc::new(Mutex::new(SeenIds::new(capacity, ttl))),
+        }
+    }
+
+    /// Returns `true` if `message_id` has been seen and is not expired.
+    pub fn is_duplicate(&self, message_id: &str) -> bool {
+        self.seen
+            .lock()
+            .unwrap()
+            .contains(message_id, Instant::now())
+    }
+}
+
+#[async_trait]
+impl ConsumerMiddleware for Deduplicate {
+    async fn handle(
+        &mut self,
+        mut next: Next<'_>,
+        channel: &Channel,
+        deliver: Deliver,
+        mut basic_properties: BasicProperties,
+        content: Vec<u8>,
+    ) {
+        let message_id = match basic_properties.message_id() {
+            Some(message_id) => message_id.clone(),
+            None => return next.run(channel, deliver, basic_properties, content).await,
+        };
+        if self.is_duplicate(&message_id) {
+            #[cfg(feature = "traces")]
+            info!(
+                "duplicate delivery {} of message {} on channel {}",
+                deliver, message_id, channel
+            );
+            match self.policy {
+                DuplicatePolicy::Flag => {
+                    let mut headers = basic_properties.headers().cloned().unwrap_or_default();
+                    headers.insert(DUPLICATE_HEADER.try_into().unwrap(), FieldValue::t(true));
+                    basic_properties.with_headers(headers);
+                }
+                DuplicatePolicy::Drop => {
+                    if channel.is_manual_ack_consumer(deliver.consumer_tag()) {
+                        let args = BasicRejectArguments::new(deliver.delivery_tag(), false);
+                        if let Err(_err) = channel.basic_reject(args).await {
+                            #[cfg(feature = "traces")]
+                            error!("failed to reject duplicate delivery, cause: {}", _err);
+                        }
+                    }
+                    return;
+                }
+                DuplicatePolicy::AckAndDrop => {
+                    if channel.is_manual_ack_consumer(deliver.consumer_tag()) {
+                        let args = BasicAckArguments::new(deliver.delivery_tag(), false);
+                        if let Err(_err) = channel.basic_ack(args).await {
+                            #[cfg(feature = "traces")]
+                            error!("failed to ack duplicate delivery, cause: {}", _err);
+                        }
+                    }
+                    return;
+                }
+            }
+        }
+        // a delivery whose handling panics may be redelivered, so it is not a duplicate
+        let handled = CatchUnwind::new(Box::pin(next.run(
+            channel,
+            deliver,
+            basic_properties,
+            content,
+        )))
+        .await;
+        match handled {
+            Ok(()) => {
+                self.seen.lock().unwrap().insert(message_id, Instant::now());
+            }
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+}
+
+/// Message ids seen within `ttl`, the least recently seen one is evicted once
+/// there are more than `capacity` of them.
+#[derive(Debug)]
+struct SeenIds {
+    capacity: usize,
+    ttl: Duration,
+    /// time each id is seen, and its sequence number in `order`
+    ids: HashMap<String, (Instant, u64)>,
+    /// ids in the order they are seen, an entry is stale if the id is seen again later
+    order: VecDeque<(u64, String)>,
+    next_seq: u64,
+}
+
+impl SeenIds {
+    fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            ids: HashMap::new(),
+            order: VecDeque::new(),
+            next_seq: 0,
+        }
+    }
+
+    fn contains(&self, id: &str, now: Instant) -> bool {
+        match self.ids.get(id) {
+            Some((seen_at, _)) => now.saturating_duration_since(*seen_at) < self.ttl,
+            None => false,
+        }
+    }
+
+    fn insert(&mut self, id: String, now: Instant) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.ids.insert(id.clone(), (now, seq));
+        self.order.push_back((seq, id));
+
+        // evict the expired and the least recently seen ids, skipping stale entries
+        while let Some((seq, id)) = self.order.front() {
+            let evict = match self.ids.get(id) {
+                Some((seen_at, current)) if current == seq => {
+                    self.ids.len() > self.capacity
+                        || now.saturating_duration_since(*seen_at) >= self.ttl
+                }
+                _ => true,
+            };
+            if !evict {
+                break;
+            }
+            let (seq, id) = self.order.pop_front().unwrap();
+            if matches!(self.ids.get(&id), Some((_, current)) if *current == seq) {
+                self.ids.remove(&id);
+            }
+        }
+        // drop stale entries left behind the front
+        if self.order.len() > 2 * self.ids.len() + 16 {
+            let ids = &self.ids;
+            self.order
+                .retain(|(seq, id)| matches!(ids.get(id), Some((_, current)) if current == seq));
+        }
+    }
 }
 
 //////////////////////////////////////////////////////////////////////////////
@@ -120,6 +700,8 @@ pub trait BlockingConsumer {
     /// If there are too many blocking consumers, user is recommended to use a thread pool for all
     /// blocking tasks. See possible solution in [`non-blocking and blocking consumer`].
     ///
+    /// A panic raised by the implementation is handled in the same way as [`AsyncConsumer::consume`].
+    ///
     /// [`non-blocking and blocking consumer`]: trait.AsyncConsumer.html#non-blocking-and-blocking-consumer
     fn consume(
         &mut self, // use `&mut self` to make trait object to be `Sync`
@@ -128,6 +710,11 @@ pub trait BlockingConsumer {
         basic_properties: BasicProperties,
         content: Vec<u8>,
     );
+
+    /// Same as [`AsyncConsumer::on_terminated`], but run in a blocking context.
+    ///
+    /// Default implementation does nothing.
+    fn on_terminated(&mut self, _channel: &Channel, _reason: ConsumerTermination) {}
 }
 
 /// Default type implements the [`BlockingConsumer`].
@@ -175,4 +762,358 @@ impl BlockingConsumer for DefaultBlockingConsumer {
             channel.basic_ack_blocking(args).unwrap();
         }
     }
+
+    fn on_terminated(&mut self, _channel: &Channel, _reason: ConsumerTermination) {
+        #[cfg(feature = "traces")]
+        info!(
+            "consumer on channel {} is terminated, {}",
+            _channel, _reason
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use tokio::{sync::mpsc, time};
+
+    use super::{
+        panic_message, AsyncConsumer, CatchUnwind, ConsumerMiddleware, ConsumerTagGenerator,
+        Deduplicate, DuplicatePolicy, MiddlewareConsumer, Next, PanicPolicy, PrefixedConsumerTag,
+        SeenIds, DUPLICATE_HEADER,
+    };
+    use crate::{
+        channel::{
+            BasicAckArguments, BasicConsumeArguments, BasicPublishArguments, Channel,
+            QueueDeclareArguments,
+        },
+        connection::{Connection, OpenConnectionArguments},
+        test_utils::setup_logging,
+        BasicProperties, Deliver,
+    };
+
+    #[tokio::test]
+    async fn test_catch_unwind() {
+        let ok = CatchUnwind::new(Box::pin(async { 1 })).await;
+        assert_eq!(1, ok.unwrap());
+
+        let err = CatchUnwind::new(Box::pin(async {
+            if true {
+                panic!("consumer failure");
+            }
+        }))
+        .await;
+        assert_eq!("consumer failure", panic_message(err.unwrap_err().as_ref()));
+    }
+
+    #[test]
+    fn test_panic_policy() {
+        assert_eq!(PanicPolicy::Requeue, PanicPolicy::default());
+        assert!(PanicPolicy::Requeue.requeue());
+        assert!(!PanicPolicy::DeadLetter.requeue());
+    }
+
+    #[test]
+    fn test_consumer_tag_generator() {
+        let generator = PrefixedConsumerTag::new("worker");
+        let pid = std::process::id();
+        assert_eq!(
+            format!("worker.{}.orders.0", pid),
+            generator.generate("orders")
+        );
+        assert_eq!(
+            format!("worker.{}.orders.1", pid),
+            generator.generate("orders")
+        );
+
+        let long_queue = "q".repeat(300);
+        let tag = PrefixedConsumerTag::new("worker").generate(&long_queue);
+        assert_eq!(255, tag.len());
+        assert!(tag.ends_with(".0"));
+
+        let generator = |queue: &str| format!("billing-{}", queue);
+        assert_eq!("billing-orders", generator.generate("orders"));
+    }
+
+    /// Records the name of each handler in the order of calls.
+    struct Record(&'static str, mpsc::UnboundedSender<&'static str>);
+
+    #[async_trait]
+    impl ConsumerMiddleware for Record {
+        async fn handle(
+            &mut self,
+            mut next: Next<'_>,
+            channel: &Channel,
+            deliver: Deliver,
+            basic_properties: BasicProperties,
+            content: Vec<u8>,
+        ) {
+            self.1.send(self.0).unwrap();
+            next.run(channel, deliver, basic_properties, content).await;
+        }
+    }
+
+    /// Runs the rest of the chain twice.
+    struct Twice;
+
+    #[async_trait]
+    impl ConsumerMiddleware for Twice {
+        async fn handle(
+            &mut self,
+            mut next: Next<'_>,
+            channel: &Channel,
+            deliver: Deliver,
+            basic_properties: BasicProperties,
+            content: Vec<u8>,
+        ) {
+            next.run(
+                channel,
+                deliver.clone(),
+                basic_properties.clone(),
+                content.clone(),
+            )
+            .await;
+            next.run(channel, deliver, basic_properties, content).await;
+        }
+    }
+
+    #[async_trait]
+    impl AsyncConsumer for Record {
+        async fn consume(
+            &mut self,
+            _channel: &Channel,
+            _deliver: Deliver,
+            _basic_properties: BasicProperties,
+            _content: Vec<u8>,
+        ) {
+            self.1.send(self.0).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_consumer() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_middleware_consumer")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let consumer = MiddlewareConsumer::new(Record("consumer", tx.clone()))
+            .layer(Record("outer", tx.clone()))
+            .layer(Twice)
+            .layer(Record("inner", tx));
+        let args = BasicConsumeArguments::new(&queue_name, "test_middleware_consumer")
+            .auto_ack(true)
+            .finish();
+        channel.basic_consume(consumer, args).await.unwrap();
+        channel
+            .basic_publish(
+                BasicProperties::default(),
+                b"middleware".to_vec(),
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .await
+            .unwrap();
+
+        let mut calls = Vec::new();
+        for _ in 0..5 {
+            let call = time::timeout(time::Duration::from_secs(5), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            calls.push(call);
+        }
+        assert_eq!(
+            vec!["outer", "inner", "consumer", "inner", "consumer"],
+            calls
+        );
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    #[test]
+    fn test_seen_ids() {
+        let start = std::time::Instant::now();
+        let ttl = time::Duration::from_secs(10);
+        let mut seen = SeenIds::new(2, ttl);
+        seen.insert("a".to_owned(), start);
+        seen.insert("b".to_owned(), start);
+        assert!(seen.contains("a", start) && seen.contains("b", start));
+
+        // "a" is seen again, so "b" is the least recently seen one to evict
+        seen.insert("a".to_owned(), start);
+        seen.insert("c".to_owned(), start);
+        assert!(seen.contains("a", start) && seen.contains("c", start));
+        assert!(!seen.contains("b", start));
+
+        // expired
+        assert!(!seen.contains("a", start + ttl));
+        seen.insert("d".to_owned(), start + ttl);
+        assert_eq!(1, seen.ids.len());
+        assert!(seen.contains("d", start + ttl));
+
+        for i in 0..1000 {
+            seen.insert("e".to_owned(), start + ttl + time::Duration::from_millis(i));
+        }
+        assert!(seen.order.len() < 100);
+    }
+
+    /// Records the content of each delivery and whether it is flagged as duplicate.
+    struct RecordDuplicate(mpsc::UnboundedSender<(Vec<u8>, bool)>);
+
+    #[async_trait]
+    impl AsyncConsumer for RecordDuplicate {
+        async fn consume(
+            &mut self,
+            _channel: &Channel,
+            _deliver: Deliver,
+            basic_properties: BasicProperties,
+            content: Vec<u8>,
+        ) {
+            let duplicate = basic_properties.header(DUPLICATE_HEADER).is_some();
+            self.0.send((content, duplicate)).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deduplicate() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_deduplicate")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        for policy in [
+            DuplicatePolicy::Drop,
+            DuplicatePolicy::Flag,
+            DuplicatePolicy::AckAndDrop,
+        ] {
+            let (queue_name, ..) = channel
+                .queue_declare(QueueDeclareArguments::default())
+                .await
+                .unwrap()
+                .unwrap();
+            let (tx, mut rx) = mpsc::unbounded_channel();
+            let dedup = Deduplicate::new(100, time::Duration::from_secs(60), policy);
+            let consumer = MiddlewareConsumer::new(RecordDuplicate(tx)).layer(dedup.clone());
+            let consumer_tag = format!("test_deduplicate_{:?}", policy);
+            let args = BasicConsumeArguments::new(&queue_name, &consumer_tag)
+                .auto_ack(true)
+                .finish();
+            channel.basic_consume(consumer, args).await.unwrap();
+
+            for (message_id, content) in [("1", "first"), ("1", "again"), ("2", "second")] {
+                channel
+                    .basic_publish(
+                        BasicProperties::default()
+                            .with_message_id(message_id)
+                            .finish(),
+                        content.as_bytes().to_vec(),
+                        BasicPublishArguments::new("", &queue_name),
+                    )
+                    .await
+                    .unwrap();
+            }
+            let mut expected = vec![(b"first".to_vec(), false)];
+            if policy == DuplicatePolicy::Flag {
+                expected.push((b"again".to_vec(), true));
+            }
+            expected.push((b"second".to_vec(), false));
+            for delivery in expected {
+                let received = time::timeout(time::Duration::from_secs(5), rx.recv())
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(delivery, received);
+            }
+            assert!(dedup.is_duplicate("1") && dedup.is_duplicate("2"));
+        }
+        // duplicates of auto ack consumers are not acked, which would close the channel
+        assert!(channel.is_open());
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
+    /// Panics on the first delivery of each message, acks and records the redelivery.
+    struct PanicOnFirstDelivery(mpsc::UnboundedSender<Vec<u8>>);
+
+    #[async_trait]
+    impl AsyncConsumer for PanicOnFirstDelivery {
+        async fn consume(
+            &mut self,
+            channel: &Channel,
+            deliver: Deliver,
+            _basic_properties: BasicProperties,
+            content: Vec<u8>,
+        ) {
+            if !deliver.redelivered() {
+                panic!("first delivery");
+            }
+            let args = BasicAckArguments::new(deliver.delivery_tag(), false);
+            channel.basic_ack(args).await.unwrap();
+            self.0.send(content).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deduplicate_manual_ack() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_deduplicate_manual_ack")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let dedup = Deduplicate::new(100, time::Duration::from_secs(60), DuplicatePolicy::Drop);
+        let consumer = MiddlewareConsumer::new(PanicOnFirstDelivery(tx)).layer(dedup.clone());
+        let args = BasicConsumeArguments::new(&queue_name, "test_deduplicate_manual_ack")
+            .panic_policy(PanicPolicy::Requeue)
+            .finish();
+        channel.basic_consume(consumer, args).await.unwrap();
+
+        for content in ["first", "again"] {
+            channel
+                .basic_publish(
+                    BasicProperties::default().with_message_id("1").finish(),
+                    content.as_bytes().to_vec(),
+                    BasicPublishArguments::new("", &queue_name),
+                )
+                .await
+                .unwrap();
+        }
+        // the delivery whose handling panics is not remembered, so its redelivery is handled
+        let received = time::timeout(time::Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(b"first".to_vec(), received);
+        assert!(dedup.is_duplicate("1"));
+
+        // the duplicate is rejected instead of left unacked
+        time::sleep(time::Duration::from_millis(500)).await;
+        assert!(rx.try_recv().is_err());
+        assert_eq!(0, channel.unacked_count());
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
 }
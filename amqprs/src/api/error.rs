@@ -13,6 +13,14 @@ use uriparse::uri_reference::URIReferenceError;
 pub enum Error {
     /// Error when using an amqp(s) uri. Usually due to incorrect usage by user.
     UriError(String),
+    /// Malformed server address, e.g. invalid host or port.
+    AddressError(String),
+    /// Server refused the credentials during openning a connection,
+    /// e.g. due to wrong username or password.
+    AuthenticationFailure {
+        /// Reply text of `connection.close` from server.
+        reply_text: String,
+    },
     /// Error during openning a connection.
     ConnectionOpenError(String),
     /// Error during closing a connection.
@@ -30,6 +38,19 @@ pub enum Error {
     /// Error in sending or receiving messages via internal communication channel.
     /// Usually due to incorrect usage by user.
     InternalChannelError(String),
+    /// Server nacked a published message in publisher confirm mode.
+    PublishNacked(String),
+    /// Operation did not complete within the given time.
+    TimeoutError(String),
+    /// Error in serializing or deserializing message content.
+    SerializationError(String),
+    /// Exchange or queue name is invalid, so it is not sent to server.
+    InvalidName(String),
+    /// Server does not support the protocol version of client.
+    ProtocolVersionMismatch {
+        /// `(major, minor, revision)` of the protocol version supported by server.
+        server: (u8, u8, u8),
+    },
 }
 
 #[cfg(feature = "urispec")]
@@ -41,7 +62,14 @@ impl From<URIReferenceError> for Error {
 
 impl From<net::Error> for Error {
     fn from(err: net::Error) -> Self {
-        Self::NetworkError(err.to_string())
+        match err {
+            net::Error::ProtocolVersionMismatch(major, minor, revision) => {
+                Self::ProtocolVersionMismatch {
+                    server: (major, minor, revision),
+                }
+            }
+            err => Self::NetworkError(err.to_string()),
+        }
     }
 }
 impl<T> From<SendError<T>> for Error {
@@ -61,7 +89,11 @@ impl fmt::Display for Error {
             Error::UriError(msg) => {
                 write!(f, "AMQP(S) URI error: {}", msg)
             }
+            Error::AddressError(msg) => write!(f, "AMQP server address error: {}", msg),
             Error::NetworkError(msg) => write!(f, "AMQP network error: {}", msg),
+            Error::AuthenticationFailure { reply_text } => {
+                write!(f, "AMQP authentication failure: {}", reply_text)
+            }
             Error::ConnectionOpenError(msg) => write!(f, "AMQP connection open error: {}", msg),
             Error::ConnectionCloseError(msg) => write!(f, "AMQP connection close error: {}", msg),
             Error::ConnectionUseError(msg) => write!(f, "AMQP connection usage error: {}", msg),
@@ -71,6 +103,19 @@ impl fmt::Display for Error {
             Error::InternalChannelError(msg) => {
                 write!(f, "AMQP internal communication error: {}", msg)
             }
+            Error::PublishNacked(msg) => write!(f, "AMQP publish nacked: {}", msg),
+            Error::TimeoutError(msg) => write!(f, "AMQP operation timeout: {}", msg),
+            Error::SerializationError(msg) => {
+                write!(f, "AMQP message serialization error: {}", msg)
+            }
+            Error::InvalidName(msg) => write!(f, "AMQP invalid name: {}", msg),
+            Error::ProtocolVersionMismatch {
+                server: (major, minor, revision),
+            } => write!(
+                f,
+                "AMQP protocol version mismatch: client supports 0-9-1, server supports {}-{}-{}",
+                major, minor, revision
+            ),
         }
     }
 }
@@ -0,0 +1,203 @@
+//! Fault injection into the transport of a connection, if feature "test-util" is enabled.
+//!
+//! A [`FaultInjector`] given to [`OpenConnectionArguments::fault_injector`] applies faults
+//! to the frames received from server, and severs the connection on demand,
+//! so that applications can test their recovery logic, e.g. reconnect or publisher confirms,
+//! against realistic failures.
+//!
+//! It is intended for testing only.
+//!
+//! # Example
+//! ```rust,no_run
+//! # use amqprs::connection::{Connection, OpenConnectionArguments};
+//! use amqprs::fault::{Fault, FaultInjector};
+//! use std::time::Duration;
+//!
+//! # async fn run() {
+//! let injector = FaultInjector::new();
+//! let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+//!     .fault_injector(injector.clone())
+//!     .finish();
+//! let connection = Connection::open(&args).await.unwrap();
+//!
+//! // the next frame from server is delayed, the one after it is lost
+//! injector.inject(Fault::Delay(Duration::from_millis(500)));
+//! injector.inject(Fault::Drop);
+//! // ...
+//! // the connection fails as if the network is down
+//! injector.sever();
+//! # }
+//! ```
+//!
+//! [`FaultInjector`]: struct.FaultInjector.html
+//! [`OpenConnectionArguments::fault_injector`]: ../connection/struct.OpenConnectionArguments.html#method.fault_injector
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use tokio::sync::Notify;
+
+/// A fault applied to a frame received from server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Deliver the frame after the delay.
+    Delay(Duration),
+    /// Discard the frame, as if it was lost.
+    Drop,
+    /// Deliver the frame twice.
+    Duplicate,
+    /// Keep only the given number of leading bytes of the frame,
+    /// so that the following data is decoded from the middle of the frame.
+    Truncate(usize),
+}
+
+#[derive(Default)]
+struct Inner {
+    faults: Mutex<VecDeque<Fault>>,
+    severed: AtomicBool,
+    notify: Notify,
+}
+
+/// Handle to inject faults into the transport of a connection.
+///
+/// Faults are applied in the order of injection, one per received frame.
+/// It is cheap to clone, clones control the same connection.
+///
+/// See [module level documentation](index.html).
+#[derive(Clone, Default)]
+pub struct FaultInjector {
+    inner: Arc<Inner>,
+}
+
+impl FaultInjector {
+    /// Create a new injector without pending faults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue `fault` to be applied to a following frame received from server.
+    pub fn inject(&self, fault: Fault) {
+        self.inner.faults.lock().unwrap().push_back(fault);
+    }
+
+    /// Returns number of injected faults which are not applied yet.
+    pub fn pending(&self) -> usize {
+        self.inner.faults.lock().unwrap().len()
+    }
+
+    /// Discard all injected faults which are not applied yet.
+    pub fn clear(&self) {
+        self.inner.faults.lock().unwrap().clear();
+    }
+
+    /// Sever the connection immediately, as if the network is down.
+    ///
+    /// The connection is closed with [`CloseInitiator::Network`].
+    ///
+    /// [`CloseInitiator::Network`]: ../state/enum.CloseInitiator.html#variant.Network
+    pub fn sever(&self) {
+        self.inner.severed.store(true, Ordering::Release);
+        self.inner.notify.notify_waiters();
+    }
+
+    /// Returns `true` if the connection has been severed.
+    pub fn is_severed(&self) -> bool {
+        self.inner.severed.load(Ordering::Acquire)
+    }
+
+    /// Take the next fault to apply.
+    pub(crate) fn next_fault(&self) -> Option<Fault> {
+        self.inner.faults.lock().unwrap().pop_front()
+    }
+
+    /// Wait until the connection is severed.
+    pub(crate) async fn severed(&self) {
+        loop {
+            // register before checking, so that no notification is missed in between
+            let notified = self.inner.notify.notified();
+            if self.is_severed() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+impl fmt::Debug for FaultInjector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FaultInjector")
+            .field("pending", &self.pending())
+            .field("severed", &self.is_severed())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{Fault, FaultInjector};
+    use crate::{
+        channel::QueueDeclareArguments,
+        connection::{Connection, OpenConnectionArguments, ShutdownCause},
+        rt,
+        state::{CloseInitiator, State},
+        test_utils::setup_logging,
+    };
+
+    #[test]
+    fn test_faults_in_order() {
+        let injector = FaultInjector::new();
+        injector.inject(Fault::Drop);
+        injector.inject(Fault::Truncate(3));
+        assert_eq!(2, injector.clone().pending());
+        assert_eq!(Some(Fault::Drop), injector.next_fault());
+        assert_eq!(Some(Fault::Truncate(3)), injector.next_fault());
+        assert_eq!(None, injector.next_fault());
+    }
+
+    #[tokio::test]
+    async fn test_fault_injection() {
+        setup_logging();
+
+        let injector = FaultInjector::new();
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_fault_injection")
+            .fault_injector(injector.clone())
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+
+        // delayed response
+        let delay = Duration::from_millis(300);
+        injector.inject(Fault::Delay(delay));
+        let start = Instant::now();
+        channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap();
+        assert!(start.elapsed() >= delay);
+        assert_eq!(0, injector.pending());
+
+        // sever the connection
+        injector.sever();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while connection.is_open() && Instant::now() < deadline {
+            rt::sleep_until(Instant::now() + Duration::from_millis(10)).await;
+        }
+        match connection.state() {
+            State::Closed { initiator, .. } => assert_eq!(CloseInitiator::Network, initiator),
+            state => panic!("unexpected state: {}", state),
+        }
+        assert!(matches!(
+            connection.shutdown_cause(),
+            Some(ShutdownCause::IoError(_))
+        ));
+    }
+}
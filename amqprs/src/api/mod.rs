@@ -37,6 +37,17 @@ pub(crate) mod helpers {
         };
     }
 
+    macro_rules! impl_chainable_into_setter {
+        ($(#[$($attrss:tt)*])* $field_name:ident, $input_type:ty) => {
+            $(#[$($attrss)*])*
+            pub fn $field_name(&mut self, $field_name: impl Into<$input_type>) -> &mut Self {
+                self.$field_name = $field_name.into();
+                self
+            }
+
+        };
+    }
+
     macro_rules! impl_chainable_alias_setter {
         ($(#[$($attrss:tt)*])* $method_name:ident, $field_name:ident, $input_type:ty) => {
             $(#[$($attrss)*])*
@@ -52,14 +63,30 @@ pub(crate) mod helpers {
 }
 
 /////////////////////////////////////////////////////////////////////////////
+#[cfg(feature = "blocking")]
+pub mod blocking;
 #[cfg(feature = "compliance_assert")]
 mod compliance_asserts;
+#[cfg(feature = "compression")]
+pub mod compression;
+#[cfg(feature = "test-util")]
+pub mod fault;
+#[cfg(any(feature = "json", feature = "msgpack"))]
+pub mod serialization;
+#[cfg(feature = "test-support")]
+pub mod test_support;
 #[cfg(feature = "tls")]
 pub mod tls;
+#[cfg(feature = "tower")]
+pub mod tower;
 
 pub mod callbacks;
 pub mod channel;
 pub mod connection;
 pub mod consumer;
 pub mod error;
+mod naming;
+pub mod pool;
+pub mod publisher;
 pub mod security;
+pub mod state;
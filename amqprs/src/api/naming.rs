@@ -0,0 +1,94 @@
+//! Client side validation of exchange and queue names, so that an invalid name fails
+//! locally instead of closing the channel by server, or failing to encode the request.
+//!
+//! Only the limits enforced by server are checked here. The stricter character rules of
+//! the specification are checked by `compliance_assert` feature.
+//!
+//! See [AMQP_0-9-1 Reference](https://www.rabbitmq.com/amqp-0-9-1-reference.html#domain.exchange-name).
+
+use super::{error::Error, Result};
+
+/// Max length of exchange and queue names in bytes.
+const MAX_NAME_LENGTH: usize = 255;
+/// Prefix of names reserved by server.
+const RESERVED_PREFIX: &str = "amq.";
+
+/// Check `name` of `kind` ("exchange" or "queue") fits in a short string. Any UTF-8 name
+/// up to 255 bytes is valid, including empty name, e.g. the default exchange.
+fn check_name(kind: &str, name: &str) -> Result<()> {
+    if name.len() > MAX_NAME_LENGTH {
+        return Err(Error::InvalidName(format!(
+            "{} name '{}' is {} bytes, longer than {} bytes",
+            kind,
+            name,
+            name.len(),
+            MAX_NAME_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+/// Check `name` of `kind` to be declared, see [`check_name`].
+/// Names with reserved prefix are only valid in passive declare, which only checks existence.
+fn check_declare(kind: &str, name: &str, passive: bool) -> Result<()> {
+    check_name(kind, name)?;
+    if !passive && name.starts_with(RESERVED_PREFIX) {
+        return Err(Error::InvalidName(format!(
+            "{} name '{}' is reserved, prefix '{}' is only for server",
+            kind, name, RESERVED_PREFIX
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_exchange_name(name: &str) -> Result<()> {
+    check_name("exchange", name)
+}
+
+pub(crate) fn check_queue_name(name: &str) -> Result<()> {
+    check_name("queue", name)
+}
+
+pub(crate) fn check_exchange_declare(name: &str, passive: bool) -> Result<()> {
+    check_declare("exchange", name, passive)
+}
+
+pub(crate) fn check_queue_declare(name: &str, passive: bool) -> Result<()> {
+    check_declare("queue", name, passive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_exchange_declare, check_exchange_name, check_queue_declare, check_queue_name,
+    };
+    use crate::error::Error;
+
+    #[test]
+    fn test_check_names() {
+        assert!(check_exchange_declare("", false).is_ok());
+        assert!(check_exchange_declare("amqprs test/exchange", false).is_ok());
+        assert!(check_queue_declare("amqprs-队列", false).is_ok());
+        assert!(check_queue_declare(&"q".repeat(255), false).is_ok());
+        assert!(matches!(
+            check_queue_declare(&"q".repeat(256), true),
+            Err(Error::InvalidName(_))
+        ));
+        assert!(check_exchange_name("amq.topic").is_ok());
+        assert!(matches!(
+            check_queue_name(&"q".repeat(256)),
+            Err(Error::InvalidName(_))
+        ));
+
+        assert!(check_exchange_declare("amqprs.amq.topic", false).is_ok());
+        assert!(check_exchange_declare("amq.topic", true).is_ok());
+        assert!(matches!(
+            check_exchange_declare("amq.topic", false),
+            Err(Error::InvalidName(_))
+        ));
+        assert!(matches!(
+            check_queue_declare("amq.gen-abc", false),
+            Err(Error::InvalidName(_))
+        ));
+    }
+}
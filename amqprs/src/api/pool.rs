@@ -0,0 +1,499 @@
+//! A pool of AMQP channels opened on a shared connection.
+//!
+//! Opening a channel costs a round trip to the server, so short-lived tasks,
+//! e.g. request handlers which publish a few messages, should reuse channels
+//! from a [`ChannelPool`] instead of opening and closing one per task.
+//!
+//! The pool keeps at least `min_size` idle channels open, limits the number of
+//! channels, idle and in use, to `max_size`, and closes idle channels not used within
+//! `max_idle_time` by a background reaper task.
+//!
+//! # Example
+//! ```rust
+//! # use amqprs::connection::{OpenConnectionArguments, Connection};
+//! # use amqprs::pool::{ChannelPool, ChannelPoolArguments};
+//! # #[tokio::main]
+//! # async fn main() {
+//! let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami");
+//! let connection = Connection::open(&args).await.unwrap();
+//!
+//! let pool = ChannelPool::new(connection.clone(), ChannelPoolArguments::new())
+//!     .await
+//!     .unwrap();
+//! {
+//!     let channel = pool.acquire().await.unwrap();
+//!     // ... use the channel ...
+//! } // channel is returned to the pool at drop
+//!
+//! connection.close().await.unwrap();
+//! # }
+//! ```
+//!
+//! [`ChannelPool`]: struct.ChannelPool.html
+use std::{
+    collections::VecDeque,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, Weak,
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{
+    api::{channel::Channel, connection::Connection, error::Error},
+    rt,
+};
+
+#[cfg(feature = "traces")]
+use tracing::{debug, info};
+
+use super::Result;
+
+/////////////////////////////////////////////////////////////////////////////
+/// Arguments for [`ChannelPool::new`]
+///
+/// # Support chainable methods to build arguments
+/// ```
+/// # use std::time::Duration;
+/// # use amqprs::pool::ChannelPoolArguments;
+///
+/// let args = ChannelPoolArguments::new()
+///     .min_size(2)
+///     .max_size(16)
+///     .max_idle_time(Duration::from_secs(30))
+///     .finish();
+/// ```
+///
+/// [`ChannelPool::new`]: struct.ChannelPool.html#method.new
+#[derive(Debug, Clone)]
+pub struct ChannelPoolArguments {
+    /// Minimum number of idle channels kept open, as long as the total number
+    /// of channels does not exceed `max_size`.
+    /// Default: 0.
+    pub min_size: usize,
+    /// Maximum number of channels opened by the pool, both idle and in use.
+    /// Default: 10.
+    pub max_size: usize,
+    /// Idle channels not used within this time are closed, unless the
+    /// number of idle channels falls below `min_size`.
+    /// Default: 60 seconds.
+    pub max_idle_time: Duration,
+    /// Maximum time to wait for a channel when `max_size` channels are in use.
+    /// Default: 5 seconds.
+    pub acquire_timeout: Duration,
+    /// Interval of the background reaper task.
+    /// Default: 10 seconds.
+    pub reap_interval: Duration,
+}
+
+impl ChannelPoolArguments {
+    /// Create new arguments with defaults.
+    pub fn new() -> Self {
+        Self {
+            min_size: 0,
+            max_size: 10,
+            max_idle_time: Duration::from_secs(60),
+            acquire_timeout: Duration::from_secs(5),
+            reap_interval: Duration::from_secs(10),
+        }
+    }
+
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        min_size, usize
+    }
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        max_size, usize
+    }
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        max_idle_time, Duration
+    }
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        acquire_timeout, Duration
+    }
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        reap_interval, Duration
+    }
+
+    /// Finish chaining and returns a new argument according to chained configurations.
+    pub fn finish(&mut self) -> Self {
+        self.clone()
+    }
+}
+
+impl Default for ChannelPoolArguments {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/// Snapshot of a pool's counters, see [`ChannelPool::stats`].
+///
+/// [`ChannelPool::stats`]: struct.ChannelPool.html#method.stats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PoolStats {
+    /// Number of acquisitions served by an idle channel.
+    pub hits: u64,
+    /// Number of acquisitions which opened a new channel.
+    pub misses: u64,
+    /// Number of acquisitions which timed out waiting for a channel.
+    pub timeouts: u64,
+    /// Number of idle channels closed by the reaper.
+    pub reaped: u64,
+    /// Number of idle channels at the time of the snapshot.
+    pub idle: usize,
+    /// Number of channels in use at the time of the snapshot.
+    pub in_use: usize,
+}
+
+struct PoolInner {
+    connection: Connection,
+    args: ChannelPoolArguments,
+    /// idle channels and the time they were returned, most recently returned at the back
+    idle: Mutex<VecDeque<(Channel, Instant)>>,
+    /// one permit per channel in use
+    permits: Arc<Semaphore>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    timeouts: AtomicU64,
+    reaped: AtomicU64,
+}
+
+impl PoolInner {
+    /// Close idle channels exceeding `max_idle_time`, and discard the closed ones.
+    ///
+    /// Returns the number of idle channels left.
+    fn reap(&self, now: Instant) -> usize {
+        let mut idle = self.idle.lock().unwrap();
+        idle.retain(|(channel, _)| channel.is_open());
+        let mut reaped = 0;
+        // least recently returned channels are at the front
+        while idle.len() > self.args.min_size {
+            match idle.front() {
+                Some((_, since)) if now.duration_since(*since) >= self.args.max_idle_time => {
+                    // channel is closed when dropped
+                    idle.pop_front();
+                    reaped += 1;
+                }
+                _ => break,
+            }
+        }
+        self.reaped.fetch_add(reaped, Ordering::Relaxed);
+        idle.len()
+    }
+
+    /// Open idle channels until there are `min_size` of them, without exceeding
+    /// `max_size` channels in total, including the ones in use.
+    async fn fill(&self, mut idle: usize) -> Result<()> {
+        let in_use = self.args.max_size - self.permits.available_permits();
+        let target = self.args.min_size.min(self.args.max_size - in_use);
+        while idle < target {
+            let channel = self.connection.open_channel(None).await?;
+            self.idle
+                .lock()
+                .unwrap()
+                .push_back((channel, Instant::now()));
+            idle += 1;
+        }
+        Ok(())
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/// A pool of channels opened on one connection.
+///
+/// Cloned instances share the same pool. The background reaper task stops
+/// once all instances are dropped or the connection is closed.
+///
+/// See [module](index.html) documentation for example.
+#[derive(Clone)]
+pub struct ChannelPool {
+    inner: Arc<PoolInner>,
+}
+
+impl ChannelPool {
+    /// Create a pool of channels on `connection`, and open `min_size` idle channels.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ChannelUseError`] if `max_size` is `0` or less than `min_size`,
+    /// or error if fail to open the idle channels.
+    ///
+    /// [`Error::ChannelUseError`]: ../error/enum.Error.html#variant.ChannelUseError
+    pub async fn new(connection: Connection, args: ChannelPoolArguments) -> Result<Self> {
+        if args.max_size == 0 || args.max_size < args.min_size {
+            return Err(Error::ChannelUseError(format!(
+                "invalid pool size, max_size {} must be positive and not less than min_size {}",
+                args.max_size, args.min_size
+            )));
+        }
+        let inner = Arc::new(PoolInner {
+            connection,
+            permits: Arc::new(Semaphore::new(args.max_size)),
+            args,
+            idle: Mutex::new(VecDeque::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            timeouts: AtomicU64::new(0),
+            reaped: AtomicU64::new(0),
+        });
+        inner.fill(0).await?;
+        inner.connection.spawn(reap_task(Arc::downgrade(&inner)));
+        Ok(Self { inner })
+    }
+
+    /// Acquire a channel from the pool.
+    ///
+    /// An idle channel is reused if any, otherwise a new channel is opened.
+    /// Wait up to `acquire_timeout` if `max_size` channels are in use.
+    ///
+    /// The channel is returned to the pool when the [`PooledChannel`] is dropped,
+    /// unless it has been closed or has state, see [`PooledChannel`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::TimeoutError`] if no channel is available within `acquire_timeout`,
+    /// or error if fail to open a new channel.
+    ///
+    /// [`PooledChannel`]: struct.PooledChannel.html
+    pub async fn acquire(&self) -> Result<PooledChannel> {
+        let deadline = Instant::now() + self.inner.args.acquire_timeout;
+        let permit =
+            match rt::timeout_at(deadline, self.inner.permits.clone().acquire_owned()).await {
+                Some(permit) => permit.expect("pool semaphore is never closed"),
+                None => {
+                    self.inner.timeouts.fetch_add(1, Ordering::Relaxed);
+                    return Err(Error::TimeoutError(format!(
+                        "no pooled channel available within {:?} on connection {}",
+                        self.inner.args.acquire_timeout, self.inner.connection
+                    )));
+                }
+            };
+        let reused = {
+            let mut idle = self.inner.idle.lock().unwrap();
+            // most recently returned channel first, so that the others can be reaped
+            let mut reused = None;
+            while let Some((channel, _)) = idle.pop_back() {
+                if channel.is_open() {
+                    reused = Some(channel);
+                    break;
+                }
+            }
+            reused
+        };
+        let channel = match reused {
+            Some(channel) => {
+                self.inner.hits.fetch_add(1, Ordering::Relaxed);
+                channel
+            }
+            None => {
+                self.inner.misses.fetch_add(1, Ordering::Relaxed);
+                self.inner.connection.open_channel(None).await?
+            }
+        };
+        Ok(PooledChannel {
+            channel: Some(channel),
+            pool: Arc::downgrade(&self.inner),
+            _permit: permit,
+        })
+    }
+
+    /// Returns a snapshot of the pool's counters.
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            hits: self.inner.hits.load(Ordering::Relaxed),
+            misses: self.inner.misses.load(Ordering::Relaxed),
+            timeouts: self.inner.timeouts.load(Ordering::Relaxed),
+            reaped: self.inner.reaped.load(Ordering::Relaxed),
+            idle: self.inner.idle.lock().unwrap().len(),
+            in_use: self.inner.args.max_size - self.inner.permits.available_permits(),
+        }
+    }
+}
+
+/// Periodically reap idle channels and open new ones to keep `min_size` of them.
+async fn reap_task(pool: Weak<PoolInner>) {
+    loop {
+        let interval = match pool.upgrade() {
+            Some(inner) => inner.args.reap_interval,
+            None => break,
+        };
+        rt::sleep_until(Instant::now() + interval).await;
+
+        let inner = match pool.upgrade() {
+            Some(inner) if inner.connection.is_open() => inner,
+            _ => break,
+        };
+        let idle = inner.reap(Instant::now());
+        if let Err(_err) = inner.fill(idle).await {
+            #[cfg(feature = "traces")]
+            debug!(
+                "failed to open idle channels on connection {}: {}",
+                inner.connection, _err
+            );
+        }
+    }
+    #[cfg(feature = "traces")]
+    info!("exit channel pool reaper task");
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/// A channel acquired from a [`ChannelPool`], which dereferences to [`Channel`].
+///
+/// The channel is returned to the pool when dropped, unless it has been closed,
+/// e.g. by a channel exception.
+///
+/// A channel which has state that must not be carried over to the next user is closed
+/// instead, i.e. once consumers, publisher confirm or transaction mode, QoS, flow or
+/// callbacks have been set on it. Use [`detach`] to keep such a channel.
+///
+/// [`ChannelPool`]: struct.ChannelPool.html
+/// [`Channel`]: ../channel/struct.Channel.html
+/// [`detach`]: struct.PooledChannel.html#method.detach
+pub struct PooledChannel {
+    channel: Option<Channel>,
+    pool: Weak<PoolInner>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl PooledChannel {
+    /// Take the channel out of the pool, it will not be returned to the pool at drop.
+    pub fn detach(mut self) -> Channel {
+        self.channel.take().unwrap()
+    }
+}
+
+impl Deref for PooledChannel {
+    type Target = Channel;
+
+    fn deref(&self) -> &Self::Target {
+        self.channel.as_ref().unwrap()
+    }
+}
+
+impl Drop for PooledChannel {
+    fn drop(&mut self) {
+        if let (Some(channel), Some(pool)) = (self.channel.take(), self.pool.upgrade()) {
+            // a stateful channel is closed when dropped
+            if channel.is_open() && !channel.is_stateful() {
+                pool.idle
+                    .lock()
+                    .unwrap()
+                    .push_back((channel, Instant::now()));
+            }
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{ChannelPool, ChannelPoolArguments};
+    use crate::{
+        api::{
+            channel::BasicQosArguments,
+            connection::{Connection, OpenConnectionArguments},
+            error::Error,
+        },
+        test_utils::setup_logging,
+    };
+
+    #[tokio::test]
+    async fn test_channel_pool() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_channel_pool")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let pool = ChannelPool::new(
+            connection.clone(),
+            ChannelPoolArguments::new()
+                .min_size(1)
+                .max_size(2)
+                .max_idle_time(Duration::from_millis(100))
+                .acquire_timeout(Duration::from_millis(100))
+                .reap_interval(Duration::from_millis(50))
+                .finish(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, pool.stats().idle);
+
+        let first = pool.acquire().await.unwrap();
+        let second = pool.acquire().await.unwrap();
+        assert!(matches!(pool.acquire().await, Err(Error::TimeoutError(_))));
+        let stats = pool.stats();
+        assert_eq!(
+            (1, 1, 1, 2),
+            (stats.hits, stats.misses, stats.timeouts, stats.in_use)
+        );
+
+        drop(first);
+        drop(second);
+        assert_eq!(2, pool.stats().idle);
+
+        // idle channels are reaped down to `min_size`
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let stats = pool.stats();
+        assert_eq!((1, 1, 0), (stats.reaped, stats.idle, stats.in_use));
+
+        // a channel with QoS is closed instead of returned to the pool
+        let stateful = pool.acquire().await.unwrap();
+        stateful
+            .basic_qos(BasicQosArguments::new(0, 1, false))
+            .await
+            .unwrap();
+        drop(stateful);
+        assert_eq!(0, pool.stats().idle);
+
+        connection.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_channel_pool_size_limit() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_channel_pool_size_limit")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        assert!(matches!(
+            ChannelPool::new(
+                connection.clone(),
+                ChannelPoolArguments::new().min_size(2).max_size(1).finish()
+            )
+            .await,
+            Err(Error::ChannelUseError(_))
+        ));
+
+        let pool = ChannelPool::new(
+            connection.clone(),
+            ChannelPoolArguments::new()
+                .min_size(2)
+                .max_size(2)
+                .reap_interval(Duration::from_millis(50))
+                .finish(),
+        )
+        .await
+        .unwrap();
+        let _first = pool.acquire().await.unwrap();
+        let _second = pool.acquire().await.unwrap();
+        // no idle channel is opened while `max_size` channels are in use
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let stats = pool.stats();
+        assert_eq!((0, 2), (stats.idle, stats.in_use));
+
+        connection.close().await.unwrap();
+    }
+}
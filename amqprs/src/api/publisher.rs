@@ -0,0 +1,405 @@
+//! A publisher which survives connection failures.
+//!
+//! [`Publisher`] publishes on a channel of a [`LazyConnection`] in publisher confirm mode.
+//! If the connection or the channel is closed, it re-opens them and re-enters confirm mode
+//! transparently, and retries a failed publish according to a [`RetryPolicy`].
+//!
+//! # Example
+//! ```rust,no_run
+//! # use amqprs::{channel::BasicPublishArguments, BasicProperties};
+//! use amqprs::connection::{LazyConnection, OpenConnectionArguments};
+//! use amqprs::publisher::{Publisher, RetryPolicy};
+//! use std::time::Duration;
+//!
+//! # async fn publish() {
+//! let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami");
+//! let publisher = Publisher::new(
+//!     LazyConnection::new(args),
+//!     RetryPolicy::new()
+//!         .max_attempts(5)
+//!         .initial_backoff(Duration::from_millis(100))
+//!         .finish(),
+//! );
+//! publisher
+//!     .publish(
+//!         BasicProperties::default(),
+//!         "hello",
+//!         BasicPublishArguments::new("amq.topic", "greeting"),
+//!     )
+//!     .await
+//!     .unwrap();
+//! # }
+//! ```
+//!
+//! [`Publisher`]: struct.Publisher.html
+//! [`LazyConnection`]: ../connection/struct.LazyConnection.html
+//! [`RetryPolicy`]: struct.RetryPolicy.html
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use tokio::sync::Mutex;
+
+use super::{
+    channel::{BasicPublishArguments, Channel, ConfirmSelectArguments, Confirmation},
+    connection::LazyConnection,
+    error::Error,
+    Result,
+};
+use crate::{frame::BasicProperties, rt};
+
+#[cfg(feature = "traces")]
+use tracing::warn;
+
+/////////////////////////////////////////////////////////////////////////////
+/// Policy to retry a failed publish of [`Publisher`].
+///
+/// The delay before each retry starts at `initial_backoff`, and is multiplied
+/// by `multiplier` after each retry, up to `max_backoff`.
+///
+/// # Support chainable methods to build arguments
+/// ```
+/// # use std::time::Duration;
+/// # use amqprs::publisher::RetryPolicy;
+///
+/// let policy = RetryPolicy::new()
+///     .max_attempts(5)
+///     .initial_backoff(Duration::from_millis(100))
+///     .max_backoff(Duration::from_secs(5))
+///     .finish();
+/// ```
+///
+/// [`Publisher`]: struct.Publisher.html
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts to publish a message, including the first one.
+    /// Default: 3.
+    pub max_attempts: u32,
+    /// Delay before the first retry.
+    /// Default: 100 milliseconds.
+    pub initial_backoff: Duration,
+    /// Maximum delay between retries.
+    /// Default: 10 seconds.
+    pub max_backoff: Duration,
+    /// Factor to increase the delay after each retry.
+    /// Default: 2.
+    pub multiplier: u32,
+    /// Retry only on transient errors, see [`is_transient`].
+    /// Default: `true`.
+    ///
+    /// [`is_transient`]: fn.is_transient.html
+    pub only_transient: bool,
+}
+
+impl RetryPolicy {
+    /// Create new policy with defaults.
+    pub fn new() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2,
+            only_transient: true,
+        }
+    }
+
+    /// Policy which never retries.
+    pub fn no_retry() -> Self {
+        Self::new().max_attempts(1).finish()
+    }
+
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        max_attempts, u32
+    }
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        initial_backoff, Duration
+    }
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        max_backoff, Duration
+    }
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        multiplier, u32
+    }
+    impl_chainable_setter! {
+        /// Chainable setter method.
+        only_transient, bool
+    }
+
+    /// Finish chaining and returns a new argument according to chained configurations.
+    pub fn finish(&mut self) -> Self {
+        self.clone()
+    }
+
+    /// Returns `true` if `error` of the given `attempt` (starting from 1) should be retried.
+    fn should_retry(&self, attempt: u32, error: &Error) -> bool {
+        attempt < self.max_attempts && (!self.only_transient || is_transient(error))
+    }
+
+    /// Returns delay before the retry after the given `attempt` (starting from 1).
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns `true` if `error` may succeed on retry, e.g. failures of network,
+/// of opening or closing connection or channel, timeouts, and nacks from server.
+///
+/// Errors due to incorrect usage, e.g. invalid URI or arguments rejected by server,
+/// are not transient.
+pub fn is_transient(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::NetworkError(_)
+            | Error::InternalChannelError(_)
+            | Error::ConnectionOpenError(_)
+            | Error::ConnectionCloseError(_)
+            | Error::ChannelOpenError(_)
+            | Error::ChannelCloseError(_)
+            | Error::TimeoutError(_)
+            | Error::PublishNacked(_)
+    )
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/// A publisher bound to a [`LazyConnection`], which retries failed publishes.
+///
+/// It opens a channel in publisher confirm mode on first use, and re-opens the connection
+/// and the channel if they are closed. A publish succeeds once server confirms the message.
+///
+/// Retried messages may be duplicated, e.g. if the connection fails after server received
+/// the message but before the confirm is received, so consumers should be idempotent.
+///
+/// Cloned instances share the same channel.
+///
+/// See [module level documentation](index.html).
+///
+/// [`LazyConnection`]: ../connection/struct.LazyConnection.html
+#[derive(Clone)]
+pub struct Publisher {
+    connection: LazyConnection,
+    channel: Arc<Mutex<Option<Channel>>>,
+    policy: RetryPolicy,
+}
+
+impl Publisher {
+    /// Create a new publisher, no channel is opened until first publish.
+    pub fn new(connection: LazyConnection, policy: RetryPolicy) -> Self {
+        Self {
+            connection,
+            channel: Default::default(),
+            policy,
+        }
+    }
+
+    /// Returns the retry policy.
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    /// Publish a message and wait until server confirms it,
+    /// retry according to the policy on failure.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the last attempt if all attempts fail,
+    /// or immediately if the error should not be retried.
+    /// A nack from server is reported as [`Error::PublishNacked`].
+    ///
+    /// [`Error::PublishNacked`]: ../error/enum.Error.html#variant.PublishNacked
+    pub async fn publish(
+        &self,
+        basic_properties: BasicProperties,
+        content: impl Into<Bytes>,
+        args: BasicPublishArguments,
+    ) -> Result<()> {
+        let content = content.into();
+        let mut attempt = 1;
+        loop {
+            let result = self
+                .try_publish(basic_properties.clone(), content.clone(), args.clone())
+                .await;
+            match result {
+                Ok(()) => return Ok(()),
+                Err(err) if self.policy.should_retry(attempt, &err) => {
+                    #[cfg(feature = "traces")]
+                    warn!("publish attempt {} failed, retry: {}", attempt, err);
+                    rt::sleep_until(Instant::now() + self.policy.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Close the channel if it is open, the connection is left open.
+    ///
+    /// A later publish will open a new channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if any failure in communication with server.
+    pub async fn close(&self) -> Result<()> {
+        let channel = self.channel.lock().await.take();
+        match channel {
+            Some(channel) if channel.is_open() => channel.close().await,
+            _ => Ok(()),
+        }
+    }
+
+    async fn try_publish(
+        &self,
+        basic_properties: BasicProperties,
+        content: Bytes,
+        args: BasicPublishArguments,
+    ) -> Result<()> {
+        let channel = self.channel().await?;
+        let result = match channel
+            .basic_publish_confirm(basic_properties, content, args)
+            .await
+        {
+            Ok(Confirmation::Ack) => return Ok(()),
+            Ok(Confirmation::Nack) => Err(Error::PublishNacked(format!(
+                "message nacked by server on channel {}",
+                channel
+            ))),
+            Err(err) => Err(err),
+        };
+        // discard a broken channel, so that next attempt opens a new one,
+        // unless a concurrent publish has already replaced it
+        if !channel.is_open() {
+            let mut guard = self.channel.lock().await;
+            if matches!(guard.as_ref(), Some(current) if current.is_same(&channel)) {
+                guard.take();
+            }
+        }
+        result
+    }
+
+    /// Returns the open channel in confirm mode, open a new one if needed.
+    async fn channel(&self) -> Result<Channel> {
+        let mut guard = self.channel.lock().await;
+        if let Some(channel) = guard.as_ref() {
+            if channel.is_open() {
+                return Ok(channel.clone());
+            }
+        }
+        let channel = self.connection.open_channel(None).await?;
+        channel
+            .confirm_select(ConfirmSelectArguments::default())
+            .await?;
+        guard.replace(channel.clone());
+        Ok(channel)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{is_transient, Publisher, RetryPolicy};
+    use crate::{
+        channel::{BasicGetArguments, BasicPublishArguments, QueueDeclareArguments},
+        connection::{LazyConnection, OpenConnectionArguments},
+        error::Error,
+        test_utils::setup_logging,
+        BasicProperties,
+    };
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy::new()
+            .max_attempts(4)
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(300))
+            .finish();
+        assert_eq!(Duration::from_millis(100), policy.backoff(1));
+        assert_eq!(Duration::from_millis(200), policy.backoff(2));
+        assert_eq!(Duration::from_millis(300), policy.backoff(3));
+        assert_eq!(Duration::from_millis(300), policy.backoff(100));
+
+        let transient = Error::NetworkError("reset".to_string());
+        let usage = Error::ChannelUseError("invalid".to_string());
+        assert!(is_transient(&transient));
+        assert!(!is_transient(&usage));
+        assert!(policy.should_retry(3, &transient));
+        assert!(!policy.should_retry(4, &transient));
+        assert!(!policy.should_retry(1, &usage));
+        assert!(policy.clone().only_transient(false).should_retry(1, &usage));
+        assert!(!RetryPolicy::no_retry().should_retry(1, &transient));
+    }
+
+    #[tokio::test]
+    async fn test_publisher_reopens_channel() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_publisher_reopens_channel")
+            .finish();
+        let lazy = LazyConnection::new(args);
+        let channel = lazy.open_channel(None).await.unwrap();
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let publisher = Publisher::new(lazy.clone(), RetryPolicy::default());
+        publisher
+            .publish(
+                BasicProperties::default(),
+                "first",
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .await
+            .unwrap();
+
+        // channel is closed by server on every attempt due to non-existing exchange
+        let err = publisher
+            .publish(
+                BasicProperties::default(),
+                "lost",
+                BasicPublishArguments::new("amqprs.not.exist", ""),
+            )
+            .await;
+        assert!(err.is_err());
+
+        // a new channel is opened for next publish
+        publisher
+            .publish(
+                BasicProperties::default(),
+                "second",
+                BasicPublishArguments::new("", &queue_name),
+            )
+            .await
+            .unwrap();
+
+        for expected in ["first", "second"] {
+            let (_, _, content) = channel
+                .basic_get(BasicGetArguments::new(&queue_name).no_ack(true).finish())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(expected.as_bytes(), content);
+        }
+
+        publisher.close().await.unwrap();
+        channel.close().await.unwrap();
+        lazy.close().await.unwrap();
+    }
+}
@@ -50,6 +50,15 @@ impl SecurityCredentials {
         }
     }
 
+    /// Get the username of current credential
+    pub(crate) fn get_username(&self) -> &str {
+        &self.username
+    }
+    /// Get the password of current credential
+    pub(crate) fn get_password(&self) -> &str {
+        &self.password
+    }
+
     /// Get the name of authentication mechanism of current credential
     pub(crate) fn get_mechanism_name(&self) -> &str {
         match self.mechanism {
@@ -0,0 +1,345 @@
+//! Typed publish and consume helpers based on [serde](https://serde.rs).
+//!
+//! [`Channel::publish_serde`] serializes a value in the given [`SerdeFormat`] and sets
+//! `content_type` of the message accordingly. [`SerdeConsumer`] adapts a [`TypedConsumer`]
+//! to [`AsyncConsumer`], it deserializes the content of each delivery according to its
+//! `content_type` before handing it over to the typed consumer.
+//!
+//! Supported formats depend on feature flags:
+//! - "json": JSON, content type "application/json".
+//! - "msgpack": MessagePack, content type "application/msgpack".
+//!
+//! [`Channel::publish_serde`]: ../channel/struct.Channel.html#method.publish_serde
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{
+    channel::{BasicPublishArguments, Channel},
+    consumer::AsyncConsumer,
+    error::Error,
+    Result,
+};
+use crate::frame::{BasicProperties, Deliver};
+
+#[cfg(feature = "json")]
+use super::channel::ConsumerMessage;
+
+/// Serialization format of message content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SerdeFormat {
+    /// JSON, content type "application/json".
+    #[cfg(feature = "json")]
+    Json,
+    /// MessagePack, content type "application/msgpack".
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+}
+
+impl SerdeFormat {
+    /// Returns the value of `content_type` property.
+    pub fn content_type(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "json")]
+            SerdeFormat::Json => "application/json",
+            #[cfg(feature = "msgpack")]
+            SerdeFormat::MessagePack => "application/msgpack",
+        }
+    }
+
+    /// Returns the format of a `content_type` property, [`None`] if unknown.
+    ///
+    /// Parameters of the media type, e.g. `charset`, are ignored.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let media_type = content_type.split(';').next().unwrap_or_default().trim();
+        match media_type {
+            #[cfg(feature = "json")]
+            "application/json" => Some(SerdeFormat::Json),
+            #[cfg(feature = "msgpack")]
+            "application/msgpack" | "application/x-msgpack" => Some(SerdeFormat::MessagePack),
+            _ => None,
+        }
+    }
+
+    /// Serialize `value` in this format.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SerializationError`] if serialization fails.
+    pub fn serialize<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>> {
+        match *self {
+            #[cfg(feature = "json")]
+            SerdeFormat::Json => {
+                serde_json::to_vec(value).map_err(|err| Error::SerializationError(err.to_string()))
+            }
+            #[cfg(feature = "msgpack")]
+            SerdeFormat::MessagePack => rmp_serde::to_vec_named(value)
+                .map_err(|err| Error::SerializationError(err.to_string())),
+        }
+    }
+
+    /// Deserialize `content` in this format.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SerializationError`] if deserialization fails.
+    pub fn deserialize<T: DeserializeOwned>(&self, content: &[u8]) -> Result<T> {
+        match *self {
+            #[cfg(feature = "json")]
+            SerdeFormat::Json => serde_json::from_slice(content)
+                .map_err(|err| Error::SerializationError(err.to_string())),
+            #[cfg(feature = "msgpack")]
+            SerdeFormat::MessagePack => rmp_serde::from_slice(content)
+                .map_err(|err| Error::SerializationError(err.to_string())),
+        }
+    }
+}
+
+/// Trait defines the callback interface of a consumer of typed messages.
+///
+/// Use [`SerdeConsumer`] to start consuming by [`Channel::basic_consume`].
+///
+/// [`Channel::basic_consume`]: ../channel/struct.Channel.html#method.basic_consume
+#[async_trait]
+pub trait TypedConsumer {
+    /// Type of the deserialized message.
+    type Message: DeserializeOwned + Send;
+
+    /// Consume a delivery from server.
+    ///
+    /// `message` is [`Err`] if the content can not be deserialized, e.g. unknown content type
+    /// or malformed content, the implementation should still acknowledge or reject the delivery.
+    ///
+    /// See also [`AsyncConsumer::consume`].
+    async fn consume(
+        &mut self,
+        channel: &Channel,
+        deliver: Deliver,
+        basic_properties: BasicProperties,
+        message: Result<Self::Message>,
+    );
+}
+
+/// Adapter of a [`TypedConsumer`] to [`AsyncConsumer`].
+///
+/// Content is deserialized by the format of `content_type` property, or by the default format
+/// if the message has no `content_type`.
+#[derive(Debug, Clone)]
+pub struct SerdeConsumer<C> {
+    inner: C,
+    default_format: Option<SerdeFormat>,
+}
+
+impl<C: TypedConsumer> SerdeConsumer<C> {
+    /// Returns a new adapter, messages without `content_type` are rejected as unknown format.
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            default_format: None,
+        }
+    }
+
+    /// Deserialize messages without `content_type` in `format`.
+    pub fn with_default_format(mut self, format: SerdeFormat) -> Self {
+        self.default_format = Some(format);
+        self
+    }
+
+    fn deserialize(
+        &self,
+        basic_properties: &BasicProperties,
+        content: &[u8],
+    ) -> Result<C::Message> {
+        let format = match basic_properties.content_type() {
+            Some(content_type) => {
+                SerdeFormat::from_content_type(content_type).ok_or_else(|| {
+                    Error::SerializationError(format!("unsupported content type {}", content_type))
+                })?
+            }
+            None => self
+                .default_format
+                .ok_or_else(|| Error::SerializationError("missing content type".to_string()))?,
+        };
+        format.deserialize(content)
+    }
+}
+
+#[async_trait]
+impl<C> AsyncConsumer for SerdeConsumer<C>
+where
+    C: TypedConsumer + Send,
+{
+    async fn consume(
+        &mut self,
+        channel: &Channel,
+        deliver: Deliver,
+        basic_properties: BasicProperties,
+        content: Vec<u8>,
+    ) {
+        let message = self.deserialize(&basic_properties, &content);
+        self.inner
+            .consume(channel, deliver, basic_properties, message)
+            .await;
+    }
+}
+
+/// APIs for typed messages.
+impl Channel {
+    /// Serialize `value` in `format` and publish it, `content_type` of the message is set
+    /// according to `format`.
+    ///
+    /// See also [`basic_publish`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SerializationError`] if serialization fails, otherwise see [`basic_publish`].
+    ///
+    /// [`basic_publish`]: struct.Channel.html#method.basic_publish
+    pub async fn publish_serde<T: Serialize + ?Sized>(
+        &self,
+        value: &T,
+        format: SerdeFormat,
+        args: BasicPublishArguments,
+    ) -> Result<()> {
+        let content = format.serialize(value)?;
+        let basic_properties = BasicProperties::default()
+            .with_content_type(format.content_type())
+            .finish();
+        self.basic_publish(basic_properties, content, args).await
+    }
+}
+
+#[cfg(feature = "json")]
+impl BasicProperties {
+    /// Returns properties of a JSON message, i.e. `content_type` is "application/json"
+    /// and `content_encoding` is "utf-8".
+    pub fn json() -> Self {
+        BasicProperties::default()
+            .with_content_type(SerdeFormat::Json.content_type())
+            .with_content_encoding("utf-8")
+            .finish()
+    }
+}
+
+/// APIs for JSON messages.
+#[cfg(feature = "json")]
+impl Channel {
+    /// Serialize `value` to JSON and publish it with [`BasicProperties::json`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SerializationError`] if serialization fails, otherwise see [`basic_publish`].
+    ///
+    /// [`basic_publish`]: struct.Channel.html#method.basic_publish
+    pub async fn publish_json<T: Serialize + ?Sized>(
+        &self,
+        value: &T,
+        args: BasicPublishArguments,
+    ) -> Result<()> {
+        let content = SerdeFormat::Json.serialize(value)?;
+        self.basic_publish(BasicProperties::json(), content, args)
+            .await
+    }
+}
+
+#[cfg(feature = "json")]
+impl ConsumerMessage {
+    /// Deserialize the content from JSON, regardless of `content_type`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SerializationError`] if deserialization fails.
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        let content = self
+            .content
+            .as_ref()
+            .ok_or_else(|| Error::SerializationError("missing content".to_string()))?;
+        SerdeFormat::Json.deserialize(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::SerdeFormat;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Order {
+        id: u64,
+        item: String,
+    }
+
+    #[test]
+    fn test_serde_format() {
+        let order = Order {
+            id: 1,
+            item: "book".to_string(),
+        };
+        #[cfg(feature = "json")]
+        {
+            let format = SerdeFormat::from_content_type("application/json; charset=utf-8").unwrap();
+            assert_eq!(SerdeFormat::Json, format);
+            let content = format.serialize(&order).unwrap();
+            assert_eq!(order, format.deserialize::<Order>(&content).unwrap());
+            assert!(format.deserialize::<Order>(b"not json").is_err());
+        }
+        #[cfg(feature = "msgpack")]
+        {
+            let format = SerdeFormat::from_content_type("application/msgpack").unwrap();
+            assert_eq!(SerdeFormat::MessagePack, format);
+            let content = format.serialize(&order).unwrap();
+            assert_eq!(order, format.deserialize::<Order>(&content).unwrap());
+        }
+        assert_eq!(None, SerdeFormat::from_content_type("text/plain"));
+    }
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn test_json_message() {
+        use crate::{
+            channel::{BasicConsumeArguments, BasicPublishArguments, QueueDeclareArguments},
+            connection::{Connection, OpenConnectionArguments},
+            test_utils::setup_logging,
+            BasicProperties,
+        };
+
+        setup_logging();
+
+        let props = BasicProperties::json();
+        assert_eq!(Some(&"application/json".to_string()), props.content_type());
+        assert_eq!(Some(&"utf-8".to_string()), props.content_encoding());
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_json_message")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let order = Order {
+            id: 2,
+            item: "pen".to_string(),
+        };
+        channel
+            .publish_json(&order, BasicPublishArguments::new("", &queue_name))
+            .await
+            .unwrap();
+        let (_, mut messages_rx) = channel
+            .basic_consume_rx(
+                BasicConsumeArguments::new(&queue_name, "test_json_message")
+                    .auto_ack(true)
+                    .finish(),
+            )
+            .await
+            .unwrap();
+        let message = messages_rx.recv().await.unwrap();
+        assert_eq!(order, message.json::<Order>().unwrap());
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+}
@@ -0,0 +1,195 @@
+//! State of a connection or a channel, see [`Connection::state`] and [`Channel::state`].
+//!
+//! [`Connection::state`]: ../connection/struct.Connection.html#method.state
+//! [`Channel::state`]: ../channel/struct.Channel.html#method.state
+use std::{fmt, sync::Mutex};
+
+use tokio::sync::Notify;
+
+use crate::frame::REPLY_SUCCESS;
+
+/// Who initiated closing a connection or a channel, see [`State::Closed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseInitiator {
+    /// Closed by client, explicitly or on drop.
+    Client,
+    /// Closed by server.
+    Server,
+    /// Closed due to network I/O failure, or because the connection is closed.
+    Network,
+}
+
+/// State of a connection or a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum State {
+    /// Opening handshake is in progress.
+    Opening,
+    /// Open to use.
+    Open,
+    /// Open, but server has blocked publishing, by `connection.blocked` for a connection
+    /// or by `channel.flow` for a channel.
+    Blocked,
+    /// Client has requested to close, and waits for response from server.
+    Closing,
+    /// Closed.
+    Closed {
+        /// Who initiated closing.
+        initiator: CloseInitiator,
+        /// Reply code of the close method, `0` if no close method is exchanged.
+        code: u16,
+        /// Reply text of the close method, or the cause if no close method is exchanged.
+        text: String,
+    },
+}
+
+impl State {
+    /// Returns `true` if open to use, i.e. [`State::Open`] or [`State::Blocked`].
+    pub fn is_open(&self) -> bool {
+        matches!(self, State::Open | State::Blocked)
+    }
+
+    /// Returns `true` if closed.
+    pub fn is_closed(&self) -> bool {
+        matches!(self, State::Closed { .. })
+    }
+
+    pub(crate) fn closed_by_client() -> Self {
+        State::Closed {
+            initiator: CloseInitiator::Client,
+            code: REPLY_SUCCESS,
+            text: "closed by client".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            State::Opening => f.write_str("opening"),
+            State::Open => f.write_str("open"),
+            State::Blocked => f.write_str("blocked"),
+            State::Closing => f.write_str("closing"),
+            State::Closed { .. } => f.write_str("closed"),
+        }
+    }
+}
+
+/// State shared by handles and internal tasks of a connection or a channel.
+#[derive(Debug)]
+pub(crate) struct SharedState {
+    state: Mutex<State>,
+    /// notified once closed.
+    closed: Notify,
+}
+
+impl SharedState {
+    pub fn new(state: State) -> Self {
+        Self {
+            state: Mutex::new(state),
+            closed: Notify::new(),
+        }
+    }
+
+    pub fn get(&self) -> State {
+        self.state.lock().unwrap().clone()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state.lock().unwrap().is_open()
+    }
+
+    /// Update to [`State::Blocked`] if `blocked`, otherwise to [`State::Open`],
+    /// unless it is not open.
+    pub fn set_blocked(&self, blocked: bool) {
+        let mut state = self.state.lock().unwrap();
+        if state.is_open() {
+            *state = if blocked { State::Blocked } else { State::Open };
+        }
+    }
+
+    /// Update to [`State::Closing`] if it is open.
+    ///
+    /// Returns `true` if updated, then the caller is responsible to close.
+    pub fn begin_close(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.is_open() {
+            *state = State::Closing;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Update to [`State::Closed`] unless it is already closed,
+    /// so that the first reason is kept.
+    pub fn close(&self, closed: State) {
+        let mut state = self.state.lock().unwrap();
+        if !state.is_closed() {
+            *state = closed;
+            self.closed.notify_waiters();
+        }
+    }
+
+    /// Wait until closed, returns the [`State::Closed`].
+    pub async fn closed(&self) -> State {
+        loop {
+            // register before checking, so that no notification is missed in between
+            let notified = self.closed.notified();
+            let state = self.get();
+            if state.is_closed() {
+                return state;
+            }
+            notified.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::{CloseInitiator, SharedState, State};
+
+    #[tokio::test]
+    async fn test_shared_state_closed() {
+        let state = Arc::new(SharedState::new(State::Open));
+        let timeout = Duration::from_millis(10);
+        assert!(tokio::time::timeout(timeout, state.closed()).await.is_err());
+
+        let waiter = {
+            let state = state.clone();
+            tokio::spawn(async move { state.closed().await })
+        };
+        state.close(State::closed_by_client());
+        assert_eq!(State::closed_by_client(), waiter.await.unwrap());
+        // resolves immediately once closed
+        assert_eq!(State::closed_by_client(), state.closed().await);
+    }
+
+    #[test]
+    fn test_shared_state_transitions() {
+        let state = SharedState::new(State::Open);
+        state.set_blocked(true);
+        assert_eq!(State::Blocked, state.get());
+        assert!(state.is_open());
+        state.set_blocked(false);
+        assert_eq!(State::Open, state.get());
+
+        assert!(state.begin_close());
+        assert!(!state.begin_close());
+        assert_eq!(State::Closing, state.get());
+        // blocked is ignored once closing
+        state.set_blocked(true);
+        assert_eq!(State::Closing, state.get());
+
+        state.close(State::closed_by_client());
+        // the first reason is kept
+        state.close(State::Closed {
+            initiator: CloseInitiator::Network,
+            code: 0,
+            text: "network I/O failure".to_string(),
+        });
+        assert_eq!(State::closed_by_client(), state.get());
+        assert!(!state.is_open());
+    }
+}
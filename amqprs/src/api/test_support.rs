@@ -0,0 +1,253 @@
+//! Scaffolding for integration tests against a real broker, if feature "test-support" is enabled.
+//!
+//! It provides logging setup, connection arguments from environment variables,
+//! unique names of queues and exchanges, so that tests run in parallel don't interfere,
+//! and a [`TestContext`] which deletes the resources declared by a test in its teardown.
+//!
+//! It is intended for testing only.
+//!
+//! # Example
+//! ```rust,no_run
+//! use amqprs::test_support::{setup_logging, TestContext};
+//!
+//! # async fn run() {
+//! setup_logging();
+//! let ctx = TestContext::new("test_orders").await.unwrap();
+//! let queue = ctx.declare_queue("orders").await.unwrap();
+//! let exchange = ctx.declare_exchange("orders", "direct").await.unwrap();
+//! // ... publish and consume on `ctx.channel()`
+//! ctx.teardown().await.unwrap();
+//! # }
+//! ```
+//!
+//! [`TestContext`]: struct.TestContext.html
+use std::{
+    env, process,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+use super::{
+    channel::{
+        Channel, ExchangeDeclareArguments, ExchangeDeleteArguments, QueueDeclareArguments,
+        QueueDeleteArguments,
+    },
+    connection::{Connection, OpenConnectionArguments},
+    Result,
+};
+
+/// Install a global subscriber that prints formatted traces to stdout,
+/// with log level according to `RUST_LOG`.
+///
+/// It can be called by every test, only the first call installs the subscriber.
+pub fn setup_logging() {
+    tracing_subscriber::registry()
+        .with(fmt::layer())
+        .with(EnvFilter::from_default_env())
+        .try_init()
+        .ok();
+}
+
+/// Username of the test broker in `docker-compose.yml`.
+const TEST_USERNAME: &str = "user";
+/// Password of the test broker in `docker-compose.yml`.
+const TEST_PASSWORD: &str = "bitnami";
+
+/// Returns arguments to connect the broker under test, given by environment variables,
+/// see [`OpenConnectionArguments::from_env`].
+///
+/// Unless `AMQP_URL` is set, the username and password default to those of the
+/// test broker in `docker-compose.yml`.
+///
+/// # Panics
+///
+/// Panics if an environment variable has invalid value, to fail the test early.
+///
+/// [`OpenConnectionArguments::from_env`]: ../connection/struct.OpenConnectionArguments.html#method.from_env
+pub fn connection_args() -> OpenConnectionArguments {
+    let url_set = env::var_os("AMQP_URL").is_some();
+    OpenConnectionArguments::from_vars(|name| {
+        env::var(name).ok().or_else(|| match name {
+            "AMQPRS_USERNAME" if !url_set => Some(TEST_USERNAME.to_string()),
+            "AMQPRS_PASSWORD" if !url_set => Some(TEST_PASSWORD.to_string()),
+            _ => None,
+        })
+    })
+    .unwrap_or_else(|err| panic!("invalid connection arguments from environment: {}", err))
+}
+
+/// Returns a name starting with `prefix`, which is unique across processes and runs,
+/// so that tests declaring resources in the same broker don't interfere.
+///
+/// The name is valid for queues and exchanges, if `prefix` is valid.
+pub fn unique_name(prefix: &str) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    format!(
+        "{}.{}.{}.{}",
+        prefix,
+        process::id(),
+        nanos,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// A connection and channel to the broker under test, which records the queues
+/// and exchanges declared by it, and deletes them in [`teardown`].
+///
+/// If a test panics before [`teardown`], the declared resources are left in the broker.
+///
+/// [`teardown`]: struct.TestContext.html#method.teardown
+pub struct TestContext {
+    connection: Connection,
+    channel: Channel,
+    queues: Mutex<Vec<String>>,
+    exchanges: Mutex<Vec<String>>,
+}
+
+impl TestContext {
+    /// Open a connection named `test_name` by [`connection_args`], and a channel on it.
+    ///
+    /// [`connection_args`]: fn.connection_args.html
+    pub async fn new(test_name: &str) -> Result<Self> {
+        Self::open(&connection_args().connection_name(test_name).finish()).await
+    }
+
+    /// Open a connection by `args`, and a channel on it.
+    pub async fn open(args: &OpenConnectionArguments) -> Result<Self> {
+        let connection = Connection::open(args).await?;
+        let channel = connection.open_channel(None).await?;
+        Ok(Self {
+            connection,
+            channel,
+            queues: Mutex::new(Vec::new()),
+            exchanges: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Returns the connection.
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Returns the channel.
+    pub fn channel(&self) -> &Channel {
+        &self.channel
+    }
+
+    /// Declare a queue of a [`unique_name`] with `prefix`, to be deleted in teardown,
+    /// and returns the name.
+    ///
+    /// [`unique_name`]: fn.unique_name.html
+    pub async fn declare_queue(&self, prefix: &str) -> Result<String> {
+        let name = unique_name(prefix);
+        self.channel
+            .queue_declare(QueueDeclareArguments::new(&name))
+            .await?;
+        self.queues.lock().unwrap().push(name.clone());
+        Ok(name)
+    }
+
+    /// Declare an exchange of `exchange_type` and a [`unique_name`] with `prefix`,
+    /// to be deleted in teardown, and returns the name.
+    ///
+    /// [`unique_name`]: fn.unique_name.html
+    pub async fn declare_exchange(&self, prefix: &str, exchange_type: &str) -> Result<String> {
+        let name = unique_name(prefix);
+        self.channel
+            .exchange_declare(ExchangeDeclareArguments::new(&name, exchange_type))
+            .await?;
+        self.exchanges.lock().unwrap().push(name.clone());
+        Ok(name)
+    }
+
+    /// Delete the declared queues and exchanges, then close the channel and connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error, after trying to delete all resources and close.
+    pub async fn teardown(self) -> Result<()> {
+        let mut result = Ok(());
+        let queues = std::mem::take(&mut *self.queues.lock().unwrap());
+        for queue in queues {
+            if let Err(err) = self
+                .channel
+                .queue_delete(QueueDeleteArguments::new(&queue))
+                .await
+            {
+                result = result.and(Err(err));
+            }
+        }
+        let exchanges = std::mem::take(&mut *self.exchanges.lock().unwrap());
+        for exchange in exchanges {
+            if let Err(err) = self
+                .channel
+                .exchange_delete(ExchangeDeleteArguments::new(&exchange))
+                .await
+            {
+                result = result.and(Err(err));
+            }
+        }
+        if let Err(err) = self.channel.close().await {
+            result = result.and(Err(err));
+        }
+        if let Err(err) = self.connection.close().await {
+            result = result.and(Err(err));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{connection_args, unique_name, TestContext};
+    use crate::channel::{QueueBindArguments, QueueDeclareArguments};
+
+    #[test]
+    fn test_unique_name() {
+        let first = unique_name("amqprs.test");
+        let second = unique_name("amqprs.test");
+        assert!(first.starts_with("amqprs.test."));
+        assert_ne!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_test_context() {
+        super::setup_logging();
+
+        let args = connection_args()
+            .connection_name("test_test_context")
+            .finish();
+        let ctx = TestContext::open(&args).await.unwrap();
+        let queue = ctx.declare_queue("amqprs.test_context").await.unwrap();
+        let exchange = ctx
+            .declare_exchange("amqprs.test_context", "direct")
+            .await
+            .unwrap();
+        ctx.channel()
+            .queue_bind(QueueBindArguments::new(&queue, &exchange, "test"))
+            .await
+            .unwrap();
+        assert_eq!("test_test_context", ctx.connection().connection_name());
+
+        let connection = ctx.connection().clone();
+        ctx.teardown().await.unwrap();
+        assert!(!connection.is_open());
+
+        // resources are deleted, so passive declare fails
+        let ctx = TestContext::open(&args).await.unwrap();
+        assert!(ctx
+            .channel()
+            .queue_declare(QueueDeclareArguments::new(&queue).passive(true).finish())
+            .await
+            .is_err());
+        ctx.connection().clone().close().await.unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+//! Adapter of publishing as a [`tower::Service`], if feature "tower" is enabled.
+//!
+//! [`PublishService`] publishes each [`PublishRequest`] on a channel or on a channel
+//! acquired from a [`ChannelPool`], so that publishing composes with tower middlewares,
+//! e.g. rate limiting, retries, timeouts or load shedding.
+//!
+//! # Example
+//! ```rust,no_run
+//! # use amqprs::{channel::{BasicPublishArguments, Channel}, BasicProperties};
+//! use amqprs::tower::{PublishRequest, PublishService};
+//! use futures_util::future::poll_fn;
+//! use tower_service::Service;
+//!
+//! # async fn publish(channel: Channel) {
+//! let mut service = PublishService::new(channel);
+//! let request = PublishRequest::new(
+//!     BasicProperties::default(),
+//!     "hello",
+//!     BasicPublishArguments::new("amq.topic", "greeting"),
+//! );
+//! poll_fn(|cx| service.poll_ready(cx)).await.unwrap();
+//! service.call(request).await.unwrap();
+//! # }
+//! ```
+//!
+//! [`tower::Service`]: https://docs.rs/tower-service/latest/tower_service/trait.Service.html
+//! [`PublishService`]: struct.PublishService.html
+//! [`PublishRequest`]: struct.PublishRequest.html
+//! [`ChannelPool`]: ../pool/struct.ChannelPool.html
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use tower_service::Service;
+
+use super::{
+    channel::{BasicPublishArguments, Channel, Confirmation},
+    error::Error,
+    pool::ChannelPool,
+    Result,
+};
+use crate::frame::BasicProperties;
+
+/// A message to publish by [`PublishService`].
+///
+/// [`PublishService`]: struct.PublishService.html
+#[derive(Debug, Clone)]
+pub struct PublishRequest {
+    /// Properties of the message.
+    pub basic_properties: BasicProperties,
+    /// Content of the message.
+    pub content: Bytes,
+    /// Arguments of `basic_publish`.
+    pub args: BasicPublishArguments,
+}
+
+impl PublishRequest {
+    /// Create a new request.
+    pub fn new(
+        basic_properties: BasicProperties,
+        content: impl Into<Bytes>,
+        args: BasicPublishArguments,
+    ) -> Self {
+        Self {
+            basic_properties,
+            content: content.into(),
+            args,
+        }
+    }
+}
+
+/// Where [`PublishService`] publishes messages.
+#[derive(Clone)]
+enum Target {
+    Channel(Channel),
+    Pool(ChannelPool),
+}
+
+/// [`tower::Service`] which publishes a message for each [`PublishRequest`].
+///
+/// If the channel is in publisher confirm mode, a request completes once server confirms
+/// the message, and fails with [`Error::PublishNacked`] if server nacks it.
+///
+/// It is cheap to clone, clones publish on the same channel or pool.
+///
+/// [`tower::Service`]: https://docs.rs/tower-service/latest/tower_service/trait.Service.html
+/// [`Error::PublishNacked`]: ../error/enum.Error.html#variant.PublishNacked
+/// [`PublishRequest`]: struct.PublishRequest.html
+#[derive(Clone)]
+pub struct PublishService {
+    target: Target,
+}
+
+impl PublishService {
+    /// Create a service which publishes on `channel`.
+    pub fn new(channel: Channel) -> Self {
+        Self {
+            target: Target::Channel(channel),
+        }
+    }
+
+    /// Create a service which publishes on a channel acquired from `pool` for each request.
+    pub fn with_pool(pool: ChannelPool) -> Self {
+        Self {
+            target: Target::Pool(pool),
+        }
+    }
+}
+
+impl Service<PublishRequest> for PublishService {
+    type Response = ();
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+    /// Ready if the channel is open. A pool is always ready, as acquiring a channel
+    /// waits within the call.
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        match &self.target {
+            Target::Channel(channel) if !channel.is_open() => Poll::Ready(Err(
+                Error::ChannelUseError(format!("channel {} is not open", channel)),
+            )),
+            _ => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn call(&mut self, request: PublishRequest) -> Self::Future {
+        let target = self.target.clone();
+        Box::pin(async move {
+            match target {
+                Target::Channel(channel) => publish(&channel, request).await,
+                Target::Pool(pool) => publish(&*pool.acquire().await?, request).await,
+            }
+        })
+    }
+}
+
+/// Publish the message of `request`, and wait for confirm in publisher confirm mode.
+async fn publish(channel: &Channel, request: PublishRequest) -> Result<()> {
+    let PublishRequest {
+        basic_properties,
+        content,
+        args,
+    } = request;
+    if !channel.is_confirm_mode() {
+        return channel.basic_publish(basic_properties, content, args).await;
+    }
+    match channel
+        .basic_publish_confirm(basic_properties, content, args)
+        .await?
+    {
+        Confirmation::Ack => Ok(()),
+        Confirmation::Nack => Err(Error::PublishNacked(format!(
+            "message nacked by server on channel {}",
+            channel
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::poll_fn;
+    use tower_service::Service;
+
+    use super::{PublishRequest, PublishService};
+    use crate::{
+        channel::{BasicGetArguments, BasicPublishArguments, QueueDeclareArguments},
+        connection::{Connection, OpenConnectionArguments},
+        pool::{ChannelPool, ChannelPoolArguments},
+        test_utils::setup_logging,
+        BasicProperties,
+    };
+
+    #[tokio::test]
+    async fn test_publish_service() {
+        setup_logging();
+
+        let args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+            .connection_name("test_publish_service")
+            .finish();
+        let connection = Connection::open(&args).await.unwrap();
+        let channel = connection.open_channel(None).await.unwrap();
+        let (queue_name, ..) = channel
+            .queue_declare(QueueDeclareArguments::default())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let pool = ChannelPool::new(connection.clone(), ChannelPoolArguments::new())
+            .await
+            .unwrap();
+        for mut service in [
+            PublishService::new(channel.clone()),
+            PublishService::with_pool(pool),
+        ] {
+            poll_fn(|cx| service.poll_ready(cx)).await.unwrap();
+            service
+                .call(PublishRequest::new(
+                    BasicProperties::default(),
+                    "tower",
+                    BasicPublishArguments::new("", &queue_name),
+                ))
+                .await
+                .unwrap();
+        }
+
+        for _ in 0..2 {
+            let (_, _, content) = channel
+                .basic_get(BasicGetArguments::new(&queue_name).auto_ack(true).finish())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(b"tower".to_vec(), content);
+        }
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+}
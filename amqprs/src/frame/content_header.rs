@@ -1,10 +1,16 @@
 use std::fmt;
 
-use amqp_serde::types::{FieldTable, LongLongUint, Octect, ShortStr, ShortUint, TimeStamp};
+use crate::{api::error::Error as ApiError, DELIVERY_MODE_PERSISTENT, DELIVERY_MODE_TRANSIENT};
+use amqp_serde::types::{
+    FieldTable, FieldValue, LongLongUint, Octect, ShortStr, ShortUint, TimeStamp,
+};
+use amqp_serde::{from_bytes, to_bytes};
 use serde::{de::Visitor, Deserialize, Serialize};
-use crate::{DELIVERY_MODE_PERSISTENT, DELIVERY_MODE_TRANSIENT};
 
-use super::Frame;
+use super::{Error, Frame};
+
+/// Property flags undefined for class `basic`, including the flag of continued property flags.
+const UNDEFINED_PROPERTY_FLAGS: Octect = 0b0000_0011;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ContentHeader {
@@ -282,10 +288,24 @@ impl BasicProperties {
         self
     }
 
+    /// Clear content encoding, e.g. after the content is decoded.
+    #[cfg(feature = "compression")]
+    pub(crate) fn clear_content_encoding(&mut self) -> &mut Self {
+        self.property_flags[0] &= !(1 << 6);
+        self.content_encoding = None;
+        self
+    }
+
     pub fn headers(&self) -> Option<&FieldTable> {
         self.headers.as_ref()
     }
 
+    /// Returns the value of header `name`, [`None`] if not set.
+    pub fn header(&self, name: &str) -> Option<&FieldValue> {
+        let name: ShortStr = name.try_into().ok()?;
+        self.headers.as_ref()?.get(&name)
+    }
+
     /// Chainable setter of headers.
     ///
     /// # Default: [`None`]
@@ -295,6 +315,17 @@ impl BasicProperties {
         self
     }
 
+    /// Remove header `name`, and clear headers if no header is left.
+    pub(crate) fn remove_header(&mut self, name: &ShortStr) -> Option<FieldValue> {
+        let headers = self.headers.as_mut()?;
+        let value = headers.remove(name);
+        if headers.as_ref().is_empty() {
+            self.property_flags[0] &= !(1 << 5);
+            self.headers = None;
+        }
+        value
+    }
+
     pub fn delivery_mode(&self) -> Option<u8> {
         self.delivery_mode
     }
@@ -361,7 +392,11 @@ impl BasicProperties {
 
     /// Chainable setter of priority.
     ///
-    /// `priority`: message priority, 0 to 9
+    /// `priority`: message priority, 0 to 9.
+    /// Only takes effect on queues declared with [`QueueDeclareArguments::max_priority`],
+    /// where priorities above the maximum are treated as the maximum.
+    ///
+    /// [`QueueDeclareArguments::max_priority`]: ../channel/struct.QueueDeclareArguments.html#method.max_priority
     ///
     /// # Default: [`None`]
     pub fn with_priority(&mut self, priority: u8) -> &mut Self {
@@ -486,6 +521,82 @@ impl BasicProperties {
     pub fn finish(&mut self) -> Self {
         self.clone()
     }
+
+    /// Encode the properties in the format of a content header frame,
+    /// i.e. the property flags followed by the present properties.
+    ///
+    /// It is the inverse of [`decode`], intended for round-trip and fuzz tests.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SerializationError`] if encoding fails.
+    ///
+    /// [`decode`]: struct.BasicProperties.html#method.decode
+    /// [`Error::SerializationError`]: error/enum.Error.html#variant.SerializationError
+    pub fn encode(&self) -> Result<Vec<u8>, ApiError> {
+        to_bytes(self).map_err(|err| ApiError::SerializationError(err.to_string()))
+    }
+
+    /// Decode properties encoded by [`encode`] or received from server.
+    ///
+    /// Property flags undefined for class `basic` are ignored, and the last field wins
+    /// if a field table has duplicate field names, which is how incoming messages are
+    /// decoded by default. Use [`decode_strict`] to reject them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SerializationError`] if `buf` is not valid properties.
+    ///
+    /// [`encode`]: struct.BasicProperties.html#method.encode
+    /// [`decode_strict`]: struct.BasicProperties.html#method.decode_strict
+    /// [`Error::SerializationError`]: error/enum.Error.html#variant.SerializationError
+    pub fn decode(buf: &[u8]) -> Result<Self, ApiError> {
+        from_bytes(buf).map_err(|err| ApiError::SerializationError(err.to_string()))
+    }
+
+    /// Same as [`decode`], but also returns error if any property flag undefined for class
+    /// `basic` is set, or if a field table is malformed, e.g. it has duplicate field names,
+    /// so that no property or header is silently dropped.
+    ///
+    /// Incoming messages are decoded strictly if enabled by
+    /// [`OpenConnectionArguments::strict_decoding`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SerializationError`] if `buf` is not valid properties.
+    ///
+    /// [`decode`]: struct.BasicProperties.html#method.decode
+    /// [`OpenConnectionArguments::strict_decoding`]: connection/struct.OpenConnectionArguments.html#method.strict_decoding
+    /// [`Error::SerializationError`]: error/enum.Error.html#variant.SerializationError
+    pub fn decode_strict(buf: &[u8]) -> Result<Self, ApiError> {
+        let basic_properties = Self::decode(buf)?;
+        basic_properties
+            .check_strict(buf.len())
+            .map_err(|err| ApiError::SerializationError(err.to_string()))?;
+        Ok(basic_properties)
+    }
+
+    /// Check the properties decoded from `size` bytes, see [`decode_strict`].
+    ///
+    /// [`decode_strict`]: struct.BasicProperties.html#method.decode_strict
+    pub(crate) fn check_strict(&self, size: usize) -> Result<(), Error> {
+        if self.property_flags[1] & UNDEFINED_PROPERTY_FLAGS != 0 {
+            return Err(Error::InvalidProperties(format!(
+                "undefined property flags {:02X?}",
+                self.property_flags
+            )));
+        }
+        // a field table keeps its size on wire, so dropped fields make the re-encoded
+        // properties shorter than the received ones
+        let encoded_size = to_bytes(self)?.len();
+        if encoded_size != size {
+            return Err(Error::InvalidProperties(format!(
+                "malformed field table, {} bytes received but {} bytes decoded",
+                size, encoded_size
+            )));
+        }
+        Ok(())
+    }
 }
 
 impl<'de> Deserialize<'de> for BasicProperties {
@@ -676,7 +787,7 @@ impl fmt::Display for BasicProperties {
 
 #[cfg(test)]
 mod tests {
-    use amqp_serde::types::FieldTable;
+    use amqp_serde::types::{FieldTable, FieldValue};
 
     use crate::{BasicProperties, DELIVERY_MODE_TRANSIENT};
 
@@ -725,4 +836,64 @@ mod tests {
         props.with_timestamp(1674404425);
         assert_eq!([0xC8, 0xC8], props.property_flags);
     }
+
+    #[test]
+    fn test_basic_properties_round_trip() {
+        let mut headers = FieldTable::new();
+        headers.insert("x-trace".try_into().unwrap(), "abc".into());
+        let props = BasicProperties::default()
+            .with_content_type("application/json")
+            .with_headers(headers)
+            .with_delivery_mode(DELIVERY_MODE_TRANSIENT)
+            .with_priority(3)
+            .with_timestamp(1674404425)
+            .with_cluster_id("my_cluster")
+            .finish();
+
+        let encoded = props.encode().unwrap();
+        let decoded = BasicProperties::decode_strict(&encoded).unwrap();
+        assert_eq!(props.property_flags, decoded.property_flags);
+        assert_eq!("application/json", decoded.content_type().unwrap());
+        assert_eq!(Some(3), decoded.priority());
+        assert_eq!(Some(1674404425), decoded.timestamp());
+        assert_eq!("my_cluster", decoded.cluster_id().unwrap());
+        assert_eq!(&FieldValue::from("abc"), decoded.header("x-trace").unwrap());
+        assert_eq!(encoded, decoded.encode().unwrap());
+
+        assert!(BasicProperties::decode(&encoded[..encoded.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_basic_properties_remove_header() {
+        let mut headers = FieldTable::new();
+        headers.insert("x-a".try_into().unwrap(), "1".into());
+        headers.insert("x-b".try_into().unwrap(), "2".into());
+        let mut props = BasicProperties::default().with_headers(headers).finish();
+
+        assert_eq!(
+            Some(FieldValue::from("1")),
+            props.remove_header(&"x-a".try_into().unwrap())
+        );
+        assert!(props.header("x-b").is_some());
+        assert_eq!([0x20, 0x00], props.property_flags);
+
+        // headers are cleared once the last one is removed
+        props.remove_header(&"x-b".try_into().unwrap());
+        assert!(props.headers().is_none());
+        assert_eq!([0x00, 0x00], props.property_flags);
+    }
+
+    #[test]
+    fn test_basic_properties_decode_strict() {
+        // undefined property flag is ignored, unless decoded strictly
+        let raw = [0x00, 0x01];
+        assert!(BasicProperties::decode(&raw).is_ok());
+        assert!(BasicProperties::decode_strict(&raw).is_err());
+
+        // headers with duplicate field names, `a = true` and `a = false`
+        let raw = [0x20, 0x00, 0, 0, 0, 8, 1, b'a', b't', 1, 1, b'a', b't', 0];
+        let props = BasicProperties::decode(&raw).unwrap();
+        assert_eq!(1, props.headers().unwrap().len());
+        assert!(BasicProperties::decode_strict(&raw).is_err());
+    }
 }
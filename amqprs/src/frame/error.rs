@@ -1,9 +1,12 @@
 use std::fmt;
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     Corrupted,
     SerdeError(String),
+    /// Content header properties rejected by strict decoding.
+    InvalidProperties(String),
 }
 
 impl From<amqp_serde::Error> for Error {
@@ -17,6 +20,7 @@ impl fmt::Display for Error {
         match self {
             Error::Corrupted => f.write_str("corrupted frame"),
             Error::SerdeError(msg) => write!(f, "serde error: {}", msg),
+            Error::InvalidProperties(msg) => write!(f, "invalid properties: {}", msg),
         }
     }
 }
@@ -1,4 +1,4 @@
-/// Deprecated: see [RabbitMQ Spec Differences](https://www.rabbitmq.com/spec-differences.html)
+//! Deprecated: see [RabbitMQ Spec Differences](https://www.rabbitmq.com/spec-differences.html)
 
 #[allow(dead_code)]
 pub struct Request; // Deprecated
@@ -176,6 +176,9 @@ impl Publish {
             self.bits &= !bit_flag::publish::MANDATORY;
         }
     }
+    pub fn mandatory(&self) -> bool {
+        self.bits & bit_flag::publish::MANDATORY != 0
+    }
     pub fn set_immediate(&mut self, value: bool) {
         if value {
             self.bits |= bit_flag::publish::IMMEDIATE;
@@ -191,7 +194,7 @@ impl Publish {
 ///
 /// [`publish_return`]: callbacks/trait.ChannelCallback.html#tymethod.publish_return
 // RX
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Return {
     reply_code: ShortUint,
     reply_text: ShortStr,
@@ -232,7 +235,7 @@ impl Return {
 ///
 /// [`consume`]: consumer/trait.AsyncConsumer.html#tymethod.consume
 // RX
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Deliver {
     consumer_tag: ShortStr,
     delivery_tag: LongLongUint,
@@ -37,6 +37,15 @@ pub struct CloseChannel {
 }
 
 impl CloseChannel {
+    pub fn new(reply_code: ShortUint, reply_text: ShortStr) -> Self {
+        Self {
+            reply_code,
+            reply_text,
+            class_id: 0,
+            method_id: 0,
+        }
+    }
+
     pub fn reply_code(&self) -> u16 {
         self.reply_code
     }
@@ -147,6 +147,15 @@ impl fmt::Display for Close {
     }
 }
 impl Close {
+    pub fn new(reply_code: ShortUint, reply_text: ShortStr) -> Self {
+        Self {
+            reply_code,
+            reply_text,
+            class_id: 0,
+            method_id: 0,
+        }
+    }
+
     pub fn reply_code(&self) -> u16 {
         self.reply_code
     }
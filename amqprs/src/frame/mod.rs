@@ -3,6 +3,7 @@ use amqp_serde::{
     types::{AmqpChannelId, LongUint, Octect, ShortUint},
 };
 
+use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -58,7 +59,9 @@ mod helpers {
                 ContentHeader(Box<ContentHeader>),
                 ContentBody(ContentBody),
                 // speical frame combination for publish
-                PublishCombo(Publish, Box<ContentHeader>, ContentBody),
+                PublishCombo(Publish, Box<ContentHeader>, Bytes),
+                // pre-encoded publish method frame and content header frame, and content body
+                PreparedPublishCombo(Bytes, Bytes),
             }
         };
     }
@@ -187,7 +190,14 @@ impl Frame {
     /// to support update of read buffer cursor, and the number of bytes are read
     /// Returns:
     ///     (num of bytes read, channel id, decoded frame)
-    pub fn decode(buf: &[u8]) -> Result<Option<(usize, AmqpChannelId, Frame)>, Error> {
+    ///
+    /// If `strict`, content header properties are validated, see [`BasicProperties::decode_strict`].
+    ///
+    /// [`BasicProperties::decode_strict`]: struct.BasicProperties.html#method.decode_strict
+    pub fn decode(
+        buf: &[u8],
+        strict: bool,
+    ) -> Result<Option<(usize, AmqpChannelId, Frame)>, Error> {
         // check frame header, 7 octects
         if buf.len() < FRAME_HEADER_SIZE {
             return Ok(None);
@@ -246,10 +256,14 @@ impl Frame {
 
                 start = end;
                 end = total_size - 1;
-                let basic_properties: BasicProperties = from_bytes(match buf.get(start..end) {
+                let properties_raw = match buf.get(start..end) {
                     Some(s) => s,
                     None => unreachable!("out of bound"),
-                })?;
+                };
+                let basic_properties: BasicProperties = from_bytes(properties_raw)?;
+                if strict {
+                    basic_properties.check_strict(properties_raw.len())?;
+                }
 
                 Ok(Some((
                     total_size,
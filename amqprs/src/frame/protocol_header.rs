@@ -18,6 +18,27 @@ pub struct ProtocolHeader {
     version: ProtocolVersion,
 }
 
+/// Size in bytes of a serialized protocol header.
+pub const PROTOCOL_HEADER_SIZE: usize = 8;
+
+impl ProtocolHeader {
+    /// Returns `(major, minor, revision)` of the protocol version.
+    pub fn version(&self) -> (Octect, Octect, Octect) {
+        (
+            self.version.major,
+            self.version.minor,
+            self.version.revision,
+        )
+    }
+
+    /// Returns `true` if `data` starts with the protocol name "AMQP".
+    ///
+    /// Server sends its own protocol header instead of a frame if it rejects client's version.
+    pub fn is_protocol_header(data: &[u8]) -> bool {
+        data.starts_with(b"AMQP")
+    }
+}
+
 impl Default for ProtocolHeader {
     fn default() -> Self {
         Self {
@@ -63,4 +84,15 @@ mod tests {
             version
         );
     }
+
+    #[test]
+    fn test_version_rejection() {
+        // server supports AMQP 1.0 only
+        let data = [65, 77, 81, 80, 0, 1, 0, 0];
+        assert!(ProtocolHeader::is_protocol_header(&data));
+        let header: ProtocolHeader = from_bytes(&data).unwrap();
+        assert_eq!((1, 0, 0), header.version());
+        // a method frame
+        assert!(!ProtocolHeader::is_protocol_header(&[1, 0, 0, 0, 0, 0, 4]));
+    }
 }
@@ -1,6 +1,6 @@
 //! AMQP 0-9-1 client implementation compatible with RabbitMQ.
 //!
-//! It is currently based on async tokio runtime, and cannot be configured to use another runtime by user.
+//! It is based on async tokio runtime by default, and can be configured to use async-std runtime by feature flags.
 //!
 //! User usually starts by openning an AMQP [`Connection`] and register the connection [`callbacks`],
 //! then open an AMQP [`Channel`] on the connection and register the channel [`callbacks`].
@@ -46,17 +46,33 @@
 //!
 //! # Optional Features
 //!
-//! - "traces": enable `tracing` in the library.
+//! - "traces" (default): enable `tracing` in the library, and wire-level frame dump by `OpenConnectionArguments::frame_dump`.
+//!   If disabled, e.g. for minimal builds, the library does not depend on `tracing` and logs nothing.
 //! - "compliance_assert": enable compliance assertion according to AMQP spec.
-//!     If enabled, library always check user inputs and `panic` if any non-compliance.
-//!     If disabled, then it relies on server to reject.
+//!   If enabled, library always check user inputs and `panic` if any non-compliance.
+//!   If disabled, then it relies on server to reject.
 //! - "tls": enable SSL/TLS.
 //! - "urispec": enable support of [RabbitMQ URI Specification](https://www.rabbitmq.com/uri-spec.html)
+//! - "rt-tokio" (default): use tokio runtime.
+//! - "rt-async-std": use async-std runtime, only takes effect if "rt-tokio" is disabled.
+//! - "blocking": enable the [`blocking`](blocking/index.html) API for non-async applications.
+//! - "cancellation": accept `tokio_util::sync::CancellationToken` to shut down connections and consumers.
+//! - "compression": enable transparent [`compression`](compression/index.html) of message content.
+//! - "json": enable JSON format of typed messages, see [`serialization`](serialization/index.html).
+//! - "msgpack": enable MessagePack format of typed messages, see [`serialization`](serialization/index.html).
+//! - "stream": implement `futures_core::Stream` for [`ConsumerStream`](channel/struct.ConsumerStream.html).
+//! - "tower": publish by a `tower::Service`, see [`tower`](tower/index.html).
+//! - "test-util": inject faults into the transport of a connection for resilience testing, see [`fault`](fault/index.html).
+//! - "test-support": scaffolding for integration tests against a real broker, see [`test_support`](test_support/index.html).
 //!
 //! [`Connection`]: connection/struct.Connection.html
 //! [`Channel`]: channel/struct.Channel.html
 //! [`callbacks`]: callbacks/index.html
 //!
+
+// `tokio::select!` expands to std items newer than `rust-version`, tokio requires them anyway
+#![allow(clippy::incompatible_msrv)]
+
 /////////////////////////////////////////////////////////////////////////////
 #[cfg(test)]
 mod test_utils;
@@ -65,6 +81,7 @@ mod test_utils;
 mod api;
 mod frame;
 mod net;
+mod rt;
 
 /// public API and types
 pub use api::*;
@@ -89,6 +89,21 @@ impl ChannelManager {
         self.resource.remove(channel_id)
     }
 
+    /// Remove dispatcher of the channel, which exits once its rx half is closed,
+    /// but keep the channel id reserved until it is removed by [`remove_resource`].
+    ///
+    /// [`remove_resource`]: #method.remove_resource
+    pub fn detach_dispatcher(&mut self, channel_id: &AmqpChannelId) {
+        if let Some(resource) = self.resource.get_mut(channel_id) {
+            resource.dispatcher = None;
+        }
+    }
+
+    /// Returns `true` if the channel id is in use, even if its dispatcher is detached.
+    pub fn contains_resource(&self, channel_id: &AmqpChannelId) -> bool {
+        self.resource.contains_key(channel_id)
+    }
+
     pub fn get_dispatcher(
         &self,
         channel_id: &AmqpChannelId,
@@ -119,3 +134,36 @@ impl ChannelManager {
             .remove(method_header)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::{self, error::TryRecvError};
+
+    use super::{ChannelManager, ChannelResource};
+
+    #[test]
+    fn test_detach_dispatcher_keeps_id_reserved() {
+        let mut manager = ChannelManager::new(2047);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let id = manager
+            .insert_resource(Some(1), ChannelResource::new(Some(tx)))
+            .unwrap();
+
+        manager.detach_dispatcher(&id);
+        // dispatcher exits once its rx half is closed
+        assert!(matches!(rx.try_recv(), Err(TryRecvError::Disconnected)));
+        assert!(manager.get_dispatcher(&id).is_none());
+        assert!(manager.contains_resource(&id));
+        // id is not reused until the resource is removed
+        assert!(manager
+            .insert_resource(Some(id), ChannelResource::new(None))
+            .is_none());
+
+        assert!(manager.remove_resource(&id).is_some());
+        assert!(!manager.contains_resource(&id));
+        assert_eq!(
+            Some(id),
+            manager.insert_resource(Some(id), ChannelResource::new(None))
+        );
+    }
+}
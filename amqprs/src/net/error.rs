@@ -1,22 +1,29 @@
 use std::{fmt, io};
 
-use crate::frame;
+use crate::{connection::ShutdownCause, frame};
 use tokio::sync::mpsc::error::SendError;
 
 #[derive(Debug)]
 pub(crate) enum Error {
-    NetworkIo(String),
+    NetworkIo(io::ErrorKind, String),
     SyncChannel(String),
     Serde(String),
     Framing(String),
     Callback,
     PeerShutdown,
     Interrupted,
+    /// server rejected the protocol version, and replied its own `(major, minor, revision)`
+    ProtocolVersionMismatch(u8, u8, u8),
+    /// server sent a frame of `size` bytes larger than `frame_max`
+    FrameTooLarge {
+        size: usize,
+        frame_max: usize,
+    },
 }
 
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Self {
-        Error::NetworkIo(err.to_string())
+        Error::NetworkIo(err.kind(), err.to_string())
     }
 }
 impl From<amqp_serde::Error> for Error {
@@ -38,15 +45,39 @@ impl<T> From<SendError<T>> for Error {
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Error::NetworkIo(msg) => write!(f, "network io error: {}", msg),
+            Error::NetworkIo(_, msg) => write!(f, "network io error: {}", msg),
             Error::SyncChannel(msg) => write!(f, "internal communication error: {}", msg),
             Error::Serde(msg) => write!(f, "serde error: {}", msg),
             Error::Framing(msg) => write!(f, "framing error: {}", msg),
             Error::Callback => write!(f, "callback error"),
             Error::PeerShutdown => f.write_str("peer shutdown"),
             Error::Interrupted => f.write_str("connection interrupted"),
+            Error::ProtocolVersionMismatch(major, minor, revision) => write!(
+                f,
+                "protocol version mismatch, server supports {}-{}-{}",
+                major, minor, revision
+            ),
+            Error::FrameTooLarge { size, frame_max } => {
+                write!(f, "frame of {} bytes exceeds frame_max {}", size, frame_max)
+            }
         }
     }
 }
 
 impl std::error::Error for Error {}
+
+impl Error {
+    /// Cause of shutting down the connection due to the error.
+    pub(crate) fn shutdown_cause(&self) -> ShutdownCause {
+        match self {
+            Error::NetworkIo(kind, _) => ShutdownCause::IoError(*kind),
+            Error::PeerShutdown => ShutdownCause::IoError(io::ErrorKind::UnexpectedEof),
+            Error::Interrupted => ShutdownCause::IoError(io::ErrorKind::ConnectionAborted),
+            Error::SyncChannel(_) | Error::Callback => ShutdownCause::IoError(io::ErrorKind::Other),
+            Error::Serde(_)
+            | Error::Framing(_)
+            | Error::ProtocolVersionMismatch(..)
+            | Error::FrameTooLarge { .. } => ShutdownCause::ProtocolError,
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Wire-level frame dump for debugging, see [`OpenConnectionArguments::frame_dump`].
+//!
+//! [`OpenConnectionArguments::frame_dump`]: ../../api/connection/struct.OpenConnectionArguments.html#method.frame_dump
+use std::fmt::Write;
+
+use amqp_serde::types::AmqpChannelId;
+use tracing::trace;
+
+use crate::{
+    api::connection::FrameDump,
+    frame::{Frame, FRAME_HEADER_SIZE},
+};
+
+/// Target of frame dump events, which can be used to filter them.
+const FRAME_DUMP_TARGET: &str = "amqprs::frame_dump";
+
+/// Log a frame in decoded form, and optionally its raw bytes in hex.
+///
+/// `raw` is the whole frame on wire, including frame header and frame end.
+pub(crate) fn dump_frame(
+    dump: &FrameDump,
+    direction: &str,
+    channel: AmqpChannelId,
+    frame: &Frame,
+    raw: &[u8],
+) {
+    let (decoded, secret) = match frame {
+        // authentication response and secrets
+        Frame::StartOk(..) | Frame::SecureOk(..) | Frame::UpdateSecret(..)
+            if dump.redact_secrets =>
+        {
+            (format!("{}(<redacted>)", frame_name(frame)), true)
+        }
+        Frame::ContentBody(body) => (format_body(dump, &body.inner), false),
+        _ => (frame.to_string(), false),
+    };
+    if dump.raw_hex {
+        // frame header only for redacted frame
+        let len = if secret {
+            FRAME_HEADER_SIZE
+        } else {
+            FRAME_HEADER_SIZE + dump.max_payload_bytes
+        };
+        trace!(
+            target: FRAME_DUMP_TARGET,
+            "{} on channel {}: {} [{} bytes: {}]",
+            direction,
+            channel,
+            decoded,
+            raw.len(),
+            format_hex(raw, len)
+        );
+    } else {
+        trace!(
+            target: FRAME_DUMP_TARGET,
+            "{} on channel {}: {}",
+            direction,
+            channel,
+            decoded
+        );
+    }
+}
+
+/// Log a content body frame, which is serialized without a [`Frame`] value.
+///
+/// `raw` is the whole frame on wire, including frame header and frame end.
+pub(crate) fn dump_content_body(
+    dump: &FrameDump,
+    direction: &str,
+    channel: AmqpChannelId,
+    raw: &[u8],
+) {
+    let body = &raw[FRAME_HEADER_SIZE..raw.len() - 1];
+    let decoded = format_body(dump, body);
+    if dump.raw_hex {
+        trace!(
+            target: FRAME_DUMP_TARGET,
+            "{} on channel {}: {} [{} bytes: {}]",
+            direction,
+            channel,
+            decoded,
+            raw.len(),
+            format_hex(raw, FRAME_HEADER_SIZE + dump.max_payload_bytes)
+        );
+    } else {
+        trace!(
+            target: FRAME_DUMP_TARGET,
+            "{} on channel {}: {}",
+            direction,
+            channel,
+            decoded
+        );
+    }
+}
+
+/// Returns name of the frame's variant, e.g. "StartOk".
+fn frame_name(frame: &Frame) -> String {
+    let decoded = format!("{:?}", frame);
+    match decoded.find('(') {
+        Some(end) => decoded[..end].to_owned(),
+        None => decoded,
+    }
+}
+
+/// Format content body as text, truncated to `max_payload_bytes`.
+fn format_body(dump: &FrameDump, body: &[u8]) -> String {
+    let shown = body.len().min(dump.max_payload_bytes);
+    let mut decoded = format!("ContentBody({:?}", String::from_utf8_lossy(&body[..shown]));
+    if shown < body.len() {
+        write!(decoded, "...").unwrap();
+    }
+    write!(decoded, ", {} bytes)", body.len()).unwrap();
+    decoded
+}
+
+/// Format bytes as hex, truncated to `max_len` bytes.
+fn format_hex(data: &[u8], max_len: usize) -> String {
+    let shown = data.len().min(max_len);
+    let mut hex = String::with_capacity(shown * 3 + 3);
+    for (i, byte) in data[..shown].iter().enumerate() {
+        if i > 0 {
+            hex.push(' ');
+        }
+        write!(hex, "{:02x}", byte).unwrap();
+    }
+    if shown < data.len() {
+        hex.push_str(" ...");
+    }
+    hex
+}
+
+/////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod tests {
+    use super::{format_body, format_hex, frame_name};
+    use crate::{
+        api::connection::FrameDump,
+        frame::{ContentBody, Frame, HeartBeat},
+    };
+
+    #[test]
+    fn test_format_hex() {
+        assert_eq!("", format_hex(&[], 4));
+        assert_eq!("01 0a ff", format_hex(&[1, 10, 255], 4));
+        assert_eq!("01 0a ...", format_hex(&[1, 10, 255], 2));
+    }
+
+    #[test]
+    fn test_format_body() {
+        let dump = FrameDump {
+            max_payload_bytes: 5,
+            ..FrameDump::default()
+        };
+        assert_eq!(
+            "ContentBody(\"hello\", 5 bytes)",
+            format_body(&dump, b"hello")
+        );
+        assert_eq!(
+            "ContentBody(\"hello\"..., 11 bytes)",
+            format_body(&dump, b"hello world")
+        );
+    }
+
+    #[test]
+    fn test_frame_name() {
+        assert_eq!("HeartBeat", frame_name(&Frame::HeartBeat(HeartBeat)));
+        assert_eq!(
+            "ContentBody",
+            frame_name(&ContentBody::new(b"secret".to_vec()).into_frame())
+        );
+    }
+}
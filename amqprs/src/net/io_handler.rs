@@ -0,0 +1,119 @@
+use std::{ops::ControlFlow, time};
+
+use amqp_serde::types::ShortUint;
+#[cfg(feature = "traces")]
+use tracing::{debug, error, trace};
+
+use crate::{
+    frame::{Frame, HeartBeat, DEFAULT_CONN_CHANNEL},
+    rt,
+};
+
+use super::{ReaderHandler, WriterHandler};
+
+/////////////////////////////////////////////////////////////////////////////
+
+/// Drive both reader and writer of a connection in a single task,
+/// see [`IoMode::SingleTask`].
+///
+/// [`IoMode::SingleTask`]: ../api/connection/enum.IoMode.html#variant.SingleTask
+pub(crate) struct IoHandler {
+    reader: ReaderHandler,
+    writer: WriterHandler,
+}
+
+impl IoHandler {
+    pub fn new(mut reader: ReaderHandler, writer: WriterHandler) -> Self {
+        // the writer's queue is drained by this task,
+        // so replies of reader are written directly
+        reader.buffer_replies();
+        Self { reader, writer }
+    }
+
+    pub async fn run_until_shutdown(mut self, heartbeat: ShortUint) {
+        // max interval to consider server's heartbeat is timeout
+        let read_interval = time::Duration::from_secs(heartbeat.into());
+        // heartbeat should be sent at a interval of timeout / 2
+        let write_interval = time::Duration::from_secs((heartbeat / 2).into());
+        let mut read_expiration = time::Instant::now() + read_interval;
+        let mut write_expiration = time::Instant::now() + write_interval;
+        // deadline to flush coalesced frames, `None` if no frame is buffered
+        let mut flush_deadline: Option<time::Instant> = None;
+        // cause of network failure
+        let mut failure = None;
+
+        loop {
+            tokio::select! {
+                biased;
+
+                _ = rt::sleep_until(flush_deadline.unwrap_or(write_expiration)), if flush_deadline.is_some() => {
+                    flush_deadline = None;
+                    if let Err(err) = self.writer.flush().await {
+                        #[cfg(feature="traces")]
+                        error!("failed to flush frames, cause: {}", err);
+                        failure = Some(err.shutdown_cause());
+                        break;
+                    }
+                }
+                command = self.reader.conn_mgmt_rx.recv() => {
+                    self.reader.handle_command(command);
+                }
+                channel_frame = self.writer.outgoing_rx.recv() => {
+                    let (channel_id, frame) = match channel_frame {
+                        None => break,
+                        Some(v) => v,
+                    };
+                    if let Err(err) = self.writer.write(channel_id, frame, false, &mut flush_deadline).await {
+                        #[cfg(feature="traces")]
+                        error!("failed to send frame, cause: {}", err);
+                        failure = Some(err.shutdown_cause());
+                        break;
+                    }
+                    write_expiration = time::Instant::now() + write_interval;
+                }
+                res = self.reader.stream.read_frame() => {
+                    // any frame can be considered as heartbeat
+                    read_expiration = time::Instant::now() + read_interval;
+                    #[cfg(feature="traces")]
+                    trace!("server heartbeat deadline is updated to {:?}", read_expiration);
+
+                    let flow = self.reader.handle_read(res).await;
+                    // write replies before closing, e.g. `CloseOk` to server's `Close`
+                    for (channel_id, frame) in self.reader.take_replies() {
+                        if let Err(err) = self.writer.write(channel_id, frame, false, &mut flush_deadline).await {
+                            #[cfg(feature="traces")]
+                            error!("failed to send reply, cause: {}", err);
+                            failure = Some(err.shutdown_cause());
+                            break;
+                        }
+                    }
+                    if let ControlFlow::Break(cause) = flow {
+                        // failure of the read takes precedence over failure of the replies
+                        failure = cause.or(failure);
+                        break;
+                    }
+                    if failure.is_some() {
+                        break;
+                    }
+                }
+                _ = rt::sleep_until(write_expiration) => {
+                    write_expiration = time::Instant::now() + write_interval;
+                    if let Err(err) = self.writer.write(DEFAULT_CONN_CHANNEL, Frame::HeartBeat(HeartBeat), true, &mut flush_deadline).await {
+                        #[cfg(feature="traces")]
+                        error!("failed to send heartbeat, cause: {}", err);
+                        failure = Some(err.shutdown_cause());
+                        break;
+                    }
+                    #[cfg(feature="traces")]
+                    debug!("sent heartbeat");
+                }
+                _ = rt::sleep_until(read_expiration) => {
+                    read_expiration = time::Instant::now() + read_interval;
+                    self.reader.handle_heartbeat_missed();
+                }
+            }
+        }
+        self.reader.shutdown(failure).await;
+        self.writer.shutdown(flush_deadline.is_some(), None).await;
+    }
+}
@@ -1,6 +1,9 @@
 mod channel_id_repo;
 mod channel_manager;
 mod error;
+#[cfg(feature = "traces")]
+mod frame_dump;
+mod io_handler;
 mod reader_handler;
 mod split_connection;
 mod writer_handler;
@@ -9,6 +12,9 @@ mod writer_handler;
 
 pub(crate) use channel_manager::*;
 pub(crate) use error::*;
+#[cfg(feature = "traces")]
+pub(crate) use frame_dump::*;
+pub(crate) use io_handler::*;
 pub(crate) use reader_handler::*;
 pub(crate) use split_connection::*;
 pub(crate) use writer_handler::*;
@@ -46,6 +52,7 @@ pub(crate) struct RegisterConnectionCallback {
 pub(crate) enum ConnManagementCommand {
     RegisterChannelResource(RegisterChannelResource),
     DeregisterChannelResource(AmqpChannelId),
+    DetachChannelDispatcher(AmqpChannelId),
 
     RegisterResponder(RegisterResponder),
     RegisterConnectionCallback(RegisterConnectionCallback),
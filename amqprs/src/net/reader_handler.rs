@@ -1,18 +1,21 @@
+use std::{ops::ControlFlow, time};
+
 use amqp_serde::types::{AmqpChannelId, ShortUint};
-use tokio::{
-    sync::{
-        broadcast,
-        mpsc::{Receiver, Sender},
-    },
-    task::yield_now,
-    time,
+use tokio::sync::{
+    broadcast,
+    mpsc::{Receiver, Sender},
 };
 #[cfg(feature = "traces")]
 use tracing::{debug, error, info, trace, warn};
 
 use crate::{
-    api::{callbacks::ConnectionCallback, connection::Connection},
-    frame::{CloseOk, Frame, DEFAULT_CONN_CHANNEL},
+    api::{
+        callbacks::ConnectionCallback,
+        connection::{Connection, ConnectionEvent, ShutdownCause},
+        state::{CloseInitiator, State},
+    },
+    frame::{Close, CloseOk, Frame, DEFAULT_CONN_CHANNEL, FRAME_ERROR},
+    rt,
 };
 
 use super::{
@@ -22,7 +25,7 @@ use super::{
 /////////////////////////////////////////////////////////////////////////////
 
 pub(crate) struct ReaderHandler {
-    stream: BufIoReader,
+    pub(super) stream: BufIoReader,
 
     /// AMQ connection
     amqp_connection: Connection,
@@ -31,7 +34,7 @@ pub(crate) struct ReaderHandler {
     outgoing_tx: Sender<OutgoingMessage>,
 
     /// receiver half to receive management command from AMQ Connection/Channel
-    conn_mgmt_rx: Receiver<ConnManagementCommand>,
+    pub(super) conn_mgmt_rx: Receiver<ConnManagementCommand>,
 
     /// AMQP protocol layer callbacks
     callback: Option<Box<dyn ConnectionCallback + Send + 'static>>,
@@ -46,6 +49,13 @@ pub(crate) struct ReaderHandler {
     /// send `true` if due to network I/O failure
     /// send `false` if other reasons
     shutdown_notifier: broadcast::Sender<bool>,
+
+    /// Replies to server buffered for the caller to write, instead of forwarding
+    /// them to `WriterHandler`, see [`buffer_replies`](#method.buffer_replies).
+    replies: Option<Vec<OutgoingMessage>>,
+
+    /// `true` if no frame is received since heartbeat timeout.
+    heartbeat_missed: bool,
 }
 
 impl ReaderHandler {
@@ -65,7 +75,34 @@ impl ReaderHandler {
             callback: None,
             channel_manager: ChannelManager::new(channel_max),
             shutdown_notifier,
+            replies: None,
+            heartbeat_missed: false,
+        }
+    }
+
+    /// Buffer replies to server, which are taken by [`take_replies`](#method.take_replies).
+    ///
+    /// Used when reader and writer run in the same task, where forwarding the replies
+    /// to the writer's queue may never complete if the queue is full.
+    pub fn buffer_replies(&mut self) {
+        self.replies = Some(vec![]);
+    }
+
+    /// Take buffered replies to server.
+    pub fn take_replies(&mut self) -> Vec<OutgoingMessage> {
+        self.replies
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Send a reply to server, either buffered or forwarded to `WriterHandler`.
+    async fn reply(&mut self, channel_id: AmqpChannelId, frame: Frame) -> Result<(), Error> {
+        match self.replies {
+            Some(ref mut replies) => replies.push((channel_id, frame)),
+            None => self.outgoing_tx.send((channel_id, frame)).await?,
         }
+        Ok(())
     }
 
     /// If OK, user can continue to handle frame
@@ -100,7 +137,10 @@ impl ReaderHandler {
                     })
             }
             Frame::CloseOk(method_header, close_ok) => {
-                self.amqp_connection.set_is_open(false);
+                self.amqp_connection.set_closed(
+                    State::closed_by_client(),
+                    ShutdownCause::ApplicationInitiated,
+                );
 
                 match self
                     .channel_manager
@@ -122,13 +162,22 @@ impl ReaderHandler {
                 info!("close connection {} OK", self.amqp_connection);
 
                 // Try to yield for last sent message to be scheduled.
-                yield_now().await;
+                rt::yield_now().await;
                 Ok(())
             }
 
             // Method frames of asynchronous request
             // Server request to close connection
             Frame::Close(_, close) => {
+                let closed = State::Closed {
+                    initiator: CloseInitiator::Server,
+                    code: close.reply_code(),
+                    text: close.reply_text().to_string(),
+                };
+                let cause = ShutdownCause::ServerInitiated {
+                    code: close.reply_code(),
+                    text: close.reply_text().to_string(),
+                };
                 if let Some(ref mut callback) = self.callback {
                     if let Err(err) = callback.close(&self.amqp_connection, close).await {
                         #[cfg(feature = "traces")]
@@ -146,9 +195,8 @@ impl ReaderHandler {
                     );
                 }
                 // respond to server if no callback registered or callback succeed
-                self.amqp_connection.set_is_open(false);
-                self.outgoing_tx
-                    .send((DEFAULT_CONN_CHANNEL, CloseOk::default().into_frame()))
+                self.amqp_connection.set_closed(closed, cause);
+                self.reply(DEFAULT_CONN_CHANNEL, CloseOk.into_frame())
                     .await?;
                 #[cfg(feature = "traces")]
                 info!(
@@ -157,15 +205,17 @@ impl ReaderHandler {
                 );
 
                 // Try to yield for last sent message to be scheduled.
-                yield_now().await;
+                rt::yield_now().await;
                 Ok(())
             }
 
             Frame::Blocked(_, blocked) => {
+                let reason: String = blocked.reason.into();
+                self.amqp_connection.set_blocked(true);
+                self.amqp_connection
+                    .notify_event(ConnectionEvent::Blocked(reason.clone()));
                 if let Some(ref mut callback) = self.callback {
-                    callback
-                        .blocked(&self.amqp_connection, blocked.reason.into())
-                        .await;
+                    callback.blocked(&self.amqp_connection, reason).await;
                 } else {
                     #[cfg(feature = "traces")]
                     error!(
@@ -176,6 +226,9 @@ impl ReaderHandler {
                 Ok(())
             }
             Frame::Unblocked(_, _unblocked) => {
+                self.amqp_connection.set_blocked(false);
+                self.amqp_connection
+                    .notify_event(ConnectionEvent::Unblocked);
                 if let Some(ref mut callback) = self.callback {
                     callback.unblocked(&self.amqp_connection).await;
                 } else {
@@ -195,6 +248,25 @@ impl ReaderHandler {
                         dispatcher.send(frame)?;
                         Ok(())
                     }
+                    // dispatcher is detached after the channel failed to close in time at drop,
+                    // its channel id is released once server responds `CloseOk`
+                    None if self.channel_manager.contains_resource(&channel_id) => {
+                        if let Frame::CloseChannelOk(..) = frame {
+                            self.channel_manager.remove_resource(&channel_id);
+                            #[cfg(feature = "traces")]
+                            debug!(
+                                "release channel {} of connection {} after late CloseOk",
+                                channel_id, self.amqp_connection
+                            );
+                        } else {
+                            #[cfg(feature = "traces")]
+                            warn!(
+                                "discard {} on detached channel {} of connection {}",
+                                frame, channel_id, self.amqp_connection
+                            );
+                        }
+                        Ok(())
+                    }
                     None => {
                         unreachable!(
                             "dispatcher must be registered for channel {} of {}",
@@ -206,47 +278,160 @@ impl ReaderHandler {
         }
     }
 
+    /// Handle a management command from AMQ Connection/Channel.
+    pub fn handle_command(&mut self, command: Option<ConnManagementCommand>) {
+        let command = match command {
+            None => {
+                // should never happen because `ReadHandler` holds
+                // a `Connection` itself
+                unreachable!(
+                    "connection command channel is closed, {}",
+                    self.amqp_connection
+                )
+            }
+            Some(v) => v,
+        };
+        match command {
+            ConnManagementCommand::RegisterChannelResource(cmd) => {
+                let id = self
+                    .channel_manager
+                    .insert_resource(cmd.channel_id, cmd.resource);
+                cmd.acker
+                    .send(id)
+                    .expect("ack to command RegisterChannelResource must succeed");
+                #[cfg(feature = "traces")]
+                debug!(
+                    "register channel resource on connection {}",
+                    self.amqp_connection
+                );
+            }
+            ConnManagementCommand::DeregisterChannelResource(channel_id) => {
+                self.channel_manager.remove_resource(&channel_id);
+                #[cfg(feature = "traces")]
+                debug!(
+                    "deregister channel {} from connection {}",
+                    channel_id, self.amqp_connection
+                );
+            }
+            ConnManagementCommand::DetachChannelDispatcher(channel_id) => {
+                self.channel_manager.detach_dispatcher(&channel_id);
+                #[cfg(feature = "traces")]
+                debug!(
+                    "detach dispatcher of channel {} from connection {}",
+                    channel_id, self.amqp_connection
+                );
+            }
+            ConnManagementCommand::RegisterResponder(cmd) => {
+                self.channel_manager.insert_responder(
+                    &cmd.channel_id,
+                    cmd.method_header,
+                    cmd.responder,
+                );
+                cmd.acker
+                    .send(())
+                    .expect("ack to command RegisterResponder must succeed");
+            }
+            ConnManagementCommand::RegisterConnectionCallback(cmd) => {
+                self.callback.replace(cmd.callback);
+                #[cfg(feature = "traces")]
+                debug!("callback registered on connection {}", self.amqp_connection);
+            }
+        }
+    }
+
+    /// Handle result of reading a frame.
+    ///
+    /// Returns `Break` if the reader should stop, with the cause if it is due to network failure.
+    pub async fn handle_read(
+        &mut self,
+        res: Result<(AmqpChannelId, Frame), Error>,
+    ) -> ControlFlow<Option<ShutdownCause>> {
+        self.amqp_connection.set_frame_received();
+        match res {
+            Ok((channel_id, frame)) => {
+                self.heartbeat_missed = false;
+                self.amqp_connection.record_frame_read(
+                    self.stream.last_frame_size(),
+                    matches!(frame, Frame::HeartBeat(_)),
+                );
+                if let Err(err) = self.handle_frame(channel_id, frame).await {
+                    // notifiy network failure
+                    #[cfg(feature = "traces")]
+                    error!(
+                        "socket will be closed due to error of handling frame, cause: {}",
+                        err
+                    );
+                    return ControlFlow::Break(Some(err.shutdown_cause()));
+                }
+                // normal close
+                if !self.amqp_connection.is_open() {
+                    #[cfg(feature = "traces")]
+                    info!(
+                        "connection {} is closed, shutting down socket I/O handlers",
+                        self.amqp_connection
+                    );
+                    return ControlFlow::Break(None);
+                }
+                ControlFlow::Continue(())
+            }
+            Err(err) => {
+                // notifiy network failure
+                #[cfg(feature = "traces")]
+                error!(
+                    "socket will be closed due to failure of reading frame, cause: {}",
+                    err
+                );
+                if let Error::FrameTooLarge { .. } | Error::Framing(_) = err {
+                    self.close_on_frame_error(&err).await;
+                }
+                ControlFlow::Break(Some(err.shutdown_cause()))
+            }
+        }
+    }
+
+    /// Close the connection with `frame-error` on receiving an invalid frame.
+    ///
+    /// The connection is not usable any more, so it does not wait for `CloseOk`.
+    async fn close_on_frame_error(&mut self, err: &Error) {
+        let text = err.to_string();
+        self.amqp_connection.set_closed(
+            State::Closed {
+                initiator: CloseInitiator::Client,
+                code: FRAME_ERROR,
+                text: text.clone(),
+            },
+            ShutdownCause::ProtocolError,
+        );
+        let close = Close::new(FRAME_ERROR, text.try_into().unwrap_or_default());
+        // best effort, the writer may have gone
+        if let Err(_err) = self.reply(DEFAULT_CONN_CHANNEL, close.into_frame()).await {
+            #[cfg(feature = "traces")]
+            warn!("failed to send close on frame error, cause: {}", _err);
+        }
+    }
+
+    /// Handle missing heartbeat from server.
+    pub fn handle_heartbeat_missed(&mut self) {
+        self.heartbeat_missed = true;
+        // TODO: what to do with missing heartbeat?
+        // should call self.io_failure_notify.notify_one();?
+        #[cfg(feature = "traces")]
+        error!("missing heartbeat from server for {}", self.amqp_connection);
+        self.amqp_connection
+            .notify_event(ConnectionEvent::HeartbeatMissed);
+    }
+
     pub async fn run_until_shutdown(mut self, heartbeat: ShortUint) {
         // max interval to consider heartbeat is timeout
         let max_interval: u64 = heartbeat.into();
         let mut expiration = time::Instant::now() + time::Duration::from_secs(max_interval);
-        let mut is_network_failure = false;
+        let mut failure = None;
         loop {
             tokio::select! {
                 biased;
 
                 command = self.conn_mgmt_rx.recv() => {
-                    let command = match command {
-                        None => {
-                            // should never happen because `ReadHandler` holds
-                            // a `Connection` itself
-                            unreachable!("connection command channel is closed, {}", self.amqp_connection)
-                        },
-                        Some(v) => v,
-                    };
-                    match command {
-                        ConnManagementCommand::RegisterChannelResource(cmd) => {
-                            let id = self.channel_manager.insert_resource(cmd.channel_id, cmd.resource);
-                            cmd.acker.send(id).expect("ack to command RegisterChannelResource must succeed");
-                            #[cfg(feature="traces")]
-                            debug!("register channel resource on connection {}", self.amqp_connection);
-
-                        },
-                        ConnManagementCommand::DeregisterChannelResource(channel_id) => {
-                            self.channel_manager.remove_resource(&channel_id);
-                            #[cfg(feature="traces")]
-                            debug!("deregister channel {} from connection {}", channel_id, self.amqp_connection);
-                        },
-                        ConnManagementCommand::RegisterResponder(cmd) => {
-                            self.channel_manager.insert_responder(&cmd.channel_id, cmd.method_header, cmd.responder);
-                            cmd.acker.send(()).expect("ack to command RegisterResponder must succeed");
-                        },
-                        ConnManagementCommand::RegisterConnectionCallback(cmd) => {
-                            self.callback.replace(cmd.callback);
-                            #[cfg(feature="traces")]
-                            debug!("callback registered on connection {}", self.amqp_connection);
-                        },
-                    }
+                    self.handle_command(command);
                 }
                 res = self.stream.read_frame() => {
                     // any frame can be considered as heartbeat
@@ -254,41 +439,17 @@ impl ReaderHandler {
                     #[cfg(feature="traces")]
                     trace!("server heartbeat deadline is updated to {:?}", expiration);
 
-                    match res {
-                        Ok((channel_id, frame)) => {
-                            if let Err(err) = self.handle_frame(channel_id, frame).await {
-                                // notifiy network failure
-                                is_network_failure = true;
-                                #[cfg(feature="traces")]
-                                error!("socket will be closed due to error of handling frame, cause: {}", err);
-                                break;
-                            }
-                            // normal close
-                            if !self.amqp_connection.is_open() {
-                                #[cfg(feature="traces")]
-                                info!("connection {} is closed, shutting down socket I/O handlers", self.amqp_connection);
-                                break;
-                            }
-                        },
-                        Err(err) => {
-                            // notifiy network failure
-                            is_network_failure = true;
-                            #[cfg(feature="traces")]
-                            error!("socket will be closed due to failure of reading frame, cause: {}", err);
-                            break;
-                        },
+                    if let ControlFlow::Break(cause) = self.handle_read(res).await {
+                        failure = cause;
+                        break;
                     }
                 }
-                _ = time::sleep_until(expiration) => {
+                _ = rt::sleep_until(expiration) => {
                     // heartbeat deadline is updated whenever any frame received
                     // in normal case, expiration is always in the future due to received frame or heartbeats.
                     if expiration <= time::Instant::now() {
                         expiration = time::Instant::now() + time::Duration::from_secs(max_interval);
-
-                        // TODO: what to do with missing heartbeat?
-                        // should call self.io_failure_notify.notify_one();?
-                        #[cfg(feature="traces")]
-                        error!("missing heartbeat from server for {}", self.amqp_connection);
+                        self.handle_heartbeat_missed();
                     }
                 }
                 else => {
@@ -296,11 +457,49 @@ impl ReaderHandler {
                 }
             }
         }
-        self.amqp_connection.set_is_open(false);
+        self.shutdown(failure).await;
+    }
+
+    /// Update status of connection, and notify shutdown to other tasks.
+    ///
+    /// `failure` is the cause if it is due to network failure.
+    pub async fn shutdown(mut self, failure: Option<ShutdownCause>) {
+        let is_network_failure = failure.is_some();
+        let cause = match failure {
+            // server has gone silent before the failure
+            Some(ShutdownCause::IoError(_)) if self.heartbeat_missed => {
+                ShutdownCause::HeartbeatTimeout
+            }
+            Some(cause) => cause,
+            None => ShutdownCause::ApplicationInitiated,
+        };
+        self.amqp_connection.set_closed(
+            State::Closed {
+                initiator: CloseInitiator::Network,
+                code: 0,
+                text: if is_network_failure {
+                    "network I/O failure"
+                } else {
+                    "shutdown"
+                }
+                .to_string(),
+            },
+            cause,
+        );
         if self.shutdown_notifier.send(is_network_failure).is_err() {
             #[cfg(feature = "traces")]
             error!("failed to notify shutdown for {}", self.amqp_connection);
         }
+        // the first cause is kept if already closed
+        let cause = self
+            .amqp_connection
+            .shutdown_cause()
+            .expect("shutdown cause is set once connection is closed");
+        self.amqp_connection
+            .notify_event(ConnectionEvent::Closed(cause.clone()));
+        if let Some(ref mut callback) = self.callback {
+            callback.on_close(&self.amqp_connection, &cause).await;
+        }
         // `self` will drop, so the `self.shutdown_notifier`
         // all tasks which have `subscribed` to `shutdown_notifier` will be notified
     }
@@ -1,29 +1,39 @@
 use crate::frame::{
-    ContentBody, Frame, FrameHeader, FRAME_CONTENT_BODY, FRAME_END, FRAME_HEADER_SIZE,
+    Frame, FrameHeader, ProtocolHeader, FRAME_CONTENT_BODY, FRAME_END, FRAME_HEADER_SIZE,
+    PROTOCOL_HEADER_SIZE,
 };
 
 use amqp_serde::{
-    to_buffer,
+    from_bytes, to_buffer,
     types::{AmqpChannelId, LongUint},
 };
-use bytes::{Buf, BufMut, BytesMut};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use serde::Serialize;
 use std::{
     io::{self, Cursor},
     pin::Pin,
 };
-use tokio::{
-    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf},
-    net::TcpStream,
-};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf};
 #[cfg(feature = "tls")]
 use tokio_rustls::{client::TlsStream, rustls, TlsConnector};
 #[cfg(feature = "traces")]
 use tracing::trace;
 
+#[cfg(feature = "traces")]
+use super::{dump_content_body, dump_frame};
+#[cfg(feature = "traces")]
+use crate::api::connection::FrameDump;
+#[cfg(feature = "test-util")]
+use crate::api::fault::{Fault, FaultInjector};
+
 use super::Error;
+use crate::rt::{self, TcpStream};
 type Result<T> = std::result::Result<T, Error>;
 const DEFAULT_IO_BUFFER_SIZE: usize = 8192;
+/// write buffer grown beyond this size is released after flush, instead of being reused
+const MAX_RETAINED_BUFFER_SIZE: usize = 1024 * 1024;
+/// max size of frames received before `frame_max` is negotiated, RabbitMQ's default `frame_max`
+const HANDSHAKE_FRAME_MAX: usize = 128 * 1024;
 
 pub(crate) struct SplitConnection {
     reader: BufIoReader,
@@ -32,13 +42,37 @@ pub(crate) struct SplitConnection {
 pub(crate) struct BufIoReader {
     stream: ReadHalf<SplitIoStream>,
     buffer: BytesMut,
+    /// max size in bytes of a received frame, larger frames are rejected without being buffered
+    frame_max: usize,
+    /// size in bytes of last decoded frame
+    last_frame_size: usize,
+    /// validate content header properties, see `BasicProperties::decode_strict`
+    strict_decoding: bool,
+    /// log every decoded frame if enabled
+    #[cfg(feature = "traces")]
+    frame_dump: Option<FrameDump>,
+    /// faults to apply to decoded frames if enabled
+    #[cfg(feature = "test-util")]
+    fault_injector: Option<FaultInjector>,
+    /// raw data of last decoded frame, only kept if faults are injected
+    #[cfg(feature = "test-util")]
+    last_frame: Bytes,
+    /// deadline of a delayed frame, which is kept in buffer until then
+    #[cfg(feature = "test-util")]
+    delayed_until: Option<std::time::Instant>,
 }
 pub(crate) struct BufIoWriter {
     stream: WriteHalf<SplitIoStream>,
     buffer: BytesMut,
+    /// number of frames in last write
+    last_frame_count: usize,
+    /// log every serialized frame if enabled
+    #[cfg(feature = "traces")]
+    frame_dump: Option<FrameDump>,
 }
 
 /// Unify Splitable IO stream types
+#[allow(clippy::large_enum_variant)]
 enum SplitIoStream {
     TcpStream(TcpStream),
     #[cfg(feature = "tls")]
@@ -110,7 +144,7 @@ impl AsyncWrite for SplitIoStream {
 // Same interfaces to read/write packet before and after split.
 impl SplitConnection {
     pub async fn open(addr: &str) -> Result<Self> {
-        let stream = TcpStream::connect(addr).await?;
+        let stream = rt::connect(addr).await?;
 
         let stream: SplitIoStream = stream.into();
         let (reader, writer) = tokio::io::split(stream);
@@ -122,10 +156,24 @@ impl SplitConnection {
             reader: BufIoReader {
                 stream: reader,
                 buffer: read_buffer,
+                frame_max: HANDSHAKE_FRAME_MAX,
+                last_frame_size: 0,
+                strict_decoding: false,
+                #[cfg(feature = "traces")]
+                frame_dump: None,
+                #[cfg(feature = "test-util")]
+                fault_injector: None,
+                #[cfg(feature = "test-util")]
+                last_frame: Bytes::new(),
+                #[cfg(feature = "test-util")]
+                delayed_until: None,
             },
             writer: BufIoWriter {
                 stream: writer,
                 buffer: write_buffer,
+                last_frame_count: 0,
+                #[cfg(feature = "traces")]
+                frame_dump: None,
             },
         })
     }
@@ -135,9 +183,7 @@ impl SplitConnection {
         let domain = rustls::ServerName::try_from(domain)
             .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid dnsname"))?;
 
-        let stream = connector
-            .connect(domain, TcpStream::connect(addr).await?)
-            .await?;
+        let stream = connector.connect(domain, rt::connect(addr).await?).await?;
         let stream: SplitIoStream = stream.into();
         let (reader, writer) = tokio::io::split(stream);
 
@@ -148,14 +194,54 @@ impl SplitConnection {
             reader: BufIoReader {
                 stream: reader,
                 buffer: read_buffer,
+                frame_max: HANDSHAKE_FRAME_MAX,
+                last_frame_size: 0,
+                strict_decoding: false,
+                #[cfg(feature = "traces")]
+                frame_dump: None,
+                #[cfg(feature = "test-util")]
+                fault_injector: None,
+                #[cfg(feature = "test-util")]
+                last_frame: Bytes::new(),
+                #[cfg(feature = "test-util")]
+                delayed_until: None,
             },
             writer: BufIoWriter {
                 stream: writer,
                 buffer: write_buffer,
+                last_frame_count: 0,
+                #[cfg(feature = "traces")]
+                frame_dump: None,
             },
         })
     }
 
+    /// log every frame read or written, including those of the opening handshake
+    #[cfg(feature = "traces")]
+    pub fn set_frame_dump(&mut self, frame_dump: FrameDump) {
+        self.reader.frame_dump = Some(frame_dump);
+        self.writer.frame_dump = Some(frame_dump);
+    }
+
+    /// reject content headers with invalid properties instead of ignoring the invalid parts
+    pub fn set_strict_decoding(&mut self, strict: bool) {
+        self.reader.strict_decoding = strict;
+    }
+
+    /// limit size of received frames to negotiated `frame_max`, `0` for no limit
+    pub fn set_frame_max(&mut self, frame_max: LongUint) {
+        self.reader.frame_max = match frame_max {
+            0 => usize::MAX,
+            frame_max => frame_max as usize,
+        };
+    }
+
+    /// apply faults of `fault_injector` to every frame read
+    #[cfg(feature = "test-util")]
+    pub fn set_fault_injector(&mut self, fault_injector: FaultInjector) {
+        self.reader.fault_injector = Some(fault_injector);
+    }
+
     /// split connection into reader half and writer half
     pub(crate) fn into_split(self) -> (BufIoReader, BufIoWriter) {
         (self.reader, self.writer)
@@ -182,12 +268,24 @@ impl SplitConnection {
         self.writer.write_frame(channel, frame, frame_max).await
     }
 
+    pub async fn buffer_frame(
+        &mut self,
+        channel: AmqpChannelId,
+        frame: Frame,
+        frame_max: LongUint,
+    ) -> Result<usize> {
+        self.writer.buffer_frame(channel, frame, frame_max).await
+    }
+
+    pub async fn flush(&mut self) -> Result<usize> {
+        self.writer.flush().await
+    }
+
     pub async fn read_frame(&mut self) -> Result<ChannelFrame> {
         self.reader.read_frame().await
     }
 }
 
-
 impl BufIoWriter {
     // write any serializable value to socket
     pub async fn write<V: Serialize>(&mut self, value: &V) -> Result<usize> {
@@ -195,7 +293,7 @@ impl BufIoWriter {
             .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
         let len = self.buffer.len();
         self.stream.write_all(&self.buffer).await?;
-        self.buffer.advance(len);
+        self.reset_buffer();
         Ok(len)
     }
 
@@ -206,26 +304,19 @@ impl BufIoWriter {
     ) -> Result<()> {
         // there can be data unsent in buffer
         let start_index = self.buffer.len();
+        serialize_frame(&mut self.buffer, channel, &frame)?;
+        self.last_frame_count += 1;
 
-        // reserve bytes for frame header, which to be updated after encoding payload
-        let header = FrameHeader {
-            frame_type: frame.get_frame_type(),
-            channel,
-            payload_size: 0,
-        };
-        to_buffer(&header, &mut self.buffer).unwrap();
-
-        // encode payload
-        let payload_size = to_buffer(&frame, &mut self.buffer)?;
-
-        // update frame's payload size
-        for (i, v) in (payload_size as u32).to_be_bytes().iter().enumerate() {
-            let p = self.buffer.get_mut(i + 3 + start_index).unwrap();
-            *p = *v;
+        #[cfg(feature = "traces")]
+        if let Some(ref frame_dump) = self.frame_dump {
+            dump_frame(
+                frame_dump,
+                "SENT",
+                channel,
+                &frame,
+                &self.buffer[start_index..],
+            );
         }
-
-        // encode frame end byte
-        self.buffer.put_u8(FRAME_END);
         Ok(())
     }
 
@@ -235,14 +326,14 @@ impl BufIoWriter {
     async fn serialize_content_body_into_buffer(
         &mut self,
         channel: AmqpChannelId,
-        body: ContentBody,
+        body: Bytes,
         frame_max: usize,
     ) -> Result<()> {
-        if body.inner.is_empty() {
+        if body.is_empty() {
             return Ok(());
         }
 
-        let mut cursor = Cursor::new(body.inner);
+        let mut cursor = Cursor::new(body);
         while cursor.has_remaining() {
             // there can be data unsent in buffer
             let start_index = self.buffer.len();
@@ -274,6 +365,12 @@ impl BufIoWriter {
             }
             // encode frame end byte
             self.buffer.put_u8(FRAME_END);
+            self.last_frame_count += 1;
+
+            #[cfg(feature = "traces")]
+            if let Some(ref frame_dump) = self.frame_dump {
+                dump_content_body(frame_dump, "SENT", channel, &self.buffer[start_index..]);
+            }
         }
 
         Ok(())
@@ -284,31 +381,106 @@ impl BufIoWriter {
         channel: AmqpChannelId,
         frame: Frame,
         frame_max: LongUint,
+    ) -> Result<usize> {
+        self.buffer_frame(channel, frame, frame_max).await?;
+        self.flush().await
+    }
+
+    // serialize a AMQP frame into write buffer without sending it,
+    // returns the number of bytes buffered for the frame
+    pub async fn buffer_frame(
+        &mut self,
+        channel: AmqpChannelId,
+        frame: Frame,
+        frame_max: LongUint,
     ) -> Result<usize> {
         // TODO: tracing
         #[cfg(feature = "traces")]
         trace!("SENT on channel {}: {}", channel, frame);
 
-        if let Frame::PublishCombo(publish, content_header, content_body) = frame {
-            self.serialize_frame_into_buffer(channel, publish.into_frame())
-                .await?;
-            self.serialize_frame_into_buffer(channel, content_header.into_frame())
-                .await?;
+        let start_len = self.buffer.len();
+        self.last_frame_count = 0;
+        match frame {
+            Frame::PublishCombo(publish, content_header, content_body) => {
+                self.reserve_publish(0, content_body.len(), frame_max as usize);
 
-            self.serialize_content_body_into_buffer(channel, content_body, frame_max as usize)
-                .await?;
-        } else {
-            self.serialize_frame_into_buffer(channel, frame).await?;
+                self.serialize_frame_into_buffer(channel, publish.into_frame())
+                    .await?;
+                self.serialize_frame_into_buffer(channel, content_header.into_frame())
+                    .await?;
+
+                self.serialize_content_body_into_buffer(channel, content_body, frame_max as usize)
+                    .await?;
+            }
+            Frame::PreparedPublishCombo(encoded, content_body) => {
+                self.reserve_publish(encoded.len(), content_body.len(), frame_max as usize);
+
+                // pre-encoded publish method frame and content header frame,
+                // only the body size of content header is updated
+                let start_index = self.buffer.len();
+                self.buffer.extend_from_slice(&encoded);
+                let index = start_index + body_size_index(&encoded);
+                self.buffer[index..index + 8]
+                    .copy_from_slice(&(content_body.len() as u64).to_be_bytes());
+                self.last_frame_count += 2;
+
+                #[cfg(feature = "traces")]
+                if let Some(ref frame_dump) = self.frame_dump {
+                    let mut raw = &self.buffer[start_index..];
+                    while let Ok(Some((len, channel, frame))) = Frame::decode(raw, false) {
+                        dump_frame(frame_dump, "SENT", channel, &frame, &raw[..len]);
+                        raw = &raw[len..];
+                    }
+                }
+
+                self.serialize_content_body_into_buffer(channel, content_body, frame_max as usize)
+                    .await?;
+            }
+            frame => self.serialize_frame_into_buffer(channel, frame).await?,
         }
+        Ok(self.buffer.len() - start_len)
+    }
+
+    // reserve buffer for all frames of a publish at once,
+    // `headers_size` is the size of publish method frame and content header frame if known
+    fn reserve_publish(&mut self, headers_size: usize, body_size: usize, frame_max: usize) {
+        let max_payload_size = frame_max - (FRAME_HEADER_SIZE + 1);
+        let body_frames = (body_size + max_payload_size - 1) / max_payload_size;
+        self.buffer
+            .reserve(headers_size + body_size + body_frames * (FRAME_HEADER_SIZE + 1));
+    }
+
+    // send all buffered data, returns the number of bytes sent
+    pub async fn flush(&mut self) -> Result<usize> {
         // flush whole buffer
         self.stream.write_all(&self.buffer).await?;
 
         // discard sent data in write buffer
         let len = self.buffer.len();
-        self.buffer.advance(len);
+        self.reset_buffer();
         Ok(len)
     }
 
+    // discard all data in write buffer, keep its allocation for next frames
+    // so that steady-state writing does not allocate, unless it grew too large
+    fn reset_buffer(&mut self) {
+        if self.buffer.capacity() > MAX_RETAINED_BUFFER_SIZE {
+            self.buffer = BytesMut::with_capacity(DEFAULT_IO_BUFFER_SIZE);
+        } else {
+            self.buffer.clear();
+        }
+    }
+
+    /// Returns number of bytes buffered but not sent yet.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns number of frames serialized by last `write_frame` or `buffer_frame`.
+    pub fn last_frame_count(&self) -> usize {
+        self.last_frame_count
+    }
+
     // // The socket connection will be shutdown if writer half is shutdown
     pub async fn close(mut self) -> Result<()> {
         self.stream.shutdown().await?;
@@ -318,15 +490,88 @@ impl BufIoWriter {
 
 type ChannelFrame = (AmqpChannelId, Frame);
 
+/// Serialize a AMQP frame into `buffer`, including frame header and frame end.
+pub(crate) fn serialize_frame(
+    buffer: &mut BytesMut,
+    channel: AmqpChannelId,
+    frame: &Frame,
+) -> Result<()> {
+    // there can be data unsent in buffer
+    let start_index = buffer.len();
+
+    // reserve bytes for frame header, which to be updated after encoding payload
+    let header = FrameHeader {
+        frame_type: frame.get_frame_type(),
+        channel,
+        payload_size: 0,
+    };
+    to_buffer(&header, buffer).unwrap();
+
+    // encode payload
+    let payload_size = to_buffer(frame, buffer)?;
+
+    // update frame's payload size
+    for (i, v) in (payload_size as u32).to_be_bytes().iter().enumerate() {
+        let p = buffer.get_mut(i + 3 + start_index).unwrap();
+        *p = *v;
+    }
+
+    // encode frame end byte
+    buffer.put_u8(FRAME_END);
+    Ok(())
+}
+
+/// Returns index of the body size in encoded `Publish` method frame and content header frame.
+fn body_size_index(encoded: &[u8]) -> usize {
+    let mut payload_size = [0; 4];
+    payload_size.copy_from_slice(&encoded[3..FRAME_HEADER_SIZE]);
+    let method_frame_size = FRAME_HEADER_SIZE + u32::from_be_bytes(payload_size) as usize + 1;
+    // content header payload starts with class id and weight
+    method_frame_size + FRAME_HEADER_SIZE + 4
+}
+
 impl BufIoReader {
     // try to decode a whole frame from the bufferred data.
     // If it is incomplete data, return None;
     // If the frame syntax is corrupted, return Error.
     fn decode(&mut self) -> Result<Option<ChannelFrame>> {
-        match Frame::decode(&self.buffer)? {
+        // server rejects the protocol version by its own protocol header
+        if ProtocolHeader::is_protocol_header(&self.buffer) {
+            if self.buffer.len() < PROTOCOL_HEADER_SIZE {
+                return Ok(None);
+            }
+            let header: ProtocolHeader = from_bytes(&self.buffer[..PROTOCOL_HEADER_SIZE])?;
+            let (major, minor, revision) = header.version();
+            return Err(Error::ProtocolVersionMismatch(major, minor, revision));
+        }
+        // check declared size before buffering the frame, so that a bogus size
+        // can't make the buffer grow without bound
+        if self.buffer.len() >= FRAME_HEADER_SIZE {
+            let mut payload_size = [0; 4];
+            payload_size.copy_from_slice(&self.buffer[3..FRAME_HEADER_SIZE]);
+            let size = FRAME_HEADER_SIZE + u32::from_be_bytes(payload_size) as usize + 1;
+            if size > self.frame_max {
+                return Err(Error::FrameTooLarge {
+                    size,
+                    frame_max: self.frame_max,
+                });
+            }
+            // grow buffer at once for the rest of the frame
+            self.buffer.reserve(size.saturating_sub(self.buffer.len()));
+        }
+        match Frame::decode(&self.buffer, self.strict_decoding)? {
             Some((len, channel_id, frame)) => {
+                #[cfg(feature = "traces")]
+                if let Some(ref frame_dump) = self.frame_dump {
+                    dump_frame(frame_dump, "RECV", channel_id, &frame, &self.buffer[..len]);
+                }
+                #[cfg(feature = "test-util")]
+                if self.fault_injector.is_some() {
+                    self.last_frame = Bytes::copy_from_slice(&self.buffer[..len]);
+                }
                 // discard parsed data in read buffer
                 self.buffer.advance(len);
+                self.last_frame_size = len;
                 // TODO: tracing
                 #[cfg(feature = "traces")]
                 trace!("RECV on channel {}: {}", channel_id, frame);
@@ -338,6 +583,72 @@ impl BufIoReader {
 
     // Read a complete frame from socket connection, return channel id and decoded frame.
     pub async fn read_frame(&mut self) -> Result<ChannelFrame> {
+        #[cfg(feature = "test-util")]
+        if let Some(fault_injector) = self.fault_injector.clone() {
+            return self.read_frame_with_faults(&fault_injector).await;
+        }
+        self.read_next_frame().await
+    }
+
+    // Read a frame and apply the next injected fault to it.
+    #[cfg(feature = "test-util")]
+    async fn read_frame_with_faults(
+        &mut self,
+        fault_injector: &FaultInjector,
+    ) -> Result<ChannelFrame> {
+        loop {
+            if fault_injector.is_severed() {
+                return Err(Error::Interrupted);
+            }
+            // the delayed frame stays in buffer while waiting, and the deadline is kept,
+            // so the frame is not lost if reading is cancelled meanwhile
+            if let Some(deadline) = self.delayed_until {
+                tokio::select! {
+                    _ = rt::sleep_until(deadline) => {}
+                    _ = fault_injector.severed() => return Err(Error::Interrupted),
+                }
+                self.delayed_until = None;
+                return self.read_next_frame().await;
+            }
+            // reading into buffer is cancel safe, no data is lost if severed meanwhile
+            let frame = tokio::select! {
+                result = self.read_next_frame() => result?,
+                _ = fault_injector.severed() => return Err(Error::Interrupted),
+            };
+            match fault_injector.next_fault() {
+                None => return Ok(frame),
+                Some(Fault::Delay(delay)) => {
+                    // put the frame back until the delay is over
+                    let raw = self.last_frame.clone();
+                    self.unread(&raw);
+                    self.delayed_until = Some(std::time::Instant::now() + delay);
+                    continue;
+                }
+                Some(Fault::Drop) => continue,
+                Some(Fault::Duplicate) => {
+                    let raw = self.last_frame.clone();
+                    self.unread(&raw);
+                    return Ok(frame);
+                }
+                Some(Fault::Truncate(len)) => {
+                    let raw = self.last_frame.clone();
+                    self.unread(&raw[..len.min(raw.len())]);
+                    continue;
+                }
+            }
+        }
+    }
+
+    // Put `data` back in front of bufferred data, to be decoded again.
+    #[cfg(feature = "test-util")]
+    fn unread(&mut self, data: &[u8]) {
+        let mut buffer = BytesMut::with_capacity(data.len() + self.buffer.len());
+        buffer.extend_from_slice(data);
+        buffer.extend_from_slice(&self.buffer);
+        self.buffer = buffer;
+    }
+
+    async fn read_next_frame(&mut self) -> Result<ChannelFrame> {
         // check if there is remaining data in buffer to decode first
         let result = self.decode()?;
         if let Some(frame) = result {
@@ -364,6 +675,11 @@ impl BufIoReader {
         }
     }
 
+    /// Returns size in bytes of the frame returned by last `read_frame`.
+    pub fn last_frame_size(&self) -> usize {
+        self.last_frame_size
+    }
+
     // do nothing except consume the reader itself
     pub async fn close(self) {}
 }
@@ -493,4 +809,77 @@ mod test {
         reader.close().await;
         writer.close().await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_write_buffer_reuse() {
+        let (mut reader, mut writer) = SplitConnection::open("localhost:5672")
+            .await
+            .unwrap()
+            .into_split();
+
+        writer.write(&ProtocolHeader::default()).await.unwrap();
+        let _ = reader.read_frame().await.unwrap();
+
+        // allocation of write buffer is reused
+        let ptr = writer.buffer.as_ptr();
+        writer
+            .buffer_frame(
+                DEFAULT_CONN_CHANNEL,
+                Frame::HeartBeat(HeartBeat),
+                FRAME_MIN_SIZE,
+            )
+            .await
+            .unwrap();
+        writer.flush().await.unwrap();
+        assert_eq!(0, writer.buffered());
+        assert_eq!(ptr, writer.buffer.as_ptr());
+        assert!(writer.buffer.capacity() >= super::DEFAULT_IO_BUFFER_SIZE);
+
+        // large write buffer is released
+        writer.buffer.reserve(super::MAX_RETAINED_BUFFER_SIZE + 1);
+        writer.flush().await.unwrap();
+        assert!(writer.buffer.capacity() <= super::MAX_RETAINED_BUFFER_SIZE);
+
+        reader.close().await;
+        writer.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reject_oversized_frame() {
+        use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+        setup_logging();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            // a heartbeat, then a header which declares a huge payload
+            stream
+                .write_all(&[FRAME_HEARTBEAT, 0, 0, 0, 0, 0, 0, FRAME_END])
+                .await
+                .unwrap();
+            stream
+                .write_all(&[FRAME_CONTENT_BODY, 0, 1, 0xff, 0xff, 0xff, 0xf0])
+                .await
+                .unwrap();
+            stream
+        });
+
+        let mut connection = SplitConnection::open(&address).await.unwrap();
+        connection.set_frame_max(FRAME_MIN_SIZE);
+        let (mut reader, _writer) = connection.into_split();
+        let (_, frame) = reader.read_frame().await.unwrap();
+        assert!(matches!(frame, Frame::HeartBeat(_)));
+        match reader.read_frame().await {
+            Err(super::Error::FrameTooLarge { size, frame_max }) => {
+                assert_eq!(0xffff_fff0 + 8, size);
+                assert_eq!(FRAME_MIN_SIZE as usize, frame_max);
+            }
+            other => panic!("unexpected result: {:?}", other.map(|(_, frame)| frame)),
+        }
+        assert!(reader.buffer.capacity() < FRAME_MIN_SIZE as usize * 4);
+
+        drop(server.await.unwrap());
+    }
 }
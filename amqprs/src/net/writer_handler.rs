@@ -1,27 +1,39 @@
-use amqp_serde::types::ShortUint;
-use tokio::{
-    sync::{broadcast, mpsc},
-    task::yield_now,
-    time,
-};
+use std::time;
+
+use amqp_serde::types::{AmqpChannelId, ShortUint};
+use tokio::sync::{broadcast, mpsc};
 #[cfg(feature = "traces")]
 use tracing::{debug, error, info, trace};
 
 use crate::{
-    connection::Connection,
+    connection::{Connection, FlushPolicy, ShutdownCause},
     frame::{Frame, HeartBeat, DEFAULT_CONN_CHANNEL},
+    rt,
+    state::{CloseInitiator, State},
 };
 
-use super::{BufIoWriter, OutgoingMessage};
+use super::{BufIoWriter, Error, OutgoingMessage};
 
 pub(crate) struct WriterHandler {
     stream: BufIoWriter,
     /// receiver half to forward outgoing messages from AMQ connection/channel to server
-    outgoing_rx: mpsc::Receiver<OutgoingMessage>,
+    pub(super) outgoing_rx: mpsc::Receiver<OutgoingMessage>,
     /// listener of shutdown signal
     shutdown: broadcast::Receiver<bool>,
     /// connection
     amqp_connection: Connection,
+    /// how to flush outgoing frames to network
+    flush_policy: FlushPolicy,
+    /// frames buffered but not flushed yet, recorded in I/O statistics once flushed
+    unflushed: Unflushed,
+}
+
+/// Counters of frames buffered but not flushed yet.
+#[derive(Default)]
+struct Unflushed {
+    frames: usize,
+    bytes: usize,
+    heartbeats: usize,
 }
 
 impl WriterHandler {
@@ -30,13 +42,65 @@ impl WriterHandler {
         outgoing_rx: mpsc::Receiver<OutgoingMessage>,
         shutdown: broadcast::Receiver<bool>,
         amqp_connection: Connection,
+        flush_policy: FlushPolicy,
     ) -> Self {
         Self {
             stream,
             outgoing_rx,
             shutdown,
             amqp_connection,
+            flush_policy,
+            unflushed: Unflushed::default(),
+        }
+    }
+
+    /// Write a frame into buffer, and flush according to flush policy.
+    ///
+    /// `flush_deadline` is set when frames are coalesced in buffer, and cleared once flushed.
+    /// Heartbeat is always flushed immediately.
+    pub async fn write(
+        &mut self,
+        channel_id: AmqpChannelId,
+        frame: Frame,
+        is_heartbeat: bool,
+        flush_deadline: &mut Option<time::Instant>,
+    ) -> Result<(), Error> {
+        let len = self
+            .stream
+            .buffer_frame(channel_id, frame, self.amqp_connection.frame_max())
+            .await?;
+        self.unflushed.frames += self.stream.last_frame_count();
+        self.unflushed.bytes += len;
+        if is_heartbeat {
+            self.unflushed.heartbeats += 1;
+        }
+        match self.flush_policy {
+            FlushPolicy::Coalesce {
+                max_bytes,
+                max_delay,
+            } if !is_heartbeat && self.stream.buffered() < max_bytes => {
+                if flush_deadline.is_none() {
+                    *flush_deadline = Some(time::Instant::now() + max_delay);
+                }
+            }
+            _ => {
+                self.flush().await?;
+                *flush_deadline = None;
+            }
         }
+        Ok(())
+    }
+
+    /// Flush coalesced frames to network, and record them in I/O statistics.
+    pub async fn flush(&mut self) -> Result<(), Error> {
+        self.stream.flush().await?;
+        let unflushed = std::mem::take(&mut self.unflushed);
+        self.amqp_connection.record_frames_written(
+            unflushed.frames,
+            unflushed.bytes,
+            unflushed.heartbeats,
+        );
+        Ok(())
     }
 
     pub async fn run_until_shutdown(mut self, heartbeat: ShortUint) {
@@ -44,43 +108,58 @@ impl WriterHandler {
         // heartbeat should be sent at a interval of timeout / 2
         let interval: u64 = (heartbeat / 2).into();
         let mut expiration = time::Instant::now() + time::Duration::from_secs(interval);
+        // deadline to flush coalesced frames, `None` if no frame is buffered
+        let mut flush_deadline: Option<time::Instant> = None;
+        // cause of network failure
+        let mut failure = None;
 
         loop {
             tokio::select! {
                 biased;
 
+                _ = rt::sleep_until(flush_deadline.unwrap_or(expiration)), if flush_deadline.is_some() => {
+                    flush_deadline = None;
+                    if let Err(err) = self.flush().await {
+                        #[cfg(feature="traces")]
+                        error!("failed to flush frames over connection {}, cause: {}", self.amqp_connection, err);
+                        failure = Some(err.shutdown_cause());
+                        break;
+                    }
+                }
                 channel_frame = self.outgoing_rx.recv() => {
                     let (channel_id, frame) = match channel_frame {
                         None => break,
                         Some(v) => v,
                     };
-                    if let Err(err) = self.stream.write_frame(channel_id, frame, self.amqp_connection.frame_max()).await {
-                        #[cfg(feature="tracing")]
+                    if let Err(err) = self.write(channel_id, frame, false, &mut flush_deadline).await {
+                        #[cfg(feature="traces")]
                         error!("failed to send frame over connection {}, cause: {}", self.amqp_connection, err);
+                        failure = Some(err.shutdown_cause());
                         break;
                     }
                     expiration = time::Instant::now() + time::Duration::from_secs(interval);
-                    #[cfg(feature="tracing")]
+                    #[cfg(feature="traces")]
                     trace!("connection {} heartbeat deadline is updated to {:?}", self.amqp_connection, expiration);
                 }
-                _ = time::sleep_until(expiration) => {
+                _ = rt::sleep_until(expiration) => {
                     if expiration <= time::Instant::now() {
                         expiration = time::Instant::now() + time::Duration::from_secs(interval);
 
-                        if let Err(err) = self.stream.write_frame(DEFAULT_CONN_CHANNEL, Frame::HeartBeat(HeartBeat), self.amqp_connection.frame_max()).await {
-                            #[cfg(feature="tracing")]
+                        if let Err(err) = self.write(DEFAULT_CONN_CHANNEL, Frame::HeartBeat(HeartBeat), true, &mut flush_deadline).await {
+                            #[cfg(feature="traces")]
                             error!("failed to send heartbeat over connection {}, cause: {}", self.amqp_connection, err);
+                            failure = Some(err.shutdown_cause());
                             break;
                         }
-                        #[cfg(feature="tracing")]
+                        #[cfg(feature="traces")]
                         debug!("sent heartbeat over connection {}", self.amqp_connection,);
                     }
                 }
                 _ = self.shutdown.recv() => {
-                    #[cfg(feature="tracing")]
+                    #[cfg(feature="traces")]
                     info!("received shutdown notification for connection {}", self.amqp_connection);
                     // try to give last chance for last message.
-                    yield_now().await;
+                    rt::yield_now().await;
                     break;
                 }
                 else => {
@@ -88,13 +167,38 @@ impl WriterHandler {
                 }
             }
         }
-        self.amqp_connection.set_is_open(false);
+        self.shutdown(flush_deadline.is_some(), failure).await;
+    }
+
+    /// Best effort to flush coalesced frames if `has_buffered`, and close the writer.
+    ///
+    /// `failure` is the cause if it is due to network failure.
+    pub async fn shutdown(mut self, has_buffered: bool, failure: Option<ShutdownCause>) {
+        self.amqp_connection.set_closed(
+            State::Closed {
+                initiator: CloseInitiator::Network,
+                code: 0,
+                text: "writer shutdown".to_string(),
+            },
+            failure.unwrap_or(ShutdownCause::ApplicationInitiated),
+        );
+
+        // best effort to send coalesced frames
+        if has_buffered {
+            if let Err(_err) = self.flush().await {
+                #[cfg(feature = "traces")]
+                error!(
+                    "failed to flush frames over connection {}, cause: {}",
+                    self.amqp_connection, _err
+                );
+            }
+        }
 
-        if let Err(err) = self.stream.close().await {
+        if let Err(_err) = self.stream.close().await {
             #[cfg(feature = "traces")]
             error!(
                 "failed to close i/o writer of connection {}, cause: {}",
-                self.amqp_connection, err
+                self.amqp_connection, _err
             );
         }
     }
@@ -0,0 +1,124 @@
+//! Runtime abstraction.
+//!
+//! The library only depends on runtime agnostic parts of tokio (`sync`, `io-util` and `macros`),
+//! everything which needs an async runtime, i.e. spawning tasks, timers and TCP connection,
+//! goes through this module.
+//!
+//! The runtime is selected by feature flags:
+//! - "rt-tokio" (default): use tokio runtime.
+//! - "rt-async-std": use async-std runtime, only takes effect if "rt-tokio" is disabled.
+//!
+use std::{future::Future, time::Instant};
+
+#[cfg(not(any(feature = "rt-tokio", feature = "rt-async-std")))]
+compile_error!("one of the features \"rt-tokio\" or \"rt-async-std\" must be enabled");
+
+/// Wait until `deadline` or `future` completes, whichever comes first.
+///
+/// Returns [`None`] if `deadline` is reached before `future` completes.
+pub(crate) async fn timeout_at<F: Future>(deadline: Instant, future: F) -> Option<F::Output> {
+    tokio::select! {
+        output = future => Some(output),
+        _ = sleep_until(deadline) => None,
+    }
+}
+
+#[cfg(feature = "rt-tokio")]
+pub(crate) use self::tokio_rt::*;
+
+#[cfg(feature = "rt-tokio")]
+mod tokio_rt {
+    use std::{future::Future, io, time::Instant};
+
+    pub(crate) type TcpStream = tokio::net::TcpStream;
+
+    pub(crate) async fn connect(addr: &str) -> io::Result<TcpStream> {
+        TcpStream::connect(addr).await
+    }
+
+    pub(crate) fn spawn<F>(future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        tokio::spawn(future);
+    }
+
+    /// Spawn `future` if called within a runtime, returns `false` otherwise.
+    pub(crate) fn try_spawn<F>(future: F) -> bool
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(future);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub(crate) fn spawn_blocking<F>(f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        tokio::task::spawn_blocking(f);
+    }
+
+    pub(crate) async fn yield_now() {
+        tokio::task::yield_now().await
+    }
+
+    pub(crate) async fn sleep_until(deadline: Instant) {
+        tokio::time::sleep_until(deadline.into()).await
+    }
+}
+
+#[cfg(all(feature = "rt-async-std", not(feature = "rt-tokio")))]
+pub(crate) use self::async_std_rt::*;
+
+#[cfg(all(feature = "rt-async-std", not(feature = "rt-tokio")))]
+mod async_std_rt {
+    use std::{future::Future, io, time::Instant};
+
+    use tokio_util::compat::{Compat, FuturesAsyncReadCompatExt};
+
+    /// async-std's TCP stream adapted to tokio's `AsyncRead` and `AsyncWrite`.
+    pub(crate) type TcpStream = Compat<async_std::net::TcpStream>;
+
+    pub(crate) async fn connect(addr: &str) -> io::Result<TcpStream> {
+        Ok(async_std::net::TcpStream::connect(addr).await?.compat())
+    }
+
+    pub(crate) fn spawn<F>(future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        async_std::task::spawn(future);
+    }
+
+    /// Spawn `future` if called within a runtime, returns `false` otherwise.
+    ///
+    /// async-std's global executor is always available.
+    pub(crate) fn try_spawn<F>(future: F) -> bool
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        spawn(future);
+        true
+    }
+
+    pub(crate) fn spawn_blocking<F>(f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        async_std::task::spawn_blocking(f);
+    }
+
+    pub(crate) async fn yield_now() {
+        async_std::task::yield_now().await
+    }
+
+    pub(crate) async fn sleep_until(deadline: Instant) {
+        async_std::task::sleep(deadline.saturating_duration_since(Instant::now())).await
+    }
+}
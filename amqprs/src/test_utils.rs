@@ -1,6 +1,22 @@
 #[cfg(test)]
+use std::time::Duration;
+
+#[cfg(test)]
+use async_trait::async_trait;
+#[cfg(test)]
+use tokio::sync::mpsc;
+#[cfg(test)]
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
+#[cfg(test)]
+use crate::{
+    callbacks::ChannelCallback,
+    channel::{Channel, QueueDeclareArguments},
+    error::Error,
+    frame::{Ack, Cancel, CloseChannel, Nack, Return},
+    BasicProperties,
+};
+
 //////////////////////////////////////////////////////////////////
 // construct a subscriber that prints formatted traces to stdout
 #[cfg(test)]
@@ -12,3 +28,64 @@ pub fn setup_logging() {
         .try_init()
         .ok();
 }
+
+//////////////////////////////////////////////////////////////////
+/// Channel event forwarded to test by [`ForwardingChannelCallback`].
+#[cfg(test)]
+#[derive(Debug, PartialEq)]
+pub enum ChannelEvent {
+    /// reply code of a returned message
+    PublishReturn(u16),
+    /// name of a removed transient queue
+    TransientQueueRemoved(String),
+    /// delivery tag of an unacked delivery reaching the ack deadline
+    AckDeadlineApproaching(u64),
+}
+
+/// Channel callback which accepts all requests from server,
+/// and forwards the events of interest to test.
+#[cfg(test)]
+pub struct ForwardingChannelCallback(pub mpsc::UnboundedSender<ChannelEvent>);
+
+#[cfg(test)]
+#[async_trait]
+impl ChannelCallback for ForwardingChannelCallback {
+    async fn close(&mut self, _channel: &Channel, _close: CloseChannel) -> Result<(), Error> {
+        Ok(())
+    }
+    async fn cancel(&mut self, _channel: &Channel, _cancel: Cancel) -> Result<(), Error> {
+        Ok(())
+    }
+    async fn flow(&mut self, _channel: &Channel, active: bool) -> Result<bool, Error> {
+        Ok(active)
+    }
+    async fn publish_ack(&mut self, _channel: &Channel, _ack: Ack) {}
+    async fn publish_nack(&mut self, _channel: &Channel, _nack: Nack) {}
+    async fn publish_return(
+        &mut self,
+        _channel: &Channel,
+        ret: Return,
+        _basic_properties: BasicProperties,
+        _content: Vec<u8>,
+    ) {
+        let _ = self.0.send(ChannelEvent::PublishReturn(ret.reply_code()));
+    }
+    async fn ack_deadline_approaching(
+        &mut self,
+        _channel: &Channel,
+        delivery_tag: u64,
+        _age: Duration,
+    ) {
+        let _ = self
+            .0
+            .send(ChannelEvent::AckDeadlineApproaching(delivery_tag));
+    }
+    async fn transient_queue_removed(
+        &mut self,
+        _channel: &Channel,
+        queue: String,
+        _args: QueueDeclareArguments,
+    ) {
+        let _ = self.0.send(ChannelEvent::TransientQueueRemoved(queue));
+    }
+}
@@ -1,19 +1,9 @@
 use amqprs::connection::OpenConnectionArguments;
-use tracing_subscriber::{fmt, prelude::*, EnvFilter};
-
-// construct a subscriber that prints formatted traces to stdout
-pub fn setup_logging() {
-    // global subscriber with log level according to RUST_LOG
-    tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(EnvFilter::from_default_env())
-        .try_init()
-        .ok();
-}
+pub use amqprs::test_support::*;
 
 #[cfg(not(feature = "tls"))]
 pub fn build_conn_args() -> OpenConnectionArguments {
-    OpenConnectionArguments::new("localhost", 5672, "user", "bitnami")
+    connection_args()
 }
 #[cfg(feature = "tls")]
 pub fn build_conn_args() -> OpenConnectionArguments {
@@ -215,3 +215,51 @@ async fn test_get_empty() {
         panic!("expect ReturnEmpty message");
     }
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_get_auto_ack() {
+    common::setup_logging();
+
+    let args = common::build_conn_args();
+    let connection = Connection::open(&args).await.unwrap();
+    let channel = connection.open_channel(None).await.unwrap();
+
+    let (queue_name, ..) = channel
+        .queue_declare(QueueDeclareArguments::default())
+        .await
+        .unwrap()
+        .unwrap();
+
+    channel
+        .basic_publish(
+            BasicProperties::default(),
+            b"auto ack".to_vec(),
+            BasicPublishArguments::new("", &queue_name),
+        )
+        .await
+        .unwrap();
+
+    let get_args = BasicGetArguments::new(&queue_name).auto_ack(true).finish();
+    let (_, _, content) = channel
+        .basic_get(get_args.clone())
+        .await
+        .unwrap()
+        .expect("expect get a message");
+    assert_eq!(b"auto ack".to_vec(), content);
+
+    // no ack required, message is removed from queue
+    let (_, message_count, _) = channel
+        .queue_declare(
+            QueueDeclareArguments::new(&queue_name)
+                .passive(true)
+                .finish(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(0, message_count);
+    assert!(channel.basic_get(get_args).await.unwrap().is_none());
+
+    channel.close().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,219 @@
+//! A small command line tool to declare a queue, publish and consume messages.
+//!
+//! Usage:
+//!
+//! ```text
+//! cargo run --example cli -- [OPTIONS] declare <queue>
+//! cargo run --example cli -- [OPTIONS] publish <queue> <count> [message]
+//! cargo run --example cli -- [OPTIONS] consume <queue> [count]
+//! ```
+//!
+//! Options:
+//!
+//! ```text
+//! --host <host>          default: localhost
+//! --port <port>          default: 5672
+//! --user <user>          default: user
+//! --password <password>  default: bitnami
+//! ```
+//!
+//! Without a command, it runs a smoke test which declares a queue,
+//! publishes 10 messages to it and consumes them.
+use std::{env, process};
+
+use amqprs::{
+    callbacks::{DefaultChannelCallback, DefaultConnectionCallback},
+    channel::{
+        BasicAckArguments, BasicConsumeArguments, BasicPublishArguments, Channel,
+        QueueDeclareArguments,
+    },
+    connection::{Connection, OpenConnectionArguments},
+    BasicProperties,
+};
+use tokio::time;
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+const USAGE: &str = "\
+usage: cli [--host <host>] [--port <port>] [--user <user>] [--password <password>] <command>
+
+commands:
+    declare <queue>                    declare a durable queue
+    publish <queue> <count> [message]  publish <count> messages to <queue>
+    consume <queue> [count]            print messages of <queue>, stop after [count] messages";
+
+enum Command {
+    Declare {
+        queue: String,
+    },
+    Publish {
+        queue: String,
+        count: usize,
+        message: String,
+    },
+    Consume {
+        queue: String,
+        count: Option<usize>,
+    },
+    SmokeTest,
+}
+
+/// Parse command line arguments, exit with usage if they are invalid.
+fn parse_args() -> (OpenConnectionArguments, Command) {
+    let mut args = OpenConnectionArguments::new("localhost", 5672, "user", "bitnami");
+    let (mut user, mut password) = (String::from("user"), String::from("bitnami"));
+    let mut positional = vec![];
+
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().unwrap_or_else(|| usage_exit());
+        match arg.as_str() {
+            "--host" => {
+                args.host(&value());
+            }
+            "--port" => {
+                args.port(value().parse().unwrap_or_else(|_| usage_exit()));
+            }
+            "--user" => user = value(),
+            "--password" => password = value(),
+            "-h" | "--help" => usage_exit(),
+            _ => positional.push(arg),
+        }
+    }
+    args.credentials(amqprs::security::SecurityCredentials::new_plain(
+        &user, &password,
+    ));
+
+    let positional: Vec<&str> = positional.iter().map(String::as_str).collect();
+    let command = match positional.as_slice() {
+        [] => Command::SmokeTest,
+        ["declare", queue] => Command::Declare {
+            queue: queue.to_string(),
+        },
+        ["publish", queue, count, message @ ..] if message.len() <= 1 => Command::Publish {
+            queue: queue.to_string(),
+            count: count.parse().unwrap_or_else(|_| usage_exit()),
+            message: message.first().unwrap_or(&"Hello, amqprs!").to_string(),
+        },
+        ["consume", queue, count @ ..] if count.len() <= 1 => Command::Consume {
+            queue: queue.to_string(),
+            count: count
+                .first()
+                .map(|count| count.parse().unwrap_or_else(|_| usage_exit())),
+        },
+        _ => usage_exit(),
+    };
+    (args.finish(), command)
+}
+
+fn usage_exit() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2)
+}
+
+async fn declare(channel: &Channel, queue: &str) -> String {
+    let (queue_name, message_count, consumer_count) = channel
+        .queue_declare(QueueDeclareArguments::durable_client_named(queue))
+        .await
+        .unwrap()
+        .unwrap();
+    println!(
+        "declared queue '{}', messages: {}, consumers: {}",
+        queue_name, message_count, consumer_count
+    );
+    queue_name
+}
+
+async fn publish(channel: &Channel, queue: &str, count: usize, message: &str) {
+    for _ in 0..count {
+        channel
+            .basic_publish(
+                BasicProperties::default(),
+                message.as_bytes().to_vec(),
+                // publish to default exchange, which routes by queue name
+                BasicPublishArguments::new("", queue),
+            )
+            .await
+            .unwrap();
+    }
+    println!("published {} messages to queue '{}'", count, queue);
+}
+
+async fn consume(channel: &Channel, queue: &str, count: Option<usize>) {
+    let (consumer_tag, mut messages) = channel
+        .basic_consume_rx(BasicConsumeArguments::new(queue, "amqprs-cli"))
+        .await
+        .unwrap();
+
+    let mut received = 0;
+    // consume until interrupted without count
+    let count = count.unwrap_or(usize::MAX);
+    while received < count {
+        let message = match messages.recv().await {
+            Some(message) => message,
+            None => break,
+        };
+        let deliver = message.deliver.unwrap();
+        println!(
+            "[{}] {}",
+            deliver.delivery_tag(),
+            String::from_utf8_lossy(&message.content.unwrap())
+        );
+        channel
+            .basic_ack(BasicAckArguments::new(deliver.delivery_tag(), false))
+            .await
+            .unwrap();
+        received += 1;
+    }
+    println!(
+        "consumed {} messages by consumer '{}'",
+        received, consumer_tag
+    );
+}
+
+#[tokio::main(flavor = "multi_thread", worker_threads = 2)]
+async fn main() {
+    // global subscriber with log level according to RUST_LOG
+    tracing_subscriber::registry()
+        .with(fmt::layer())
+        .with(EnvFilter::from_default_env())
+        .try_init()
+        .ok();
+
+    let (args, command) = parse_args();
+
+    let connection = Connection::open(&args).await.unwrap();
+    connection
+        .register_callback(DefaultConnectionCallback)
+        .await
+        .unwrap();
+    let channel = connection.open_channel(None).await.unwrap();
+    channel
+        .register_callback(DefaultChannelCallback)
+        .await
+        .unwrap();
+
+    match command {
+        Command::Declare { queue } => {
+            declare(&channel, &queue).await;
+        }
+        Command::Publish {
+            queue,
+            count,
+            message,
+        } => publish(&channel, &queue, count, &message).await,
+        Command::Consume { queue, count } => consume(&channel, &queue, count).await,
+        Command::SmokeTest => {
+            let queue = declare(&channel, "amqprs.examples.cli").await;
+            publish(&channel, &queue, 10, "Hello, amqprs!").await;
+            time::timeout(
+                time::Duration::from_secs(5),
+                consume(&channel, &queue, Some(10)),
+            )
+            .await
+            .expect("smoke test timed out");
+        }
+    }
+
+    channel.close().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -18,7 +18,7 @@ check_result
 cargo test
 check_result
 
-cargo test -F traces
+cargo test -p amqprs --no-default-features -F rt-tokio
 check_result
 
 cargo test -F compliance_assert